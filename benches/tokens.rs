@@ -0,0 +1,33 @@
+//! Compare the bulk tokenizer behind `Keyword::parse_line` with parsing
+//! the tokens of `split_whitespace` one by one.
+//!
+//! Run with `cargo bench --bench tokens`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use portage_metadata::{Keyword, Result};
+
+const LINE: &str = "alpha amd64 arm arm64 hppa ~loong ~m68k ~mips ppc ppc64 ~riscv ~s390 sparc \
+                    x86 ~amd64-linux ~x86-linux ~arm64-macos ~ppc-macos ~x64-macos ~x64-solaris";
+const ROUNDS: usize = 1_000_000;
+
+fn main() {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let keywords: Result<Vec<Keyword>> = black_box(LINE)
+            .split_whitespace()
+            .map(Keyword::parse)
+            .collect();
+        black_box(keywords.unwrap());
+    }
+    let split = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(Keyword::parse_line(black_box(LINE)).unwrap());
+    }
+    let bulk = start.elapsed();
+
+    println!("split_whitespace: {split:?}, Tokens: {bulk:?}");
+}
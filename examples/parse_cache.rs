@@ -1,4 +1,4 @@
-use portage_metadata::CacheEntry;
+use portage_metadata::{CacheEntry, MetadataRow};
 use std::env;
 use std::fs;
 
@@ -39,80 +39,11 @@ fn main() {
 }
 
 fn print_entry(entry: &CacheEntry) {
-    let m = &entry.metadata;
-
     println!("=== Parsed Cache Entry ===");
-    println!("EAPI:         {}", m.eapi);
-    println!("Description:  {}", m.description);
-    println!("Slot:         {}", m.slot);
-    println!("Homepage:     {}", m.homepage.join(" "));
-    println!(
-        "Keywords:     {}",
-        m.keywords
-            .iter()
-            .map(|k| k.to_string())
-            .collect::<Vec<_>>()
-            .join(" ")
-    );
-    println!(
-        "IUSE:         {}",
-        m.iuse
-            .iter()
-            .map(|i| i.to_string())
-            .collect::<Vec<_>>()
-            .join(" ")
-    );
-    if let Some(ref license) = m.license {
-        println!("License:      {}", license);
-    }
-    if let Some(ref ru) = m.required_use {
-        println!("Required USE: {}", ru);
-    }
-    if !m.restrict.is_empty() {
-        println!(
-            "Restrict:     {}",
-            m.restrict
-                .iter()
-                .map(|r| r.to_string())
-                .collect::<Vec<_>>()
-                .join(" ")
-        );
-    }
-    println!(
-        "Phases:       {}",
-        m.defined_phases
-            .iter()
-            .map(|p| p.to_string())
-            .collect::<Vec<_>>()
-            .join(" ")
-    );
-    println!(
-        "DEPEND:       {}",
-        m.depend
-            .iter()
-            .map(|d| d.to_string())
-            .collect::<Vec<_>>()
-            .join(" ")
-    );
-    println!(
-        "RDEPEND:      {}",
-        m.rdepend
-            .iter()
-            .map(|d| d.to_string())
-            .collect::<Vec<_>>()
-            .join(" ")
-    );
-    println!(
-        "SRC_URI:      {}",
-        m.src_uri
-            .iter()
-            .map(|u| u.to_string())
-            .collect::<Vec<_>>()
-            .join(" ")
-    );
+    print!("{}", entry.metadata);
 
     if let Some(ref md5) = entry.md5 {
-        println!("MD5:          {}", md5);
+        println!("MD5:            {}", md5);
     }
     if !entry.eclasses.is_empty() {
         println!("Eclasses:");
@@ -121,6 +52,10 @@ fn print_entry(entry: &CacheEntry) {
         }
     }
 
+    println!("\n=== Summary ===");
+    println!("{}", MetadataRow::HEADER);
+    println!("{}", entry.metadata.row());
+
     println!("\n=== Serialized Back ===");
     print!("{}", entry.serialize());
 }
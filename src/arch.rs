@@ -0,0 +1,164 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::interner::{DefaultInterner, Interned, Interner};
+
+// The keywords listed in `profiles/arch.list` of the Gentoo repository.
+const KNOWN: &[&str] = &[
+    "alpha",
+    "amd64",
+    "arm",
+    "arm64",
+    "hppa",
+    "loong",
+    "m68k",
+    "mips",
+    "ppc",
+    "ppc64",
+    "riscv",
+    "s390",
+    "sparc",
+    "x86",
+    "amd64-linux",
+    "arm-linux",
+    "arm64-linux",
+    "ppc64-linux",
+    "riscv-linux",
+    "x86-linux",
+    "arm64-macos",
+    "ppc-macos",
+    "x86-macos",
+    "x64-macos",
+    "x64-solaris",
+    "x86-solaris",
+];
+
+/// An architecture name from a `KEYWORDS` entry.
+///
+/// Prefix keywords such as `amd64-linux` or `x64-macos` name a CPU and an
+/// operating system; [`Arch::cpu`] and [`Arch::os`] split them. Names
+/// outside the canonical Gentoo list are accepted and reported by
+/// [`Arch::is_known`].
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::Arch;
+///
+/// let arch: Arch = Arch::new("ppc-aix");
+/// assert_eq!(arch.cpu(), "ppc");
+/// assert_eq!(arch.os(), Some("aix"));
+/// assert!(!arch.is_known());
+///
+/// let arch: Arch = Arch::new("amd64");
+/// assert!(arch.is_known() && !arch.is_prefix());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arch<I = DefaultInterner>
+where
+    I: Interner,
+{
+    name: Interned<I>,
+}
+
+impl<I: Interner> Arch<I> {
+    /// Wrap an architecture name. No validation is performed.
+    pub fn new(name: &str) -> Self {
+        Arch {
+            name: Interned::intern(name),
+        }
+    }
+
+    /// The full architecture name, e.g. `amd64-linux`.
+    pub fn as_str(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The CPU part: the whole name, or the part before the `-` of a
+    /// prefix keyword.
+    pub fn cpu(&self) -> &str {
+        self.split().0
+    }
+
+    /// The operating system of a prefix keyword, e.g. `macos` for
+    /// `x64-macos`. `None` for plain Gentoo Linux arches.
+    pub fn os(&self) -> Option<&str> {
+        self.split().1
+    }
+
+    /// Whether this is a prefix keyword (`cpu-os`).
+    pub fn is_prefix(&self) -> bool {
+        self.os().is_some()
+    }
+
+    /// Whether the name is in the canonical Gentoo arch list.
+    pub fn is_known(&self) -> bool {
+        KNOWN.contains(&self.as_str())
+    }
+
+    /// The canonical Gentoo arch list, plain arches first.
+    pub fn known() -> &'static [&'static str] {
+        KNOWN
+    }
+
+    fn split(&self) -> (&str, Option<&str>) {
+        match self.as_str().split_once('-') {
+            Some((cpu, os)) => (cpu, Some(os)),
+            None => (self.as_str(), None),
+        }
+    }
+}
+
+impl<I: Interner> fmt::Display for Arch<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<I: Interner> Hash for Arch<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl<I: Interner> PartialEq<str> for Arch<I> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<I: Interner> PartialEq<&str> for Arch<I> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_prefix_arches() {
+        for (name, cpu, os) in [
+            ("amd64-linux", "amd64", Some("linux")),
+            ("x64-macos", "x64", Some("macos")),
+            ("ppc-aix", "ppc", Some("aix")),
+            ("amd64", "amd64", None),
+        ] {
+            let arch: Arch = Arch::new(name);
+            assert_eq!(arch.cpu(), cpu);
+            assert_eq!(arch.os(), os);
+            assert_eq!(arch.is_prefix(), os.is_some());
+        }
+    }
+
+    #[test]
+    fn known_arches() {
+        assert!(Arch::<DefaultInterner>::new("x64-solaris").is_known());
+        assert!(Arch::<DefaultInterner>::new("riscv").is_known());
+        assert!(!Arch::<DefaultInterner>::new("pdp11").is_known());
+        assert_eq!(Arch::<DefaultInterner>::new("pdp11"), "pdp11");
+    }
+}
@@ -0,0 +1,464 @@
+use std::fmt;
+
+use crate::required_use::RequiredUseExpr;
+use crate::use_flags::UseFlagSet;
+
+/// A USE flag together with a required state.
+///
+/// Used both for the conditions and effects of an [`Implication`] and for
+/// the flag adjustments reported by [`AutoEnforce::enforce`].
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UseFlagState {
+    /// Flag name.
+    pub name: String,
+    /// `true` if the flag must be enabled, `false` if it must be disabled.
+    pub enabled: bool,
+}
+
+impl UseFlagState {
+    pub(crate) fn new(name: &str, enabled: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            enabled,
+        }
+    }
+
+    fn negated(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            enabled: !self.enabled,
+        }
+    }
+
+    pub(crate) fn holds(&self, use_flags: &UseFlagSet) -> bool {
+        use_flags.is_enabled(&self.name) == self.enabled
+    }
+}
+
+impl fmt::Display for UseFlagState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.enabled {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "!{}", self.name)
+        }
+    }
+}
+
+/// A flattened `REQUIRED_USE` constraint in GLEP 73 form.
+///
+/// When every state in `conditions` holds, the solver enforces `effect`.
+/// An implication without conditions is unconditional.
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints)
+/// and [GLEP 73](https://www.gentoo.org/glep/glep-0073.html#processing-algorithm).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Implication {
+    /// Flag states that must all hold for the implication to apply.
+    pub conditions: Vec<UseFlagState>,
+    /// The flag state enforced when the implication applies.
+    pub effect: UseFlagState,
+}
+
+impl Implication {
+    fn applies(&self, use_flags: &UseFlagSet) -> bool {
+        self.conditions.iter().all(|c| c.holds(use_flags))
+    }
+
+    /// Convert back into a (nested) `REQUIRED_USE` expression.
+    pub fn to_expr(&self) -> RequiredUseExpr {
+        let mut expr = RequiredUseExpr::Flag {
+            name: self.effect.name.clone(),
+            negated: !self.effect.enabled,
+        };
+        for cond in self.conditions.iter().rev() {
+            expr = RequiredUseExpr::UseConditional {
+                flag: cond.name.clone(),
+                negated: !cond.enabled,
+                entries: vec![expr],
+            };
+        }
+        expr
+    }
+}
+
+impl fmt::Display for Implication {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_expr())
+    }
+}
+
+/// Reason why a `REQUIRED_USE` constraint cannot be auto-enforced.
+///
+/// Indices refer to [`AutoEnforce::implications`].
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnforceIssue {
+    /// An `||`, `^^` or `??` group contains something other than plain flags.
+    UnsupportedNesting(String),
+    /// The implication's effect negates one of its own conditions.
+    SelfConflict(usize),
+    /// Two implications can apply together but enforce opposite states.
+    Conflict {
+        /// Index of the earlier implication.
+        first: usize,
+        /// Index of the later implication.
+        second: usize,
+    },
+    /// A later implication's effect can make an earlier implication apply
+    /// after it was already processed, so one pass is not enough.
+    BackAlteration {
+        /// Index of the implication whose condition is altered.
+        earlier: usize,
+        /// Index of the implication whose effect alters it.
+        later: usize,
+    },
+}
+
+impl fmt::Display for EnforceIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnforceIssue::UnsupportedNesting(group) => {
+                write!(f, "unsupported nesting in group: {group}")
+            }
+            EnforceIssue::SelfConflict(i) => write!(f, "implication {i} conflicts with itself"),
+            EnforceIssue::Conflict { first, second } => {
+                write!(f, "implications {first} and {second} conflict")
+            }
+            EnforceIssue::BackAlteration { earlier, later } => {
+                write!(f, "implication {later} alters the condition of {earlier}")
+            }
+        }
+    }
+}
+
+/// GLEP 73 analysis of a `REQUIRED_USE` constraint.
+///
+/// The constraint is flattened into an ordered list of [`Implication`]s,
+/// which are then checked for the conditions under which a package manager
+/// may solve them automatically in a single pass: no unsupported nesting,
+/// no self-conflicting or mutually conflicting implications, and no later
+/// implication altering the condition of an earlier one.
+///
+/// See [GLEP 73](https://www.gentoo.org/glep/glep-0073.html)
+/// and [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoEnforce {
+    implications: Vec<Implication>,
+    issues: Vec<EnforceIssue>,
+}
+
+impl AutoEnforce {
+    /// Flatten and verify a `REQUIRED_USE` constraint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{AutoEnforce, RequiredUseExpr, UseFlagSet};
+    ///
+    /// let expr = RequiredUseExpr::parse("^^ ( gtk qt5 )").unwrap();
+    /// let analysis = AutoEnforce::analyze(&expr);
+    /// assert!(analysis.is_enforceable());
+    ///
+    /// let mut flags = UseFlagSet::parse("gtk qt5");
+    /// let changes = analysis.enforce(&mut flags);
+    /// assert_eq!(changes.len(), 1);
+    /// assert!(expr.is_satisfied(&flags));
+    /// ```
+    pub fn analyze(expr: &RequiredUseExpr) -> Self {
+        let mut implications = Vec::new();
+        let mut issues = Vec::new();
+        flatten(expr, &[], &mut implications, &mut issues);
+        verify(&implications, &mut issues);
+        Self {
+            implications,
+            issues,
+        }
+    }
+
+    /// Whether the constraint can be enforced deterministically.
+    pub fn is_enforceable(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The flattened implications, in solving order.
+    pub fn implications(&self) -> &[Implication] {
+        &self.implications
+    }
+
+    /// Problems preventing automatic enforcement.
+    pub fn issues(&self) -> &[EnforceIssue] {
+        &self.issues
+    }
+
+    /// The transformed constraint as a `REQUIRED_USE` expression.
+    ///
+    /// Each implication becomes a chain of nested USE-conditional groups,
+    /// e.g. `|| ( a b )` becomes `!b? ( a )`.
+    pub fn to_expr(&self) -> RequiredUseExpr {
+        let mut entries: Vec<RequiredUseExpr> =
+            self.implications.iter().map(Implication::to_expr).collect();
+        if entries.len() == 1 {
+            entries.remove(0)
+        } else {
+            RequiredUseExpr::All(entries)
+        }
+    }
+
+    /// Apply the implications to `use_flags` in a single pass.
+    ///
+    /// Returns the flag adjustments that were made, in order. The result
+    /// is only guaranteed to satisfy the constraint when
+    /// [`is_enforceable`](Self::is_enforceable) returns `true`.
+    pub fn enforce(&self, use_flags: &mut UseFlagSet) -> Vec<UseFlagState> {
+        let mut changes = Vec::new();
+        for imp in &self.implications {
+            if imp.applies(use_flags) && use_flags.set(&imp.effect.name, imp.effect.enabled) {
+                changes.push(imp.effect.clone());
+            }
+        }
+        changes
+    }
+}
+
+/// Flatten `expr` into implications, prefixing `conditions` to each.
+fn flatten(
+    expr: &RequiredUseExpr,
+    conditions: &[UseFlagState],
+    out: &mut Vec<Implication>,
+    issues: &mut Vec<EnforceIssue>,
+) {
+    match expr {
+        RequiredUseExpr::Flag { name, negated } => out.push(Implication {
+            conditions: conditions.to_vec(),
+            effect: UseFlagState::new(name, !negated),
+        }),
+        RequiredUseExpr::All(entries) => {
+            for entry in entries {
+                flatten(entry, conditions, out, issues);
+            }
+        }
+        RequiredUseExpr::UseConditional {
+            flag,
+            negated,
+            entries,
+        } => {
+            let mut inner = conditions.to_vec();
+            inner.push(UseFlagState::new(flag, !negated));
+            for entry in entries {
+                flatten(entry, &inner, out, issues);
+            }
+        }
+        RequiredUseExpr::AnyOf(entries) => {
+            if let Some(states) = group_states(expr, entries, issues) {
+                any_of(&states, conditions, out);
+            }
+        }
+        RequiredUseExpr::AtMostOne(entries) => {
+            if let Some(states) = group_states(expr, entries, issues) {
+                at_most_one(&states, conditions, out);
+            }
+        }
+        RequiredUseExpr::ExactlyOne(entries) => {
+            if let Some(states) = group_states(expr, entries, issues) {
+                any_of(&states, conditions, out);
+                at_most_one(&states, conditions, out);
+            }
+        }
+    }
+}
+
+/// Collect the flag states of an n-ary group, which may only hold flags.
+fn group_states(
+    group: &RequiredUseExpr,
+    entries: &[RequiredUseExpr],
+    issues: &mut Vec<EnforceIssue>,
+) -> Option<Vec<UseFlagState>> {
+    let states: Option<Vec<UseFlagState>> = entries
+        .iter()
+        .map(|e| match e {
+            RequiredUseExpr::Flag { name, negated } => Some(UseFlagState::new(name, !negated)),
+            _ => None,
+        })
+        .collect();
+    if states.is_none() {
+        issues.push(EnforceIssue::UnsupportedNesting(group.to_string()));
+    }
+    states
+}
+
+/// `|| ( a b c )` becomes `!b? ( !c? ( a ) )`.
+fn any_of(states: &[UseFlagState], conditions: &[UseFlagState], out: &mut Vec<Implication>) {
+    let Some((first, rest)) = states.split_first() else {
+        return;
+    };
+    let mut conds = conditions.to_vec();
+    conds.extend(rest.iter().map(UseFlagState::negated));
+    out.push(Implication {
+        conditions: conds,
+        effect: first.clone(),
+    });
+}
+
+/// `?? ( a b c )` becomes `a? ( !b !c ) b? ( !c )`.
+fn at_most_one(states: &[UseFlagState], conditions: &[UseFlagState], out: &mut Vec<Implication>) {
+    for (i, state) in states.iter().enumerate() {
+        for other in &states[i + 1..] {
+            let mut conds = conditions.to_vec();
+            conds.push(state.clone());
+            out.push(Implication {
+                conditions: conds,
+                effect: other.negated(),
+            });
+        }
+    }
+}
+
+/// Whether two condition lists can hold at the same time.
+fn compatible(a: &[UseFlagState], b: &[UseFlagState]) -> bool {
+    a.iter()
+        .all(|x| b.iter().all(|y| x.name != y.name || x.enabled == y.enabled))
+}
+
+fn verify(implications: &[Implication], issues: &mut Vec<EnforceIssue>) {
+    for (i, imp) in implications.iter().enumerate() {
+        if imp.conditions.contains(&imp.effect.negated()) {
+            issues.push(EnforceIssue::SelfConflict(i));
+        }
+    }
+
+    for (i, earlier) in implications.iter().enumerate() {
+        for (j, later) in implications.iter().enumerate().skip(i + 1) {
+            if !compatible(&earlier.conditions, &later.conditions) {
+                continue;
+            }
+            if earlier.effect == later.effect.negated() {
+                issues.push(EnforceIssue::Conflict {
+                    first: i,
+                    second: j,
+                });
+            } else if earlier.conditions.contains(&later.effect)
+                && earlier.effect != later.effect
+                && !later.conditions.contains(&earlier.effect)
+            {
+                issues.push(EnforceIssue::BackAlteration {
+                    earlier: i,
+                    later: j,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(input: &str) -> AutoEnforce {
+        AutoEnforce::analyze(&RequiredUseExpr::parse(input).unwrap())
+    }
+
+    #[test]
+    fn flatten_any_of() {
+        let a = analyze("|| ( a b c )");
+        assert_eq!(a.implications().len(), 1);
+        assert_eq!(a.to_expr().to_string(), "!b? ( !c? ( a ) )");
+        assert!(a.is_enforceable());
+    }
+
+    #[test]
+    fn flatten_at_most_one() {
+        let a = analyze("?? ( a b c )");
+        assert_eq!(a.to_expr().to_string(), "a? ( !b ) a? ( !c ) b? ( !c )");
+        assert!(a.is_enforceable());
+    }
+
+    #[test]
+    fn flatten_conditional() {
+        let a = analyze("ssl? ( || ( gnutls openssl ) )");
+        assert_eq!(a.to_expr().to_string(), "ssl? ( !openssl? ( gnutls ) )");
+    }
+
+    #[test]
+    fn enforce_exactly_one() {
+        let a = analyze("^^ ( a b c )");
+        assert!(a.is_enforceable());
+
+        let mut flags = UseFlagSet::new();
+        let changes = a.enforce(&mut flags);
+        assert_eq!(changes, vec![UseFlagState::new("a", true)]);
+
+        let mut flags = UseFlagSet::parse("a b c");
+        a.enforce(&mut flags);
+        assert_eq!(flags.to_string(), "a");
+    }
+
+    #[test]
+    fn enforce_result_satisfies_constraint() {
+        let input = "ssl? ( ^^ ( gnutls openssl ) ) !test? ( !doc )";
+        let expr = RequiredUseExpr::parse(input).unwrap();
+        let a = AutoEnforce::analyze(&expr);
+        assert!(a.is_enforceable());
+        for use_str in [
+            "",
+            "ssl",
+            "ssl gnutls openssl",
+            "doc",
+            "doc test",
+            "ssl doc",
+        ] {
+            let mut flags = UseFlagSet::parse(use_str);
+            a.enforce(&mut flags);
+            assert!(expr.is_satisfied(&flags), "USE={use_str}");
+        }
+    }
+
+    #[test]
+    fn conflict_detected() {
+        let a = analyze("a? ( b ) c? ( !b )");
+        assert!(!a.is_enforceable());
+        assert_eq!(
+            a.issues(),
+            &[EnforceIssue::Conflict {
+                first: 0,
+                second: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn exclusive_conditions_do_not_conflict() {
+        let a = analyze("a? ( b ) !a? ( !b )");
+        assert!(a.is_enforceable());
+    }
+
+    #[test]
+    fn self_conflict_detected() {
+        let a = analyze("a? ( !a )");
+        assert_eq!(a.issues(), &[EnforceIssue::SelfConflict(0)]);
+    }
+
+    #[test]
+    fn back_alteration_detected() {
+        let a = analyze("b? ( c ) a? ( b )");
+        assert_eq!(
+            a.issues(),
+            &[EnforceIssue::BackAlteration {
+                earlier: 0,
+                later: 1
+            }]
+        );
+        // Reordering fixes it.
+        assert!(analyze("a? ( b ) b? ( c )").is_enforceable());
+    }
+
+    #[test]
+    fn nested_group_unsupported() {
+        let a = analyze("|| ( a b? ( c ) )");
+        assert!(!a.is_enforceable());
+        assert!(matches!(a.issues()[0], EnforceIssue::UnsupportedNesting(_)));
+    }
+}
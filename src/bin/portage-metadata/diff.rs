@@ -0,0 +1,70 @@
+use portage_atom::Cpv;
+use portage_metadata::{MetadataRepo, RepoDiff, Result};
+
+use crate::json;
+
+/// Print the metadata changes between two trees; `Ok(false)` if they
+/// differ.
+pub fn run(old: &str, new: &str, json: bool) -> Result<bool> {
+    let old = MetadataRepo::new(old).load()?;
+    let new = MetadataRepo::new(new).load()?;
+    let diff = RepoDiff::new(&old, &new);
+    if json {
+        println!("{}", to_json(&diff));
+    } else {
+        print!("{diff}");
+    }
+    Ok(diff.is_empty())
+}
+
+fn to_json(diff: &RepoDiff) -> String {
+    let list = |cpvs: &[Cpv]| {
+        let items: Vec<_> = cpvs
+            .iter()
+            .map(|cpv| json::string(&cpv.to_string()))
+            .collect();
+        format!("[{}]", items.join(","))
+    };
+    let changed: Vec<_> = diff
+        .changed
+        .iter()
+        .map(|package| {
+            let changes: Vec<_> = package
+                .changes
+                .iter()
+                .map(|change| {
+                    format!(
+                        "{{\"key\":{},\"old\":{},\"new\":{}}}",
+                        json::string(change.key.as_str()),
+                        json::string(&change.old),
+                        json::string(&change.new)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"cpv\":{},\"changes\":[{}]}}",
+                json::string(&package.cpv.to_string()),
+                changes.join(",")
+            )
+        })
+        .collect();
+    format!(
+        "{{\"added\":{},\"removed\":{},\"changed\":[{}]}}",
+        list(&diff.added),
+        list(&diff.removed),
+        changed.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_diff() {
+        assert_eq!(
+            to_json(&RepoDiff::default()),
+            "{\"added\":[],\"removed\":[],\"changed\":[]}"
+        );
+    }
+}
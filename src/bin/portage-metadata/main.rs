@@ -0,0 +1,58 @@
+//! Inspect and validate ebuild metadata caches.
+
+use std::env;
+use std::process::ExitCode;
+
+mod diff;
+mod json;
+mod regen_check;
+mod show;
+mod stats;
+mod validate;
+
+const USAGE: &str = "\
+usage: portage-metadata <command> [args]
+
+commands:
+  show <cache-file>...  pretty-print md5-cache entries
+  validate <repo>       check every cache entry of a repository
+  diff [--json] <old-repo> <new-repo>
+                        list metadata changes between two trees
+  regen-check <repo> [<master-repo>...]
+                        list out-of-date cache entries
+  stats [--json] <repo> count EAPIs, keywords and licenses
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, rest)) => match (command.as_str(), rest) {
+            ("show", files @ [_, ..]) => show::run(files),
+            ("validate", [repo]) => validate::run(repo),
+            ("diff", [old, new]) => diff::run(old, new, false),
+            ("diff", [flag, old, new]) if flag == "--json" => diff::run(old, new, true),
+            ("stats", [repo]) => stats::run(repo, false),
+            ("stats", [flag, repo]) if flag == "--json" => stats::run(repo, true),
+            ("regen-check", [repo, masters @ ..]) => regen_check::run(repo, masters),
+            ("-h" | "--help" | "help", _) => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => return usage(),
+        },
+        None => return usage(),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("portage-metadata: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn usage() -> ExitCode {
+    eprint!("{USAGE}");
+    ExitCode::from(2)
+}
@@ -0,0 +1,16 @@
+use portage_metadata::{MetadataRepo, RegenPlan, Result};
+
+/// List the stale cache entries of `repo`, resolving eclasses through
+/// `masters`; `Ok(false)` if any is stale.
+pub fn run(repo: &str, masters: &[String]) -> Result<bool> {
+    let repo = MetadataRepo::new(repo);
+    let masters: Vec<_> = masters.iter().map(MetadataRepo::new).collect();
+    let plan = RegenPlan::new(&repo, &masters)?;
+    for entry in &plan.stale {
+        println!("{entry}");
+    }
+    if !plan.is_empty() {
+        eprintln!("{} stale cache entries", plan.stale.len());
+    }
+    Ok(plan.is_empty())
+}
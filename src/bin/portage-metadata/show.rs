@@ -0,0 +1,37 @@
+use std::fs;
+
+use portage_metadata::{CacheEntry, Error, Result};
+
+/// Print each cache file; `Ok(false)` if any failed to parse.
+pub fn run(files: &[String]) -> Result<bool> {
+    let mut ok = true;
+    for (i, file) in files.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let contents = fs::read_to_string(file).map_err(|e| Error::io(file, e))?;
+        match CacheEntry::parse(&contents) {
+            Ok(entry) => {
+                if files.len() > 1 {
+                    println!("==> {file} <==");
+                }
+                print_entry(&entry);
+            }
+            Err(e) => {
+                eprintln!("{file}: {e}");
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+fn print_entry(entry: &CacheEntry) {
+    print!("{}", entry.metadata);
+    if let Some(md5) = &entry.md5 {
+        println!("{:<16}{md5}", "_md5_:");
+    }
+    for (name, checksum) in &entry.eclasses {
+        println!("{:<16}{name} {checksum}", "_eclasses_:");
+    }
+}
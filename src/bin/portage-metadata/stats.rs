@@ -0,0 +1,62 @@
+use portage_metadata::{MetadataRepo, RepoStats, Result};
+
+use crate::json;
+
+/// Print the EAPI, keyword and license counts of a repository.
+pub fn run(repo: &str, json: bool) -> Result<bool> {
+    let entries = MetadataRepo::new(repo).load()?;
+    let stats = RepoStats::new(entries.values());
+    if json {
+        println!("{}", to_json(&stats));
+    } else {
+        print!("{stats}");
+    }
+    Ok(true)
+}
+
+fn to_json(stats: &RepoStats) -> String {
+    let counts = |counts: &mut dyn Iterator<Item = (&str, usize)>| {
+        let items: Vec<_> = counts
+            .map(|(name, count)| format!("{}:{count}", json::string(name)))
+            .collect();
+        format!("{{{}}}", items.join(","))
+    };
+    let keywords: Vec<_> = stats
+        .keywords
+        .iter()
+        .map(|(arch, coverage)| {
+            format!(
+                "{}:{{\"stable\":{},\"testing\":{}}}",
+                json::string(arch),
+                coverage.stable,
+                coverage.testing
+            )
+        })
+        .collect();
+    format!(
+        "{{\"entries\":{},\"eapis\":{},\"keywords\":{{{}}},\"licenses\":{}}}",
+        stats.entries,
+        counts(&mut stats.eapis.iter().map(|(eapi, &n)| (eapi.as_str(), n))),
+        keywords.join(","),
+        counts(&mut stats.licenses_by_count().into_iter())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portage_metadata::CacheEntry;
+
+    #[test]
+    fn json_stats() {
+        let entry =
+            CacheEntry::parse("EAPI=8\nDESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64\nLICENSE=MIT\n")
+                .unwrap();
+        assert_eq!(
+            to_json(&RepoStats::new([&entry])),
+            "{\"entries\":1,\"eapis\":{\"8\":1},\
+             \"keywords\":{\"amd64\":{\"stable\":0,\"testing\":1}},\
+             \"licenses\":{\"MIT\":1}}"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use portage_metadata::{MetadataRepo, Result, Strictness};
+
+/// Check every cache entry of `repo` strictly; `Ok(false)` if any has
+/// errors. Warnings are reported but do not fail the run.
+pub fn run(repo: &str) -> Result<bool> {
+    let repo = MetadataRepo::new(repo);
+    let (mut errors, mut warnings) = (0, 0);
+    let cpvs = repo.cpvs()?;
+    for cpv in &cpvs {
+        let report = repo.read_report(cpv, Strictness::Strict)?;
+        for error in &report.errors {
+            println!("error: {cpv}: {error} [{}]", error.code());
+        }
+        for warning in &report.warnings {
+            println!("warning: {cpv}: {warning}");
+        }
+        errors += report.errors.len();
+        warnings += report.warnings.len();
+    }
+    println!(
+        "{} entries, {errors} errors, {warnings} warnings",
+        cpvs.len()
+    );
+    Ok(errors == 0)
+}
@@ -0,0 +1,321 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::interner::Interner;
+use crate::license::LicenseExpr;
+use crate::license_dir::LicenseDir;
+use crate::metadata::EbuildMetadata;
+use crate::restrict::{RestrictExpr, RestrictKind};
+use crate::use_flags::UseFlagSet;
+
+/// How freely a license lets binaries be passed on, from most to least
+/// permissive.
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LicenseClass {
+    /// A free software license.
+    Free,
+    /// Not free, but binaries may be redistributed.
+    Redistributable,
+    /// An end-user license agreement forbidding redistribution.
+    Eula,
+    /// In none of the groups; redistribution is not known to be allowed.
+    Unknown,
+}
+
+impl LicenseClass {
+    /// Whether binaries under this license may be redistributed.
+    pub fn is_redistributable(self) -> bool {
+        self <= LicenseClass::Redistributable
+    }
+}
+
+impl fmt::Display for LicenseClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LicenseClass::Free => "free",
+            LicenseClass::Redistributable => "binary-redistributable",
+            LicenseClass::Eula => "EULA",
+            LicenseClass::Unknown => "unknown",
+        })
+    }
+}
+
+/// Classifies licenses by the `profiles/license_groups` groups they
+/// belong to.
+///
+/// By default the `FREE`, `BINARY-REDISTRIBUTABLE` and `EULA` groups of
+/// the Gentoo repository are used; [`LicenseClasses::from_groups`] takes
+/// other names for repositories defining their own. A license in several
+/// groups gets the most permissive class.
+///
+/// See [PMS 4.6](https://projects.gentoo.org/pms/9/pms.html#the-licenses-directory)
+/// and [PMS 7.3.6](https://projects.gentoo.org/pms/9/pms.html#restrict).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{CacheEntry, LicenseClass, LicenseClasses, LicenseDir, UseFlagSet};
+///
+/// let mut dir = LicenseDir::new();
+/// dir.add_groups("FREE MIT\nBINARY-REDISTRIBUTABLE @FREE freedist\nEULA nvidia\n");
+/// let classes = LicenseClasses::new(&dir);
+/// assert_eq!(classes.classify("freedist"), LicenseClass::Redistributable);
+///
+/// let entry = CacheEntry::parse(
+///     "DESCRIPTION=x\nSLOT=0\nIUSE=bindist\n\
+///      LICENSE=MIT !bindist? ( nvidia )\nRESTRICT=!bindist? ( bindist )\n",
+/// )
+/// .unwrap();
+/// let report = classes.bindist(&entry.metadata, &UseFlagSet::new());
+/// assert!(!report.may_redistribute());
+/// assert_eq!(report.blocking(), ["nvidia"]);
+/// assert!(report.is_consistent());
+///
+/// let report = classes.bindist(&entry.metadata, &UseFlagSet::parse("bindist"));
+/// assert!(report.may_redistribute());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LicenseClasses {
+    free: BTreeSet<String>,
+    redistributable: BTreeSet<String>,
+    eula: BTreeSet<String>,
+}
+
+impl LicenseClasses {
+    /// Classify with the `FREE`, `BINARY-REDISTRIBUTABLE` and `EULA`
+    /// groups of `dir`.
+    pub fn new(dir: &LicenseDir) -> Self {
+        Self::from_groups(dir, "FREE", "BINARY-REDISTRIBUTABLE", "EULA")
+    }
+
+    /// Classify with the named groups of `dir`; nested `@group`
+    /// references are resolved and unknown groups are empty.
+    pub fn from_groups(dir: &LicenseDir, free: &str, redistributable: &str, eula: &str) -> Self {
+        let expand = |group| {
+            dir.expand_group(group)
+                .into_iter()
+                .map(str::to_string)
+                .collect()
+        };
+        LicenseClasses {
+            free: expand(free),
+            redistributable: expand(redistributable),
+            eula: expand(eula),
+        }
+    }
+
+    /// The class of `license`.
+    pub fn classify(&self, license: &str) -> LicenseClass {
+        if self.free.contains(license) {
+            LicenseClass::Free
+        } else if self.redistributable.contains(license) {
+            LicenseClass::Redistributable
+        } else if self.eula.contains(license) {
+            LicenseClass::Eula
+        } else {
+            LicenseClass::Unknown
+        }
+    }
+
+    /// The licenses `expr` imposes under `use_flags`, with their classes,
+    /// in source order without duplicates.
+    ///
+    /// Of each `||` group the alternative whose least permissive license
+    /// is most permissive is taken, the first one on ties.
+    pub fn evaluate<'a, I: Interner>(
+        &self,
+        expr: &'a LicenseExpr<I>,
+        use_flags: &UseFlagSet,
+    ) -> Vec<(&'a str, LicenseClass)> {
+        let mut out = Vec::new();
+        self.select(expr, use_flags, &mut out);
+        let mut seen = BTreeSet::new();
+        out.retain(|(name, _)| seen.insert(*name));
+        out
+    }
+
+    fn select<'a, I: Interner>(
+        &self,
+        expr: &'a LicenseExpr<I>,
+        use_flags: &UseFlagSet,
+        out: &mut Vec<(&'a str, LicenseClass)>,
+    ) {
+        match expr {
+            LicenseExpr::License(name) => out.push((name, self.classify(name))),
+            LicenseExpr::All(entries) => {
+                for entry in entries {
+                    self.select(entry, use_flags, out);
+                }
+            }
+            LicenseExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => {
+                if use_flags.is_enabled(flag) != *negated {
+                    for entry in entries {
+                        self.select(entry, use_flags, out);
+                    }
+                }
+            }
+            LicenseExpr::AnyOf(entries) => {
+                let best = entries
+                    .iter()
+                    .map(|entry| {
+                        let mut chosen = Vec::new();
+                        self.select(entry, use_flags, &mut chosen);
+                        chosen
+                    })
+                    .filter(|chosen| !chosen.is_empty())
+                    .min_by_key(|chosen| chosen.iter().map(|&(_, class)| class).max());
+                out.extend(best.into_iter().flatten());
+            }
+        }
+    }
+
+    /// Whether binaries of `metadata` built with `use_flags` may be
+    /// redistributed, judging by `LICENSE` and `RESTRICT=bindist`.
+    pub fn bindist<'a, I: Interner>(
+        &self,
+        metadata: &'a EbuildMetadata<I>,
+        use_flags: &UseFlagSet,
+    ) -> BindistReport<'a> {
+        let licenses = metadata
+            .license
+            .as_ref()
+            .map(|expr| self.evaluate(expr, use_flags))
+            .unwrap_or_default();
+        let restricted = RestrictExpr::applies(
+            &metadata.restrict,
+            &RestrictKind::Other("bindist".to_string()),
+            use_flags,
+        );
+        let bindist_flag = metadata
+            .iuse
+            .iter()
+            .any(|iuse| iuse.name() == "bindist")
+            .then(|| use_flags.is_enabled("bindist"));
+        BindistReport {
+            licenses,
+            restricted,
+            bindist_flag,
+        }
+    }
+}
+
+/// The redistribution verdict for one package configuration, from
+/// [`LicenseClasses::bindist`].
+///
+/// See [PMS 7.3.6](https://projects.gentoo.org/pms/9/pms.html#restrict).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindistReport<'a> {
+    /// The licenses in effect, with their classes.
+    pub licenses: Vec<(&'a str, LicenseClass)>,
+    /// Whether `RESTRICT=bindist` is in effect.
+    pub restricted: bool,
+    /// The state of the `bindist` USE flag, or `None` if it is not in
+    /// `IUSE`.
+    pub bindist_flag: Option<bool>,
+}
+
+impl BindistReport<'_> {
+    /// Whether binaries may be redistributed: every license allows it and
+    /// `RESTRICT=bindist` is not in effect.
+    pub fn may_redistribute(&self) -> bool {
+        !self.restricted && self.blocking().is_empty()
+    }
+
+    /// The licenses forbidding redistribution.
+    pub fn blocking(&self) -> Vec<&str> {
+        self.licenses
+            .iter()
+            .filter(|(_, class)| !class.is_redistributable())
+            .map(|&(name, _)| name)
+            .collect()
+    }
+
+    /// Whether `RESTRICT` agrees with `LICENSE`: a configuration under a
+    /// non-redistributable license must restrict `bindist`, and one built
+    /// with `USE=bindist` must not.
+    pub fn is_consistent(&self) -> bool {
+        let blocked = !self.blocking().is_empty();
+        (!blocked || self.restricted) && !(self.bindist_flag == Some(true) && self.restricted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheEntry;
+
+    fn classes() -> LicenseClasses {
+        let mut dir = LicenseDir::new();
+        dir.add_groups("FREE MIT GPL-2\nBINARY-REDISTRIBUTABLE @FREE freedist\nEULA nvidia\n");
+        LicenseClasses::new(&dir)
+    }
+
+    #[test]
+    fn any_of_prefers_redistributable() {
+        let classes = classes();
+        let expr = LicenseExpr::all([
+            LicenseExpr::parse("|| ( nvidia freedist )").unwrap(),
+            LicenseExpr::any_of([
+                LicenseExpr::all([LicenseExpr::license("MIT"), LicenseExpr::license("foo")]),
+                LicenseExpr::license("GPL-2"),
+            ]),
+            LicenseExpr::license("MIT"),
+        ]);
+        assert_eq!(
+            classes.evaluate(&expr, &UseFlagSet::new()),
+            [
+                ("freedist", LicenseClass::Redistributable),
+                ("GPL-2", LicenseClass::Free),
+                ("MIT", LicenseClass::Free),
+            ]
+        );
+        let expr = LicenseExpr::parse("|| ( doc? ( foo ) nvidia )").unwrap();
+        assert_eq!(
+            classes.evaluate(&expr, &UseFlagSet::new()),
+            [("nvidia", LicenseClass::Eula)]
+        );
+
+        let mut dir = LicenseDir::new();
+        dir.add_groups("OSI MIT\nMINE @OSI\n");
+        let custom = LicenseClasses::from_groups(&dir, "MINE", "NONE", "NONE");
+        assert_eq!(custom.classify("MIT"), LicenseClass::Free);
+        assert_eq!(custom.classify("GPL-2"), LicenseClass::Unknown);
+    }
+
+    #[test]
+    fn restrict_cross_check() {
+        let classes = classes();
+        let report = |text: &str, flags: &str| {
+            let entry = CacheEntry::parse(&format!("DESCRIPTION=x\nSLOT=0\n{text}")).unwrap();
+            let report = classes.bindist(&entry.metadata, &UseFlagSet::parse(flags));
+            (
+                report.may_redistribute(),
+                report.is_consistent(),
+                report.bindist_flag,
+            )
+        };
+        assert_eq!(report("LICENSE=MIT\n", ""), (true, true, None));
+        assert_eq!(report("LICENSE=nvidia\n", ""), (false, false, None));
+        assert_eq!(
+            report("LICENSE=nvidia\nRESTRICT=bindist\n", ""),
+            (false, true, None)
+        );
+        assert_eq!(
+            report("IUSE=bindist\nLICENSE=MIT\nRESTRICT=bindist\n", "bindist"),
+            (false, false, Some(true))
+        );
+        assert_eq!(
+            report(
+                "IUSE=bindist\nLICENSE=MIT\nRESTRICT=!bindist? ( bindist )\n",
+                "bindist"
+            ),
+            (true, true, Some(true))
+        );
+    }
+}
@@ -0,0 +1,144 @@
+/// A kind of token whose known values map to bit indices.
+pub(crate) trait BitIndex: Sized + 'static {
+    /// Every known value, in bit order.
+    const KNOWN: &'static [Self];
+
+    /// The bit of this value, `None` if it has no dedicated variant.
+    fn bit_index(&self) -> Option<u8>;
+}
+
+/// Define a `Copy` set of the known values of a [`BitIndex`] type, one bit
+/// each in an integer of type `$bits`.
+///
+/// Values without a bit cannot be stored and are ignored. Iteration
+/// follows [`BitIndex::KNOWN`]; `Display` joins the values with spaces and
+/// shows an empty set as `$empty`.
+macro_rules! bit_set {
+    ($(#[$attr:meta])* $set:ident($bits:ty), $kind:ty, empty = $empty:literal) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $set($bits);
+
+        impl $set {
+            /// The empty set.
+            pub fn new() -> Self {
+                Self(0)
+            }
+
+            /// The set of every known value.
+            pub fn all() -> Self {
+                Self(<$bits>::MAX >> (<$bits>::BITS as usize - <$kind as BitIndex>::KNOWN.len()))
+            }
+
+            /// Whether `value` is in the set. Always `false` for values that
+            /// cannot be stored.
+            pub fn contains(&self, value: &$kind) -> bool {
+                Self::bit(value).is_some_and(|bit| self.0 & bit != 0)
+            }
+
+            /// Add `value`. Returns `false` if it was already present or
+            /// cannot be stored.
+            pub fn insert(&mut self, value: &$kind) -> bool {
+                match Self::bit(value) {
+                    Some(bit) if self.0 & bit == 0 => {
+                        self.0 |= bit;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+
+            /// Remove `value`. Returns `true` if it was present.
+            pub fn remove(&mut self, value: &$kind) -> bool {
+                let present = self.contains(value);
+                if let Some(bit) = Self::bit(value) {
+                    self.0 &= !bit;
+                }
+                present
+            }
+
+            /// Number of values in the set.
+            pub fn len(&self) -> usize {
+                self.0.count_ones() as usize
+            }
+
+            /// Whether the set is empty.
+            pub fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            /// Iterate over the values in the set.
+            pub fn iter(&self) -> impl Iterator<Item = $kind> + '_ {
+                <$kind as BitIndex>::KNOWN
+                    .iter()
+                    .filter(|value| self.contains(value))
+                    .cloned()
+            }
+
+            fn bit(value: &$kind) -> Option<$bits> {
+                value.bit_index().map(|index| 1 << index)
+            }
+        }
+
+        impl std::ops::BitOr for $set {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl std::ops::BitAnd for $set {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self {
+                Self(self.0 & rhs.0)
+            }
+        }
+
+        impl std::ops::Sub for $set {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 & !rhs.0)
+            }
+        }
+
+        impl<'a> FromIterator<&'a $kind> for $set {
+            fn from_iter<T: IntoIterator<Item = &'a $kind>>(iter: T) -> Self {
+                let mut set = Self::new();
+                for value in iter {
+                    set.insert(value);
+                }
+                set
+            }
+        }
+
+        impl FromIterator<$kind> for $set {
+            fn from_iter<T: IntoIterator<Item = $kind>>(iter: T) -> Self {
+                let mut set = Self::new();
+                for value in iter {
+                    set.insert(&value);
+                }
+                set
+            }
+        }
+
+        impl std::fmt::Display for $set {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                if self.is_empty() {
+                    return f.write_str($empty);
+                }
+                for (i, value) in self.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+pub(crate) use bit_set;
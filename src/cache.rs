@@ -1,23 +1,26 @@
-use crate::interner::{DefaultInterner, Interner};
-use portage_atom::{DepEntry, Slot};
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::interner::{DefaultInterner, Interned, Interner};
+use portage_atom::{Cpv, DepEntry, Slot};
+use smallvec::SmallVec;
 
 use crate::eapi::Eapi;
-use crate::error::{Error, Result};
-use crate::iuse::IUse;
-use crate::keyword::Keyword;
-use crate::license::LicenseExpr;
-use crate::metadata::EbuildMetadata;
+use crate::eapi_registry::EapiRegistry;
+use crate::error::{Error, Result, Span};
+use crate::keyword::Stability;
+use crate::metadata::{fnv1a, EbuildMetadata};
+use crate::metadata_key::MetadataKey;
+use crate::parse_report::{ParseReport, Strictness};
 use crate::phase::Phase;
-use crate::required_use::RequiredUseExpr;
-use crate::restrict::RestrictExpr;
-use crate::src_uri::SrcUriEntry;
 
 /// A parsed md5-cache entry.
 ///
 /// Represents a single file from `metadata/md5-cache/<category>/<package>-<version>`.
 /// Contains the full ebuild metadata plus cache-specific fields (`md5`, `eclasses`).
 ///
-/// See [PMS 14.2](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CacheEntry<I = DefaultInterner>
 where
@@ -32,10 +35,37 @@ where
     /// All transitively inherited eclasses with their checksums (from `_eclasses_`).
     ///
     /// Each tuple is `(eclass_name, md5_checksum)`.  Pairs are tab-separated
-    /// as described in [PMS 14.3](https://projects.gentoo.org/pms/latest/pms.html#md5-dict-cache-file-format).
-    pub eclasses: Vec<(String, String)>,
+    /// as described in [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+    /// Names are interned like `INHERITED`; checksums change with every
+    /// eclass edit and are kept as plain strings.
+    pub eclasses: Vec<(Interned<I>, String)>,
 }
 
+/// The keys of a flat_list cache file, one per line in this order.
+const FLAT_LIST_KEYS: [&str; 18] = [
+    "DEPEND",
+    "RDEPEND",
+    "SLOT",
+    "SRC_URI",
+    "RESTRICT",
+    "HOMEPAGE",
+    "LICENSE",
+    "DESCRIPTION",
+    "KEYWORDS",
+    "INHERITED",
+    "IUSE",
+    "REQUIRED_USE",
+    "PDEPEND",
+    "BDEPEND",
+    "EAPI",
+    "PROPERTIES",
+    "DEFINED_PHASES",
+    "IDEPEND",
+];
+
+/// Lines in a flat_list cache file, including the blank padding.
+const FLAT_LIST_LINES: usize = 22;
+
 /// Accumulator for key-value pairs before building a `CacheEntry`.
 ///
 /// Holds `&str` slices into the source data — no intermediate String
@@ -61,6 +91,8 @@ struct ParseState<'a> {
     defined_phases: &'a str,
     md5: Option<&'a str>,
     eclasses_raw: &'a str,
+    /// The whole input, when parsing text, for error locations.
+    source: Option<&'a str>,
 }
 
 impl<'a> ParseState<'a> {
@@ -86,6 +118,19 @@ impl<'a> ParseState<'a> {
             defined_phases: "",
             md5: None,
             eclasses_raw: "",
+            source: None,
+        }
+    }
+
+    /// Wrap an error from parsing `text`, a slice of the `field` value,
+    /// with the field name and location.
+    fn at(&self, field: &'static str, text: &'a str) -> impl Fn(Error) -> Error + 'a {
+        let source = self.source;
+        move |error| Error::Field {
+            field: field.to_string(),
+            text: text.to_string(),
+            span: source.and_then(|source| Span::of(source, text)),
+            source: Box::new(error),
         }
     }
 
@@ -115,143 +160,157 @@ impl<'a> ParseState<'a> {
         }
     }
 
-    fn finish<I: Interner>(self) -> Result<CacheEntry<I>> {
-        let eapi_val = if self.eapi.is_empty() {
-            Eapi::Zero
-        } else {
-            self.eapi
-                .parse::<Eapi>()
-                .map_err(|_| Error::InvalidEapi(self.eapi.to_string()))?
-        };
-
-        let description_val = self
-            .description
-            .ok_or_else(|| Error::MissingField("DESCRIPTION".to_string()))?
-            .to_string();
-
-        let slot_val = match self.slot {
-            Some(s) => parse_slot(s)?,
-            None => return Err(Error::MissingField("SLOT".to_string())),
-        };
-
-        let homepage_val: Vec<String> = if self.homepage.is_empty() {
-            Vec::new()
-        } else {
-            self.homepage
-                .split_whitespace()
-                .map(|s| s.to_string())
-                .collect()
-        };
-
-        let src_uri_val = if self.src_uri.is_empty() {
-            Vec::new()
-        } else {
-            SrcUriEntry::parse(self.src_uri)?
-        };
-
-        let license_val = if self.license.is_empty() {
-            None
-        } else {
-            Some(LicenseExpr::parse(self.license)?)
-        };
-
-        let keywords_val: Vec<Keyword<I>> = if self.keywords.is_empty() {
-            Vec::new()
-        } else {
-            self.keywords
-                .split_whitespace()
-                .map(|token| Keyword::parse(token))
-                .collect::<Result<_>>()?
-        };
-
-        let iuse_val: Vec<IUse<I>> = if self.iuse.is_empty() {
-            Vec::new()
-        } else {
-            self.iuse
-                .split_whitespace()
-                .map(|token| IUse::parse(token))
-                .collect::<Result<_>>()?
-        };
-
-        let required_use_val = if self.required_use.is_empty() {
-            None
-        } else {
-            Some(RequiredUseExpr::parse(self.required_use)?)
-        };
+    /// The value read for `key`: `None` for a missing `DESCRIPTION` or
+    /// `SLOT`, and for `INHERITED`, which md5-cache entries do not carry.
+    fn value(&self, key: MetadataKey) -> Option<&'a str> {
+        Some(match key {
+            MetadataKey::Eapi => self.eapi,
+            MetadataKey::Description => return self.description,
+            MetadataKey::Slot => return self.slot,
+            MetadataKey::Homepage => self.homepage,
+            MetadataKey::SrcUri => self.src_uri,
+            MetadataKey::License => self.license,
+            MetadataKey::Keywords => self.keywords,
+            MetadataKey::Iuse => self.iuse,
+            MetadataKey::RequiredUse => self.required_use,
+            MetadataKey::Restrict => self.restrict,
+            MetadataKey::Properties => self.properties,
+            MetadataKey::Depend => self.depend,
+            MetadataKey::Rdepend => self.rdepend,
+            MetadataKey::Bdepend => self.bdepend,
+            MetadataKey::Pdepend => self.pdepend,
+            MetadataKey::Idepend => self.idepend,
+            MetadataKey::Inherit => self.inherit,
+            MetadataKey::Inherited => return None,
+            MetadataKey::DefinedPhases => self.defined_phases,
+        })
+    }
 
-        let restrict_val = if self.restrict.is_empty() {
-            Vec::new()
-        } else {
-            RestrictExpr::parse(self.restrict)?
-        };
+    /// Collect the `KEY=VALUE` lines of a cache file.
+    fn read(input: &'a str) -> Self {
+        Self::read_mapped(input, |key, _| Some(key))
+    }
 
-        let properties_val = if self.properties.is_empty() {
-            Vec::new()
-        } else {
-            RestrictExpr::parse(self.properties)?
-        };
+    /// Collect the `KEY=VALUE` lines of a cache file, feeding each value
+    /// under the key `map` returns for it, if any.
+    fn read_mapped(
+        input: &'a str,
+        mut map: impl FnMut(&'a str, &'a str) -> Option<&'a str>,
+    ) -> Self {
+        let mut state = ParseState::new();
+        state.source = Some(input);
+        // `lines` also drops the `\r` of CRLF line endings.
+        let text = input.strip_prefix('\u{feff}').unwrap_or(input);
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                if let Some(key) = map(key, value) {
+                    state.feed(key, value);
+                }
+            }
+        }
+        state
+    }
 
-        let depend_val = parse_dep_field(self.depend)?;
-        let rdepend_val = parse_dep_field(self.rdepend)?;
-        let bdepend_val = parse_dep_field(self.bdepend)?;
-        let pdepend_val = parse_dep_field(self.pdepend)?;
-        let idepend_val = parse_dep_field(self.idepend)?;
+    /// Build the entry, checking fields as `strictness` asks. Fails with
+    /// the first error found.
+    fn finish<I: Interner>(self, strictness: Strictness) -> Result<CacheEntry<I>> {
+        let mut errors = Vec::new();
+        let entry = self.finish_all(strictness, &mut errors);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(entry),
+        }
+    }
 
+    /// Build the entry, collecting every error into `errors`. Failed
+    /// fields take the fallbacks documented on [`ParseReport`].
+    fn finish_all<I: Interner>(
+        self,
+        strictness: Strictness,
+        errors: &mut Vec<Error>,
+    ) -> CacheEntry<I> {
         let eclasses = parse_eclasses(self.eclasses_raw);
+        let mut metadata = EbuildMetadata {
+            eapi: Eapi::Zero,
+            description: String::new(),
+            slot: Slot::new("0"),
+            homepage: Vec::new(),
+            src_uri: Vec::new(),
+            license: None,
+            keywords: SmallVec::new(),
+            iuse: Vec::new(),
+            required_use: None,
+            restrict: Vec::new(),
+            properties: Vec::new(),
+            depend: Vec::new(),
+            rdepend: Vec::new(),
+            bdepend: Vec::new(),
+            pdepend: Vec::new(),
+            idepend: Vec::new(),
+            inherit: Vec::new(),
+            // PMS 14.3: md5-dict format excludes the INHERITED key; the
+            // transitive eclass list is carried by _eclasses_ instead.
+            inherited: eclasses.iter().map(|(name, _)| name.clone()).collect(),
+            defined_phases: Vec::new(),
+        };
+        for &key in MetadataKey::ALL {
+            match self.value(key) {
+                Some(value) => metadata.parse_field(key, value, strictness, &mut |text, error| {
+                    errors.push(self.at(key.as_str(), text)(error))
+                }),
+                None if key == MetadataKey::Inherited => {}
+                None => errors.push(Error::MissingField(key.to_string())),
+            }
+        }
 
-        let inherit_val: Vec<String> = self
-            .inherit
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
-
-        // PMS 14.3: md5-dict format excludes the INHERITED key; the
-        // transitive eclass list is carried by _eclasses_ instead.
-        let inherited_val: Vec<String> = eclasses.iter().map(|(name, _)| name.clone()).collect();
-
-        let defined_phases_val = Phase::parse_line(self.defined_phases)?;
-
-        Ok(CacheEntry {
-            metadata: EbuildMetadata {
-                eapi: eapi_val,
-                description: description_val,
-                slot: slot_val,
-                homepage: homepage_val,
-                src_uri: src_uri_val,
-                license: license_val,
-                keywords: keywords_val,
-                iuse: iuse_val,
-                required_use: required_use_val,
-                restrict: restrict_val,
-                properties: properties_val,
-                depend: depend_val,
-                rdepend: rdepend_val,
-                bdepend: bdepend_val,
-                pdepend: pdepend_val,
-                idepend: idepend_val,
-                inherit: inherit_val,
-                inherited: inherited_val,
-                defined_phases: defined_phases_val,
-            },
+        CacheEntry {
+            metadata,
             md5: self.md5.map(|s| s.to_string()),
             eclasses,
-        })
+        }
+    }
+}
+
+impl<I: Interner> Hash for CacheEntry<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.metadata.hash(state);
+        self.md5.hash(state);
+        self.eclasses.hash(state);
     }
 }
 
 impl<I: Interner> CacheEntry<I> {
-    fn parse_impl(input: &str) -> Result<CacheEntry<I>> {
-        let mut state = ParseState::new();
-        for line in input.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            if let Some((key, value)) = line.split_once('=') {
-                state.feed(key, value);
-            }
-        }
-        state.finish()
+    fn parse_impl(input: &str, strictness: Strictness) -> Result<CacheEntry<I>> {
+        ParseState::read(input).finish(strictness)
+    }
+
+    /// Parse everything but `SRC_URI`, `LICENSE`, `REQUIRED_USE` and the
+    /// dependency classes, returning their raw values in that order.
+    pub(crate) fn parse_eager(input: &str) -> Result<(CacheEntry<I>, [&str; 8])> {
+        let mut state = ParseState::read(input);
+        let raw = [
+            std::mem::take(&mut state.src_uri),
+            std::mem::take(&mut state.license),
+            std::mem::take(&mut state.required_use),
+            std::mem::take(&mut state.depend),
+            std::mem::take(&mut state.rdepend),
+            std::mem::take(&mut state.bdepend),
+            std::mem::take(&mut state.pdepend),
+            std::mem::take(&mut state.idepend),
+        ];
+        Ok((state.finish(Strictness::Lenient)?, raw))
+    }
+
+    /// A fingerprint of the serialized entry, stable across runs.
+    ///
+    /// Covers `_md5_` and `_eclasses_` as well as the metadata; see
+    /// [`EbuildMetadata::content_digest`] for the metadata alone.
+    pub fn content_digest(&self) -> u64 {
+        fnv1a(self.serialize().as_bytes())
     }
 
     /// Serialize this cache entry back to md5-cache format.
@@ -259,96 +318,134 @@ impl<I: Interner> CacheEntry<I> {
     /// Produces a string suitable for writing to a cache file.
     /// Empty-valued fields are omitted.
     pub fn serialize(&self) -> String {
+        let mut lines: Vec<String> = self
+            .fields()
+            .into_iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        lines.push(String::new()); // trailing newline
+        lines.join("\n")
+    }
+
+    /// Serialize this cache entry in the flat_list format of
+    /// `metadata/cache`.
+    ///
+    /// Writes one value per line in the fixed order of PMS 14.2, padded
+    /// to 22 lines. `INHERITED` lists the eclass names only, so the
+    /// `_md5_` and `_eclasses_` checksums are lost, as is `INHERIT`.
+    ///
+    /// See [PMS 14.2](https://projects.gentoo.org/pms/9/pms.html#cache-file-format).
+    pub fn serialize_flat_list(&self) -> String {
+        let fields = self.fields();
+        let inherited: Vec<&str> = self
+            .metadata
+            .inherited
+            .iter()
+            .map(Interned::as_str)
+            .collect();
+        let mut out = String::new();
+        for key in FLAT_LIST_KEYS {
+            match key {
+                "INHERITED" => out.push_str(&inherited.join(" ")),
+                key => {
+                    if let Some((_, value)) = fields.iter().find(|(k, _)| *k == key) {
+                        out.push_str(value);
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        for _ in FLAT_LIST_KEYS.len()..FLAT_LIST_LINES {
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The md5-dict fields in serialization order. Optional fields are
+    /// left out when empty.
+    pub(crate) fn fields(&self) -> Vec<(&'static str, String)> {
         let m = &self.metadata;
-        let mut lines = Vec::new();
+        let mut fields = Vec::new();
 
         // Always emit mandatory fields
-        lines.push(format!(
-            "DEFINED_PHASES={}",
-            format_phases(&m.defined_phases)
-        ));
+        fields.push(("DEFINED_PHASES", format_phases(&m.defined_phases)));
 
         if !m.depend.is_empty() {
-            lines.push(format!("DEPEND={}", format_dep_entries(&m.depend)));
+            fields.push(("DEPEND", format_dep_entries(&m.depend)));
         }
 
-        lines.push(format!("DESCRIPTION={}", m.description));
-        lines.push(format!("EAPI={}", m.eapi));
+        fields.push(("DESCRIPTION", m.description.clone()));
+        fields.push(("EAPI", m.eapi.to_string()));
 
         if !m.homepage.is_empty() {
-            lines.push(format!("HOMEPAGE={}", m.homepage.join(" ")));
+            fields.push(("HOMEPAGE", format_list(&m.homepage)));
         }
 
         if !m.iuse.is_empty() {
-            let iuse_str: Vec<String> = m.iuse.iter().map(|i| i.to_string()).collect();
-            lines.push(format!("IUSE={}", iuse_str.join(" ")));
+            fields.push(("IUSE", format_list(&m.iuse)));
         }
 
         if !m.keywords.is_empty() {
-            let kw_str: Vec<String> = m.keywords.iter().map(|k| k.to_string()).collect();
-            lines.push(format!("KEYWORDS={}", kw_str.join(" ")));
+            fields.push(("KEYWORDS", format_list(&m.keywords)));
         }
 
         if let Some(ref lic) = m.license {
-            lines.push(format!("LICENSE={}", lic));
+            fields.push(("LICENSE", lic.to_string()));
         }
 
         if !m.pdepend.is_empty() {
-            lines.push(format!("PDEPEND={}", format_dep_entries(&m.pdepend)));
+            fields.push(("PDEPEND", format_dep_entries(&m.pdepend)));
         }
 
         if !m.rdepend.is_empty() {
-            lines.push(format!("RDEPEND={}", format_dep_entries(&m.rdepend)));
+            fields.push(("RDEPEND", format_dep_entries(&m.rdepend)));
         }
 
         if let Some(ref ru) = m.required_use {
-            lines.push(format!("REQUIRED_USE={}", ru));
+            fields.push(("REQUIRED_USE", ru.to_string()));
         }
 
         if !m.restrict.is_empty() {
-            let r_str: Vec<String> = m.restrict.iter().map(|r| r.to_string()).collect();
-            lines.push(format!("RESTRICT={}", r_str.join(" ")));
+            fields.push(("RESTRICT", format_list(&m.restrict)));
         }
 
-        lines.push(format!("SLOT={}", m.slot));
+        fields.push(("SLOT", m.slot.to_string()));
 
         if !m.src_uri.is_empty() {
-            let uri_str: Vec<String> = m.src_uri.iter().map(|u| u.to_string()).collect();
-            lines.push(format!("SRC_URI={}", uri_str.join(" ")));
+            fields.push(("SRC_URI", format_list(&m.src_uri)));
         }
 
         if !m.bdepend.is_empty() {
-            lines.push(format!("BDEPEND={}", format_dep_entries(&m.bdepend)));
+            fields.push(("BDEPEND", format_dep_entries(&m.bdepend)));
         }
 
         if !m.idepend.is_empty() {
-            lines.push(format!("IDEPEND={}", format_dep_entries(&m.idepend)));
+            fields.push(("IDEPEND", format_dep_entries(&m.idepend)));
         }
 
         if !m.properties.is_empty() {
-            let p_str: Vec<String> = m.properties.iter().map(|p| p.to_string()).collect();
-            lines.push(format!("PROPERTIES={}", p_str.join(" ")));
+            fields.push(("PROPERTIES", format_list(&m.properties)));
         }
 
         if !m.inherit.is_empty() {
-            lines.push(format!("INHERIT={}", m.inherit.join(" ")));
+            let names: Vec<&str> = m.inherit.iter().map(Interned::as_str).collect();
+            fields.push(("INHERIT", names.join(" ")));
         }
 
         if !self.eclasses.is_empty() {
-            let parts: Vec<String> = self
+            let parts: Vec<&str> = self
                 .eclasses
                 .iter()
-                .flat_map(|(name, checksum)| vec![name.clone(), checksum.clone()])
+                .flat_map(|(name, checksum)| [name.as_str(), checksum.as_str()])
                 .collect();
-            lines.push(format!("_eclasses_={}", parts.join("\t")));
+            fields.push(("_eclasses_", parts.join("\t")));
         }
 
         if let Some(ref md5) = self.md5 {
-            lines.push(format!("_md5_={}", md5));
+            fields.push(("_md5_", md5.clone()));
         }
 
-        lines.push(String::new()); // trailing newline
-        lines.join("\n")
+        fields
     }
 }
 
@@ -357,6 +454,7 @@ impl CacheEntry<DefaultInterner> {
     ///
     /// The input is the full text of a cache file. Lines are `KEY=VALUE`
     /// pairs in arbitrary order. Empty values may be omitted entirely.
+    /// A leading UTF-8 byte order mark and CRLF line endings are accepted.
     ///
     /// # Examples
     ///
@@ -374,7 +472,148 @@ impl CacheEntry<DefaultInterner> {
     /// assert_eq!(entry.metadata.description, "Example package");
     /// ```
     pub fn parse(input: &str) -> Result<Self> {
-        Self::parse_impl(input)
+        Self::parse_impl(input, Strictness::Lenient)
+    }
+
+    /// Like [`CacheEntry::parse`], also joining lines that end in a
+    /// backslash with the next one, as a shell would.
+    ///
+    /// Some producers wrap long values this way; a plain parse would
+    /// instead cut the value at the backslash and drop the rest. Error
+    /// spans refer to the joined text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let input = "\u{feff}DESCRIPTION=x\r\nSLOT=0\r\nRDEPEND=dev-libs/a \\\r\n\tdev-libs/b\r\n";
+    /// let entry = CacheEntry::parse_lenient(input).unwrap();
+    /// assert_eq!(entry.metadata.rdepend.len(), 2);
+    /// assert!(CacheEntry::parse(input).is_err());
+    /// ```
+    pub fn parse_lenient(input: &str) -> Result<Self> {
+        Self::parse(&join_continuations(input))
+    }
+
+    /// Like [`CacheEntry::parse`], reading each value under the key `map`
+    /// returns for its line's key, or skipping it on `None`.
+    pub(crate) fn parse_mapped<'a>(
+        input: &'a str,
+        map: impl FnMut(&'a str, &'a str) -> Option<&'a str>,
+    ) -> Result<Self> {
+        ParseState::read_mapped(input, map).finish(Strictness::Lenient)
+    }
+
+    /// Parse a flat_list cache file from `metadata/cache`.
+    ///
+    /// Values are taken by line number in the order of PMS 14.2; lines
+    /// past the last known key are ignored. The format carries no
+    /// checksums, so `md5` and `eclasses` are left empty and `INHERITED`
+    /// is kept in [`EbuildMetadata::inherited`].
+    ///
+    /// See [PMS 14.2](https://projects.gentoo.org/pms/9/pms.html#cache-file-format).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let input = "dev-libs/a\n\n0\n\n\n\n\nExample\n~amd64\nflag-o-matic\n\n\n\n\n8\n";
+    /// let entry = CacheEntry::parse_flat_list(input).unwrap();
+    /// assert_eq!(entry.metadata.description, "Example");
+    /// assert_eq!(entry.metadata.inherited[0].as_str(), "flag-o-matic");
+    /// assert_eq!(CacheEntry::parse_flat_list(&entry.serialize_flat_list()).unwrap(), entry);
+    /// ```
+    pub fn parse_flat_list(input: &str) -> Result<Self> {
+        let mut state = ParseState::new();
+        state.source = Some(input);
+        let text = input.strip_prefix('\u{feff}').unwrap_or(input);
+        let mut inherited = "";
+        for (key, line) in FLAT_LIST_KEYS.into_iter().zip(text.lines()) {
+            match key {
+                "INHERITED" => inherited = line.trim(),
+                key => state.feed(key, line.trim()),
+            }
+        }
+        let mut entry: Self = state.finish(Strictness::Lenient)?;
+        entry.metadata.inherited = inherited.split_whitespace().map(Interned::intern).collect();
+        Ok(entry)
+    }
+
+    /// Parse a md5-cache entry, also rejecting syntax its EAPI does not allow.
+    ///
+    /// On top of [`CacheEntry::parse`], this applies
+    /// [`SrcUriEntry::parse_with_eapi`], [`RequiredUseExpr::validate`],
+    /// [`RestrictExpr::parse_with_eapi`] and
+    /// [`PropertiesExpr::parse_with_eapi`], [`IUse::parse_strict`] and
+    /// [`Homepage::parse_strict`], and checks `DEFINED_PHASES` with
+    /// [`Phase::is_valid_in`].
+    ///
+    /// [`SrcUriEntry::parse_with_eapi`]: crate::SrcUriEntry::parse_with_eapi
+    /// [`RequiredUseExpr::validate`]: crate::RequiredUseExpr::validate
+    /// [`RestrictExpr::parse_with_eapi`]: crate::RestrictExpr::parse_with_eapi
+    /// [`PropertiesExpr::parse_with_eapi`]: crate::PropertiesExpr::parse_with_eapi
+    /// [`IUse::parse_strict`]: crate::IUse::parse_strict
+    /// [`Homepage::parse_strict`]: crate::Homepage::parse_strict
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let input = "EAPI=7\nDESCRIPTION=x\nSLOT=0\nRESTRICT=!test? ( test )\n";
+    /// assert!(CacheEntry::parse(input).is_ok());
+    /// assert!(CacheEntry::parse_strict(input).is_err());
+    /// ```
+    pub fn parse_strict(input: &str) -> Result<Self> {
+        Self::parse_impl(input, Strictness::Strict)
+    }
+
+    /// Like [`CacheEntry::parse_strict`], validating entries with a custom
+    /// EAPI against the feature set it was registered with. Errors name the
+    /// declared EAPI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, Eapi, EapiRegistry};
+    ///
+    /// let mut registry = EapiRegistry::new();
+    /// registry.register_like("7-fork", Eapi::Seven);
+    /// let input = "EAPI=7-fork\nDESCRIPTION=x\nSLOT=0\nRESTRICT=!test? ( test )\n";
+    /// assert!(CacheEntry::parse_strict(input).is_ok());
+    /// assert!(CacheEntry::parse_strict_with(input, &registry).is_err());
+    /// ```
+    pub fn parse_strict_with(input: &str, registry: &EapiRegistry) -> Result<Self> {
+        Self::parse_impl(input, Strictness::StrictWith(registry))
+    }
+
+    /// Parse every field of a md5-cache entry, collecting all errors
+    /// instead of stopping at the first one.
+    ///
+    /// Fields are checked as `strictness` asks. Fields that fail to parse
+    /// take the fallbacks documented on [`ParseReport`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, Strictness};
+    ///
+    /// let input = "EAPI=8\nSLOT=0\nKEYWORDS=amd64 ~x8@6\n";
+    /// let report = CacheEntry::parse_report(input, Strictness::Lenient);
+    /// assert_eq!(report.errors.len(), 2);
+    /// assert_eq!(report.entry.metadata.keywords.len(), 1);
+    /// ```
+    pub fn parse_report(input: &str, strictness: Strictness) -> ParseReport {
+        let mut errors = Vec::new();
+        let entry: Self = ParseState::read(input).finish_all(strictness, &mut errors);
+        let warnings = entry.metadata.validate();
+        ParseReport {
+            entry,
+            errors,
+            warnings,
+        }
     }
 
     /// Build a `CacheEntry` from an iterator of `(key, value)` string pairs.
@@ -387,14 +626,132 @@ impl CacheEntry<DefaultInterner> {
         for (key, value) in pairs {
             state.feed(key, value);
         }
-        state.finish()
+        state.finish(Strictness::Lenient)
+    }
+
+    /// Derive the entry for a straight version bump of `cpv` to `new_cpv`.
+    ///
+    /// `KEYWORDS` are carried over as `keywords` asks, and `_md5_` is
+    /// cleared since there is no ebuild to hash yet. In `SRC_URI`, the old
+    /// version (without revision) is replaced by the new one, both as
+    /// written and with dots turned into `_` or `-`, wherever it stands as
+    /// a whole version. The `_eclasses_` are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_atom::Cpv;
+    /// use portage_metadata::{BumpKeywords, CacheEntry};
+    ///
+    /// let entry = CacheEntry::parse(
+    ///     "DESCRIPTION=x\nSLOT=0\nKEYWORDS=amd64 ~x86\n\
+    ///      SRC_URI=https://e.org/foo-1.2.tar.gz https://e.org/v1_2/doc.zip\n_md5_=abc\n",
+    /// )
+    /// .unwrap();
+    /// let old = Cpv::parse("app-misc/foo-1.2-r1").unwrap();
+    /// let new = Cpv::parse("app-misc/foo-1.3").unwrap();
+    /// let bumped = entry.template_bump(&old, &new, BumpKeywords::Testing).unwrap();
+    /// let text = bumped.serialize();
+    /// assert!(text.contains("KEYWORDS=~amd64 ~x86\n"));
+    /// assert!(text.contains("SRC_URI=https://e.org/foo-1.3.tar.gz https://e.org/v1_3/doc.zip\n"));
+    /// assert_eq!(bumped.md5, None);
+    /// ```
+    pub fn template_bump(&self, cpv: &Cpv, new_cpv: &Cpv, keywords: BumpKeywords) -> Result<Self> {
+        let mut bumped = self.clone();
+        bumped.md5 = None;
+        match keywords {
+            BumpKeywords::Keep => {}
+            BumpKeywords::Testing => {
+                for keyword in &mut bumped.metadata.keywords {
+                    if keyword.stability == Stability::Stable {
+                        keyword.stability = Stability::Testing;
+                    }
+                }
+            }
+            BumpKeywords::Drop => bumped.metadata.keywords.clear(),
+        }
+        let old = package_version(cpv);
+        let new = package_version(new_cpv);
+        if old != new {
+            let mut src_uri = self.metadata.get(MetadataKey::SrcUri).to_string();
+            src_uri = replace_version(&src_uri, &old, &new);
+            if old.contains('.') {
+                for sep in ["_", "-"] {
+                    src_uri =
+                        replace_version(&src_uri, &old.replace('.', sep), &new.replace('.', sep));
+                }
+            }
+            bumped.metadata.set(MetadataKey::SrcUri, &src_uri)?;
+        }
+        Ok(bumped)
+    }
+}
+
+/// What [`CacheEntry::template_bump`] does with the `KEYWORDS` of the
+/// old version.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BumpKeywords {
+    /// Downgrade stable keywords to `~arch`, as a new version usually
+    /// starts in testing.
+    #[default]
+    Testing,
+    /// Keep every keyword as it is.
+    Keep,
+    /// Drop every keyword, as for a live or experimental version.
+    Drop,
+}
+
+// `input` with every backslash-newline pair removed.
+fn join_continuations(input: &str) -> Cow<'_, str> {
+    if !input.contains("\\\n") && !input.contains("\\\r\n") {
+        return Cow::Borrowed(input);
+    }
+    Cow::Owned(input.replace("\\\r\n", "").replace("\\\n", ""))
+}
+
+// The version as written, without its `-rN` revision (`PV`).
+fn package_version(cpv: &Cpv) -> String {
+    let version = cpv.version.to_string();
+    match version.rsplit_once("-r") {
+        Some((pv, _)) => pv.to_string(),
+        None => version,
     }
 }
 
+// Replace `old` with `new` where it is not part of a longer version, e.g.
+// `1.2` in `foo-1.2.tar` but not in `11.2`, `1.2.3` or `1.2_rc1`.
+fn replace_version(text: &str, old: &str, new: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(old) {
+        let before = rest[..i].bytes().last();
+        let after = &rest.as_bytes()[i + old.len()..];
+        let continues = match after {
+            [d, ..] if d.is_ascii_digit() => true,
+            [b'.' | b'_' | b'-', d, ..] if d.is_ascii_digit() => true,
+            [b'_', suffix @ ..] => ["alpha", "beta", "pre", "rc", "p"].iter().any(|name| {
+                suffix.starts_with(name.as_bytes())
+                    && !suffix
+                        .get(name.len())
+                        .is_some_and(|c| c.is_ascii_alphabetic())
+            }),
+            _ => false,
+        };
+        let whole = !before.is_some_and(|b| b.is_ascii_digit() || b == b'.') && !continues;
+        out.push_str(&rest[..i]);
+        out.push_str(if whole { new } else { old });
+        rest = &rest[i + old.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
 /// Check that a slot or subslot name is valid per PMS 3.1.3.
 ///
 /// Slot names may contain `[A-Za-z0-9+_.-]` and must not begin with `-`, `.`, or `+`.
-fn is_valid_slot_name(s: &str) -> bool {
+pub(crate) fn is_valid_slot_name(s: &str) -> bool {
     if s.is_empty() {
         return false;
     }
@@ -407,7 +764,7 @@ fn is_valid_slot_name(s: &str) -> bool {
 }
 
 /// Parse a SLOT value into a `Slot`.
-fn parse_slot(s: &str) -> Result<Slot> {
+pub(crate) fn parse_slot(s: &str) -> Result<Slot> {
     if s.is_empty() {
         return Err(Error::MissingField("SLOT".to_string()));
     }
@@ -425,28 +782,23 @@ fn parse_slot(s: &str) -> Result<Slot> {
 }
 
 /// Parse a dependency field value into `Vec<DepEntry>`.
-fn parse_dep_field(s: &str) -> Result<Vec<DepEntry>> {
+pub(crate) fn parse_dep_field(s: &str) -> Result<Vec<DepEntry>> {
     if s.is_empty() {
         return Ok(Vec::new());
     }
-    DepEntry::parse(s).map_err(|e| Error::DepError(format!("{e}")))
+    Ok(DepEntry::parse(s)?)
 }
 
 /// Parse the `_eclasses_` value: tab-separated pairs of `name\tchecksum`.
-fn parse_eclasses(s: &str) -> Vec<(String, String)> {
+///
+/// A trailing name without a checksum is ignored.
+fn parse_eclasses<I: Interner>(s: &str) -> Vec<(Interned<I>, String)> {
     if s.is_empty() {
         return Vec::new();
     }
-    let parts: Vec<&str> = s.split('\t').collect();
-    parts
-        .chunks(2)
-        .filter_map(|chunk| {
-            if chunk.len() == 2 {
-                Some((chunk[0].to_string(), chunk[1].to_string()))
-            } else {
-                None
-            }
-        })
+    let mut parts = s.split('\t');
+    std::iter::from_fn(|| Some((parts.next()?, parts.next()?)))
+        .map(|(name, md5)| (Interned::intern(name), md5.to_string()))
         .collect()
 }
 
@@ -463,6 +815,12 @@ fn format_phases(phases: &[Phase]) -> String {
     }
 }
 
+/// Format space-separated values for serialization.
+fn format_list<T: fmt::Display>(items: &[T]) -> String {
+    let strs: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+    strs.join(" ")
+}
+
 /// Format dependency entries for serialization.
 fn format_dep_entries(entries: &[DepEntry]) -> String {
     let strs: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
@@ -473,6 +831,8 @@ fn format_dep_entries(entries: &[DepEntry]) -> String {
 mod tests {
     use super::*;
     use crate::eapi::Eapi;
+    use crate::eapi_feature::EapiFeature;
+    use crate::homepage::Homepage;
     use crate::keyword::Stability;
 
     const EXAMPLE_CACHE: &str = "\
@@ -526,6 +886,35 @@ _md5_=4539d849d3cea8ac84debad9b3154143
         assert_eq!(entry.metadata.inherited, vec!["llvm.org", "multibuild"]);
     }
 
+    #[test]
+    fn parse_bom_and_crlf() {
+        let crlf = format!("\u{feff}{}", EXAMPLE_CACHE.replace('\n', "\r\n"));
+        assert_eq!(
+            CacheEntry::parse(&crlf).unwrap(),
+            CacheEntry::parse(EXAMPLE_CACHE).unwrap()
+        );
+        let err = CacheEntry::parse("\u{feff}DESCRIPTION=x\r\nSLOT=0\r\nKEYWORDS=~am@d64\r\n")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Field {
+                span: Some(Span {
+                    line: 3,
+                    column: 10,
+                    ..
+                }),
+                ..
+            }
+        ));
+        assert_eq!(
+            CacheEntry::parse_lenient("DESCRIPTION=a \\\nb\\\n\nSLOT=0\n")
+                .unwrap()
+                .metadata
+                .description,
+            "a b"
+        );
+    }
+
     #[test]
     fn parse_minimal() {
         let input = "DESCRIPTION=Minimal\nSLOT=0\n";
@@ -562,25 +951,25 @@ _md5_=4539d849d3cea8ac84debad9b3154143
 
     #[test]
     fn parse_eclasses() {
-        let eclasses = super::parse_eclasses("llvm.org\tabc123\tmultibuild\tdef456");
+        let eclasses =
+            super::parse_eclasses::<DefaultInterner>("llvm.org\tabc123\tmultibuild\tdef456");
         assert_eq!(eclasses.len(), 2);
-        assert_eq!(eclasses[0], ("llvm.org".to_string(), "abc123".to_string()));
-        assert_eq!(
-            eclasses[1],
-            ("multibuild".to_string(), "def456".to_string())
-        );
+        assert_eq!(eclasses[0].0, "llvm.org");
+        assert_eq!(eclasses[0].1, "abc123");
+        assert_eq!(eclasses[1].0, "multibuild");
+        assert_eq!(eclasses[1].1, "def456");
     }
 
     #[test]
     fn parse_eclasses_empty() {
-        let eclasses = super::parse_eclasses("");
+        let eclasses = super::parse_eclasses::<DefaultInterner>("");
         assert!(eclasses.is_empty());
     }
 
     #[test]
     fn parse_eclasses_odd_count() {
         // Odd number of tab-separated values: last one is ignored
-        let eclasses = super::parse_eclasses("llvm.org\tabc123\torphan");
+        let eclasses = super::parse_eclasses::<DefaultInterner>("llvm.org\tabc123\torphan");
         assert_eq!(eclasses.len(), 1);
     }
 
@@ -734,4 +1123,156 @@ _eclasses_=foo\taabb\tbar\tccdd\tbaz\teeff
         assert_eq!(entry.metadata.slot.slot, "0");
         assert!(entry.metadata.keywords.len() == 1);
     }
+
+    #[test]
+    fn parse_unknown_eapi() {
+        let input = "EAPI=7-paludis\nDESCRIPTION=x\nSLOT=0\n";
+        let entry = CacheEntry::parse(input).unwrap();
+        assert_eq!(entry.metadata.eapi, Eapi::Unknown("7-paludis".to_string()));
+        assert!(!entry.metadata.eapi.is_known());
+        assert!(entry.serialize().contains("EAPI=7-paludis\n"));
+    }
+
+    #[test]
+    fn parse_strict_unknown_phase() {
+        let input = "EAPI=8\nDESCRIPTION=x\nSLOT=0\nDEFINED_PHASES=compile frobnicate\n";
+        assert!(CacheEntry::parse(input).is_ok());
+        let err = CacheEntry::parse_strict(input).unwrap_err();
+        assert_eq!(err.root(), &Error::InvalidPhase("frobnicate".to_string()));
+        assert_eq!(err.field(), Some("DEFINED_PHASES"));
+        assert_eq!(
+            err.span(),
+            Some(Span {
+                line: 4,
+                column: 24,
+                offset: 51,
+            })
+        );
+
+        let input = "EAPI=8\nDESCRIPTION=x\nSLOT=0\nDEFINED_PHASES=compile instal\n";
+        let err = CacheEntry::parse_strict(input).unwrap_err();
+        assert_eq!(err.suggestion(), Some("install"));
+        assert!(err
+            .to_string()
+            .ends_with("invalid phase: instal (did you mean `install`?)"));
+    }
+
+    #[test]
+    fn replace_whole_versions_only() {
+        assert_eq!(
+            replace_version("a-1.2.tar v1.2/x 11.2 1.2.3 1.2_p1 x1.2", "1.2", "2.0"),
+            "a-2.0.tar v2.0/x 11.2 1.2.3 1.2_p1 x2.0"
+        );
+    }
+
+    #[test]
+    fn template_bump_renamed() {
+        let entry = CacheEntry::parse(
+            "DESCRIPTION=x\nSLOT=0\nSRC_URI=https://e.org/1-2/a.tgz -> foo-1.2.tgz\n",
+        )
+        .unwrap();
+        let old = Cpv::parse("dev-libs/foo-1.2").unwrap();
+        let new = Cpv::parse("dev-libs/foo-1.4").unwrap();
+        let bumped = entry.template_bump(&old, &new, BumpKeywords::Keep).unwrap();
+        assert_eq!(
+            bumped.metadata.get(MetadataKey::SrcUri).to_string(),
+            "https://e.org/1-4/a.tgz -> foo-1.4.tgz"
+        );
+        assert_eq!(bumped.eclasses, entry.eclasses);
+
+        let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=amd64 ~x86\n").unwrap();
+        let keywords = |policy| {
+            let bumped = entry.template_bump(&old, &new, policy).unwrap();
+            bumped.metadata.get(MetadataKey::Keywords).to_string()
+        };
+        assert_eq!(keywords(BumpKeywords::Keep), "amd64 ~x86");
+        assert_eq!(keywords(BumpKeywords::Testing), "~amd64 ~x86");
+        assert_eq!(keywords(BumpKeywords::Drop), "");
+    }
+
+    #[test]
+    fn error_spans() {
+        let input = "DESCRIPTION=x\nSLOT=0\nLICENSE=MIT || (\n";
+        let err = CacheEntry::parse(input).unwrap_err();
+        assert_eq!(err.field(), Some("LICENSE"));
+        assert!(matches!(err.root(), Error::InvalidLicense(_)));
+        assert!(matches!(
+            &err,
+            Error::Field { text, span: Some(Span { line: 3, column: 9, offset: 29 }), .. }
+                if text == "MIT || ("
+        ));
+
+        let err =
+            CacheEntry::parse_strict("EAPI=8\nDESCRIPTION=x\nSLOT=0\nIUSE=a b$\n").unwrap_err();
+        assert_eq!(err.span().map(|s| (s.line, s.column)), Some((4, 8)));
+
+        // Entries built from pairs have no source text to point into.
+        let err = CacheEntry::from_kv_pairs(
+            [("DESCRIPTION", "x"), ("SLOT", "0"), ("KEYWORDS", "~~x")].into_iter(),
+        )
+        .unwrap_err();
+        assert_eq!(err.field(), Some("KEYWORDS"));
+        assert_eq!(err.span(), None);
+    }
+
+    #[test]
+    fn parse_homepage() {
+        let input = "DESCRIPTION=x\nSLOT=0\nHOMEPAGE=https://a.org www.b.org\n";
+        let entry = CacheEntry::parse(input).unwrap();
+        assert!(entry.metadata.homepage[0].is_valid());
+        assert_eq!(
+            entry.metadata.homepage[1],
+            Homepage::Invalid("www.b.org".to_string())
+        );
+        assert!(entry
+            .serialize()
+            .contains("HOMEPAGE=https://a.org www.b.org\n"));
+        assert_eq!(
+            CacheEntry::parse_strict(input).unwrap_err().root(),
+            &Error::InvalidHomepage("www.b.org".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_strict_eapi_gating() {
+        let ok = "EAPI=8\nDESCRIPTION=x\nSLOT=0\nSRC_URI=fetch+https://e.com/a\nREQUIRED_USE=?? ( a b )\nPROPERTIES=test? ( test_network )\n";
+        assert!(CacheEntry::parse_strict(ok).is_ok());
+        for bad in [
+            "EAPI=7\nDESCRIPTION=x\nSLOT=0\nSRC_URI=fetch+https://e.com/a\n",
+            "EAPI=4\nDESCRIPTION=x\nSLOT=0\nREQUIRED_USE=?? ( a b )\n",
+            "EAPI=7\nDESCRIPTION=x\nSLOT=0\nPROPERTIES=test? ( test_network )\n",
+            "EAPI=3\nDESCRIPTION=x\nSLOT=0\nDEFINED_PHASES=pretend setup\n",
+        ] {
+            assert!(CacheEntry::parse(bad).is_ok());
+            assert!(matches!(
+                CacheEntry::parse_strict(bad).unwrap_err().root(),
+                Error::UnsupportedInEapi { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_strict_registered_features() {
+        let mut registry = EapiRegistry::new();
+        registry.register(
+            "8-myfork",
+            Eapi::Eight,
+            Eapi::Eight
+                .features()
+                .filter(|f| *f != EapiFeature::UseConditionalRestrict),
+        );
+        let input = "EAPI=8-myfork\nDESCRIPTION=x\nSLOT=0\nRESTRICT=!test? ( test )\n";
+        assert_eq!(
+            CacheEntry::parse_strict_with(input, &registry)
+                .unwrap_err()
+                .root(),
+            &Error::UnsupportedInEapi {
+                feature: "conditional RESTRICT 'test? ( ... )'".to_string(),
+                eapi: Eapi::Unknown("8-myfork".to_string()),
+            }
+        );
+        // Unregistered unknown EAPIs are as permissive as with no registry.
+        let input = "EAPI=10\nDESCRIPTION=x\nSLOT=0\nRESTRICT=!test? ( test )\n";
+        assert!(CacheEntry::parse_strict_with(input, &registry).is_ok());
+    }
 }
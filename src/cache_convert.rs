@@ -0,0 +1,205 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::error::{Error, Result};
+use crate::metadata_repo::{list_cache_dir, write_atomic, MetadataRepo};
+use crate::regen_plan::{digest, find_ebuilds, EclassDigests};
+
+/// An on-disk metadata cache format.
+///
+/// The sqlite backend some package managers offer is not supported.
+///
+/// See [PMS 14.1](https://projects.gentoo.org/pms/9/pms.html#directory-contents).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CacheFormat {
+    /// `KEY=VALUE` lines in `metadata/md5-cache`, with checksums.
+    #[default]
+    Md5Dict,
+    /// Positional lines in `metadata/cache`, without checksums.
+    FlatList,
+}
+
+impl CacheFormat {
+    /// The cache directory relative to the repository root.
+    pub fn dir(self) -> &'static str {
+        match self {
+            CacheFormat::Md5Dict => "metadata/md5-cache",
+            CacheFormat::FlatList => "metadata/cache",
+        }
+    }
+
+    /// Parse one cache file of this format.
+    pub fn parse(self, input: &str) -> Result<CacheEntry> {
+        match self {
+            CacheFormat::Md5Dict => CacheEntry::parse(input),
+            CacheFormat::FlatList => CacheEntry::parse_flat_list(input),
+        }
+    }
+
+    /// Serialize `entry` as one cache file of this format.
+    pub fn serialize(self, entry: &CacheEntry) -> String {
+        match self {
+            CacheFormat::Md5Dict => entry.serialize(),
+            CacheFormat::FlatList => entry.serialize_flat_list(),
+        }
+    }
+}
+
+impl fmt::Display for CacheFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CacheFormat::Md5Dict => "md5-dict",
+            CacheFormat::FlatList => "flat_list",
+        })
+    }
+}
+
+/// The outcome of converting a repository's cache between formats.
+///
+/// Checksums carry over between md5-dict caches. Converting from
+/// flat_list to md5-dict computes `_md5_` from the ebuild and
+/// `_eclasses_` from the repository's `eclass/` directory; entries whose
+/// ebuild or an inherited eclass cannot be found are written without
+/// `_md5_`, so [`RegenPlan`](crate::RegenPlan) reports them for
+/// regeneration, and listed in
+/// [`unchecksummed`](CacheConversion::unchecksummed).
+///
+/// See [PMS 14](https://projects.gentoo.org/pms/9/pms.html#metadata-cache).
+///
+/// # Examples
+///
+/// ```no_run
+/// use portage_metadata::{CacheConversion, CacheFormat};
+///
+/// let conversion = CacheConversion::convert_tree(
+///     "/var/db/repos/overlay",
+///     CacheFormat::FlatList,
+///     CacheFormat::Md5Dict,
+/// )
+/// .unwrap();
+/// for cpv in &conversion.unchecksummed {
+///     println!("{cpv}: needs regeneration");
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheConversion {
+    /// Number of entries written.
+    pub converted: usize,
+    /// Entries written without checksums, sorted by package version.
+    pub unchecksummed: Vec<Cpv>,
+}
+
+impl CacheConversion {
+    /// Read every entry of the `from` cache of the repository at `root`
+    /// and write it to the `to` cache.
+    ///
+    /// Existing files in the destination are overwritten; entries it has
+    /// that the source lacks are left alone. Parse errors carry the
+    /// package version as [`Error::Package`] context.
+    pub fn convert_tree(
+        root: impl AsRef<Path>,
+        from: CacheFormat,
+        to: CacheFormat,
+    ) -> Result<Self> {
+        let root = root.as_ref();
+        let repo = MetadataRepo::new(root);
+        let src = root.join(from.dir());
+        let dst = root.join(to.dir());
+        let mut entries = list_cache_dir(&src)?;
+        entries.sort();
+
+        let needs_checksums = from == CacheFormat::FlatList && to == CacheFormat::Md5Dict;
+        let ebuilds = if needs_checksums {
+            find_ebuilds(root)?
+        } else {
+            Default::default()
+        };
+        let mut eclasses = EclassDigests::new(&repo, &[]);
+
+        let mut conversion = CacheConversion::default();
+        for (cpv, path) in entries {
+            let text = fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+            let mut entry = from.parse(&text).map_err(|e| e.in_package(&cpv))?;
+            if needs_checksums && !add_checksums(&mut entry, ebuilds.get(&cpv), &mut eclasses)? {
+                conversion.unchecksummed.push(cpv.clone());
+            }
+            write_atomic(&dst.join(cpv.to_string()), &to.serialize(&entry), None)?;
+            conversion.converted += 1;
+        }
+        Ok(conversion)
+    }
+}
+
+/// Fill in `_md5_` and `_eclasses_` for an entry read from flat_list;
+/// `false` if some file was missing, leaving `_md5_` unset.
+fn add_checksums(
+    entry: &mut CacheEntry,
+    ebuild: Option<&PathBuf>,
+    eclasses: &mut EclassDigests,
+) -> Result<bool> {
+    let mut complete = true;
+    for name in &entry.metadata.inherited {
+        match eclasses.get(name.as_str())? {
+            Some(md5) => entry.eclasses.push((*name, md5.to_string())),
+            None => complete = false,
+        }
+    }
+    match ebuild {
+        Some(ebuild) if complete => entry.md5 = Some(digest(ebuild)?),
+        _ => complete = false,
+    }
+    Ok(complete)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md5::md5_hex;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn flat_list_to_md5_dict_and_back() {
+        let root = TempDir::new("cache-convert");
+        let flat = root.join("metadata/cache/app-misc");
+        fs::create_dir_all(&flat).unwrap();
+        fs::create_dir_all(root.join("app-misc/foo")).unwrap();
+        fs::create_dir_all(root.join("eclass")).unwrap();
+        fs::write(root.join("app-misc/foo/foo-1.ebuild"), "EAPI=8\n").unwrap();
+        fs::write(root.join("eclass/local.eclass"), "# local\n").unwrap();
+        let line = |description, inherited| {
+            let mut lines = vec![""; 22];
+            lines[2] = "0";
+            lines[7] = description;
+            lines[9] = inherited;
+            lines[14] = "8";
+            lines.join("\n")
+        };
+        fs::write(flat.join("foo-1"), line("foo", "local")).unwrap();
+        fs::write(flat.join("bar-1"), line("bar", "local gone")).unwrap();
+
+        let conversion =
+            CacheConversion::convert_tree(&root, CacheFormat::FlatList, CacheFormat::Md5Dict)
+                .unwrap();
+        assert_eq!(conversion.converted, 2);
+        assert_eq!(
+            conversion.unchecksummed,
+            [Cpv::parse("app-misc/bar-1").unwrap()]
+        );
+        let repo = MetadataRepo::new(&root);
+        let foo = repo.read(&Cpv::parse("app-misc/foo-1").unwrap()).unwrap();
+        assert_eq!(foo.md5, Some(md5_hex(b"EAPI=8\n")));
+        assert_eq!(foo.eclasses[0].1.as_str(), md5_hex(b"# local\n"));
+        assert_eq!(foo.metadata.inherited[0].as_str(), "local");
+
+        fs::remove_dir_all(root.join("metadata/cache")).unwrap();
+        CacheConversion::convert_tree(&root, CacheFormat::Md5Dict, CacheFormat::FlatList).unwrap();
+        let text = fs::read_to_string(flat.join("foo-1")).unwrap();
+        let back = CacheFormat::FlatList.parse(&text).unwrap();
+        assert_eq!(back.metadata, foo.metadata);
+        assert_eq!(text.lines().count(), 22);
+    }
+}
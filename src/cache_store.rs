@@ -0,0 +1,185 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+#[cfg(feature = "fs")]
+use crate::error::Result;
+#[cfg(feature = "fs")]
+use crate::metadata_repo::MetadataRepo;
+
+/// A pool of shared cache entries, deduplicated by content.
+///
+/// Every entry handed to [`CacheStore::insert`] comes back as an
+/// [`Arc`]; an entry equal to one already in the store returns the
+/// existing allocation instead. Tools holding several overlays or
+/// snapshots of the same tree in memory, such as diffs between two
+/// checkouts, thus keep one copy of each unchanged package version.
+///
+/// Entries are bucketed by [`CacheEntry::content_digest`] and compared in
+/// full within a bucket, so digest collisions never merge different
+/// entries.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use portage_metadata::{CacheEntry, CacheStore};
+///
+/// let mut store = CacheStore::new();
+/// let a = store.insert(CacheEntry::parse("DESCRIPTION=x\nSLOT=0\n").unwrap());
+/// let b = store.insert(CacheEntry::parse("SLOT=0\nDESCRIPTION=x\n").unwrap());
+/// assert!(Arc::ptr_eq(&a, &b));
+/// assert_eq!(store.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CacheStore {
+    entries: HashMap<u64, Vec<Arc<CacheEntry>>>,
+}
+
+impl CacheStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        CacheStore::default()
+    }
+
+    /// The shared copy of `entry`, adding it if no equal entry is stored.
+    pub fn insert(&mut self, entry: CacheEntry) -> Arc<CacheEntry> {
+        let bucket = self.entries.entry(entry.content_digest()).or_default();
+        if let Some(shared) = bucket.iter().find(|shared| ***shared == entry) {
+            return Arc::clone(shared);
+        }
+        let shared = Arc::new(entry);
+        bucket.push(Arc::clone(&shared));
+        shared
+    }
+
+    /// Share every entry of a loaded tree.
+    pub fn insert_all(
+        &mut self,
+        entries: impl IntoIterator<Item = (Cpv, CacheEntry)>,
+    ) -> BTreeMap<Cpv, Arc<CacheEntry>> {
+        entries
+            .into_iter()
+            .map(|(cpv, entry)| (cpv, self.insert(entry)))
+            .collect()
+    }
+
+    /// Load every entry of `repo`, sharing those already in the store.
+    ///
+    /// Entries are read one at a time, so at most one unshared copy of a
+    /// package version is held while loading.
+    #[cfg(feature = "fs")]
+    pub fn load(&mut self, repo: &MetadataRepo) -> Result<BTreeMap<Cpv, Arc<CacheEntry>>> {
+        repo.cpvs()?
+            .into_iter()
+            .map(|cpv| {
+                let entry = self.insert(repo.read(&cpv)?);
+                Ok((cpv, entry))
+            })
+            .collect()
+    }
+
+    /// Drop the entries no longer referenced outside the store, and return
+    /// how many were dropped.
+    pub fn prune(&mut self) -> usize {
+        let before = self.len();
+        for bucket in self.entries.values_mut() {
+            bucket.retain(|shared| Arc::strong_count(shared) > 1);
+        }
+        self.entries.retain(|_, bucket| !bucket.is_empty());
+        before - self.len()
+    }
+
+    /// The number of distinct entries.
+    pub fn len(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    /// Whether the store holds no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str) -> CacheEntry {
+        CacheEntry::parse(text).unwrap()
+    }
+
+    #[test]
+    fn shares_equal_entries() {
+        let mut store = CacheStore::new();
+        let old = store.insert_all([
+            (
+                Cpv::parse("app-misc/foo-1").unwrap(),
+                entry("DESCRIPTION=a\nSLOT=0\n"),
+            ),
+            (
+                Cpv::parse("app-misc/bar-1").unwrap(),
+                entry("DESCRIPTION=b\nSLOT=0\n"),
+            ),
+        ]);
+        let new = store.insert_all([
+            (
+                Cpv::parse("app-misc/foo-1").unwrap(),
+                entry("DESCRIPTION=a\nSLOT=0\n"),
+            ),
+            (
+                Cpv::parse("app-misc/bar-1").unwrap(),
+                entry("DESCRIPTION=c\nSLOT=0\n"),
+            ),
+        ]);
+        let foo = Cpv::parse("app-misc/foo-1").unwrap();
+        let bar = Cpv::parse("app-misc/bar-1").unwrap();
+        assert!(Arc::ptr_eq(&old[&foo], &new[&foo]));
+        assert!(!Arc::ptr_eq(&old[&bar], &new[&bar]));
+        assert_eq!(store.len(), 3);
+
+        drop(old);
+        assert_eq!(store.prune(), 1);
+        assert_eq!(store.len(), 2);
+        drop(new);
+        assert_eq!(store.prune(), 2);
+        assert!(store.is_empty());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn load_snapshots() {
+        use crate::temp_dir::TempDir;
+        let base = TempDir::new("cache-store");
+        let (a, b) = (
+            MetadataRepo::new(base.join("a")),
+            MetadataRepo::new(base.join("b")),
+        );
+        let tree: BTreeMap<Cpv, CacheEntry> = [
+            (
+                Cpv::parse("app-misc/foo-1").unwrap(),
+                entry("DESCRIPTION=a\nSLOT=0\n"),
+            ),
+            (
+                Cpv::parse("dev-libs/bar-1").unwrap(),
+                entry("DESCRIPTION=b\nSLOT=0\n"),
+            ),
+        ]
+        .into();
+        a.write_all(&tree, 1).unwrap();
+        b.write_all(&tree, 1).unwrap();
+
+        let mut store = CacheStore::new();
+        let first = store.load(&a).unwrap();
+        let second = store.load(&b).unwrap();
+        assert_eq!(store.len(), 2);
+        assert!(first
+            .iter()
+            .zip(&second)
+            .all(|((_, x), (_, y))| Arc::ptr_eq(x, y)));
+    }
+}
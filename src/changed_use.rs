@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::iuse::{IUse, IUseDefault};
+use crate::use_flags::UseFlagSet;
+
+/// The USE state an installed package was built with, as recorded in its
+/// `IUSE` and `USE` entries in the installed package database.
+///
+/// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstalledUse {
+    /// The `IUSE` the package was built with.
+    pub iuse: Vec<IUse>,
+    /// The flags that were enabled.
+    pub use_flags: UseFlagSet,
+}
+
+/// An installed package that would be rebuilt because the USE flags it
+/// would now be built with differ from those it was built with.
+///
+/// This is the `--changed-use` computation: only flags enabled on one
+/// side count, restricted to each side's `IUSE`, so a flag added to
+/// `IUSE` but left disabled triggers nothing.
+///
+/// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, ChangedUse, IUse, InstalledUse, UseFlagSet};
+///
+/// let cpv = Cpv::parse("net-misc/curl-8.0").unwrap();
+/// let installed = BTreeMap::from([(
+///     cpv.clone(),
+///     InstalledUse {
+///         iuse: IUse::parse_line("+ssl http2").unwrap(),
+///         use_flags: UseFlagSet::parse("ssl"),
+///     },
+/// )]);
+/// let repo = BTreeMap::from([(
+///     cpv,
+///     CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nIUSE=+ssl +http2 brotli\n").unwrap(),
+/// )]);
+///
+/// let rebuilds = ChangedUse::scan(&installed, &repo, "brotli");
+/// assert_eq!(rebuilds[0].to_string(), "net-misc/curl-8.0: +brotli +http2");
+/// assert!(ChangedUse::scan(&installed, &repo, "-http2").is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedUse {
+    /// The installed package version.
+    pub cpv: Cpv,
+    /// Flags that would now be enabled, sorted.
+    pub enabled: Vec<String>,
+    /// Flags that would no longer be enabled, sorted.
+    pub disabled: Vec<String>,
+}
+
+impl ChangedUse {
+    /// Compare the recorded USE state of `cpv` with the one its repository
+    /// entry would get under `config`.
+    ///
+    /// `config` is a `USE`-style string applied on top of the `IUSE`
+    /// defaults of `entry`, e.g. `make.conf` `USE` followed by the
+    /// matching `package.use` lines. Returns `None` if nothing changed.
+    pub fn compare(
+        cpv: &Cpv,
+        installed: &InstalledUse,
+        entry: &CacheEntry,
+        config: &str,
+    ) -> Option<Self> {
+        let old: BTreeSet<&str> = installed
+            .iuse
+            .iter()
+            .map(IUse::name)
+            .filter(|flag| installed.use_flags.is_enabled(flag))
+            .collect();
+        let new_use = use_for(&entry.metadata.iuse, config);
+        let new: BTreeSet<&str> = entry
+            .metadata
+            .iuse
+            .iter()
+            .map(IUse::name)
+            .filter(|flag| new_use.is_enabled(flag))
+            .collect();
+        let changed = ChangedUse {
+            cpv: cpv.clone(),
+            enabled: new.difference(&old).map(|s| s.to_string()).collect(),
+            disabled: old.difference(&new).map(|s| s.to_string()).collect(),
+        };
+        (!changed.enabled.is_empty() || !changed.disabled.is_empty()).then_some(changed)
+    }
+
+    /// Every installed package whose version is still in `repo` and
+    /// would be rebuilt under `config`, sorted by package version.
+    pub fn scan(
+        installed: &BTreeMap<Cpv, InstalledUse>,
+        repo: &BTreeMap<Cpv, CacheEntry>,
+        config: &str,
+    ) -> Vec<Self> {
+        installed
+            .iter()
+            .filter_map(|(cpv, installed)| Self::compare(cpv, installed, repo.get(cpv)?, config))
+            .collect()
+    }
+}
+
+/// The USE flags of `iuse` enabled by default, with `config` applied.
+fn use_for(iuse: &[IUse], config: &str) -> UseFlagSet {
+    let defaults = iuse
+        .iter()
+        .filter(|flag| flag.default == Some(IUseDefault::Enabled))
+        .map(IUse::name)
+        .collect::<Vec<_>>()
+        .join(" ");
+    UseFlagSet::parse(&format!("{defaults} {config}"))
+}
+
+impl fmt::Display for ChangedUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.cpv)?;
+        let mut changes: Vec<(&str, char)> = self
+            .enabled
+            .iter()
+            .map(|flag| (flag.as_str(), '+'))
+            .chain(self.disabled.iter().map(|flag| (flag.as_str(), '-')))
+            .collect();
+        changes.sort();
+        for (flag, sign) in changes {
+            write!(f, " {sign}{flag}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed(iuse: &str, use_flags: &str) -> InstalledUse {
+        InstalledUse {
+            iuse: IUse::parse_line(iuse).unwrap(),
+            use_flags: UseFlagSet::parse(use_flags),
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let cpv = Cpv::parse("app-misc/foo-1").unwrap();
+        let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nIUSE=+a b c\n").unwrap();
+        let compare = |installed: &InstalledUse, config| {
+            ChangedUse::compare(&cpv, installed, &entry, config).map(|c| c.to_string())
+        };
+        // `d` left IUSE while enabled; `c` joined IUSE disabled.
+        assert_eq!(
+            compare(&installed("a b d", "a d"), ""),
+            Some("app-misc/foo-1: -d".to_string())
+        );
+        assert_eq!(
+            compare(&installed("a b", "a"), "-a b"),
+            Some("app-misc/foo-1: -a +b".to_string())
+        );
+        // Flags enabled outside IUSE are ignored on both sides.
+        assert_eq!(compare(&installed("a b", "a x"), "y"), None);
+        assert!(ChangedUse::scan(
+            &BTreeMap::from([(Cpv::parse("app-misc/foo-2").unwrap(), installed("a", ""))]),
+            &BTreeMap::from([(cpv.clone(), entry.clone())]),
+            "",
+        )
+        .is_empty());
+    }
+}
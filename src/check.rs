@@ -0,0 +1,539 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::check_config::CheckConfig;
+use crate::error::{Error, Result};
+use crate::metadata::MetadataIssue;
+
+/// How serious a [`Finding`] is.
+///
+/// Ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing, not necessarily wrong.
+    Info,
+    /// Likely a mistake.
+    Warning,
+    /// Invalid metadata.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl FromStr for Severity {
+    type Err = Error;
+
+    /// Parse `info`, `warning` or `error`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(Error::InvalidConfig(format!("unknown severity: {s}"))),
+        }
+    }
+}
+
+/// One problem reported by a [`MetadataCheck`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Finding {
+    /// The package version it applies to.
+    pub cpv: Cpv,
+    /// The [`MetadataCheck::name`] of the check that reported it.
+    pub check: &'static str,
+    /// How serious it is.
+    pub severity: Severity,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} [{}]: {}",
+            self.cpv, self.severity, self.check, self.message
+        )
+    }
+}
+
+/// How [`FindingFormat::write`] renders findings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FindingFormat {
+    /// One [`Finding`] per line, as its `Display` form.
+    #[default]
+    Text,
+    /// A JSON array of objects with `cpv`, `check`, `severity` and
+    /// `message` keys.
+    Json,
+    /// GitHub Actions workflow commands (`::warning file=...::message`),
+    /// pointing at `<category>/<package>/<package>-<version>.ebuild`.
+    Github,
+}
+
+impl FindingFormat {
+    /// Write `findings` to `out` in this format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_atom::Cpv;
+    /// use portage_metadata::{Finding, FindingFormat, Severity};
+    ///
+    /// let findings = [Finding {
+    ///     cpv: Cpv::parse("app-misc/foo-1").unwrap(),
+    ///     check: "description",
+    ///     severity: Severity::Warning,
+    ///     message: "DESCRIPTION ends with a period".to_string(),
+    /// }];
+    /// let mut out = Vec::new();
+    /// FindingFormat::Github.write(&mut out, &findings).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "::warning file=app-misc/foo/foo-1.ebuild,title=description::DESCRIPTION ends with a period\n"
+    /// );
+    /// ```
+    pub fn write(self, mut out: impl Write, findings: &[Finding]) -> io::Result<()> {
+        match self {
+            FindingFormat::Text => {
+                for finding in findings {
+                    writeln!(out, "{finding}")?;
+                }
+            }
+            FindingFormat::Json => {
+                write!(out, "[")?;
+                for (i, finding) in findings.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(
+                        out,
+                        "{separator}{{\"cpv\":{},\"check\":{},\"severity\":\"{}\",\"message\":{}}}",
+                        json_string(&finding.cpv.to_string()),
+                        json_string(finding.check),
+                        finding.severity,
+                        json_string(&finding.message)
+                    )?;
+                }
+                writeln!(out, "]")?;
+            }
+            FindingFormat::Github => {
+                for finding in findings {
+                    let level = match finding.severity {
+                        Severity::Info => "notice",
+                        Severity::Warning => "warning",
+                        Severity::Error => "error",
+                    };
+                    let cpn = &finding.cpv.cpn;
+                    let file = format!(
+                        "{cpn}/{}-{}.ebuild",
+                        cpn.package.as_str(),
+                        finding.cpv.version
+                    );
+                    writeln!(
+                        out,
+                        "::{level} file={},title={}::{}",
+                        github_property(&file),
+                        github_property(finding.check),
+                        github_escape(&finding.message)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FindingFormat {
+    type Err = Error;
+
+    /// Parse `text`, `json` or `github`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(FindingFormat::Text),
+            "json" => Ok(FindingFormat::Json),
+            "github" => Ok(FindingFormat::Github),
+            _ => Err(Error::InvalidConfig(format!("unknown output format: {s}"))),
+        }
+    }
+}
+
+/// `s` as a JSON string literal.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Escape the characters GitHub workflow commands treat specially.
+fn github_escape(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// [`github_escape`] for a property value, which also ends at `:` or `,`.
+fn github_property(s: &str) -> String {
+    github_escape(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// What a [`MetadataCheck`] sees of the entry it inspects.
+#[derive(Debug, Clone, Copy)]
+pub struct CheckContext<'a> {
+    /// Every entry of the repository.
+    pub repo: &'a BTreeMap<Cpv, CacheEntry>,
+    /// The package version being checked.
+    pub cpv: &'a Cpv,
+    /// Its cache entry.
+    pub entry: &'a CacheEntry,
+    /// The next lower version of the same package, if any.
+    pub previous: Option<(&'a Cpv, &'a CacheEntry)>,
+}
+
+impl CheckContext<'_> {
+    /// A finding for the entry being checked.
+    pub fn finding(
+        &self,
+        check: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Finding {
+        Finding {
+            cpv: self.cpv.clone(),
+            check,
+            severity,
+            message: message.into(),
+        }
+    }
+}
+
+/// A QA check over single cache entries, run by [`CheckRunner`].
+///
+/// QA checks are not specified by PMS.
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{CheckContext, Finding, MetadataCheck, Severity};
+///
+/// struct LongDescription;
+///
+/// impl MetadataCheck for LongDescription {
+///     fn name(&self) -> &'static str {
+///         "long-description"
+///     }
+///
+///     fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+///         if cx.entry.metadata.description.len() > 80 {
+///             findings.push(cx.finding(self.name(), Severity::Warning, "DESCRIPTION is too long"));
+///         }
+///     }
+/// }
+/// ```
+pub trait MetadataCheck: Send + Sync {
+    /// A short identifier, such as `metadata-validate`.
+    fn name(&self) -> &'static str;
+
+    /// Inspect `cx.entry`, pushing any problems to `findings`.
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>);
+}
+
+/// Reports the [`MetadataIssue`]s of [`EbuildMetadata::validate`](crate::EbuildMetadata::validate).
+///
+/// Issues that make the metadata invalid for its EAPI are errors; the
+/// rest are warnings.
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValidateCheck;
+
+impl MetadataCheck for ValidateCheck {
+    fn name(&self) -> &'static str {
+        "metadata-validate"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        for issue in cx.entry.metadata.validate() {
+            let severity = match issue {
+                MetadataIssue::InvalidSlot(_)
+                | MetadataIssue::UnsupportedInEapi { .. }
+                | MetadataIssue::PhaseNotInEapi { .. } => Severity::Error,
+                _ => Severity::Warning,
+            };
+            findings.push(cx.finding(self.name(), severity, issue.to_string()));
+        }
+    }
+}
+
+/// Runs a set of [`MetadataCheck`]s over the entries of a repository.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, Severity, ValidateCheck};
+///
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-1").unwrap(),
+///     CacheEntry::parse("EAPI=6\nDESCRIPTION=x\nSLOT=0\nBDEPEND=dev-libs/bar\n").unwrap(),
+/// )]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(ValidateCheck);
+/// let findings = runner.run(&repo);
+/// assert_eq!(findings[0].severity, Severity::Error);
+/// assert_eq!(
+///     findings[0].to_string(),
+///     "app-misc/foo-1: error [metadata-validate]: BDEPEND is not supported in EAPI 6"
+/// );
+/// ```
+#[derive(Default)]
+pub struct CheckRunner {
+    checks: Vec<Box<dyn MetadataCheck>>,
+    min_severity: Option<Severity>,
+    config: CheckConfig,
+}
+
+impl CheckRunner {
+    /// Create a runner with no checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a check; checks run in the order they were added.
+    pub fn add(&mut self, check: impl MetadataCheck + 'static) -> &mut Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// Drop findings less severe than `severity`.
+    pub fn min_severity(&mut self, severity: Severity) -> &mut Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// Adjust severities and skip findings as `config` says.
+    ///
+    /// The configuration applies before [`CheckRunner::min_severity`].
+    pub fn config(&mut self, config: CheckConfig) -> &mut Self {
+        self.config = config;
+        self
+    }
+
+    /// The names of the registered checks.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.checks.iter().map(|check| check.name())
+    }
+
+    /// Run every check on one entry.
+    pub fn check(&self, cx: &CheckContext<'_>) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for check in &self.checks {
+            check.check(cx, &mut findings);
+        }
+        let mut findings: Vec<Finding> = findings
+            .into_iter()
+            .filter_map(|finding| self.config.apply(finding))
+            .collect();
+        if let Some(min) = self.min_severity {
+            findings.retain(|finding| finding.severity >= min);
+        }
+        findings
+    }
+
+    /// Run every check on every entry of `repo`, as loaded by
+    /// [`MetadataRepo::load`](crate::MetadataRepo::load).
+    ///
+    /// Findings are grouped by package version, in version order.
+    pub fn run(&self, repo: &BTreeMap<Cpv, CacheEntry>) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut previous: Option<(&Cpv, &CacheEntry)> = None;
+        for (cpv, entry) in repo {
+            let cx = CheckContext {
+                repo,
+                cpv,
+                entry,
+                previous: previous.filter(|(prev, _)| prev.cpn == cpv.cpn),
+            };
+            findings.extend(self.check(&cx));
+            previous = Some((cpv, entry));
+        }
+        findings
+    }
+}
+
+impl fmt::Debug for CheckRunner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CheckRunner")
+            .field("checks", &self.names().collect::<Vec<_>>())
+            .field("min_severity", &self.min_severity)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Run `check` alone over a repository of `(cpv, cache entry)` pairs.
+#[cfg(test)]
+pub(crate) fn run_check(
+    check: impl MetadataCheck + 'static,
+    entries: &[(&str, &str)],
+) -> Vec<Finding> {
+    let mut runner = CheckRunner::new();
+    runner.add(check);
+    runner.run(&test_repo(entries))
+}
+
+/// Parse `(cpv, cache entry)` pairs into a repository.
+#[cfg(test)]
+fn test_repo(entries: &[(&str, &str)]) -> BTreeMap<Cpv, CacheEntry> {
+    entries
+        .iter()
+        .map(|(cpv, text)| (Cpv::parse(cpv).unwrap(), CacheEntry::parse(text).unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyword::Stability;
+
+    /// Stable keywords of the previous version missing from this one.
+    struct DroppedStable;
+
+    impl MetadataCheck for DroppedStable {
+        fn name(&self) -> &'static str {
+            "dropped-stable"
+        }
+
+        fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+            let Some((_, previous)) = cx.previous else {
+                return;
+            };
+            let keywords = &cx.entry.metadata.keywords;
+            for old in &previous.metadata.keywords {
+                if old.stability == Stability::Stable
+                    && !keywords.iter().any(|new| new.arch == old.arch)
+                {
+                    let message = format!("dropped {old}");
+                    findings.push(cx.finding(self.name(), Severity::Info, message));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn previous_version() {
+        let repo = test_repo(&[
+            (
+                "app-misc/foo-1",
+                "DESCRIPTION=x\nSLOT=0\nKEYWORDS=amd64 x86\n",
+            ),
+            ("app-misc/foo-2", "DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64\n"),
+            ("app-misc/goo-1", "DESCRIPTION=x\nSLOT=0\n"),
+        ]);
+        let mut runner = CheckRunner::new();
+        runner.add(DroppedStable);
+        let findings: Vec<_> = runner.run(&repo).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            findings,
+            ["app-misc/foo-2: info [dropped-stable]: dropped x86"]
+        );
+
+        runner.min_severity(Severity::Warning);
+        assert!(runner.run(&repo).is_empty());
+    }
+
+    #[test]
+    fn config_and_formats() {
+        let repo = test_repo(&[
+            ("app-misc/foo-1", "DESCRIPTION= \nSLOT=0\n"),
+            ("dev-libs/bar-1", "DESCRIPTION= \nSLOT=0\n"),
+        ]);
+        let config = CheckConfig::parse(
+            "[metadata-validate]\nseverity = \"error\"\nignore = [\"dev-libs\"]\n",
+        )
+        .unwrap();
+        let mut runner = CheckRunner::new();
+        runner.add(ValidateCheck).config(config);
+        let findings = runner.run(&repo);
+
+        let mut out = Vec::new();
+        FindingFormat::Json.write(&mut out, &findings).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[{\"cpv\":\"app-misc/foo-1\",\"check\":\"metadata-validate\",\
+             \"severity\":\"error\",\"message\":\"DESCRIPTION is empty\"}]\n"
+        );
+        let mut out = Vec::new();
+        FindingFormat::Text.write(&mut out, &findings).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "app-misc/foo-1: error [metadata-validate]: DESCRIPTION is empty\n"
+        );
+        assert_eq!("github".parse(), Ok(FindingFormat::Github));
+        assert!("xml".parse::<FindingFormat>().is_err());
+        assert_eq!(github_escape("50%\nmore"), "50%25%0Amore");
+    }
+
+    #[test]
+    fn github_properties_escaped() {
+        let findings = [Finding {
+            cpv: Cpv::parse("app-misc/foo-1").unwrap(),
+            check: "style:long,lines",
+            severity: Severity::Info,
+            message: "a: b, 100%".to_string(),
+        }];
+        let mut out = Vec::new();
+        FindingFormat::Github.write(&mut out, &findings).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "::notice file=app-misc/foo/foo-1.ebuild,title=style%3Along%2Clines::a: b, 100%25\n"
+        );
+    }
+
+    #[test]
+    fn json_strings() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("a \"b\"\\\n"), "\"a \\\"b\\\"\\\\\\n\"");
+        assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn validate_severities() {
+        let repo = test_repo(&[(
+            "app-misc/foo-1",
+            "EAPI=3\nDESCRIPTION= \nSLOT=0\nDEFINED_PHASES=pretend\n",
+        )]);
+        let mut runner = CheckRunner::new();
+        runner.add(ValidateCheck).add(DroppedStable);
+        assert_eq!(
+            runner.names().collect::<Vec<_>>(),
+            ["metadata-validate", "dropped-stable"]
+        );
+        let severities: Vec<_> = runner.run(&repo).iter().map(|f| f.severity).collect();
+        assert_eq!(severities, [Severity::Warning, Severity::Error]);
+    }
+}
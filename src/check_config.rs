@@ -0,0 +1,310 @@
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use portage_atom::Cpv;
+
+use crate::check::{Finding, Severity};
+use crate::error::{Error, Result};
+
+/// Per-check severities and ignores for a [`CheckRunner`](crate::CheckRunner).
+///
+/// The configuration has one table per check name. `severity` replaces
+/// the severity of the check's findings, or disables the check with
+/// `"off"`; `ignore` lists categories and `category/package` names to
+/// skip. A table named after a check and a category or package overrides
+/// the severity for just those entries; the most specific table wins.
+///
+/// The file format is line-based and looks like TOML, but is not TOML:
+///
+/// - `[check]` or `[check."scope"]` starts a table; the check name may be
+///   quoted;
+/// - `key = "value"` and `key = ["a", "b"]` each take exactly one line;
+/// - strings are double-quoted, with `\"` and `\\` as the only escapes;
+/// - `#` outside a string starts a comment.
+///
+/// # Examples
+///
+/// ```
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CheckConfig, Finding, Severity};
+///
+/// let config = CheckConfig::parse(
+///     r#"
+/// [description]
+/// severity = "info"
+/// ignore = ["virtual", "dev-libs/foo"]
+///
+/// [description."app-misc"]
+/// severity = "error"
+/// "#,
+/// )
+/// .unwrap();
+///
+/// let finding = |cpv| Finding {
+///     cpv: Cpv::parse(cpv).unwrap(),
+///     check: "description",
+///     severity: Severity::Warning,
+///     message: "DESCRIPTION ends with a period".to_string(),
+/// };
+/// assert_eq!(config.apply(finding("dev-libs/bar-1")).unwrap().severity, Severity::Info);
+/// assert_eq!(config.apply(finding("app-misc/bar-1")).unwrap().severity, Severity::Error);
+/// assert!(config.apply(finding("dev-libs/foo-1")).is_none());
+/// assert!(config.apply(finding("virtual/baz-1")).is_none());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckConfig {
+    checks: BTreeMap<String, CheckRule>,
+}
+
+/// What the configuration says about one check.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct CheckRule {
+    /// Severities by scope: `""` for every package, else a category or
+    /// `category/package`; `None` disables the check there.
+    severities: BTreeMap<String, Option<Severity>>,
+    /// Categories and packages to skip.
+    ignore: BTreeSet<String>,
+}
+
+impl CheckConfig {
+    /// An empty configuration that leaves findings unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a configuration file.
+    ///
+    /// Unknown keys and malformed lines are reported as
+    /// [`Error::InvalidConfig`] with their line number.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut config = CheckConfig::new();
+        let mut table: Option<(String, String)> = None;
+        for (n, line) in input.lines().enumerate() {
+            let invalid = |what: &str| Error::InvalidConfig(format!("line {}: {what}", n + 1));
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| invalid("unterminated table header"))?;
+                table = Some(parse_header(header.trim()).ok_or_else(|| invalid("bad table name"))?);
+                continue;
+            }
+            let (check, scope) = table
+                .as_ref()
+                .ok_or_else(|| invalid("key outside of a table"))?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `key = value`"))?;
+            let rule = config.checks.entry(check.clone()).or_default();
+            match key.trim() {
+                "severity" => {
+                    let value = parse_string(value.trim()).ok_or_else(|| invalid("bad string"))?;
+                    let severity = match value.as_str() {
+                        "off" => None,
+                        other => Some(other.parse().map_err(|_| invalid("unknown severity"))?),
+                    };
+                    rule.severities.insert(scope.clone(), severity);
+                }
+                "ignore" if scope.is_empty() => {
+                    let values = parse_array(value.trim()).ok_or_else(|| invalid("bad array"))?;
+                    rule.ignore.extend(values);
+                }
+                key => return Err(invalid(&format!("unknown key `{key}`"))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Read and parse a configuration file.
+    #[cfg(feature = "fs")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        Self::parse(&text)
+    }
+
+    /// Apply the configuration to `finding`: `None` if it is ignored or
+    /// its check is off, else the finding with its configured severity.
+    pub fn apply(&self, mut finding: Finding) -> Option<Finding> {
+        let Some(rule) = self.checks.get(finding.check) else {
+            return Some(finding);
+        };
+        let scopes = scopes(&finding.cpv);
+        if scopes.iter().any(|scope| rule.ignore.contains(scope)) {
+            return None;
+        }
+        let configured = scopes
+            .iter()
+            .map(String::as_str)
+            .chain([""])
+            .find_map(|scope| rule.severities.get(scope));
+        match configured {
+            Some(None) => None,
+            Some(&Some(severity)) => {
+                finding.severity = severity;
+                Some(finding)
+            }
+            None => Some(finding),
+        }
+    }
+}
+
+/// The scopes a package version falls in, most specific first.
+fn scopes(cpv: &Cpv) -> [String; 2] {
+    [cpv.cpn.to_string(), cpv.cpn.category.as_str().to_string()]
+}
+
+/// `line` up to a `#` outside of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// `check` or `check."scope"` as (check, scope), with `""` for no scope.
+fn parse_header(header: &str) -> Option<(String, String)> {
+    let (check, scope) = if header.starts_with('"') {
+        let (check, rest) = take_string(header)?;
+        (check, rest.trim())
+    } else {
+        match header.find('.') {
+            Some(i) => (header[..i].trim().to_string(), &header[i..]),
+            None => (header.to_string(), ""),
+        }
+    };
+    let scope = match scope {
+        "" => String::new(),
+        scope => parse_string(scope.strip_prefix('.')?.trim())?,
+    };
+    (!check.is_empty()).then_some((check, scope))
+}
+
+/// A quoted string at the start of `input`, unescaped, and the rest of
+/// `input` after its closing quote.
+fn take_string(input: &str) -> Option<(String, &str)> {
+    let mut chars = input.strip_prefix('"')?.char_indices();
+    let mut out = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            // `i` is relative to the text after the opening quote.
+            '"' => return Some((out, &input[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                c @ ('"' | '\\') => out.push(c),
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+fn parse_string(value: &str) -> Option<String> {
+    let (s, rest) = take_string(value)?;
+    rest.trim().is_empty().then_some(s)
+}
+
+fn parse_array(value: &str) -> Option<Vec<String>> {
+    let mut rest = value.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let mut out = Vec::new();
+    while !rest.is_empty() {
+        let (s, after) = take_string(rest)?;
+        out.push(s);
+        let after = after.trim_start();
+        rest = match after.strip_prefix(',') {
+            Some(after) => after.trim_start(),
+            None if after.is_empty() => after,
+            None => return None,
+        };
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(check: &'static str, cpv: &str) -> Finding {
+        Finding {
+            cpv: Cpv::parse(cpv).unwrap(),
+            check,
+            severity: Severity::Warning,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn scopes_and_off() {
+        let config = CheckConfig::parse(
+            "# lint settings\n\
+             [\"use-flags\"]\n\
+             severity = \"off\" # noisy\n\
+             [use-flags.\"dev-libs/foo\"]\n\
+             severity = \"error\"\n",
+        )
+        .unwrap();
+        assert!(config
+            .apply(finding("use-flags", "dev-libs/bar-1"))
+            .is_none());
+        assert_eq!(
+            config
+                .apply(finding("use-flags", "dev-libs/foo-1"))
+                .unwrap()
+                .severity,
+            Severity::Error
+        );
+        assert_eq!(
+            config.apply(finding("description", "dev-libs/bar-1")),
+            Some(finding("description", "dev-libs/bar-1"))
+        );
+    }
+
+    #[test]
+    fn quoted_strings() {
+        let config = CheckConfig::parse(
+            "[\"odd \\\"name\\\" # here\"] # comment\n\
+             ignore = [\"a,b\", \"c\\\\\" ,] # \"x\"\n",
+        )
+        .unwrap();
+        let rule = &config.checks["odd \"name\" # here"];
+        assert_eq!(
+            rule.ignore,
+            BTreeSet::from(["a,b".to_string(), "c\\".to_string()])
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for (input, message) in [
+            ("severity = \"info\"\n", "line 1: key outside of a table"),
+            ("[a]\nseverity = \"fatal\"\n", "line 2: unknown severity"),
+            ("[a]\nlevel = \"info\"\n", "line 2: unknown key `level`"),
+            (
+                "[a.\"b\"]\nignore = [\"c\"]\n",
+                "line 2: unknown key `ignore`",
+            ),
+            ("[a]\nignore = [c]\n", "line 2: bad array"),
+            ("[a\n", "line 1: unterminated table header"),
+            ("[a]\nseverity = \"in\"fo\"\n", "line 2: bad string"),
+            ("[a]\nseverity = \"info\\q\"\n", "line 2: bad string"),
+        ] {
+            assert_eq!(
+                CheckConfig::parse(input).unwrap_err(),
+                Error::InvalidConfig(message.to_string()),
+                "{input:?}"
+            );
+        }
+    }
+}
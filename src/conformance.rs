@@ -0,0 +1,180 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::error::{Error, Result};
+use crate::metadata_repo::MetadataRepo;
+
+/// How a cache entry failed [`ConformanceReport::verify_tree`].
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConformanceIssue {
+    /// The entry does not parse.
+    Parse(Error),
+    /// The serialized entry does not parse back.
+    Reparse(Error),
+    /// The serialized entry parses back to different metadata.
+    Lossy,
+    /// Serializing the reparsed entry gives different text.
+    NotIdempotent {
+        /// The first serialization.
+        first: String,
+        /// The serialization of the reparsed entry.
+        second: String,
+    },
+}
+
+/// A cache entry that failed verification, found by [`ConformanceReport`].
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceFailure {
+    /// The package version.
+    pub cpv: Cpv,
+    /// What went wrong.
+    pub issue: ConformanceIssue,
+}
+
+/// Whether this crate reads and writes every entry of a metadata cache
+/// faithfully.
+///
+/// Each entry is parsed, serialized and parsed again; the two parses must
+/// agree and serializing the second must reproduce the first
+/// serialization. Run it against a repository snapshot to certify the
+/// crate before relying on it there.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+///
+/// # Examples
+///
+/// ```no_run
+/// use portage_metadata::ConformanceReport;
+///
+/// let report = ConformanceReport::verify_tree("/var/db/repos/gentoo").unwrap();
+/// for failure in &report.failures {
+///     eprintln!("{failure}");
+/// }
+/// println!("{} entries, {} failures", report.checked, report.failures.len());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// The number of entries verified.
+    pub checked: usize,
+    /// Every failing entry, sorted by package version.
+    pub failures: Vec<ConformanceFailure>,
+}
+
+impl ConformanceReport {
+    /// Verify every cache entry of the repository rooted at `root`.
+    ///
+    /// File access failures are returned as errors.
+    pub fn verify_tree(root: impl Into<PathBuf>) -> Result<Self> {
+        Self::verify(&MetadataRepo::new(root))
+    }
+
+    /// Verify every cache entry of `repo`.
+    pub fn verify(repo: &MetadataRepo) -> Result<Self> {
+        let mut report = ConformanceReport::default();
+        for cpv in repo.cpvs()? {
+            let text = std::fs::read_to_string(repo.entry_path(&cpv))
+                .map_err(|e| Error::io(repo.entry_path(&cpv), e))?;
+            report.checked += 1;
+            if let Some(issue) = Self::verify_entry(&text) {
+                report.failures.push(ConformanceFailure { cpv, issue });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Verify a single cache entry, returning what went wrong if anything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{ConformanceIssue, ConformanceReport};
+    ///
+    /// assert_eq!(ConformanceReport::verify_entry("DESCRIPTION=x\nSLOT=0\n"), None);
+    /// assert!(matches!(
+    ///     ConformanceReport::verify_entry("DESCRIPTION=x\n"),
+    ///     Some(ConformanceIssue::Parse(_))
+    /// ));
+    /// ```
+    pub fn verify_entry(text: &str) -> Option<ConformanceIssue> {
+        let entry = match CacheEntry::parse(text) {
+            Ok(entry) => entry,
+            Err(e) => return Some(ConformanceIssue::Parse(e)),
+        };
+        let first = entry.serialize();
+        let reparsed = match CacheEntry::parse(&first) {
+            Ok(reparsed) => reparsed,
+            Err(e) => return Some(ConformanceIssue::Reparse(e)),
+        };
+        if reparsed != entry {
+            return Some(ConformanceIssue::Lossy);
+        }
+        let second = reparsed.serialize();
+        (second != first).then_some(ConformanceIssue::NotIdempotent { first, second })
+    }
+
+    /// Whether every entry passed.
+    pub fn is_conformant(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for ConformanceIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConformanceIssue::Parse(e) => write!(f, "parse failed: {e}"),
+            ConformanceIssue::Reparse(e) => write!(f, "serialized entry does not parse: {e}"),
+            ConformanceIssue::Lossy => f.write_str("round trip changes the metadata"),
+            ConformanceIssue::NotIdempotent { .. } => {
+                f.write_str("serialization is not idempotent")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.cpv, self.issue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+    use std::fs;
+
+    #[test]
+    fn verify_cache() {
+        let root = TempDir::new("conformance");
+        let cache = root.join("metadata/md5-cache");
+        fs::create_dir_all(cache.join("app-misc")).unwrap();
+        fs::write(
+            cache.join("app-misc/foo-1"),
+            "EAPI=8\nDESCRIPTION=foo\nSLOT=0\nIUSE=+ssl  doc\nKEYWORDS=~amd64 amd64\n\
+             RDEPEND=ssl? ( dev-libs/openssl:= )\n",
+        )
+        .unwrap();
+        fs::write(cache.join("app-misc/bar-1"), "DESCRIPTION=bar\n").unwrap();
+
+        let report = ConformanceReport::verify_tree(&root).unwrap();
+        assert_eq!(report.checked, 2);
+        assert!(!report.is_conformant());
+        assert_eq!(
+            report
+                .failures
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["app-misc/bar-1: parse failed: missing required field: SLOT"]
+        );
+
+        assert!(ConformanceReport::verify_tree(root.join("missing")).is_err());
+    }
+}
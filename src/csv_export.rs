@@ -0,0 +1,184 @@
+use std::io::{self, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+#[cfg(feature = "fs")]
+use crate::error::Result;
+use crate::interner::Interner;
+use crate::metadata::EbuildMetadata;
+use crate::metadata_key::MetadataKey;
+#[cfg(feature = "fs")]
+use crate::metadata_repo::MetadataRepo;
+
+/// Flattened CSV export of repository metadata, one row per package
+/// version.
+///
+/// The first column is `CPV`; the others are the selected
+/// [`MetadataKey`]s, holding each value as written in the md5-cache.
+/// Empty values are empty cells. Quoting follows RFC 4180, so the output
+/// loads directly into pandas, DuckDB or a spreadsheet.
+///
+/// The layout is not specified by PMS; the columns are the variables of
+/// [PMS 7](https://projects.gentoo.org/pms/9/pms.html#ebuilddefined-variables).
+///
+/// # Examples
+///
+/// ```
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CsvExport, MetadataKey};
+///
+/// let export = CsvExport::new([MetadataKey::Slot, MetadataKey::Description]);
+/// let entry = CacheEntry::parse("DESCRIPTION=Foo, the tool\nSLOT=0\n").unwrap();
+/// let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+///
+/// let mut out = Vec::new();
+/// export.write(&mut out, [(&cpv, &entry)]).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "CPV,SLOT,DESCRIPTION\napp-misc/foo-1.0,0,\"Foo, the tool\"\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvExport {
+    columns: Vec<MetadataKey>,
+}
+
+impl CsvExport {
+    /// Create an export of the given columns, in order.
+    pub fn new(columns: impl IntoIterator<Item = MetadataKey>) -> Self {
+        CsvExport {
+            columns: columns.into_iter().collect(),
+        }
+    }
+
+    /// The selected columns, without the leading `CPV`.
+    pub fn columns(&self) -> &[MetadataKey] {
+        &self.columns
+    }
+
+    /// The header line, without the line terminator.
+    pub fn header(&self) -> String {
+        let mut line = "CPV".to_string();
+        for key in &self.columns {
+            line.push(',');
+            line.push_str(key.as_str());
+        }
+        line
+    }
+
+    /// The row for one package version, without the line terminator.
+    pub fn row<I: Interner>(&self, cpv: &Cpv, metadata: &EbuildMetadata<I>) -> String {
+        let mut line = quote(&cpv.to_string());
+        for &key in &self.columns {
+            let value = metadata.get(key);
+            line.push(',');
+            if !value.is_empty() {
+                line.push_str(&quote(&value.to_string()));
+            }
+        }
+        line
+    }
+
+    /// Write the header and one row per entry.
+    pub fn write<'a, W, I>(
+        &self,
+        mut out: W,
+        entries: impl IntoIterator<Item = (&'a Cpv, &'a CacheEntry<I>)>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        I: Interner + 'a,
+    {
+        writeln!(out, "{}", self.header())?;
+        for (cpv, entry) in entries {
+            writeln!(out, "{}", self.row(cpv, &entry.metadata))?;
+        }
+        Ok(())
+    }
+
+    /// Export every entry of `repo` to a new file at `path`, sorted by
+    /// package version, and return the number of rows written.
+    ///
+    /// Errors in an entry carry its package version as
+    /// [`Error::Package`](crate::Error::Package) context; file access
+    /// failures are reported as [`Error::Io`](crate::Error::Io).
+    #[cfg(feature = "fs")]
+    pub fn write_repo(&self, repo: &MetadataRepo, path: impl AsRef<Path>) -> Result<usize> {
+        repo.export(path.as_ref(), |out, entries| self.write(out, entries))
+    }
+}
+
+impl Default for CsvExport {
+    /// Export every metadata variable.
+    fn default() -> Self {
+        Self::new(MetadataKey::ALL.iter().copied())
+    }
+}
+
+// RFC 4180: quote cells containing separators, quotes or line breaks.
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("plain"), "plain");
+        assert_eq!(quote("a,b"), "\"a,b\"");
+        assert_eq!(quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn empty_cells() {
+        let export = CsvExport::new([MetadataKey::License, MetadataKey::DefinedPhases]);
+        let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\n").unwrap();
+        let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+        assert_eq!(export.row(&cpv, &entry.metadata), "app-misc/foo-1.0,,");
+    }
+
+    #[test]
+    fn default_columns() {
+        let export = CsvExport::default();
+        assert_eq!(export.columns(), MetadataKey::ALL);
+        assert!(export.header().starts_with("CPV,EAPI,DESCRIPTION,SLOT,"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn repo_to_file() {
+        use crate::temp_dir::TempDir;
+        use std::fs;
+
+        let root = TempDir::new("csv-export");
+        let cache = root.join("metadata/md5-cache");
+        fs::create_dir_all(cache.join("app-misc")).unwrap();
+        fs::create_dir_all(cache.join("dev-libs")).unwrap();
+        fs::write(cache.join("dev-libs/bar-2"), "DESCRIPTION=bar\nSLOT=2\n").unwrap();
+        fs::write(cache.join("app-misc/foo-1.0"), "DESCRIPTION=foo\nSLOT=0\n").unwrap();
+
+        let export = CsvExport::new([MetadataKey::Slot]);
+        let repo = MetadataRepo::new(&root);
+        let out = root.join("metadata.csv");
+        assert_eq!(export.write_repo(&repo, &out).unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "CPV,SLOT\napp-misc/foo-1.0,0\ndev-libs/bar-2,2\n"
+        );
+
+        fs::write(cache.join("dev-libs/bar-2"), "DESCRIPTION=bar\n").unwrap();
+        let err = export.write_repo(&repo, &out).unwrap_err();
+        assert_eq!(err.cpv(), Some("dev-libs/bar-2"));
+        let missing = MetadataRepo::new(root.join("missing"));
+        assert!(export.write_repo(&missing, &out).is_err());
+    }
+}
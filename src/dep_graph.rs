@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use portage_atom::{Cpn, Cpv};
+
+use crate::cache::CacheEntry;
+use crate::check::json_string;
+use crate::dep_spec::DepSpec;
+use crate::interner::Interner;
+use crate::metadata::EbuildMetadata;
+use crate::metadata_key::{FieldValue, MetadataKey};
+
+/// The package-level dependency graph of a set of cache entries.
+///
+/// Nodes are packages (`category/package`); there is an edge from a
+/// package to every package named by an atom in one of its versions'
+/// dependency classes, labelled with those classes. Atoms under
+/// USE-conditional and `||` groups are included; blockers are not
+/// dependencies and are skipped.
+///
+/// See [PMS 8.1](https://projects.gentoo.org/pms/9/pms.html#dependency-classes).
+///
+/// # Examples
+///
+/// ```
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, DepGraph, DotOptions};
+///
+/// let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nRDEPEND=dev-libs/bar !app-misc/old\n").unwrap();
+/// let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+/// let graph = DepGraph::new([(&cpv, &entry)]);
+///
+/// let mut dot = Vec::new();
+/// graph.to_dot(&mut dot, &DotOptions::default()).unwrap();
+/// assert_eq!(
+///     String::from_utf8(dot).unwrap(),
+///     "digraph deps {\n  \"app-misc/foo\";\n  \"dev-libs/bar\";\n  \
+///      \"app-misc/foo\" -> \"dev-libs/bar\" [label=\"RDEPEND\"];\n}\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepGraph {
+    packages: BTreeSet<Cpn>,
+    edges: BTreeMap<(Cpn, Cpn), BTreeSet<MetadataKey>>,
+}
+
+/// What [`DepGraph::to_dot`] draws.
+///
+/// See [PMS 8.1](https://projects.gentoo.org/pms/9/pms.html#dependency-classes).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Dependency classes to draw; edges with none of them are left out.
+    pub classes: Vec<MetadataKey>,
+    /// Draw one node per category instead of per package, dropping edges
+    /// within a category.
+    pub by_category: bool,
+}
+
+impl Default for DotOptions {
+    /// Every dependency class, one node per package.
+    fn default() -> Self {
+        DotOptions {
+            classes: MetadataKey::DEPENDENCIES.to_vec(),
+            by_category: false,
+        }
+    }
+}
+
+impl DepGraph {
+    /// Build the graph of `entries`.
+    pub fn new<'a, I: Interner + 'a>(
+        entries: impl IntoIterator<Item = (&'a Cpv, &'a CacheEntry<I>)>,
+    ) -> Self {
+        let mut graph = DepGraph::default();
+        for (cpv, entry) in entries {
+            graph.add(cpv, &entry.metadata);
+        }
+        graph
+    }
+
+    /// Add the dependencies of one package version.
+    pub fn add<I: Interner>(&mut self, cpv: &Cpv, metadata: &EbuildMetadata<I>) {
+        self.packages.insert(cpv.cpn);
+        for &class in MetadataKey::DEPENDENCIES {
+            if let FieldValue::Deps(entries) = metadata.get(class) {
+                let targets: BTreeSet<Cpn> = DepSpec::from_deps(entries)
+                    .leaves()
+                    .filter(|dep| dep.blocker.is_none())
+                    .map(|dep| dep.cpn)
+                    .collect();
+                for target in targets {
+                    self.packages.insert(target);
+                    self.edges
+                        .entry((cpv.cpn, target))
+                        .or_default()
+                        .insert(class);
+                }
+            }
+        }
+    }
+
+    /// Every package, as a source or a target.
+    pub fn packages(&self) -> impl Iterator<Item = &Cpn> {
+        self.packages.iter()
+    }
+
+    /// Every edge with its dependency classes, sorted.
+    pub fn edges(&self) -> impl Iterator<Item = (&Cpn, &Cpn, &BTreeSet<MetadataKey>)> {
+        self.edges
+            .iter()
+            .map(|((from, to), classes)| (from, to, classes))
+    }
+
+    /// Write the graph in Graphviz DOT format.
+    pub fn to_dot(&self, mut out: impl Write, options: &DotOptions) -> io::Result<()> {
+        let node = |cpn: &Cpn| {
+            if options.by_category {
+                cpn.category.as_str().to_string()
+            } else {
+                cpn.to_string()
+            }
+        };
+        let nodes: BTreeSet<String> = self.packages.iter().map(node).collect();
+        let mut edges: BTreeMap<(String, String), BTreeSet<MetadataKey>> = BTreeMap::new();
+        for ((from, to), classes) in &self.edges {
+            let (from, to) = (node(from), node(to));
+            if from == to && options.by_category {
+                continue;
+            }
+            let classes: Vec<_> = classes
+                .iter()
+                .filter(|class| options.classes.contains(class))
+                .collect();
+            if !classes.is_empty() {
+                edges.entry((from, to)).or_default().extend(classes);
+            }
+        }
+
+        // Quoted DOT ids escape backslashes and double quotes as JSON does.
+        writeln!(out, "digraph deps {{")?;
+        for node in &nodes {
+            writeln!(out, "  {};", json_string(node))?;
+        }
+        for ((from, to), classes) in &edges {
+            let label: Vec<_> = classes.iter().map(|class| class.as_str()).collect();
+            writeln!(
+                out,
+                "  {} -> {} [label={}];",
+                json_string(from),
+                json_string(to),
+                json_string(&label.join(" "))
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(entries: &[(&str, &str)]) -> DepGraph {
+        let entries: Vec<(Cpv, CacheEntry)> = entries
+            .iter()
+            .map(|(cpv, text)| (Cpv::parse(cpv).unwrap(), CacheEntry::parse(text).unwrap()))
+            .collect();
+        DepGraph::new(entries.iter().map(|(cpv, entry)| (cpv, entry)))
+    }
+
+    fn dot(graph: &DepGraph, options: &DotOptions) -> String {
+        let mut out = Vec::new();
+        graph.to_dot(&mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn edges() {
+        let graph = graph(&[
+            (
+                "app-misc/foo-1",
+                "DESCRIPTION=x\nSLOT=0\nDEPEND=dev-libs/bar\nRDEPEND=ssl? ( >=dev-libs/bar-2 )\n",
+            ),
+            (
+                "app-misc/foo-2",
+                "DESCRIPTION=x\nSLOT=0\nBDEPEND=|| ( dev-lang/a app-misc/baz )\n",
+            ),
+        ]);
+        let edges: Vec<_> = graph
+            .edges()
+            .map(|(from, to, classes)| (from.to_string(), to.to_string(), classes.len()))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                ("app-misc/foo".to_string(), "app-misc/baz".to_string(), 1),
+                ("app-misc/foo".to_string(), "dev-lang/a".to_string(), 1),
+                ("app-misc/foo".to_string(), "dev-libs/bar".to_string(), 2),
+            ]
+        );
+        assert_eq!(graph.packages().count(), 4);
+    }
+
+    #[test]
+    fn dot_options() {
+        let graph = graph(&[(
+            "app-misc/foo-1",
+            "DESCRIPTION=x\nSLOT=0\nDEPEND=dev-libs/bar app-misc/baz\nRDEPEND=dev-libs/bar\n",
+        )]);
+        let options = DotOptions {
+            classes: vec![MetadataKey::Rdepend],
+            by_category: false,
+        };
+        assert_eq!(
+            dot(&graph, &options),
+            "digraph deps {\n  \"app-misc/baz\";\n  \"app-misc/foo\";\n  \"dev-libs/bar\";\n  \
+             \"app-misc/foo\" -> \"dev-libs/bar\" [label=\"RDEPEND\"];\n}\n"
+        );
+        let options = DotOptions {
+            by_category: true,
+            ..DotOptions::default()
+        };
+        assert_eq!(
+            dot(&graph, &options),
+            "digraph deps {\n  \"app-misc\";\n  \"dev-libs\";\n  \
+             \"app-misc\" -> \"dev-libs\" [label=\"DEPEND RDEPEND\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,1107 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use portage_atom::{Dep, DepEntry};
+use winnow::ascii::{multispace0, multispace1};
+use winnow::combinator::{alt, cut_err, delimited, opt, peek, preceded, repeat};
+use winnow::error::StrContext;
+use winnow::prelude::*;
+use winnow::token::take_while;
+
+use crate::auto_enforce::UseFlagState;
+use crate::interner::Interner;
+use crate::license::LicenseExpr;
+use crate::pretty::write_pretty;
+use crate::properties::{PropertiesExpr, PropertyKind};
+use crate::required_use::RequiredUseExpr;
+use crate::restrict::{RestrictExpr, RestrictKind};
+use crate::src_uri::SrcUriEntry;
+use crate::use_flags::UseFlagSet;
+
+/// A generic dependency-specification tree.
+///
+/// The dependency classes, `LICENSE`, `SRC_URI`, `REQUIRED_USE`,
+/// `RESTRICT` and `PROPERTIES` all share the grammar of PMS 8.2 and
+/// differ only in their leaves and in which group kinds they allow.
+/// `DepSpec<L>` captures that shared shape, and each variable-specific
+/// type converts into it with [`From`]:
+///
+/// | Type | Leaf |
+/// |------|------|
+/// | `[DepEntry]` | [`Dep`] |
+/// | [`LicenseExpr`] | `String` |
+/// | [`RequiredUseExpr`] | [`UseFlagState`] |
+/// | `[SrcUriEntry]` | [`SrcUriEntry`] (URI entries only) |
+/// | `[RestrictExpr]` | [`RestrictKind`] |
+/// | `[PropertiesExpr]` | [`PropertyKind`] |
+///
+/// The variable-specific types are not aliases of `DepSpec`: their
+/// variants are public API and stay as they are. They share a single
+/// parser, `Display`, traversal and USE evaluation implemented in this
+/// module, and [`LicenseExpr`] and [`RequiredUseExpr`] normalize through
+/// [`DepSpec::normalize`].
+///
+/// Conditional flags are `String`s by default; [`DepSpec::from_deps`]
+/// builds a tree borrowing both atoms and flags instead.
+///
+/// See [PMS 8.2](https://projects.gentoo.org/pms/9/pms.html#dependency-specification-format).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DepSpec<L, F = String> {
+    /// A leaf value.
+    Leaf(L),
+    /// `( ... )` — all children apply.
+    AllOf(Vec<DepSpec<L, F>>),
+    /// `|| ( ... )` — at least one child applies.
+    AnyOf(Vec<DepSpec<L, F>>),
+    /// `^^ ( ... )` — exactly one child applies.
+    ExactlyOne(Vec<DepSpec<L, F>>),
+    /// `?? ( ... )` — at most one child applies.
+    AtMostOne(Vec<DepSpec<L, F>>),
+    /// `flag? ( ... )` or `!flag? ( ... )` conditional group.
+    UseConditional {
+        /// USE flag name.
+        flag: F,
+        /// `true` for `!flag?` (negated conditional).
+        negated: bool,
+        /// Children guarded by this flag.
+        entries: Vec<DepSpec<L, F>>,
+    },
+}
+
+impl<L, F> DepSpec<L, F> {
+    /// The children of a group, or an empty slice for a leaf.
+    pub fn children(&self) -> &[DepSpec<L, F>] {
+        match self {
+            DepSpec::Leaf(_) => &[],
+            DepSpec::AllOf(entries)
+            | DepSpec::AnyOf(entries)
+            | DepSpec::ExactlyOne(entries)
+            | DepSpec::AtMostOne(entries)
+            | DepSpec::UseConditional { entries, .. } => entries,
+        }
+    }
+
+    /// Consume the node, returning its children (or itself for a leaf).
+    fn into_children(self) -> Vec<DepSpec<L, F>> {
+        match self {
+            DepSpec::Leaf(_) => vec![self],
+            DepSpec::AllOf(entries)
+            | DepSpec::AnyOf(entries)
+            | DepSpec::ExactlyOne(entries)
+            | DepSpec::AtMostOne(entries)
+            | DepSpec::UseConditional { entries, .. } => entries,
+        }
+    }
+
+    /// Call `f` on this node and every descendant, in pre-order.
+    pub fn visit<'a>(&'a self, f: &mut impl FnMut(&'a DepSpec<L, F>)) {
+        f(self);
+        for child in self.children() {
+            child.visit(f);
+        }
+    }
+
+    /// Walk the tree depth-first, calling `visitor` on entry and exit of
+    /// every node.
+    pub fn walk<V: SpecVisitor<Self> + ?Sized>(&self, visitor: &mut V) {
+        walk(self, DepSpec::children, visitor, 0);
+    }
+
+    /// Iterate over every leaf, ignoring the tree structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{DepSpec, LicenseExpr};
+    ///
+    /// let expr = LicenseExpr::parse("MIT ssl? ( || ( GPL-2 BSD ) )").unwrap();
+    /// let spec = DepSpec::from(&expr);
+    /// let leaves: Vec<&String> = spec.leaves().collect();
+    /// assert_eq!(leaves, ["MIT", "GPL-2", "BSD"]);
+    /// ```
+    pub fn leaves(&self) -> impl Iterator<Item = &L> {
+        let mut out = Vec::new();
+        self.visit(&mut |node| {
+            if let DepSpec::Leaf(leaf) = node {
+                out.push(leaf);
+            }
+        });
+        out.into_iter()
+    }
+
+    /// How deeply groups nest: 0 for a leaf, else one more than the
+    /// deepest child.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{DepSpec, LicenseExpr};
+    ///
+    /// let expr = LicenseExpr::parse("ssl? ( || ( GPL-2 BSD ) )").unwrap();
+    /// assert_eq!(DepSpec::from(&expr).depth(), 2);
+    /// ```
+    pub fn depth(&self) -> usize {
+        match self {
+            DepSpec::Leaf(_) => 0,
+            _ => {
+                1 + self
+                    .children()
+                    .iter()
+                    .map(DepSpec::depth)
+                    .max()
+                    .unwrap_or(0)
+            }
+        }
+    }
+
+    /// The flags of every USE-conditional group, sorted and deduplicated.
+    pub fn conditional_flags(&self) -> BTreeSet<&str>
+    where
+        F: AsRef<str>,
+    {
+        let mut out = BTreeSet::new();
+        self.visit(&mut |node| {
+            if let DepSpec::UseConditional { flag, .. } = node {
+                out.insert(flag.as_ref());
+            }
+        });
+        out
+    }
+
+    /// Convert every leaf with `f`, keeping the tree structure.
+    pub fn map<M>(&self, mut f: impl FnMut(&L) -> M) -> DepSpec<M, F>
+    where
+        F: Clone,
+    {
+        self.map_with(&mut f)
+    }
+
+    fn map_with<M>(&self, f: &mut impl FnMut(&L) -> M) -> DepSpec<M, F>
+    where
+        F: Clone,
+    {
+        let map = |entries: &[DepSpec<L, F>], f: &mut _| {
+            entries.iter().map(|entry| entry.map_with(f)).collect()
+        };
+        match self {
+            DepSpec::Leaf(leaf) => DepSpec::Leaf(f(leaf)),
+            DepSpec::AllOf(entries) => DepSpec::AllOf(map(entries, f)),
+            DepSpec::AnyOf(entries) => DepSpec::AnyOf(map(entries, f)),
+            DepSpec::ExactlyOne(entries) => DepSpec::ExactlyOne(map(entries, f)),
+            DepSpec::AtMostOne(entries) => DepSpec::AtMostOne(map(entries, f)),
+            DepSpec::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => DepSpec::UseConditional {
+                flag: flag.clone(),
+                negated: *negated,
+                entries: map(entries, f),
+            },
+        }
+    }
+
+    /// The leaves outside `||`, `^^` and `??` groups, each with the
+    /// USE conditionals guarding it, outermost first, as `(flag, negated)`.
+    ///
+    /// These are the leaves that all apply together once their conditions
+    /// hold; alternatives are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{DepSpec, LicenseExpr};
+    ///
+    /// let expr = LicenseExpr::parse("MIT ssl? ( !bindist? ( OpenSSL ) || ( GPL-2 BSD ) )").unwrap();
+    /// let spec = DepSpec::from(&expr);
+    /// let required: Vec<_> = spec.required_leaves();
+    /// assert_eq!(required[0], (vec![], &"MIT".to_string()));
+    /// assert_eq!(required[1], (vec![("ssl", false), ("bindist", true)], &"OpenSSL".to_string()));
+    /// assert_eq!(required.len(), 2);
+    /// ```
+    pub fn required_leaves(&self) -> Vec<(Vec<(&str, bool)>, &L)>
+    where
+        F: AsRef<str>,
+    {
+        let mut out = Vec::new();
+        self.required_into(&mut Vec::new(), &mut out);
+        out
+    }
+
+    fn required_into<'a>(
+        &'a self,
+        guards: &mut Vec<(&'a str, bool)>,
+        out: &mut Vec<(Vec<(&'a str, bool)>, &'a L)>,
+    ) where
+        F: AsRef<str>,
+    {
+        match self {
+            DepSpec::Leaf(leaf) => out.push((guards.clone(), leaf)),
+            DepSpec::AllOf(entries) => {
+                for entry in entries {
+                    entry.required_into(guards, out);
+                }
+            }
+            DepSpec::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => {
+                guards.push((flag.as_ref(), *negated));
+                for entry in entries {
+                    entry.required_into(guards, out);
+                }
+                guards.pop();
+            }
+            DepSpec::AnyOf(_) | DepSpec::ExactlyOne(_) | DepSpec::AtMostOne(_) => {}
+        }
+    }
+
+    /// Resolve USE-conditional groups against `use_flags`.
+    ///
+    /// Groups whose condition holds are replaced by their children, others
+    /// are dropped. Choice groups (`||`, `^^`, `??`) are kept.
+    pub fn evaluate(&self, use_flags: &UseFlagSet) -> DepSpec<L, F>
+    where
+        L: Clone,
+        F: Clone + AsRef<str>,
+    {
+        DepSpec::AllOf(evaluate_into(std::slice::from_ref(self), use_flags))
+    }
+
+    /// Return a canonical form of the tree.
+    ///
+    /// Nested all-of groups and same-kind `||` groups are flattened,
+    /// single-child groups are unwrapped where that preserves meaning,
+    /// groups that always hold are dropped, duplicates are removed except
+    /// within `^^` and `??` groups, and children are sorted. An empty `^^`
+    /// group can never hold and is kept.
+    pub fn normalize(&self) -> DepSpec<L, F>
+    where
+        L: Clone + Ord,
+        F: Clone + Ord,
+    {
+        let mut entries = normalize_all(std::slice::from_ref(self));
+        match entries.len() {
+            1 => entries.pop().unwrap(),
+            _ => DepSpec::AllOf(entries),
+        }
+    }
+}
+
+/// A depth-first visitor over a tree of type `T`: a [`DepSpec`], a
+/// [`LicenseExpr`] or a [`RequiredUseExpr`].
+///
+/// Both methods default to doing nothing; implement the ones you need and
+/// pass the visitor to the `walk` method of the tree. `depth` is 0 for the
+/// root.
+///
+/// See [PMS 8.2](https://projects.gentoo.org/pms/9/pms.html#dependency-specification-format).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{LicenseExpr, SpecVisitor};
+///
+/// struct MaxDepth(usize);
+///
+/// impl SpecVisitor<LicenseExpr> for MaxDepth {
+///     fn enter(&mut self, _: &LicenseExpr, depth: usize) {
+///         self.0 = self.0.max(depth);
+///     }
+/// }
+///
+/// let expr = LicenseExpr::parse("MIT doc? ( CC-BY-4.0 )").unwrap();
+/// let mut visitor = MaxDepth(0);
+/// expr.walk(&mut visitor);
+/// assert_eq!(visitor.0, 2);
+/// ```
+pub trait SpecVisitor<T: ?Sized> {
+    /// Called before the children of `node` are visited.
+    fn enter(&mut self, node: &T, depth: usize) {
+        let _ = (node, depth);
+    }
+
+    /// Called after the children of `node` have been visited.
+    fn leave(&mut self, node: &T, depth: usize) {
+        let _ = (node, depth);
+    }
+}
+
+/// Walk the tree below `node` for [`SpecVisitor`], finding the children of
+/// each node with `children`.
+pub(crate) fn walk<T, V: SpecVisitor<T> + ?Sized>(
+    node: &T,
+    children: fn(&T) -> &[T],
+    visitor: &mut V,
+    depth: usize,
+) {
+    visitor.enter(node, depth);
+    for child in children(node) {
+        walk(child, children, visitor, depth + 1);
+    }
+    visitor.leave(node, depth);
+}
+
+fn evaluate_into<L: Clone, F: Clone + AsRef<str>>(
+    entries: &[DepSpec<L, F>],
+    use_flags: &UseFlagSet,
+) -> Vec<DepSpec<L, F>> {
+    let mut out = Vec::new();
+    for entry in entries {
+        match entry {
+            DepSpec::Leaf(_) => out.push(entry.clone()),
+            DepSpec::AllOf(entries) => out.extend(evaluate_into(entries, use_flags)),
+            DepSpec::AnyOf(entries) => out.push(DepSpec::AnyOf(evaluate_group(entries, use_flags))),
+            DepSpec::ExactlyOne(entries) => {
+                out.push(DepSpec::ExactlyOne(evaluate_group(entries, use_flags)))
+            }
+            DepSpec::AtMostOne(entries) => {
+                out.push(DepSpec::AtMostOne(evaluate_group(entries, use_flags)))
+            }
+            DepSpec::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => {
+                if use_flags.is_enabled(flag.as_ref()) != *negated {
+                    out.extend(evaluate_into(entries, use_flags));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Evaluate the children of a choice group, keeping each child a single node.
+fn evaluate_group<L: Clone, F: Clone + AsRef<str>>(
+    entries: &[DepSpec<L, F>],
+    use_flags: &UseFlagSet,
+) -> Vec<DepSpec<L, F>> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let mut all = evaluate_into(std::slice::from_ref(entry), use_flags);
+            match all.len() {
+                0 => None,
+                1 => all.pop(),
+                _ => Some(DepSpec::AllOf(all)),
+            }
+        })
+        .collect()
+}
+
+/// Normalize a list of entries that must all apply.
+fn normalize_all<L: Clone + Ord, F: Clone + Ord>(entries: &[DepSpec<L, F>]) -> Vec<DepSpec<L, F>> {
+    let mut out: Vec<DepSpec<L, F>> = entries.iter().flat_map(normalize_entry).collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Normalize one entry into the all-of entries it contributes to its parent.
+fn normalize_entry<L: Clone + Ord, F: Clone + Ord>(entry: &DepSpec<L, F>) -> Vec<DepSpec<L, F>> {
+    match entry {
+        DepSpec::Leaf(_) => vec![entry.clone()],
+        DepSpec::AllOf(entries) => normalize_all(entries),
+        DepSpec::AnyOf(entries) => {
+            let choices = normalize_choices(entries, true);
+            // An empty choice always holds, and so does the group.
+            if choices.contains(&DepSpec::AllOf(Vec::new())) {
+                return Vec::new();
+            }
+            let mut out = Vec::new();
+            for child in choices {
+                match child {
+                    DepSpec::AnyOf(inner) => out.extend(inner),
+                    other => out.push(other),
+                }
+            }
+            out.sort();
+            out.dedup();
+            match out.len() {
+                0 => out,
+                1 => into_all(out.pop().unwrap()),
+                _ => vec![DepSpec::AnyOf(out)],
+            }
+        }
+        DepSpec::ExactlyOne(entries) => {
+            let mut out = normalize_choices(entries, false);
+            match out.len() {
+                1 => into_all(out.pop().unwrap()),
+                _ => vec![DepSpec::ExactlyOne(out)],
+            }
+        }
+        DepSpec::AtMostOne(entries) => {
+            let out = normalize_choices(entries, false);
+            match out.len() {
+                0 | 1 => Vec::new(),
+                _ => vec![DepSpec::AtMostOne(out)],
+            }
+        }
+        DepSpec::UseConditional {
+            flag,
+            negated,
+            entries,
+        } => {
+            let entries = normalize_all(entries);
+            if entries.is_empty() {
+                return Vec::new();
+            }
+            vec![DepSpec::UseConditional {
+                flag: flag.clone(),
+                negated: *negated,
+                entries,
+            }]
+        }
+    }
+}
+
+/// Normalize the children of a choice group, each as a single node.
+///
+/// Children normalizing to nothing always hold and are kept as empty
+/// all-of groups. Repeated children are only removed with `dedup`: they
+/// change the count `^^` and `??` constrain, but not the outcome of `||`.
+fn normalize_choices<L: Clone + Ord, F: Clone + Ord>(
+    entries: &[DepSpec<L, F>],
+    dedup: bool,
+) -> Vec<DepSpec<L, F>> {
+    let mut out: Vec<DepSpec<L, F>> = entries
+        .iter()
+        .map(|entry| {
+            let mut all = normalize_entry(entry);
+            match all.len() {
+                1 => all.pop().unwrap(),
+                _ => DepSpec::AllOf(all),
+            }
+        })
+        .collect();
+    out.sort();
+    if dedup {
+        out.dedup();
+    }
+    out
+}
+
+/// The all-of entries the only remaining child of a choice group
+/// contributes to its parent.
+fn into_all<L, F>(child: DepSpec<L, F>) -> Vec<DepSpec<L, F>> {
+    match child {
+        DepSpec::AllOf(entries) => entries,
+        other => vec![other],
+    }
+}
+
+impl<L: fmt::Display, F: AsRef<str>> SpecTree for DepSpec<L, F> {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            DepSpec::Leaf(_) => Shape::Leaf,
+            DepSpec::AllOf(entries) => Shape::Group(GroupKind::AllOf, entries),
+            DepSpec::AnyOf(entries) => Shape::Group(GroupKind::AnyOf, entries),
+            DepSpec::ExactlyOne(entries) => Shape::Group(GroupKind::ExactlyOne, entries),
+            DepSpec::AtMostOne(entries) => Shape::Group(GroupKind::AtMostOne, entries),
+            DepSpec::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => Shape::UseConditional {
+                flag: flag.as_ref(),
+                negated: *negated,
+                entries,
+            },
+        }
+    }
+
+    fn fmt_leaf(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DepSpec::Leaf(leaf) => write!(f, "{leaf}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<L: fmt::Display, F: AsRef<str>> fmt::Display for DepSpec<L, F> {
+    /// `{:#}` prints an indented multi-line tree.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_spec(f, self, true)
+    }
+}
+
+/// The kinds of group in PMS 8.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GroupKind {
+    /// `( ... )`
+    AllOf,
+    /// `|| ( ... )`
+    AnyOf,
+    /// `^^ ( ... )`
+    ExactlyOne,
+    /// `?? ( ... )`
+    AtMostOne,
+}
+
+impl GroupKind {
+    /// The operator before the opening parenthesis; empty for all-of.
+    fn operator(self) -> &'static str {
+        match self {
+            GroupKind::AllOf => "",
+            GroupKind::AnyOf => "||",
+            GroupKind::ExactlyOne => "^^",
+            GroupKind::AtMostOne => "??",
+        }
+    }
+}
+
+/// One node of a [`SpecTree`], with its children borrowed.
+pub(crate) enum Shape<'a, T> {
+    /// A leaf value.
+    Leaf,
+    /// A group of the given kind.
+    Group(GroupKind, &'a [T]),
+    /// `flag? ( ... )` or `!flag? ( ... )`.
+    UseConditional {
+        flag: &'a str,
+        negated: bool,
+        entries: &'a [T],
+    },
+}
+
+impl<T> Shape<'_, T> {
+    /// What a group prints before its opening parenthesis, e.g. `"|| "`
+    /// or `"!flag? "`; empty for an all-of group or a leaf.
+    pub(crate) fn prefix(&self) -> String {
+        match self {
+            Shape::Leaf | Shape::Group(GroupKind::AllOf, _) => String::new(),
+            Shape::Group(kind, _) => format!("{} ", kind.operator()),
+            Shape::UseConditional { flag, negated, .. } => {
+                format!("{}{flag}? ", if *negated { "!" } else { "" })
+            }
+        }
+    }
+}
+
+/// An expression tree in the grammar of PMS 8.2.
+///
+/// [`DepSpec`] and the variable-specific expression types implement this
+/// so that printing, traversal and USE evaluation exist only once, here.
+pub(crate) trait SpecTree: Sized {
+    /// The shape of this node.
+    fn shape(&self) -> Shape<'_, Self>;
+
+    /// Write this node, which has [`Shape::Leaf`].
+    fn fmt_leaf(&self, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+/// A [`SpecTree`] parsed by [`parse_spec`].
+pub(crate) trait ParseSpec: SpecTree {
+    /// The choice groups the grammar allows; all-of and USE-conditional
+    /// groups are always allowed.
+    const CHOICES: &'static [GroupKind] = &[];
+
+    /// Parse a leaf.
+    fn parse_leaf(input: &mut &str) -> ModalResult<Self>;
+
+    /// Build a group of `kind`, one of [`ParseSpec::CHOICES`].
+    fn choice(kind: GroupKind, entries: Vec<Self>) -> Self {
+        let _ = entries;
+        unreachable!("{kind:?} is not a choice of this grammar")
+    }
+
+    /// Build a USE-conditional group.
+    fn conditional(flag: String, negated: bool, entries: Vec<Self>) -> Self;
+
+    /// The entries a bare `( ... )` group contributes to its parent; by
+    /// default the group is flattened into it.
+    fn all_of(entries: Vec<Self>) -> Vec<Self> {
+        entries
+    }
+}
+
+/// The children of `node`, or an empty slice for a leaf.
+pub(crate) fn children<T: SpecTree>(node: &T) -> &[T] {
+    match node.shape() {
+        Shape::Leaf => &[],
+        Shape::Group(_, entries) | Shape::UseConditional { entries, .. } => entries,
+    }
+}
+
+/// `node` and all its descendants, depth-first in pre-order.
+pub(crate) fn iter<T: SpecTree>(node: &T) -> impl Iterator<Item = &T> {
+    let mut stack = vec![node];
+    std::iter::from_fn(move || {
+        let node = stack.pop()?;
+        stack.extend(children(node).iter().rev());
+        Some(node)
+    })
+}
+
+/// Every leaf below `entries`, in source order, ignoring USE conditionals.
+pub(crate) fn leaves<T: SpecTree>(entries: &[T]) -> Vec<&T> {
+    entries
+        .iter()
+        .flat_map(iter)
+        .filter(|node| matches!(node.shape(), Shape::Leaf))
+        .collect()
+}
+
+/// The leaves below `entries` that apply under `use_flags`, in source
+/// order. Like [`DepSpec::required_leaves`], choice groups are left out.
+pub(crate) fn active_leaves<'a, T: SpecTree>(
+    entries: &'a [T],
+    use_flags: &UseFlagSet,
+) -> Vec<&'a T> {
+    let mut out = Vec::new();
+    for entry in entries {
+        match entry.shape() {
+            Shape::Leaf => out.push(entry),
+            Shape::Group(GroupKind::AllOf, entries) => {
+                out.extend(active_leaves(entries, use_flags))
+            }
+            Shape::Group(..) => {}
+            Shape::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => {
+                if use_flags.is_enabled(flag) != negated {
+                    out.extend(active_leaves(entries, use_flags));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Add the flag of every USE-conditional group below `entries` to `out`.
+pub(crate) fn collect_flags<'a, T: SpecTree>(entries: &'a [T], out: &mut BTreeSet<&'a str>) {
+    for node in entries.iter().flat_map(iter) {
+        if let Shape::UseConditional { flag, .. } = node.shape() {
+            out.insert(flag);
+        }
+    }
+}
+
+/// Write `node` in the syntax of PMS 8.2, or as an indented tree for
+/// `{:#}`. An all-of group is parenthesized unless it is the `root` of
+/// the expression.
+pub(crate) fn fmt_spec<T: SpecTree + fmt::Display>(
+    f: &mut fmt::Formatter,
+    node: &T,
+    root: bool,
+) -> fmt::Result {
+    if f.alternate() {
+        return write_pretty(f, node, root);
+    }
+    write_node(f, node, root)
+}
+
+fn write_node<T: SpecTree>(f: &mut fmt::Formatter, node: &T, root: bool) -> fmt::Result {
+    let shape = node.shape();
+    match shape {
+        Shape::Leaf => node.fmt_leaf(f),
+        Shape::Group(GroupKind::AllOf, entries) if root => write_entries(f, entries),
+        Shape::Group(_, entries) | Shape::UseConditional { entries, .. } => {
+            write!(f, "{}(", shape.prefix())?;
+            if !entries.is_empty() {
+                write!(f, " ")?;
+                write_entries(f, entries)?;
+            }
+            write!(f, " )")
+        }
+    }
+}
+
+/// Write space-separated entries; nested all-of groups get parentheses.
+fn write_entries<T: SpecTree>(f: &mut fmt::Formatter, entries: &[T]) -> fmt::Result {
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write_node(f, entry, false)?;
+    }
+    Ok(())
+}
+
+impl<'a> DepSpec<&'a Dep, &'a str> {
+    /// Build a tree borrowing the atoms and flags of `entries`, without
+    /// cloning them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_atom::DepEntry;
+    /// use portage_metadata::DepSpec;
+    ///
+    /// let entries = DepEntry::parse("dev-libs/a ssl? ( || ( dev-libs/b dev-libs/c ) )").unwrap();
+    /// let spec = DepSpec::from_deps(&entries);
+    /// assert_eq!(spec.leaves().count(), 3);
+    /// assert!(spec.conditional_flags().contains("ssl"));
+    /// ```
+    pub fn from_deps(entries: &'a [DepEntry]) -> Self {
+        convert_deps(entries, &|dep| dep, &|flag| flag)
+    }
+}
+
+impl From<&[DepEntry]> for DepSpec<Dep> {
+    fn from(entries: &[DepEntry]) -> Self {
+        convert_deps(entries, &Dep::clone, &str::to_string)
+    }
+}
+
+/// Convert `entries`, building leaves with `leaf` and flags with `flag`.
+fn convert_deps<'a, L, F>(
+    entries: &'a [DepEntry],
+    leaf: &impl Fn(&'a Dep) -> L,
+    flag: &impl Fn(&'a str) -> F,
+) -> DepSpec<L, F> {
+    let convert = |entries: &'a [DepEntry]| convert_deps(entries, leaf, flag).into_children();
+    DepSpec::AllOf(
+        entries
+            .iter()
+            .map(|entry| match entry {
+                DepEntry::Atom(dep) => DepSpec::Leaf(leaf(dep)),
+                DepEntry::UseConditional {
+                    flag: name,
+                    negate,
+                    children,
+                } => DepSpec::UseConditional {
+                    flag: flag(name.as_str()),
+                    negated: *negate,
+                    entries: convert(children),
+                },
+                DepEntry::AllOf(children) => convert_deps(children, leaf, flag),
+                DepEntry::AnyOf(children) => DepSpec::AnyOf(convert(children)),
+                DepEntry::ExactlyOneOf(children) => DepSpec::ExactlyOne(convert(children)),
+                DepEntry::AtMostOneOf(children) => DepSpec::AtMostOne(convert(children)),
+            })
+            .collect(),
+    )
+}
+
+impl<I: Interner> From<&LicenseExpr<I>> for DepSpec<String> {
+    fn from(expr: &LicenseExpr<I>) -> Self {
+        match expr {
+            LicenseExpr::License(name) => DepSpec::Leaf(name.to_string()),
+            LicenseExpr::AnyOf(entries) => DepSpec::AnyOf(entries.iter().map(Into::into).collect()),
+            LicenseExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => DepSpec::UseConditional {
+                flag: flag.clone(),
+                negated: *negated,
+                entries: entries.iter().map(Into::into).collect(),
+            },
+            LicenseExpr::All(entries) => DepSpec::AllOf(entries.iter().map(Into::into).collect()),
+        }
+    }
+}
+
+impl From<&RequiredUseExpr> for DepSpec<UseFlagState> {
+    fn from(expr: &RequiredUseExpr) -> Self {
+        let convert = |entries: &[RequiredUseExpr]| entries.iter().map(Into::into).collect();
+        match expr {
+            RequiredUseExpr::Flag { name, negated } => DepSpec::Leaf(UseFlagState {
+                name: name.clone(),
+                enabled: !negated,
+            }),
+            RequiredUseExpr::AnyOf(entries) => DepSpec::AnyOf(convert(entries)),
+            RequiredUseExpr::ExactlyOne(entries) => DepSpec::ExactlyOne(convert(entries)),
+            RequiredUseExpr::AtMostOne(entries) => DepSpec::AtMostOne(convert(entries)),
+            RequiredUseExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => DepSpec::UseConditional {
+                flag: flag.clone(),
+                negated: *negated,
+                entries: convert(entries),
+            },
+            RequiredUseExpr::All(entries) => DepSpec::AllOf(convert(entries)),
+        }
+    }
+}
+
+impl From<DepSpec<UseFlagState>> for RequiredUseExpr {
+    fn from(spec: DepSpec<UseFlagState>) -> Self {
+        let convert =
+            |entries: Vec<DepSpec<UseFlagState>>| entries.into_iter().map(Into::into).collect();
+        match spec {
+            DepSpec::Leaf(UseFlagState { name, enabled }) => RequiredUseExpr::Flag {
+                name,
+                negated: !enabled,
+            },
+            DepSpec::AllOf(entries) => RequiredUseExpr::All(convert(entries)),
+            DepSpec::AnyOf(entries) => RequiredUseExpr::AnyOf(convert(entries)),
+            DepSpec::ExactlyOne(entries) => RequiredUseExpr::ExactlyOne(convert(entries)),
+            DepSpec::AtMostOne(entries) => RequiredUseExpr::AtMostOne(convert(entries)),
+            DepSpec::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => RequiredUseExpr::UseConditional {
+                flag,
+                negated,
+                entries: convert(entries),
+            },
+        }
+    }
+}
+
+impl From<&[SrcUriEntry]> for DepSpec<SrcUriEntry> {
+    fn from(entries: &[SrcUriEntry]) -> Self {
+        DepSpec::AllOf(
+            entries
+                .iter()
+                .map(|entry| match entry {
+                    SrcUriEntry::Uri { .. } | SrcUriEntry::Renamed { .. } => {
+                        DepSpec::Leaf(entry.clone())
+                    }
+                    SrcUriEntry::UseConditional {
+                        flag,
+                        negated,
+                        entries,
+                    } => DepSpec::UseConditional {
+                        flag: flag.clone(),
+                        negated: *negated,
+                        entries: DepSpec::from(entries.as_slice()).into_children(),
+                    },
+                    SrcUriEntry::Group(entries) => DepSpec::from(entries.as_slice()),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<&[RestrictExpr]> for DepSpec<RestrictKind> {
+    fn from(entries: &[RestrictExpr]) -> Self {
+        DepSpec::AllOf(
+            entries
+                .iter()
+                .map(|entry| match entry {
+                    RestrictExpr::Token(kind) => DepSpec::Leaf(kind.clone()),
+                    RestrictExpr::UseConditional {
+                        flag,
+                        negated,
+                        entries,
+                    } => DepSpec::UseConditional {
+                        flag: flag.clone(),
+                        negated: *negated,
+                        entries: DepSpec::from(entries.as_slice()).into_children(),
+                    },
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<&[PropertiesExpr]> for DepSpec<PropertyKind> {
+    fn from(entries: &[PropertiesExpr]) -> Self {
+        DepSpec::AllOf(
+            entries
+                .iter()
+                .map(|entry| match entry {
+                    PropertiesExpr::Token(kind) => DepSpec::Leaf(kind.clone()),
+                    PropertiesExpr::UseConditional {
+                        flag,
+                        negated,
+                        entries,
+                    } => DepSpec::UseConditional {
+                        flag: flag.clone(),
+                        negated: *negated,
+                        entries: DepSpec::from(entries.as_slice()).into_children(),
+                    },
+                })
+                .collect(),
+        )
+    }
+}
+
+// Winnow parsers
+
+fn is_flag_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '@')
+}
+
+/// Parse a USE flag name per PMS 3.1.4.
+pub(crate) fn parse_flag_name<'s>(input: &mut &'s str) -> ModalResult<&'s str> {
+    take_while(1.., is_flag_char)
+        .verify(|name: &str| name.starts_with(|c: char| c.is_ascii_alphanumeric()))
+        .parse_next(input)
+}
+
+/// Parse `[!]flag? ( entries... )`.
+fn parse_use_conditional<T: ParseSpec>(input: &mut &str) -> ModalResult<T> {
+    let negated = opt('!').parse_next(input)?.is_some();
+    let flag = parse_flag_name.parse_next(input)?;
+    '?'.parse_next(input)?;
+    // Only commit once `?` ends the token: `download?id=1` is a URI with a
+    // query string, not a conditional on `download`.
+    peek(alt((multispace1, "("))).parse_next(input)?;
+    multispace0.parse_next(input)?;
+    let entries = cut_err(delimited('(', parse_entries, (multispace0, ')')))
+        .context(StrContext::Label("USE conditional group"))
+        .parse_next(input)?;
+    Ok(T::conditional(flag.to_string(), negated, entries))
+}
+
+/// Parse `op ( entries... )` for the choice group `kind`.
+fn parse_choice<T: ParseSpec>(kind: GroupKind, input: &mut &str) -> ModalResult<T> {
+    let label = match kind {
+        GroupKind::AllOf => "group",
+        GroupKind::AnyOf => "'||' group",
+        GroupKind::ExactlyOne => "'^^' group",
+        GroupKind::AtMostOne => "'??' group",
+    };
+    kind.operator().parse_next(input)?;
+    multispace0.parse_next(input)?;
+    let entries = cut_err(delimited('(', parse_entries, (multispace0, ')')))
+        .context(StrContext::Label(label))
+        .parse_next(input)?;
+    Ok(T::choice(kind, entries))
+}
+
+/// Parse a single entry; a bare `( ... )` group may contribute several.
+fn parse_entry<T: ParseSpec>(input: &mut &str) -> ModalResult<Vec<T>> {
+    if input.starts_with('(') {
+        return delimited(
+            '(',
+            parse_entries,
+            cut_err((multispace0, ')')).context(StrContext::Label("closing ')'")),
+        )
+        .map(T::all_of)
+        .parse_next(input);
+    }
+    if let Some(&kind) = T::CHOICES
+        .iter()
+        .find(|kind| input.starts_with(kind.operator()))
+    {
+        return parse_choice(kind, input).map(|entry| vec![entry]);
+    }
+    alt((parse_use_conditional, T::parse_leaf))
+        .map(|entry| vec![entry])
+        .parse_next(input)
+}
+
+/// Parse zero or more whitespace-separated entries.
+fn parse_entries<T: ParseSpec>(input: &mut &str) -> ModalResult<Vec<T>> {
+    repeat(0.., preceded(multispace0, parse_entry))
+        .fold(Vec::new, |mut acc: Vec<T>, batch: Vec<T>| {
+            acc.extend(batch);
+            acc
+        })
+        .parse_next(input)
+}
+
+/// Parse a complete expression into its top-level entries.
+pub(crate) fn parse_spec<T: ParseSpec>(input: &mut &str) -> ModalResult<Vec<T>> {
+    let entries = parse_entries(input)?;
+    multispace0.parse_next(input)?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependency_round_trip() {
+        let input = ">=dev-libs/a-1:= || ( b/c ( d/e f/g ) ) ssl? ( !x/y )";
+        let spec = DepSpec::from(DepEntry::parse(input).unwrap().as_slice());
+        assert_eq!(spec.to_string(), input);
+    }
+
+    #[test]
+    fn license_round_trip() {
+        let input = "MIT || ( GPL-2 BSD ) ssl? ( openssl )";
+        let spec = DepSpec::from(&LicenseExpr::parse(input).unwrap());
+        assert_eq!(spec.to_string(), input);
+    }
+
+    #[test]
+    fn required_use_round_trip() {
+        let input = "^^ ( a b ) ?? ( !c d ) e? ( f )";
+        let spec = DepSpec::from(&RequiredUseExpr::parse(input).unwrap());
+        assert_eq!(spec.to_string(), input);
+    }
+
+    #[test]
+    fn evaluate_restrict() {
+        let entries = RestrictExpr::parse("mirror !test? ( test ) bindist? ( fetch )").unwrap();
+        let spec = DepSpec::from(entries.as_slice());
+        let evaluated = spec.evaluate(&UseFlagSet::parse("bindist"));
+        assert_eq!(evaluated.to_string(), "mirror test fetch");
+        assert_eq!(
+            spec.conditional_flags().into_iter().collect::<Vec<_>>(),
+            vec!["bindist", "test"]
+        );
+    }
+
+    #[test]
+    fn evaluate_keeps_choice_groups() {
+        let expr = RequiredUseExpr::parse("|| ( a b? ( c d ) ) e? ( f )").unwrap();
+        let spec = DepSpec::from(&expr);
+        assert_eq!(
+            spec.evaluate(&UseFlagSet::parse("b")).to_string(),
+            "|| ( a ( c d ) )"
+        );
+        assert_eq!(spec.evaluate(&UseFlagSet::new()).to_string(), "|| ( a )");
+    }
+
+    #[test]
+    fn src_uri_leaves() {
+        let entries =
+            SrcUriEntry::parse("https://e.com/a ssl? ( https://e.com/b ( https://e.com/c ) )")
+                .unwrap();
+        let spec = DepSpec::from(entries.as_slice());
+        assert_eq!(spec.leaves().count(), 3);
+        assert_eq!(
+            spec.to_string(),
+            "https://e.com/a ssl? ( https://e.com/b ( https://e.com/c ) )"
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        let spec = DepSpec::from(
+            &RequiredUseExpr::parse("|| ( b a || ( a c ) ) ?? ( x ) b? ( )").unwrap(),
+        );
+        assert_eq!(spec.normalize().to_string(), "|| ( a b c )");
+
+        let spec = DepSpec::from(&RequiredUseExpr::parse("?? ( a b a ) ^^ ( c c )").unwrap());
+        assert_eq!(spec.normalize().to_string(), "^^ ( c c ) ?? ( a a b )");
+
+        let leaf = |name: &str| DepSpec::<String>::Leaf(name.to_string());
+        let spec = DepSpec::AllOf(vec![
+            DepSpec::AnyOf(vec![leaf("a"), DepSpec::AllOf(Vec::new())]),
+            DepSpec::ExactlyOne(vec![DepSpec::AllOf(vec![leaf("c"), leaf("b")])]),
+            leaf("d"),
+        ]);
+        assert_eq!(spec.normalize().to_string(), "b c d");
+
+        // An empty `^^` can never hold, so it must not be dropped.
+        let spec = DepSpec::from(&RequiredUseExpr::parse("a ^^ ( ) ?? ( )").unwrap());
+        assert_eq!(spec.normalize().to_string(), "a ^^ ( )");
+    }
+
+    #[test]
+    fn required_leaves_skip_choices() {
+        let entries =
+            DepEntry::parse("a/b ssl? ( !test? ( c/d ) || ( e/f g/h ) ) ( i/j )").unwrap();
+        let spec = DepSpec::from_deps(&entries);
+        let required: Vec<(Vec<(&str, bool)>, String)> = spec
+            .required_leaves()
+            .into_iter()
+            .map(|(guards, dep)| (guards, dep.to_string()))
+            .collect();
+        assert_eq!(
+            required,
+            [
+                (vec![], "a/b".to_string()),
+                (vec![("ssl", false), ("test", true)], "c/d".to_string()),
+                (vec![], "i/j".to_string()),
+            ]
+        );
+        assert_eq!(spec.map(|dep| dep.cpn).leaves().count(), 5);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+
+/// Reports `DESCRIPTION`s breaking the style rules of the Gentoo
+/// developer manual.
+///
+/// Flags descriptions longer than [`max_length`](DescriptionCheck::max_length)
+/// characters, ending in a period, or starting with the package name, and
+/// with [`ascii_only`](DescriptionCheck::ascii_only) set, non-ASCII ones.
+/// Empty descriptions are left to [`ValidateCheck`](crate::ValidateCheck).
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, DescriptionCheck};
+///
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-1").unwrap(),
+///     CacheEntry::parse("DESCRIPTION=Foo is a tool.\nSLOT=0\n").unwrap(),
+/// )]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(DescriptionCheck::default());
+/// let findings: Vec<_> = runner.run(&repo).into_iter().map(|f| f.message).collect();
+/// assert_eq!(
+///     findings,
+///     ["DESCRIPTION ends with a period", "DESCRIPTION starts with the package name"]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptionCheck {
+    /// Longest description accepted, in characters; 80 by default.
+    pub max_length: usize,
+    /// Whether to report non-ASCII characters; off by default.
+    pub ascii_only: bool,
+}
+
+impl Default for DescriptionCheck {
+    fn default() -> Self {
+        DescriptionCheck {
+            max_length: 80,
+            ascii_only: false,
+        }
+    }
+}
+
+impl MetadataCheck for DescriptionCheck {
+    fn name(&self) -> &'static str {
+        "description"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let description = cx.entry.metadata.description.trim();
+        if description.is_empty() {
+            return;
+        }
+        let mut report = |message: String| {
+            findings.push(cx.finding(self.name(), Severity::Warning, message));
+        };
+        let length = description.chars().count();
+        if length > self.max_length {
+            report(format!(
+                "DESCRIPTION is {length} characters long (limit {})",
+                self.max_length
+            ));
+        }
+        if description.ends_with('.') && !description.ends_with("...") {
+            report("DESCRIPTION ends with a period".to_string());
+        }
+        let package = cx.cpv.cpn.package.as_str();
+        let first = description
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ':')
+            .next()
+            .unwrap_or_default();
+        if first.eq_ignore_ascii_case(package) {
+            report("DESCRIPTION starts with the package name".to_string());
+        }
+        if self.ascii_only && !description.is_ascii() {
+            report("DESCRIPTION contains non-ASCII characters".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn thresholds() {
+        let messages = |check: DescriptionCheck, description: &str| -> Vec<String> {
+            let text = format!("DESCRIPTION={description}\nSLOT=0\n");
+            run_check(check, &[("dev-libs/libfoo-1", &text)])
+                .into_iter()
+                .map(|f| f.message)
+                .collect()
+        };
+        let strict = DescriptionCheck {
+            max_length: 10,
+            ascii_only: true,
+        };
+        assert_eq!(
+            messages(strict, "Bibliothèque libfoo"),
+            [
+                "DESCRIPTION is 19 characters long (limit 10)",
+                "DESCRIPTION contains non-ASCII characters"
+            ]
+        );
+        assert_eq!(
+            messages(DescriptionCheck::default(), "LibFoo: a library"),
+            ["DESCRIPTION starts with the package name"]
+        );
+        assert!(messages(DescriptionCheck::default(), "Bibliothèque and more...").is_empty());
+        assert!(messages(DescriptionCheck::default(), "libfoobar bindings").is_empty());
+    }
+}
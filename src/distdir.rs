@@ -0,0 +1,174 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::manifest::Manifest;
+
+/// The state of a distfile in `DISTDIR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DistfileStatus {
+    /// The file exists and matches the Manifest, or no Manifest entry exists.
+    Present,
+    /// The file does not exist.
+    Missing,
+    /// The file exists but its size differs from the Manifest.
+    SizeMismatch {
+        /// Size recorded in the Manifest.
+        expected: u64,
+        /// Size on disk.
+        actual: u64,
+    },
+    /// The file exists but a digest differs from the Manifest.
+    HashMismatch {
+        /// The algorithm whose digest differs (e.g. `BLAKE2B`).
+        algorithm: String,
+    },
+}
+
+/// A distfile directory (`DISTDIR`).
+///
+/// Reports which distfiles are already downloaded. When a [`Manifest`] is
+/// supplied, sizes are compared; digests are compared only through
+/// [`Distdir::check_with`], since this crate does not implement the hash
+/// algorithms itself.
+///
+/// See [PMS 11.1](https://projects.gentoo.org/pms/9/pms.html#defined-variables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distdir {
+    path: PathBuf,
+}
+
+impl Distdir {
+    /// Use the directory at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The directory path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The status of each of `files`, comparing sizes against `manifest`.
+    pub fn check<'a>(
+        &self,
+        files: impl IntoIterator<Item = &'a str>,
+        manifest: Option<&Manifest>,
+    ) -> Result<Vec<(&'a str, DistfileStatus)>> {
+        self.check_with(files, manifest, |_, _| Ok(None))
+    }
+
+    /// Like [`Distdir::check`], also verifying digests with `hasher`.
+    ///
+    /// `hasher` is called with the file path and a Manifest algorithm name
+    /// and returns the lowercase hex digest, or `None` if it does not
+    /// support the algorithm. Digests are only checked when sizes match.
+    /// Errors of `hasher` are reported against the file it was hashing.
+    pub fn check_with<'a, F>(
+        &self,
+        files: impl IntoIterator<Item = &'a str>,
+        manifest: Option<&Manifest>,
+        mut hasher: F,
+    ) -> Result<Vec<(&'a str, DistfileStatus)>>
+    where
+        F: FnMut(&Path, &str) -> io::Result<Option<String>>,
+    {
+        files
+            .into_iter()
+            .map(|file| Ok((file, self.status(file, manifest, &mut hasher)?)))
+            .collect()
+    }
+
+    fn status<F>(
+        &self,
+        file: &str,
+        manifest: Option<&Manifest>,
+        hasher: &mut F,
+    ) -> Result<DistfileStatus>
+    where
+        F: FnMut(&Path, &str) -> io::Result<Option<String>>,
+    {
+        let path = self.path.join(file);
+        let actual = match fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(DistfileStatus::Missing),
+            Err(e) => return Err(Error::io(&path, e)),
+        };
+        let Some(entry) = manifest.and_then(|m| m.get(file)) else {
+            return Ok(DistfileStatus::Present);
+        };
+        if entry.size != actual {
+            return Ok(DistfileStatus::SizeMismatch {
+                expected: entry.size,
+                actual,
+            });
+        }
+        for (algorithm, expected) in &entry.hashes {
+            if let Some(digest) = hasher(&path, algorithm).map_err(|e| Error::io(&path, e))? {
+                if !digest.eq_ignore_ascii_case(expected) {
+                    return Ok(DistfileStatus::HashMismatch {
+                        algorithm: algorithm.clone(),
+                    });
+                }
+            }
+        }
+        Ok(DistfileStatus::Present)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn check_files() {
+        let root = TempDir::new("distdir");
+        fs::write(root.join("a.tgz"), "12345").unwrap();
+        fs::write(root.join("b.tgz"), "123").unwrap();
+        fs::write(root.join("c.tgz"), "1").unwrap();
+        let manifest = Manifest::parse(
+            "DIST a.tgz 5 SHA512 good\nDIST b.tgz 5 SHA512 x\nDIST c.tgz 1 SHA512 good\n",
+        )
+        .unwrap();
+        let distdir = Distdir::new(&root);
+
+        let files = ["a.tgz", "b.tgz", "missing.tgz"];
+        assert_eq!(
+            distdir.check(files, None).unwrap(),
+            vec![
+                ("a.tgz", DistfileStatus::Present),
+                ("b.tgz", DistfileStatus::Present),
+                ("missing.tgz", DistfileStatus::Missing),
+            ]
+        );
+        assert_eq!(
+            distdir.check(files, Some(&manifest)).unwrap()[1].1,
+            DistfileStatus::SizeMismatch {
+                expected: 5,
+                actual: 3
+            }
+        );
+
+        let status = distdir
+            .check_with(["a.tgz", "c.tgz"], Some(&manifest), |path, _| {
+                Ok(Some(
+                    if path.ends_with("a.tgz") {
+                        "GOOD"
+                    } else {
+                        "bad"
+                    }
+                    .to_string(),
+                ))
+            })
+            .unwrap();
+        assert_eq!(status[0].1, DistfileStatus::Present);
+        assert_eq!(
+            status[1].1,
+            DistfileStatus::HashMismatch {
+                algorithm: "SHA512".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use crate::restrict::{RestrictExpr, RestrictKind};
+use crate::src_uri::SrcUriEntry;
+use crate::src_url::SrcUrl;
+use crate::use_flags::UseFlagSet;
+
+/// Fetch and mirror permissions for a single distfile URI.
+///
+/// Combines the package-wide `RESTRICT` tokens with the EAPI 8 `fetch+` and
+/// `mirror+` URI prefixes:
+///
+/// - `RESTRICT=fetch` forbids automatic fetching and mirroring of every URI
+///   without a prefix;
+/// - `RESTRICT=mirror` forbids mirroring of every URI without a prefix;
+/// - `fetch+` lifts fetch restriction for that URI, but it still may not be
+///   mirrored if `RESTRICT` contains `fetch` or `mirror`;
+/// - `mirror+` lifts both fetch and mirror restriction for that URI.
+///
+/// See [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri)
+/// and [PMS 7.3.6](https://projects.gentoo.org/pms/9/pms.html#restrict).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistfileAccess<'a> {
+    /// The download URL, without restriction prefix.
+    pub url: &'a SrcUrl,
+    /// The local filename.
+    pub filename: &'a str,
+    /// Whether the package manager may fetch the file automatically.
+    pub fetch: bool,
+    /// Whether the file may be mirrored.
+    pub mirror: bool,
+}
+
+impl<'a> DistfileAccess<'a> {
+    /// Compute the permissions of every URI active under `use_flags`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{DistfileAccess, RestrictExpr, SrcUriEntry, UseFlagSet};
+    ///
+    /// let src_uri = SrcUriEntry::parse(
+    ///     "https://example.com/a.tar.gz fetch+https://example.com/b.tar.gz",
+    /// )
+    /// .unwrap();
+    /// let restrict = RestrictExpr::parse("fetch").unwrap();
+    /// let access = DistfileAccess::compute(&src_uri, &restrict, &UseFlagSet::new());
+    /// assert!(!access[0].fetch);
+    /// assert!(access[1].fetch);
+    /// assert!(!access[1].mirror);
+    /// ```
+    pub fn compute(
+        src_uri: &'a [SrcUriEntry],
+        restrict: &[RestrictExpr],
+        use_flags: &UseFlagSet,
+    ) -> Vec<Self> {
+        let tokens = RestrictExpr::evaluate(restrict, use_flags);
+        let fetch_restricted = tokens.contains(&&RestrictKind::Fetch);
+        let mirror_restricted = fetch_restricted || tokens.contains(&&RestrictKind::Mirror);
+
+        SrcUriEntry::evaluate(src_uri, use_flags)
+            .into_iter()
+            .filter_map(|entry| {
+                let (url, filename, restriction) = match entry {
+                    SrcUriEntry::Uri {
+                        url,
+                        filename,
+                        restriction,
+                    } => (url, filename, restriction),
+                    SrcUriEntry::Renamed {
+                        url,
+                        target,
+                        restriction,
+                    } => (url, target, restriction),
+                    SrcUriEntry::UseConditional { .. } | SrcUriEntry::Group(_) => return None,
+                };
+                let prefix = restriction.as_deref();
+                Some(DistfileAccess {
+                    url,
+                    filename,
+                    fetch: !fetch_restricted || prefix.is_some(),
+                    mirror: !mirror_restricted || prefix == Some("mirror"),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(src_uri: &str, restrict: &str) -> Vec<(bool, bool)> {
+        let src_uri = SrcUriEntry::parse(src_uri).unwrap();
+        let restrict = RestrictExpr::parse(restrict).unwrap();
+        DistfileAccess::compute(&src_uri, &restrict, &UseFlagSet::new())
+            .iter()
+            .map(|a| (a.fetch, a.mirror))
+            .collect()
+    }
+
+    const URIS: &str = "https://e.com/a fetch+https://e.com/b mirror+https://e.com/c";
+
+    #[test]
+    fn unrestricted() {
+        assert_eq!(access(URIS, ""), vec![(true, true); 3]);
+    }
+
+    #[test]
+    fn restrict_fetch() {
+        assert_eq!(
+            access(URIS, "fetch"),
+            vec![(false, false), (true, false), (true, true)]
+        );
+    }
+
+    #[test]
+    fn restrict_mirror() {
+        assert_eq!(
+            access(URIS, "mirror"),
+            vec![(true, false), (true, false), (true, true)]
+        );
+    }
+
+    #[test]
+    fn conditional_restrict_and_rename() {
+        let src_uri = SrcUriEntry::parse("https://e.com/x.tgz -> y.tgz").unwrap();
+        let restrict = RestrictExpr::parse("bindist? ( mirror )").unwrap();
+        let on = DistfileAccess::compute(&src_uri, &restrict, &UseFlagSet::parse("bindist"));
+        assert_eq!(on[0].filename, "y.tgz");
+        assert!(on[0].fetch && !on[0].mirror);
+        let off = DistfileAccess::compute(&src_uri, &restrict, &UseFlagSet::new());
+        assert!(off[0].mirror);
+    }
+}
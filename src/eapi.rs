@@ -1,15 +1,62 @@
 use std::fmt;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
 use std::str::FromStr;
 
+use crate::eapi_feature::EapiFeature;
+use crate::eapi_policy::EapiPolicy;
 use crate::error::{Error, Result};
+use crate::phase::Phase;
+
+const PHASES_EAPI0: &[Phase] = &[
+    Phase::PkgSetup,
+    Phase::SrcUnpack,
+    Phase::SrcCompile,
+    Phase::SrcTest,
+    Phase::SrcInstall,
+    Phase::PkgPreinst,
+    Phase::PkgPostinst,
+    Phase::PkgPrerm,
+    Phase::PkgPostrm,
+    Phase::PkgConfig,
+    Phase::PkgInfo,
+    Phase::PkgNofetch,
+];
+
+const PHASES_EAPI2: &[Phase] = &[
+    Phase::PkgSetup,
+    Phase::SrcUnpack,
+    Phase::SrcPrepare,
+    Phase::SrcConfigure,
+    Phase::SrcCompile,
+    Phase::SrcTest,
+    Phase::SrcInstall,
+    Phase::PkgPreinst,
+    Phase::PkgPostinst,
+    Phase::PkgPrerm,
+    Phase::PkgPostrm,
+    Phase::PkgConfig,
+    Phase::PkgInfo,
+    Phase::PkgNofetch,
+];
 
 /// EAPI (Ebuild API) version.
 ///
 /// The EAPI controls which features and behaviours are available to an ebuild.
 /// Each EAPI builds on the previous one, adding or modifying capabilities.
 ///
+/// EAPIs this crate does not know, such as a future `10` or a fork's
+/// `7-paludis`, parse as [`Eapi::Unknown`]. PMS asks for such packages to
+/// be treated as masked rather than invalid. An unknown EAPI compares
+/// greater than every known one, so feature queries assume the newest
+/// syntax.
+///
 /// See [PMS 2](https://projects.gentoo.org/pms/9/pms.html#eapis).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Eapi {
     /// EAPI 0 — base (legacy).
     Zero,
@@ -33,28 +80,177 @@ pub enum Eapi {
     ///
     /// See [PMS 2](https://projects.gentoo.org/pms/9/pms.html#eapis).
     Nine,
+    /// An EAPI not known to this crate, holding its name as written.
+    Unknown(String),
 }
 
 impl Eapi {
+    /// Every EAPI known to this crate, oldest first.
+    pub const ALL: &'static [Eapi] = &[
+        Eapi::Zero,
+        Eapi::One,
+        Eapi::Two,
+        Eapi::Three,
+        Eapi::Four,
+        Eapi::Five,
+        Eapi::Six,
+        Eapi::Seven,
+        Eapi::Eight,
+        Eapi::Nine,
+    ];
+
+    /// The EAPI's name, as written in ebuilds.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Eapi::Zero => "0",
+            Eapi::One => "1",
+            Eapi::Two => "2",
+            Eapi::Three => "3",
+            Eapi::Four => "4",
+            Eapi::Five => "5",
+            Eapi::Six => "6",
+            Eapi::Seven => "7",
+            Eapi::Eight => "8",
+            Eapi::Nine => "9",
+            Eapi::Unknown(name) => name,
+        }
+    }
+
+    /// Whether this is an EAPI the crate knows, i.e. not [`Eapi::Unknown`].
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Eapi::Unknown(_))
+    }
+
+    /// Read the EAPI of an ebuild from its source text without sourcing it.
+    ///
+    /// Implements the PMS rule: the first line that is neither blank nor a
+    /// comment is matched against
+    /// `^[ \t]*EAPI=(['"]?)([A-Za-z0-9+_.-]*)\1[ \t]*([ \t]#.*)?$`. If it
+    /// does not match, or assigns an empty value, the EAPI is 0.
+    ///
+    /// See [PMS 7.3.1](https://projects.gentoo.org/pms/9/pms.html#eapi).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Eapi;
+    ///
+    /// let src = "# Copyright 2024 Gentoo Authors\n\nEAPI=\"8\" # latest\n\ninherit meson\n";
+    /// assert_eq!(Eapi::from_ebuild_source(src).unwrap(), Eapi::Eight);
+    /// assert_eq!(Eapi::from_ebuild_source("inherit eutils\nEAPI=8\n").unwrap(), Eapi::Zero);
+    /// ```
+    pub fn from_ebuild_source(source: &str) -> Result<Eapi> {
+        let line = source
+            .lines()
+            .map(|line| line.trim_start_matches([' ', '\t']))
+            .find(|line| !line.trim_end().is_empty() && !line.starts_with('#'));
+        match line.and_then(eapi_assignment) {
+            Some("") | None => Ok(Eapi::Zero),
+            Some(value) => value.parse(),
+        }
+    }
+
+    /// Parse the contents of an `eapi` file, as found in profile
+    /// directories and `metadata/`.
+    ///
+    /// The file holds a single EAPI name; surrounding whitespace is
+    /// ignored.
+    ///
+    /// See [PMS 5.2.2](https://projects.gentoo.org/pms/9/pms.html#the-eapi-file).
+    pub fn parse_eapi_file(contents: &str) -> Result<Eapi> {
+        let mut words = contents.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(name), None) => name.parse(),
+            _ => Err(Error::InvalidEapi(contents.trim().to_string())),
+        }
+    }
+
+    /// Read the `eapi` file of `dir`, defaulting to EAPI 0 when there is
+    /// none.
+    ///
+    /// Loaders should reject the directory if the result is an
+    /// [`Eapi::Unknown`] or otherwise newer than they support. Failing to
+    /// read the file is reported as [`Error::Io`].
+    ///
+    /// See [PMS 5.2.2](https://projects.gentoo.org/pms/9/pms.html#the-eapi-file).
+    #[cfg(feature = "fs")]
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Eapi> {
+        let path = dir.as_ref().join("eapi");
+        match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse_eapi_file(&contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Eapi::Zero),
+            Err(e) => Err(Error::io(path, e)),
+        }
+    }
+
+    /// Whether the [default policy](EapiPolicy::default) deprecates this
+    /// EAPI.
+    pub fn is_deprecated(&self) -> bool {
+        EapiPolicy::default().is_deprecated(self)
+    }
+
+    /// Whether the [default policy](EapiPolicy::default) bans this EAPI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Eapi;
+    ///
+    /// assert!(Eapi::Five.is_banned());
+    /// assert!(Eapi::Seven.is_deprecated());
+    /// assert!(!Eapi::Eight.is_banned() && !Eapi::Eight.is_deprecated());
+    /// ```
+    pub fn is_banned(&self) -> bool {
+        EapiPolicy::default().is_banned(self)
+    }
+
+    /// Whether this EAPI has `feature`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Eapi, EapiFeature};
+    ///
+    /// assert!(Eapi::Eight.supports(EapiFeature::DosymRelative));
+    /// assert!(!Eapi::Seven.supports(EapiFeature::DosymRelative));
+    /// assert!(Eapi::Six.supports(EapiFeature::Dohtml));
+    /// assert!(!Eapi::Seven.supports(EapiFeature::Dohtml));
+    /// ```
+    pub fn supports(&self, feature: EapiFeature) -> bool {
+        let removed = match feature.removed_in() {
+            Some(removed) => *self >= removed,
+            None => false,
+        };
+        *self >= feature.introduced_in() && !removed
+    }
+
+    /// Every feature this EAPI has.
+    pub fn features(&self) -> impl Iterator<Item = EapiFeature> + '_ {
+        EapiFeature::ALL
+            .iter()
+            .copied()
+            .filter(move |f| self.supports(*f))
+    }
+
     /// Whether this EAPI supports `BDEPEND` (build-host dependencies).
     ///
     /// Introduced in EAPI 7.
     pub fn has_bdepend(&self) -> bool {
-        *self >= Eapi::Seven
+        self.supports(EapiFeature::Bdepend)
     }
 
     /// Whether this EAPI supports `IDEPEND` (install-time dependencies).
     ///
     /// Introduced in EAPI 8.
     pub fn has_idepend(&self) -> bool {
-        *self >= Eapi::Eight
+        self.supports(EapiFeature::Idepend)
     }
 
     /// Whether this EAPI supports `REQUIRED_USE`.
     ///
     /// Introduced in EAPI 4.
     pub fn has_required_use(&self) -> bool {
-        *self >= Eapi::Four
+        self.supports(EapiFeature::RequiredUse)
     }
 
     /// Whether this EAPI supports the `??` (at-most-one-of) operator
@@ -62,93 +258,132 @@ impl Eapi {
     ///
     /// Introduced in EAPI 5.
     pub fn has_at_most_one_of(&self) -> bool {
-        *self >= Eapi::Five
+        self.supports(EapiFeature::AtMostOneOf)
     }
 
     /// Whether this EAPI supports `src_prepare` and `src_configure` phases.
     ///
     /// Introduced in EAPI 2.
     pub fn has_src_prepare(&self) -> bool {
-        *self >= Eapi::Two
+        self.supports(EapiFeature::SrcPrepare)
     }
 
     /// Whether this EAPI supports the `pkg_pretend` phase.
     ///
     /// Introduced in EAPI 4.
     pub fn has_pkg_pretend(&self) -> bool {
-        *self >= Eapi::Four
+        self.supports(EapiFeature::PkgPretend)
     }
 
     /// Whether this EAPI supports SRC_URI arrow renaming (`-> filename`).
     ///
     /// Introduced in EAPI 2.
     pub fn has_src_uri_arrows(&self) -> bool {
-        *self >= Eapi::Two
+        self.supports(EapiFeature::SrcUriArrows)
     }
 
     /// Whether this EAPI supports sub-slots and slot operators (`:=`, `:*`).
     ///
     /// Introduced in EAPI 5.
     pub fn has_slot_operators(&self) -> bool {
-        *self >= Eapi::Five
+        self.supports(EapiFeature::SlotOperators)
     }
 
     /// Whether this EAPI supports `PROPERTIES`.
     ///
     /// Introduced in EAPI 3.
     pub fn has_properties(&self) -> bool {
-        *self >= Eapi::Three
+        self.supports(EapiFeature::Properties)
     }
 
     /// Whether this EAPI supports USE-conditional `PROPERTIES` and `RESTRICT`.
     ///
     /// Introduced in EAPI 8.
     pub fn has_use_conditional_restrict(&self) -> bool {
-        *self >= Eapi::Eight
+        self.supports(EapiFeature::UseConditionalRestrict)
     }
 
     /// Whether this EAPI supports selective URI restrictions (`fetch+`/`mirror+` prefixes).
     ///
     /// Introduced in EAPI 8.
     pub fn has_selective_uri_restrictions(&self) -> bool {
-        *self >= Eapi::Eight
+        self.supports(EapiFeature::SelectiveUriRestrictions)
+    }
+
+    /// The phase functions an ebuild may define in this EAPI, in
+    /// execution order.
+    ///
+    /// See [PMS 9.1](https://projects.gentoo.org/pms/9/pms.html#list-of-phase-functions).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Eapi, Phase};
+    ///
+    /// assert!(!Eapi::One.phases().contains(&Phase::SrcPrepare));
+    /// assert!(Eapi::Two.phases().contains(&Phase::SrcPrepare));
+    /// assert_eq!(Eapi::Eight.phases()[0], Phase::PkgPretend);
+    /// ```
+    pub fn phases(&self) -> &'static [Phase] {
+        phases_where(|feature| self.supports(feature))
+    }
+}
+
+/// The phase functions of an EAPI with the features `supports` accepts.
+pub(crate) fn phases_where(supports: impl Fn(EapiFeature) -> bool) -> &'static [Phase] {
+    if supports(EapiFeature::PkgPretend) {
+        Phase::ALL
+    } else if supports(EapiFeature::SrcPrepare) {
+        PHASES_EAPI2
+    } else {
+        PHASES_EAPI0
     }
 }
 
 impl fmt::Display for Eapi {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let n = match self {
-            Eapi::Zero => "0",
-            Eapi::One => "1",
-            Eapi::Two => "2",
-            Eapi::Three => "3",
-            Eapi::Four => "4",
-            Eapi::Five => "5",
-            Eapi::Six => "6",
-            Eapi::Seven => "7",
-            Eapi::Eight => "8",
-            Eapi::Nine => "9",
-        };
-        f.write_str(n)
+        f.write_str(self.as_str())
     }
 }
 
+/// The value of a line matching the PMS EAPI assignment regex, with
+/// leading blanks already stripped.
+fn eapi_assignment(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("EAPI=")?;
+    let (quote, rest) = match rest.strip_prefix(['\'', '"']) {
+        Some(unquoted) => (&rest[..1], unquoted),
+        None => ("", rest),
+    };
+    let len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '_' | '.' | '-')))
+        .unwrap_or(rest.len());
+    let (value, rest) = rest.split_at(len);
+    let rest = rest.strip_prefix(quote)?;
+    let trailing = rest.trim_start_matches([' ', '\t']);
+    let blank = rest.len() - trailing.len();
+    (trailing.is_empty() || (blank > 0 && trailing.starts_with('#'))).then_some(value)
+}
+
+/// EAPI names may contain `[A-Za-z0-9+_.-]` and must not begin with `-`,
+/// `.` or `+` (PMS 3.1.9).
+fn is_valid_eapi_name(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(['-', '.', '+'])
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'_' | b'.' | b'-'))
+}
+
 impl FromStr for Eapi {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        match s {
-            "0" => Ok(Eapi::Zero),
-            "1" => Ok(Eapi::One),
-            "2" => Ok(Eapi::Two),
-            "3" => Ok(Eapi::Three),
-            "4" => Ok(Eapi::Four),
-            "5" => Ok(Eapi::Five),
-            "6" => Ok(Eapi::Six),
-            "7" => Ok(Eapi::Seven),
-            "8" => Ok(Eapi::Eight),
-            "9" => Ok(Eapi::Nine),
-            _ => Err(Error::InvalidEapi(s.to_string())),
+        if let Some(eapi) = Eapi::ALL.iter().find(|eapi| eapi.as_str() == s) {
+            Ok(eapi.clone())
+        } else if is_valid_eapi_name(s) {
+            Ok(Eapi::Unknown(s.to_string()))
+        } else {
+            let names = Eapi::ALL.iter().map(Eapi::as_str);
+            Err(Error::InvalidEapi(s.to_string()).suggest(s, names))
         }
     }
 }
@@ -177,27 +412,33 @@ mod tests {
 
     #[test]
     fn display_round_trip() {
-        for eapi in [
-            Eapi::Zero,
-            Eapi::One,
-            Eapi::Two,
-            Eapi::Three,
-            Eapi::Four,
-            Eapi::Five,
-            Eapi::Six,
-            Eapi::Seven,
-            Eapi::Eight,
-            Eapi::Nine,
-        ] {
-            assert_eq!(eapi.to_string().parse::<Eapi>().unwrap(), eapi);
+        for eapi in Eapi::ALL {
+            assert_eq!(&eapi.to_string().parse::<Eapi>().unwrap(), eapi);
+        }
+    }
+
+    #[test]
+    fn unknown_eapi() {
+        for s in ["10", "7-paludis", "foo"] {
+            let eapi: Eapi = s.parse().unwrap();
+            assert_eq!(eapi, Eapi::Unknown(s.to_string()));
+            assert!(!eapi.is_known());
+            assert!(eapi > Eapi::Nine);
+            assert_eq!(eapi.to_string(), s);
         }
+        assert!(Eapi::Nine.is_known());
     }
 
     #[test]
     fn invalid_eapi() {
-        assert!("10".parse::<Eapi>().is_err());
         assert!("".parse::<Eapi>().is_err());
-        assert!("foo".parse::<Eapi>().is_err());
+        assert!("-1".parse::<Eapi>().is_err());
+        assert!("8 9".parse::<Eapi>().is_err());
+
+        let err = "-1".parse::<Eapi>().unwrap_err();
+        assert_eq!(err.root(), &Error::InvalidEapi("-1".to_string()));
+        assert_eq!(err.suggestion(), Some("1"));
+        assert_eq!("".parse::<Eapi>().unwrap_err().suggestion(), None);
     }
 
     #[test]
@@ -208,6 +449,75 @@ mod tests {
         assert!(Eapi::Four > Eapi::Three);
     }
 
+    #[test]
+    fn phases_per_eapi() {
+        assert_eq!(Eapi::Zero.phases().len(), 12);
+        assert_eq!(Eapi::Three.phases().len(), 14);
+        assert_eq!(Eapi::Four.phases().len(), 15);
+        for eapi in [Eapi::Zero, Eapi::Two, Eapi::Nine] {
+            let mut sorted = eapi.phases().to_vec();
+            Phase::sort_execution_order(&mut sorted);
+            assert_eq!(sorted, eapi.phases());
+        }
+    }
+
+    #[test]
+    fn from_ebuild_source() {
+        for (src, expected) in [
+            ("EAPI=7\n", Eapi::Seven),
+            ("  \t\n# c\n\tEAPI='6'\t\n", Eapi::Six),
+            ("EAPI=8 # comment\n", Eapi::Eight),
+            ("EAPI=\"5\"\n", Eapi::Five),
+            ("EAPI=\n", Eapi::Zero),
+            ("", Eapi::Zero),
+            ("EAPI=\"8'\n", Eapi::Zero),
+            ("EAPI=8#x\n", Eapi::Zero),
+            ("EAPI=8 foo\n", Eapi::Zero),
+            ("DESCRIPTION=x\nEAPI=8\n", Eapi::Zero),
+            ("EAPI=10\n", Eapi::Unknown("10".to_string())),
+        ] {
+            assert_eq!(Eapi::from_ebuild_source(src).unwrap(), expected, "{src:?}");
+        }
+        assert!(Eapi::from_ebuild_source("EAPI=-1\n").is_err());
+    }
+
+    #[test]
+    fn eapi_file() {
+        assert_eq!(Eapi::parse_eapi_file("5\n").unwrap(), Eapi::Five);
+        assert!(Eapi::parse_eapi_file("").is_err());
+        assert!(Eapi::parse_eapi_file("5\n6\n").is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn eapi_from_dir() {
+        use crate::temp_dir::TempDir;
+        let root = TempDir::new("eapi-file");
+        assert_eq!(Eapi::from_dir(&root).unwrap(), Eapi::Zero);
+        fs::write(root.join("eapi"), "8\n").unwrap();
+        assert_eq!(Eapi::from_dir(&root).unwrap(), Eapi::Eight);
+        fs::write(root.join("eapi"), "").unwrap();
+        assert!(matches!(
+            Eapi::from_dir(&root).unwrap_err(),
+            Error::InvalidEapi(_)
+        ));
+        assert!(Eapi::from_dir(root.join("eapi"))
+            .unwrap_err()
+            .io_kind()
+            .is_some());
+    }
+
+    #[test]
+    fn feature_table() {
+        assert!(Eapi::Zero.supports(EapiFeature::DosedDohard));
+        assert!(!Eapi::Four.supports(EapiFeature::DosedDohard));
+        assert!(Eapi::Seven.supports(EapiFeature::HasqHasvUseq));
+        assert!(!Eapi::Eight.supports(EapiFeature::HasqHasvUseq));
+        assert!(Eapi::Nine.supports(EapiFeature::Pipestatus));
+        assert!(Eapi::Unknown("10".to_string()).supports(EapiFeature::Idepend));
+        assert_eq!(Eapi::Zero.features().count(), 4);
+    }
+
     #[test]
     fn feature_queries() {
         assert!(!Eapi::Six.has_bdepend());
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::eapi_policy::EapiPolicy;
+
+/// Reports entries using an EAPI that an [`EapiPolicy`] bans (errors) or
+/// deprecates (warnings).
+///
+/// The crate does not read `metadata.xml`; callers that have maintainer
+/// data can register it with [`EapiCheck::maintainers`] and get per
+/// maintainer counts from [`EapiCheck::maintainer_counts`].
+///
+/// See [PMS 2](https://projects.gentoo.org/pms/9/pms.html#eapis).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, EapiCheck, EapiPolicy, Severity};
+///
+/// let entry = |eapi| CacheEntry::parse(&format!("EAPI={eapi}\nDESCRIPTION=x\nSLOT=0\n")).unwrap();
+/// let repo = BTreeMap::from([
+///     (Cpv::parse("app-misc/foo-1").unwrap(), entry(6)),
+///     (Cpv::parse("app-misc/foo-2").unwrap(), entry(7)),
+///     (Cpv::parse("dev-libs/bar-1").unwrap(), entry(8)),
+/// ]);
+///
+/// let mut check = EapiCheck::new(EapiPolicy::default());
+/// check.maintainers("app-misc/foo", ["alice@gentoo.org", "base-system@gentoo.org"]);
+/// let mut runner = CheckRunner::new();
+/// runner.add(check.clone());
+/// let findings = runner.run(&repo);
+/// assert_eq!(findings[0].severity, Severity::Error);
+/// assert_eq!(findings[0].message, "EAPI 6 is banned");
+/// assert_eq!(findings[1].severity, Severity::Warning);
+/// assert_eq!(check.maintainer_counts(&findings)["alice@gentoo.org"], 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EapiCheck {
+    policy: EapiPolicy,
+    maintainers: BTreeMap<String, Vec<String>>,
+}
+
+impl EapiCheck {
+    /// Check against `policy`.
+    pub fn new(policy: EapiPolicy) -> Self {
+        EapiCheck {
+            policy,
+            maintainers: BTreeMap::new(),
+        }
+    }
+
+    /// Record the maintainers of `package` (`category/name`), as listed in
+    /// its `metadata.xml`.
+    pub fn maintainers<S: Into<String>>(
+        &mut self,
+        package: &str,
+        maintainers: impl IntoIterator<Item = S>,
+    ) {
+        self.maintainers.insert(
+            package.to_string(),
+            maintainers.into_iter().map(Into::into).collect(),
+        );
+    }
+
+    /// The number of this check's findings per maintainer.
+    ///
+    /// Findings of other checks and of packages without recorded
+    /// maintainers are ignored.
+    pub fn maintainer_counts(&self, findings: &[Finding]) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for finding in findings.iter().filter(|f| f.check == self.name()) {
+            let package = finding.cpv.cpn.to_string();
+            for maintainer in self.maintainers.get(&package).into_iter().flatten() {
+                *counts.entry(maintainer.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+}
+
+impl MetadataCheck for EapiCheck {
+    fn name(&self) -> &'static str {
+        "eapi-policy"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let eapi = &cx.entry.metadata.eapi;
+        let (severity, status) = if self.policy.is_banned(eapi) {
+            (Severity::Error, "banned")
+        } else if self.policy.is_deprecated(eapi) {
+            (Severity::Warning, "deprecated")
+        } else {
+            return;
+        };
+        findings.push(cx.finding(self.name(), severity, format!("EAPI {eapi} is {status}")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn layout_conf_policy() {
+        let policy = EapiPolicy::from_layout_conf("eapis-deprecated = 0 5\n");
+        let repo = [
+            ("app-misc/foo-1", "EAPI=5\nDESCRIPTION=x\nSLOT=0\n"),
+            ("app-misc/foo-2", "DESCRIPTION=x\nSLOT=0\n"),
+        ];
+        let findings: Vec<_> = run_check(EapiCheck::new(policy), &repo)
+            .into_iter()
+            .map(|f| (f.severity, f.message))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (Severity::Warning, "EAPI 5 is deprecated".to_string()),
+                (Severity::Warning, "EAPI 0 is deprecated".to_string()),
+            ]
+        );
+        assert!(EapiCheck::default().maintainer_counts(&[]).is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt;
+
+use crate::eapi::Eapi;
+
+/// A capability that differs between EAPIs.
+///
+/// Each feature records the EAPI that introduced it and, for commands
+/// that were later banned, the EAPI that removed it.
+/// [`Eapi::supports`] checks a feature against an EAPI.
+///
+/// See [PMS 2](https://projects.gentoo.org/pms/9/pms.html#eapis) and the
+/// feature tables throughout PMS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EapiFeature {
+    /// `IUSE` defaults (`+flag`/`-flag`), EAPI 1+.
+    IuseDefaults,
+    /// Slot dependencies (`cat/pkg:slot`), EAPI 1+.
+    SlotDeps,
+    /// `SRC_URI` arrow renaming (`-> filename`), EAPI 2+.
+    SrcUriArrows,
+    /// USE dependencies (`cat/pkg[flag]`), EAPI 2+.
+    UseDeps,
+    /// Strong blockers (`!!cat/pkg`), EAPI 2+.
+    StrongBlockers,
+    /// The `src_prepare` phase, EAPI 2+.
+    SrcPrepare,
+    /// The `src_configure` phase, EAPI 2+.
+    SrcConfigure,
+    /// The `default` function and `default_*` phase functions, EAPI 2+.
+    DefaultPhaseFunctions,
+    /// `PROPERTIES`, EAPI 3+.
+    Properties,
+    /// Offset-prefix variables `EPREFIX`, `ED` and `EROOT`, EAPI 3+.
+    PrefixVariables,
+    /// `REQUIRED_USE`, EAPI 4+.
+    RequiredUse,
+    /// The `pkg_pretend` phase, EAPI 4+.
+    PkgPretend,
+    /// USE dependency defaults (`[flag(+)]`), EAPI 4+.
+    UseDepDefaults,
+    /// `REPLACING_VERSIONS` and `REPLACED_BY_VERSION`, EAPI 4+.
+    ReplacingVersions,
+    /// `DOCS` installed by the default `src_install`, EAPI 4+.
+    DefaultDocs,
+    /// `??` (at-most-one-of) groups in `REQUIRED_USE`, EAPI 5+.
+    AtMostOneOf,
+    /// Sub-slots and slot operators (`:=`, `:*`), EAPI 5+.
+    SlotOperators,
+    /// The `usex` helper, EAPI 5+.
+    Usex,
+    /// Profile `IUSE` injection (`IUSE_IMPLICIT` and friends), EAPI 5+.
+    ProfileIuseInjection,
+    /// `eapply` and `eapply_user`, EAPI 6+.
+    Eapply,
+    /// `PATCHES` applied by the default `src_prepare`, EAPI 6+.
+    PatchesArray,
+    /// `einstalldocs`, EAPI 6+.
+    Einstalldocs,
+    /// `get_libdir`, EAPI 6+.
+    GetLibdir,
+    /// `BDEPEND`, EAPI 7+.
+    Bdepend,
+    /// `SYSROOT`, `ESYSROOT` and `BROOT`, EAPI 7+.
+    SysrootBroot,
+    /// The `ver_cut`, `ver_rs` and `ver_test` functions, EAPI 7+.
+    VersionFunctions,
+    /// `dostrip`, EAPI 7+.
+    Dostrip,
+    /// `IDEPEND`, EAPI 8+.
+    Idepend,
+    /// USE-conditional `PROPERTIES` and `RESTRICT`, EAPI 8+.
+    UseConditionalRestrict,
+    /// Selective URI restrictions (`fetch+`/`mirror+` prefixes), EAPI 8+.
+    SelectiveUriRestrictions,
+    /// `dosym -r` (relative symlinks), EAPI 8+.
+    DosymRelative,
+    /// Second argument to `usev`, EAPI 8+.
+    UsevSecondArg,
+    /// Empty initial working directory in `pkg_*` phases, EAPI 8+.
+    EmptyPkgWorkdir,
+    /// The `pipestatus` command, EAPI 9+.
+    Pipestatus,
+    /// The `ver_replacing` command, EAPI 9+.
+    VerReplacing,
+    /// `dosed` and `dohard`, banned in EAPI 4.
+    DosedDohard,
+    /// `einstall`, banned in EAPI 6.
+    Einstall,
+    /// `dohtml`, banned in EAPI 7.
+    Dohtml,
+    /// `hasq`, `hasv` and `useq`, banned in EAPI 8.
+    HasqHasvUseq,
+}
+
+impl EapiFeature {
+    /// Every feature, in declaration order.
+    pub const ALL: &'static [EapiFeature] = &[
+        EapiFeature::IuseDefaults,
+        EapiFeature::SlotDeps,
+        EapiFeature::SrcUriArrows,
+        EapiFeature::UseDeps,
+        EapiFeature::StrongBlockers,
+        EapiFeature::SrcPrepare,
+        EapiFeature::SrcConfigure,
+        EapiFeature::DefaultPhaseFunctions,
+        EapiFeature::Properties,
+        EapiFeature::PrefixVariables,
+        EapiFeature::RequiredUse,
+        EapiFeature::PkgPretend,
+        EapiFeature::UseDepDefaults,
+        EapiFeature::ReplacingVersions,
+        EapiFeature::DefaultDocs,
+        EapiFeature::AtMostOneOf,
+        EapiFeature::SlotOperators,
+        EapiFeature::Usex,
+        EapiFeature::ProfileIuseInjection,
+        EapiFeature::Eapply,
+        EapiFeature::PatchesArray,
+        EapiFeature::Einstalldocs,
+        EapiFeature::GetLibdir,
+        EapiFeature::Bdepend,
+        EapiFeature::SysrootBroot,
+        EapiFeature::VersionFunctions,
+        EapiFeature::Dostrip,
+        EapiFeature::Idepend,
+        EapiFeature::UseConditionalRestrict,
+        EapiFeature::SelectiveUriRestrictions,
+        EapiFeature::DosymRelative,
+        EapiFeature::UsevSecondArg,
+        EapiFeature::EmptyPkgWorkdir,
+        EapiFeature::Pipestatus,
+        EapiFeature::VerReplacing,
+        EapiFeature::DosedDohard,
+        EapiFeature::Einstall,
+        EapiFeature::Dohtml,
+        EapiFeature::HasqHasvUseq,
+    ];
+
+    /// The first EAPI with this feature.
+    pub fn introduced_in(self) -> Eapi {
+        match self {
+            EapiFeature::DosedDohard
+            | EapiFeature::Einstall
+            | EapiFeature::Dohtml
+            | EapiFeature::HasqHasvUseq => Eapi::Zero,
+            EapiFeature::IuseDefaults | EapiFeature::SlotDeps => Eapi::One,
+            EapiFeature::SrcUriArrows
+            | EapiFeature::UseDeps
+            | EapiFeature::StrongBlockers
+            | EapiFeature::SrcPrepare
+            | EapiFeature::SrcConfigure
+            | EapiFeature::DefaultPhaseFunctions => Eapi::Two,
+            EapiFeature::Properties | EapiFeature::PrefixVariables => Eapi::Three,
+            EapiFeature::RequiredUse
+            | EapiFeature::PkgPretend
+            | EapiFeature::UseDepDefaults
+            | EapiFeature::ReplacingVersions
+            | EapiFeature::DefaultDocs => Eapi::Four,
+            EapiFeature::AtMostOneOf
+            | EapiFeature::SlotOperators
+            | EapiFeature::Usex
+            | EapiFeature::ProfileIuseInjection => Eapi::Five,
+            EapiFeature::Eapply
+            | EapiFeature::PatchesArray
+            | EapiFeature::Einstalldocs
+            | EapiFeature::GetLibdir => Eapi::Six,
+            EapiFeature::Bdepend
+            | EapiFeature::SysrootBroot
+            | EapiFeature::VersionFunctions
+            | EapiFeature::Dostrip => Eapi::Seven,
+            EapiFeature::Idepend
+            | EapiFeature::UseConditionalRestrict
+            | EapiFeature::SelectiveUriRestrictions
+            | EapiFeature::DosymRelative
+            | EapiFeature::UsevSecondArg
+            | EapiFeature::EmptyPkgWorkdir => Eapi::Eight,
+            EapiFeature::Pipestatus | EapiFeature::VerReplacing => Eapi::Nine,
+        }
+    }
+
+    /// The first EAPI without this feature, for banned commands.
+    pub fn removed_in(self) -> Option<Eapi> {
+        match self {
+            EapiFeature::DosedDohard => Some(Eapi::Four),
+            EapiFeature::Einstall => Some(Eapi::Six),
+            EapiFeature::Dohtml => Some(Eapi::Seven),
+            EapiFeature::HasqHasvUseq => Some(Eapi::Eight),
+            _ => None,
+        }
+    }
+
+    /// Short kebab-case name of the feature (same as `Display`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EapiFeature::IuseDefaults => "iuse-defaults",
+            EapiFeature::SlotDeps => "slot-deps",
+            EapiFeature::SrcUriArrows => "src-uri-arrows",
+            EapiFeature::UseDeps => "use-deps",
+            EapiFeature::StrongBlockers => "strong-blockers",
+            EapiFeature::SrcPrepare => "src-prepare",
+            EapiFeature::SrcConfigure => "src-configure",
+            EapiFeature::DefaultPhaseFunctions => "default-phase-functions",
+            EapiFeature::Properties => "properties",
+            EapiFeature::PrefixVariables => "prefix-variables",
+            EapiFeature::RequiredUse => "required-use",
+            EapiFeature::PkgPretend => "pkg-pretend",
+            EapiFeature::UseDepDefaults => "use-dep-defaults",
+            EapiFeature::ReplacingVersions => "replacing-versions",
+            EapiFeature::DefaultDocs => "default-docs",
+            EapiFeature::AtMostOneOf => "at-most-one-of",
+            EapiFeature::SlotOperators => "slot-operators",
+            EapiFeature::Usex => "usex",
+            EapiFeature::ProfileIuseInjection => "profile-iuse-injection",
+            EapiFeature::Eapply => "eapply",
+            EapiFeature::PatchesArray => "patches-array",
+            EapiFeature::Einstalldocs => "einstalldocs",
+            EapiFeature::GetLibdir => "get-libdir",
+            EapiFeature::Bdepend => "bdepend",
+            EapiFeature::SysrootBroot => "sysroot-broot",
+            EapiFeature::VersionFunctions => "version-functions",
+            EapiFeature::Dostrip => "dostrip",
+            EapiFeature::Idepend => "idepend",
+            EapiFeature::UseConditionalRestrict => "use-conditional-restrict",
+            EapiFeature::SelectiveUriRestrictions => "selective-uri-restrictions",
+            EapiFeature::DosymRelative => "dosym-relative",
+            EapiFeature::UsevSecondArg => "usev-second-arg",
+            EapiFeature::EmptyPkgWorkdir => "empty-pkg-workdir",
+            EapiFeature::Pipestatus => "pipestatus",
+            EapiFeature::VerReplacing => "ver-replacing",
+            EapiFeature::DosedDohard => "dosed-dohard",
+            EapiFeature::Einstall => "einstall",
+            EapiFeature::Dohtml => "dohtml",
+            EapiFeature::HasqHasvUseq => "hasq-hasv-useq",
+        }
+    }
+}
+
+impl fmt::Display for EapiFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_is_complete_and_unique() {
+        let mut names: Vec<&str> = EapiFeature::ALL.iter().map(|f| f.as_str()).collect();
+        let len = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), len);
+        assert!(EapiFeature::ALL.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn removed_after_introduced() {
+        for feature in EapiFeature::ALL {
+            if let Some(removed) = feature.removed_in() {
+                assert!(removed > feature.introduced_in(), "{feature}");
+            }
+        }
+    }
+}
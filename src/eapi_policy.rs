@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+
+use crate::eapi::Eapi;
+
+/// Which EAPIs a repository deprecates or bans.
+///
+/// The [default](EapiPolicy::default) follows the Gentoo repository's
+/// `metadata/layout.conf`: EAPIs 0 to 6 are banned and EAPI 7 is
+/// deprecated. Other repositories can load their own with
+/// [`EapiPolicy::from_layout_conf`].
+///
+/// See [PMS 2](https://projects.gentoo.org/pms/9/pms.html#eapis).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{Eapi, EapiPolicy};
+///
+/// let policy = EapiPolicy::from_layout_conf("eapis-banned = 0 1 2\neapis-deprecated = 3 4\n");
+/// assert!(policy.is_banned(&Eapi::Two));
+/// assert!(policy.is_deprecated(&Eapi::Four));
+/// assert!(!policy.is_deprecated(&Eapi::Eight));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EapiPolicy {
+    deprecated: BTreeSet<Eapi>,
+    banned: BTreeSet<Eapi>,
+}
+
+impl EapiPolicy {
+    /// A policy that allows every EAPI.
+    pub fn new() -> Self {
+        Self {
+            deprecated: BTreeSet::new(),
+            banned: BTreeSet::new(),
+        }
+    }
+
+    /// Read the `eapis-banned` and `eapis-deprecated` keys of a
+    /// `layout.conf`. Other keys and unparsable EAPI names are ignored.
+    pub fn from_layout_conf(contents: &str) -> Self {
+        let mut policy = Self::new();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let eapis = value.split_whitespace().filter_map(|s| s.parse().ok());
+            match key.trim() {
+                "eapis-banned" => policy.banned.extend(eapis),
+                "eapis-deprecated" => policy.deprecated.extend(eapis),
+                _ => {}
+            }
+        }
+        policy
+    }
+
+    /// Mark `eapi` as deprecated.
+    pub fn deprecate(&mut self, eapi: &Eapi) {
+        self.deprecated.insert(eapi.clone());
+    }
+
+    /// Mark `eapi` as banned.
+    pub fn ban(&mut self, eapi: &Eapi) {
+        self.banned.insert(eapi.clone());
+    }
+
+    /// Whether `eapi` is deprecated.
+    pub fn is_deprecated(&self, eapi: &Eapi) -> bool {
+        self.deprecated.contains(eapi)
+    }
+
+    /// Whether `eapi` is banned.
+    pub fn is_banned(&self, eapi: &Eapi) -> bool {
+        self.banned.contains(eapi)
+    }
+}
+
+impl Default for EapiPolicy {
+    fn default() -> Self {
+        let mut policy = Self::new();
+        for eapi in Eapi::ALL.iter().filter(|eapi| **eapi < Eapi::Seven) {
+            policy.ban(eapi);
+        }
+        policy.deprecate(&Eapi::Seven);
+        policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy() {
+        let policy = EapiPolicy::default();
+        assert!(policy.is_banned(&Eapi::Six));
+        assert!(!policy.is_banned(&Eapi::Seven));
+        assert!(policy.is_deprecated(&Eapi::Seven));
+        assert!(!policy.is_deprecated(&Eapi::Eight));
+        assert!(!policy.is_banned(&Eapi::Unknown("10".to_string())));
+    }
+
+    #[test]
+    fn new_allows_everything() {
+        let policy = EapiPolicy::new();
+        assert!(!policy.is_banned(&Eapi::Zero));
+        assert!(!policy.is_deprecated(&Eapi::Zero));
+    }
+}
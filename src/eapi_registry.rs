@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::eapi::{phases_where, Eapi};
+use crate::eapi_feature::EapiFeature;
+use crate::error::Error;
+use crate::phase::Phase;
+
+/// EAPIs defined outside PMS, such as a fork's `7-paludis` or an
+/// experimental EAPI, registered at runtime.
+///
+/// Such names parse as [`Eapi::Unknown`]. Registering one gives it a
+/// feature set, which is what validating metadata with
+/// [`CacheEntry::parse_strict_with`] checks against, and the known EAPI it
+/// was derived from.
+///
+/// [`CacheEntry::parse_strict_with`]: crate::CacheEntry::parse_strict_with
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{Eapi, EapiFeature, EapiRegistry};
+///
+/// let mut registry = EapiRegistry::new();
+/// registry.register("8-myfork", Eapi::Eight, [EapiFeature::Idepend]);
+/// let eapi: Eapi = "8-myfork".parse().unwrap();
+/// assert!(registry.supports(&eapi, EapiFeature::Idepend));
+/// assert!(!registry.supports(&eapi, EapiFeature::Bdepend));
+/// assert_eq!(registry.base_of(&eapi), Eapi::Eight);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EapiRegistry {
+    custom: BTreeMap<String, (Eapi, BTreeSet<EapiFeature>)>,
+}
+
+impl EapiRegistry {
+    /// Create a registry with no custom EAPIs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `name` with an explicit feature set, derived from `base`.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        base: Eapi,
+        features: impl IntoIterator<Item = EapiFeature>,
+    ) {
+        self.custom
+            .insert(name.into(), (base, features.into_iter().collect()));
+    }
+
+    /// Register `name` as behaving exactly like `base`.
+    pub fn register_like(&mut self, name: impl Into<String>, base: Eapi) {
+        let features: Vec<EapiFeature> = base.features().collect();
+        self.register(name, base, features);
+    }
+
+    /// Whether `name` has been registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.custom.contains_key(name)
+    }
+
+    /// Whether `eapi` has `feature`.
+    ///
+    /// Registered EAPIs answer from their feature set, every other one as
+    /// [`Eapi::supports`] does.
+    pub fn supports(&self, eapi: &Eapi, feature: EapiFeature) -> bool {
+        match eapi {
+            Eapi::Unknown(name) => match self.custom.get(name) {
+                Some((_, features)) => features.contains(&feature),
+                None => eapi.supports(feature),
+            },
+            known => known.supports(feature),
+        }
+    }
+
+    /// The known EAPI `eapi` was registered as derived from.
+    ///
+    /// Known EAPIs and unregistered unknown ones are returned as is.
+    pub fn base_of(&self, eapi: &Eapi) -> Eapi {
+        match eapi {
+            Eapi::Unknown(name) => match self.custom.get(name) {
+                Some((base, _)) => base.clone(),
+                None => eapi.clone(),
+            },
+            known => known.clone(),
+        }
+    }
+}
+
+/// The features of one EAPI as validation sees them: from a registry when
+/// there is one, else from the PMS table.
+pub(crate) struct EapiRules<'a> {
+    eapi: &'a Eapi,
+    registry: Option<&'a EapiRegistry>,
+}
+
+impl<'a> EapiRules<'a> {
+    pub(crate) fn new(eapi: &'a Eapi, registry: Option<&'a EapiRegistry>) -> Self {
+        EapiRules { eapi, registry }
+    }
+
+    pub(crate) fn supports(&self, feature: EapiFeature) -> bool {
+        match self.registry {
+            Some(registry) => registry.supports(self.eapi, feature),
+            None => self.eapi.supports(feature),
+        }
+    }
+
+    /// The phase functions an ebuild may define, as [`Eapi::phases`].
+    pub(crate) fn phases(&self) -> &'static [Phase] {
+        phases_where(|feature| self.supports(feature))
+    }
+
+    /// An [`Error::UnsupportedInEapi`] for `feature`, naming the declared
+    /// EAPI.
+    pub(crate) fn unsupported(&self, feature: impl Into<String>) -> Error {
+        Error::UnsupportedInEapi {
+            feature: feature.into(),
+            eapi: self.eapi.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_feature_set() {
+        let mut registry = EapiRegistry::new();
+        registry.register("exp", Eapi::Five, [EapiFeature::Bdepend]);
+        let eapi = Eapi::Unknown("exp".to_string());
+        assert!(registry.contains("exp"));
+        assert!(registry.supports(&eapi, EapiFeature::Bdepend));
+        assert!(!registry.supports(&eapi, EapiFeature::SlotOperators));
+        assert_eq!(registry.base_of(&eapi), Eapi::Five);
+        let rules = EapiRules::new(&eapi, Some(&registry));
+        assert!(!rules.supports(EapiFeature::RequiredUse));
+        assert_eq!(rules.phases(), Eapi::One.phases());
+    }
+
+    #[test]
+    fn unregistered_and_known() {
+        let registry = EapiRegistry::new();
+        let other = Eapi::Unknown("other".to_string());
+        for feature in EapiFeature::ALL {
+            assert_eq!(
+                registry.supports(&other, *feature),
+                other.supports(*feature)
+            );
+        }
+        assert_eq!(registry.base_of(&other), other);
+        assert!(registry.supports(&Eapi::Seven, EapiFeature::Bdepend));
+        assert_eq!(registry.base_of(&Eapi::Seven), Eapi::Seven);
+    }
+}
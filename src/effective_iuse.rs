@@ -0,0 +1,91 @@
+use std::collections::BTreeSet;
+
+use crate::interner::Interner;
+use crate::metadata::EbuildMetadata;
+use crate::profile::Profile;
+
+/// The flags an ebuild may legitimately reference: its declared `IUSE`
+/// plus what the profile injects.
+///
+/// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{CacheEntry, EffectiveIuse, Profile};
+///
+/// let entry = CacheEntry::parse(
+///     "DESCRIPTION=x\nSLOT=0\nIUSE=ssl\nRDEPEND=elibc_musl? ( sys-libs/fts ) doc? ( app-doc/x )\n",
+/// )
+/// .unwrap();
+/// let profile = Profile::from_vars([
+///     ("USE_EXPAND", "ELIBC"),
+///     ("USE_EXPAND_IMPLICIT", "ELIBC"),
+///     ("USE_EXPAND_VALUES_ELIBC", "glibc musl"),
+/// ]);
+/// let iuse = EffectiveIuse::combine(&entry.metadata, &profile);
+/// assert!(iuse.contains("elibc_musl"));
+/// assert_eq!(iuse.undeclared_flags(&entry.metadata), vec!["doc"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EffectiveIuse {
+    flags: BTreeSet<String>,
+}
+
+impl EffectiveIuse {
+    /// Merge the `IUSE` of `metadata` with the implicit flags of `profile`.
+    pub fn combine<I: Interner>(metadata: &EbuildMetadata<I>, profile: &Profile) -> Self {
+        let mut flags = profile.implicit_flags();
+        flags.extend(metadata.iuse.iter().map(|i| i.name().to_string()));
+        Self { flags }
+    }
+
+    /// Whether `flag` is in the effective IUSE.
+    pub fn contains(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    /// Iterate over the flags in sorted order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.flags.iter().map(String::as_str)
+    }
+
+    /// Number of flags.
+    pub fn len(&self) -> usize {
+        self.flags.len()
+    }
+
+    /// Whether there are no flags.
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// Flags referenced by `metadata` but absent from this set.
+    ///
+    /// See [`EbuildMetadata::undeclared_flags`].
+    pub fn undeclared_flags<'a, I: Interner>(
+        &self,
+        metadata: &'a EbuildMetadata<I>,
+    ) -> Vec<&'a str> {
+        let implicit: Vec<&str> = self.iter().collect();
+        metadata.undeclared_flags(&implicit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheEntry;
+
+    #[test]
+    fn iuse_implicit_counts_as_declared() {
+        let entry = CacheEntry::parse(
+            "DESCRIPTION=x\nSLOT=0\nIUSE=a\nRDEPEND=prefix? ( x/y ) a? ( x/z )\n",
+        )
+        .unwrap();
+        let profile = Profile::from_vars([("IUSE_IMPLICIT", "prefix")]);
+        let iuse = EffectiveIuse::combine(&entry.metadata, &profile);
+        assert_eq!(iuse.iter().collect::<Vec<_>>(), ["a", "prefix"]);
+        assert!(iuse.undeclared_flags(&entry.metadata).is_empty());
+    }
+}
@@ -1,5 +1,15 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::eapi::Eapi;
+
 /// Error type for portage-metadata parsing and operations.
+///
+/// New variants may be added in minor releases, so matches need a
+/// wildcard arm.
 #[derive(Debug, Clone, thiserror::Error, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Error {
     /// Invalid EAPI value.
     #[error("invalid EAPI: {0}")]
@@ -48,7 +58,423 @@ pub enum Error {
     /// Invalid SLOT value (does not conform to PMS 3.1.3).
     #[error("invalid SLOT: {0}")]
     InvalidSlot(String),
+
+    /// Syntax not supported by the EAPI in use.
+    #[error("{feature} is not supported in EAPI {eapi}")]
+    UnsupportedInEapi {
+        /// The offending construct, e.g. `?? ( ... )`.
+        feature: String,
+        /// The EAPI that lacks it.
+        eapi: Eapi,
+    },
+
+    /// A group with no entries, e.g. `|| ( )`.
+    #[error("empty group: {0}")]
+    EmptyGroup(String),
+
+    /// Invalid Manifest line.
+    #[error("invalid Manifest: {0}")]
+    InvalidManifest(String),
+
+    /// `HOMEPAGE` entry that is not a valid URL.
+    #[error("invalid HOMEPAGE: {0}")]
+    InvalidHomepage(String),
+
+    /// Invalid lint configuration.
+    #[error("invalid check configuration: {0}")]
+    InvalidConfig(String),
+
+    /// Unrecognized repository timestamp, e.g. in `metadata/timestamp.chk`.
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    /// An error in one field of a md5-cache entry.
+    #[error("{field}{}: {source}", located(.span))]
+    Field {
+        /// The variable name, e.g. `LICENSE`.
+        field: String,
+        /// The offending text: the whole value, or the single token at
+        /// fault for whitespace-separated lists.
+        text: String,
+        /// Where `text` starts in the parsed input; `None` for entries not
+        /// built from text.
+        span: Option<Span>,
+        /// The underlying error.
+        source: Box<Error>,
+    },
+
+    /// Failure reading or writing a file.
+    ///
+    /// The [`io::Error`] is kept as its kind and message so `Error` stays
+    /// `Clone` and `Eq`.
+    #[error("{}: {message}", .path.display())]
+    Io {
+        /// The file or directory accessed.
+        path: PathBuf,
+        /// The kind of the underlying error.
+        kind: io::ErrorKind,
+        /// The message of the underlying error.
+        message: String,
+    },
+
+    /// An error with the value that was most likely meant, e.g. for a
+    /// misspelled phase name.
+    #[error("{source} (did you mean `{suggestion}`?)")]
+    Suggested {
+        /// The closest valid value.
+        suggestion: String,
+        /// The underlying error.
+        source: Box<Error>,
+    },
+
+    /// An error in the metadata of a specific package version.
+    #[error("{cpv}: {source}")]
+    Package {
+        /// The package version, e.g. `dev-libs/foo-1.0`.
+        cpv: String,
+        /// The underlying error.
+        source: Box<Error>,
+    },
+}
+
+impl From<portage_atom::Error> for Error {
+    /// Report a portage-atom parse failure as [`Error::DepError`].
+    fn from(error: portage_atom::Error) -> Self {
+        Error::DepError(error.to_string())
+    }
+}
+
+impl Error {
+    /// Wrap an I/O error on `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io;
+    /// use portage_metadata::Error;
+    ///
+    /// let err = Error::io("metadata/md5-cache", io::Error::from(io::ErrorKind::NotFound));
+    /// assert_eq!(err.io_kind(), Some(io::ErrorKind::NotFound));
+    /// assert!(err.to_string().starts_with("metadata/md5-cache: "));
+    /// ```
+    pub fn io(path: impl AsRef<Path>, error: io::Error) -> Error {
+        Error::Io {
+            path: path.as_ref().to_path_buf(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+
+    /// The kind of I/O error, if this is an [`Error::Io`], possibly within
+    /// [`Error::Package`] context.
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self.root() {
+            Error::Io { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// Attach the package version the error belongs to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, Error};
+    ///
+    /// let err = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nLICENSE=|| (\n")
+    ///     .map_err(|e| e.in_package("dev-libs/foo-1.0"))
+    ///     .unwrap_err();
+    /// assert_eq!(err.cpv(), Some("dev-libs/foo-1.0"));
+    /// assert_eq!(err.field(), Some("LICENSE"));
+    /// assert!(err.to_string().starts_with("dev-libs/foo-1.0: LICENSE at line 3, column 9: "));
+    /// ```
+    pub fn in_package(self, cpv: impl fmt::Display) -> Error {
+        Error::Package {
+            cpv: cpv.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The underlying error, without any [`Error::Field`],
+    /// [`Error::Package`] or [`Error::Suggested`] context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, Error};
+    ///
+    /// let err = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=amd64 ~~x86\n").unwrap_err();
+    /// assert_eq!(err.field(), Some("KEYWORDS"));
+    /// assert_eq!(err.root(), &Error::InvalidKeyword("~~x86".to_string()));
+    /// assert_eq!(err.to_string(), "KEYWORDS at line 3, column 16: invalid keyword: ~~x86");
+    /// ```
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Field { source, .. }
+            | Error::Package { source, .. }
+            | Error::Suggested { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// A stable code identifying the kind of error, e.g. `PM0012` for an
+    /// invalid `SLOT`.
+    ///
+    /// Codes are never reused or renumbered, so tools can track failures
+    /// without matching on the message. [`Error::Field`],
+    /// [`Error::Package`] and [`Error::Suggested`] only add context and
+    /// report the code of the underlying error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let err = CacheEntry::parse("DESCRIPTION=x\nSLOT=0/\n").unwrap_err();
+    /// assert_eq!(err.code(), "PM0012");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self.root() {
+            Error::InvalidEapi(_) => "PM0001",
+            Error::InvalidKeyword(_) => "PM0002",
+            Error::InvalidIUse(_) => "PM0003",
+            Error::InvalidPhase(_) => "PM0004",
+            Error::InvalidSrcUri(_) => "PM0005",
+            Error::InvalidLicense(_) => "PM0006",
+            Error::InvalidRequiredUse(_) => "PM0007",
+            Error::InvalidRestrict(_) => "PM0008",
+            Error::InvalidCacheEntry(_) => "PM0009",
+            Error::MissingField(_) => "PM0010",
+            Error::DepError(_) => "PM0011",
+            Error::InvalidSlot(_) => "PM0012",
+            Error::UnsupportedInEapi { .. } => "PM0013",
+            Error::EmptyGroup(_) => "PM0014",
+            Error::InvalidManifest(_) => "PM0015",
+            Error::InvalidHomepage(_) => "PM0016",
+            Error::Io { .. } => "PM0017",
+            Error::InvalidConfig(_) => "PM0018",
+            Error::InvalidTimestamp(_) => "PM0019",
+            Error::Field { .. } | Error::Package { .. } | Error::Suggested { .. } => {
+                unreachable!("root() strips context")
+            }
+        }
+    }
+
+    /// The closest valid value to the one at fault, if there is a likely
+    /// candidate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Phase;
+    ///
+    /// let err = "comp!le".parse::<Phase>().unwrap_err();
+    /// assert_eq!(err.suggestion(), Some("compile"));
+    /// assert_eq!(err.to_string(), "invalid phase: comp!le (did you mean `compile`?)");
+    /// ```
+    pub fn suggestion(&self) -> Option<&str> {
+        match self {
+            Error::Suggested { suggestion, .. } => Some(suggestion),
+            Error::Field { source, .. } | Error::Package { source, .. } => source.suggestion(),
+            _ => None,
+        }
+    }
+
+    /// Attach the candidate closest to `input`, if any is close enough.
+    pub(crate) fn suggest<'a>(
+        self,
+        input: &str,
+        candidates: impl IntoIterator<Item = &'a str>,
+    ) -> Error {
+        match closest(input, candidates) {
+            Some(suggestion) => Error::Suggested {
+                suggestion: suggestion.to_string(),
+                source: Box::new(self),
+            },
+            None => self,
+        }
+    }
+
+    /// The package version the error belongs to, if known.
+    pub fn cpv(&self) -> Option<&str> {
+        match self {
+            Error::Package { cpv, .. } => Some(cpv),
+            _ => None,
+        }
+    }
+
+    /// The md5-cache variable the error was found in, if known.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Error::Field { field, .. } => Some(field),
+            Error::Package { source, .. } | Error::Suggested { source, .. } => source.field(),
+            _ => None,
+        }
+    }
+
+    /// Where in the parsed input the error was found, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Field { span, .. } => *span,
+            Error::Package { source, .. } | Error::Suggested { source, .. } => source.span(),
+            _ => None,
+        }
+    }
+}
+
+/// The candidate closest to `input` by edit distance, if it is within a
+/// third of the input's length (at least one edit). Ties go to the first
+/// candidate; an empty input has none.
+pub(crate) fn closest<'a>(
+    input: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    if input.is_empty() {
+        return None;
+    }
+    let limit = (input.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(input, candidate), candidate))
+        .filter(|&(distance, _)| distance > 0 && distance <= limit)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// Levenshtein distance, in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn located(span: &Option<Span>) -> String {
+    match span {
+        Some(span) => format!(" at {span}"),
+        None => String::new(),
+    }
+}
+
+/// A location in a md5-cache entry.
+///
+/// Lines and columns count from 1; columns and `offset` are in bytes.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Line number.
+    pub line: usize,
+    /// Column within the line.
+    pub column: usize,
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+}
+
+impl Span {
+    /// Locate `text`, which must be a slice of `source`.
+    pub(crate) fn of(source: &str, text: &str) -> Option<Span> {
+        let offset = (text.as_ptr() as usize).checked_sub(source.as_ptr() as usize)?;
+        if offset + text.len() > source.len() {
+            return None;
+        }
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Some(Span {
+            line: before.matches('\n').count() + 1,
+            column: offset - line_start + 1,
+            offset,
+        })
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
 }
 
 /// Result type for portage-metadata operations.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique() {
+        let errors = [
+            Error::InvalidEapi(String::new()),
+            Error::InvalidKeyword(String::new()),
+            Error::InvalidIUse(String::new()),
+            Error::InvalidPhase(String::new()),
+            Error::InvalidSrcUri(String::new()),
+            Error::InvalidLicense(String::new()),
+            Error::InvalidRequiredUse(String::new()),
+            Error::InvalidRestrict(String::new()),
+            Error::InvalidCacheEntry(String::new()),
+            Error::MissingField(String::new()),
+            Error::DepError(String::new()),
+            Error::InvalidSlot(String::new()),
+            Error::UnsupportedInEapi {
+                feature: String::new(),
+                eapi: Eapi::Zero,
+            },
+            Error::EmptyGroup(String::new()),
+            Error::InvalidManifest(String::new()),
+            Error::InvalidHomepage(String::new()),
+            Error::io("x", io::Error::from(io::ErrorKind::NotFound)),
+            Error::InvalidConfig(String::new()),
+            Error::InvalidTimestamp(String::new()),
+        ];
+        let codes: Vec<_> = errors.iter().map(Error::code).collect();
+        let expected: Vec<_> = (1..=errors.len()).map(|i| format!("PM{i:04}")).collect();
+        assert_eq!(codes, expected);
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("compile", "compile"), 0);
+        assert_eq!(edit_distance("mirrors", "mirror"), 1);
+    }
+
+    #[test]
+    fn closest_candidate() {
+        let known = ["fetch", "mirror", "test", "strip"];
+        assert_eq!(closest("tset", known), None);
+        assert_eq!(closest("tests", known), Some("test"));
+        assert_eq!(closest("mirorr", known), Some("mirror"));
+        assert_eq!(closest("test", known), None);
+        assert_eq!(closest("bindist", known), None);
+    }
+
+    #[test]
+    fn suggestion_context() {
+        let err = Error::InvalidPhase("instal".to_string())
+            .suggest("instal", ["install"])
+            .in_package("dev-libs/foo-1.0");
+        assert_eq!(err.suggestion(), Some("install"));
+        assert_eq!(err.root(), &Error::InvalidPhase("instal".to_string()));
+        assert_eq!(err.code(), "PM0004");
+        assert_eq!(
+            Error::InvalidPhase("x".to_string()).suggest("x", ["install"]),
+            Error::InvalidPhase("x".to_string())
+        );
+    }
+
+    #[test]
+    fn code_sees_through_context() {
+        let err = Error::InvalidLicense("|| (".to_string()).in_package("dev-libs/foo-1.0");
+        assert_eq!(err.code(), "PM0006");
+    }
+}
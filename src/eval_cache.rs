@@ -0,0 +1,330 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+use portage_atom::Dep;
+
+use crate::dep_spec::DepSpec;
+use crate::required_use::RequiredUseExpr;
+use crate::use_flags::UseFlagSet;
+
+/// Flags beyond which results are only shared between identical
+/// configurations: the state of the referenced flags is packed into a
+/// `u64`.
+const MAX_FLAGS: usize = 64;
+
+/// Memoized USE evaluation for many packages under a few configurations.
+///
+/// Expressions and USE configurations are registered once, returning
+/// cheap handles; identical expressions of different packages get the
+/// same handle. A lookup by handles then costs one vector index. The first
+/// lookup of an expression under a configuration projects the
+/// configuration onto the flags the expression references, so two
+/// configurations differing only in unrelated flags still share one
+/// evaluation. A resolver evaluating a whole repository against a handful
+/// of profiles then walks each distinct tree once per relevant flag
+/// combination.
+///
+/// Holds `REQUIRED_USE` satisfaction results and evaluated
+/// [`DepSpec`] trees with leaves of type `L`.
+///
+/// See [PMS 8.2](https://projects.gentoo.org/pms/9/pms.html#dependency-specification-format).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{EvalCache, RequiredUseExpr, UseFlagSet};
+///
+/// let expr = RequiredUseExpr::parse("ssl? ( || ( gnutls openssl ) )").unwrap();
+/// let mut cache: EvalCache = EvalCache::new();
+/// let id = cache.register_required_use(&expr);
+/// let x = cache.register_flags(&UseFlagSet::parse("ssl X"));
+/// let gtk = cache.register_flags(&UseFlagSet::parse("ssl gtk"));
+/// let openssl = cache.register_flags(&UseFlagSet::parse("ssl openssl"));
+///
+/// assert!(!cache.is_satisfied(id, x));
+/// // Only ssl, gnutls and openssl matter: the answer is reused.
+/// assert!(!cache.is_satisfied(id, gtk));
+/// assert!(cache.is_satisfied(id, openssl));
+/// assert!(cache.is_satisfied(id, openssl));
+/// assert_eq!((cache.hits(), cache.misses()), (2, 2));
+/// ```
+#[derive(Debug, Clone)]
+pub struct EvalCache<L = Dep> {
+    flag_ids: HashMap<UseFlagSet, FlagsId>,
+    flags: Vec<UseFlagSet>,
+    required_use_ids: HashMap<RequiredUseExpr, RequiredUseId>,
+    required_use: Vec<Memo<RequiredUseExpr, bool>>,
+    dep_spec_ids: HashMap<DepSpec<L>, DepSpecId>,
+    dep_specs: Vec<Memo<DepSpec<L>, DepSpec<L>>>,
+    hits: u64,
+    misses: u64,
+}
+
+/// A USE configuration registered with an [`EvalCache`].
+///
+/// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlagsId(usize);
+
+/// A `REQUIRED_USE` expression registered with an [`EvalCache`].
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequiredUseId(usize);
+
+/// A [`DepSpec`] registered with an [`EvalCache`].
+///
+/// See [PMS 8.2](https://projects.gentoo.org/pms/9/pms.html#dependency-specification-format).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepSpecId(usize);
+
+/// A registered expression and its cached results.
+#[derive(Debug, Clone)]
+struct Memo<E, T> {
+    expr: E,
+    /// The flags the expression references, sorted.
+    flags: Vec<String>,
+    /// Results by configuration, indexed by [`FlagsId`].
+    by_config: Vec<Option<T>>,
+    /// Results by the state of `flags`, one bit per flag.
+    by_flags: HashMap<u64, T>,
+}
+
+/// Counters shared by every [`Memo`] of a cache.
+struct Stats<'a> {
+    hits: &'a mut u64,
+    misses: &'a mut u64,
+}
+
+impl<E, T> Memo<E, T> {
+    fn new<'a>(expr: E, flags: impl IntoIterator<Item = &'a str>) -> Self {
+        Memo {
+            expr,
+            flags: flags.into_iter().map(str::to_string).collect(),
+            by_config: Vec::new(),
+            by_flags: HashMap::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.by_config.clear();
+        self.by_flags.clear();
+    }
+
+    /// The result under configuration `id`, computing it with `eval` on a
+    /// miss.
+    fn get(
+        &mut self,
+        id: FlagsId,
+        use_flags: &UseFlagSet,
+        stats: Stats<'_>,
+        eval: impl FnOnce(&E, &UseFlagSet) -> T,
+    ) -> T
+    where
+        T: Clone,
+    {
+        if let Some(Some(result)) = self.by_config.get(id.0) {
+            *stats.hits += 1;
+            return result.clone();
+        }
+        let result = if self.flags.len() > MAX_FLAGS {
+            *stats.misses += 1;
+            eval(&self.expr, use_flags)
+        } else {
+            let key = self
+                .flags
+                .iter()
+                .enumerate()
+                .filter(|(_, flag)| use_flags.is_enabled(flag))
+                .fold(0u64, |key, (i, _)| key | 1 << i);
+            match self.by_flags.entry(key) {
+                Entry::Occupied(entry) => {
+                    *stats.hits += 1;
+                    entry.get().clone()
+                }
+                Entry::Vacant(entry) => {
+                    *stats.misses += 1;
+                    entry.insert(eval(&self.expr, use_flags)).clone()
+                }
+            }
+        };
+        if self.by_config.len() <= id.0 {
+            self.by_config.resize(id.0 + 1, None);
+        }
+        self.by_config[id.0] = Some(result.clone());
+        result
+    }
+}
+
+impl<L> Default for EvalCache<L> {
+    fn default() -> Self {
+        EvalCache {
+            flag_ids: HashMap::new(),
+            flags: Vec::new(),
+            required_use_ids: HashMap::new(),
+            required_use: Vec::new(),
+            dep_spec_ids: HashMap::new(),
+            dep_specs: Vec::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+}
+
+impl<L> EvalCache<L> {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a USE configuration, returning the handle of an identical
+    /// one if it was registered before.
+    pub fn register_flags(&mut self, use_flags: &UseFlagSet) -> FlagsId {
+        if let Some(&id) = self.flag_ids.get(use_flags) {
+            return id;
+        }
+        let id = FlagsId(self.flags.len());
+        self.flags.push(use_flags.clone());
+        self.flag_ids.insert(use_flags.clone(), id);
+        id
+    }
+
+    /// Register `expr`, returning the handle of an identical expression
+    /// if one was registered before.
+    pub fn register_required_use(&mut self, expr: &RequiredUseExpr) -> RequiredUseId {
+        if let Some(&id) = self.required_use_ids.get(expr) {
+            return id;
+        }
+        let id = RequiredUseId(self.required_use.len());
+        let mut flags = BTreeSet::new();
+        expr.collect_flags(&mut flags);
+        self.required_use.push(Memo::new(expr.clone(), flags));
+        self.required_use_ids.insert(expr.clone(), id);
+        id
+    }
+
+    /// Register `spec`, returning the handle of an identical tree if one
+    /// was registered before.
+    pub fn register_dep_spec(&mut self, spec: &DepSpec<L>) -> DepSpecId
+    where
+        L: Clone + Eq + Hash,
+    {
+        if let Some(&id) = self.dep_spec_ids.get(spec) {
+            return id;
+        }
+        let id = DepSpecId(self.dep_specs.len());
+        let memo = Memo::new(spec.clone(), spec.conditional_flags());
+        self.dep_specs.push(memo);
+        self.dep_spec_ids.insert(spec.clone(), id);
+        id
+    }
+
+    /// Memoized [`RequiredUseExpr::is_satisfied`].
+    ///
+    /// # Panics
+    ///
+    /// If a handle was registered with another cache.
+    pub fn is_satisfied(&mut self, id: RequiredUseId, flags: FlagsId) -> bool {
+        let stats = Stats {
+            hits: &mut self.hits,
+            misses: &mut self.misses,
+        };
+        self.required_use[id.0].get(flags, &self.flags[flags.0], stats, |expr, use_flags| {
+            expr.is_satisfied(use_flags)
+        })
+    }
+
+    /// Memoized [`DepSpec::evaluate`].
+    ///
+    /// # Panics
+    ///
+    /// If a handle was registered with another cache.
+    pub fn evaluate(&mut self, id: DepSpecId, flags: FlagsId) -> DepSpec<L>
+    where
+        L: Clone,
+    {
+        let stats = Stats {
+            hits: &mut self.hits,
+            misses: &mut self.misses,
+        };
+        self.dep_specs[id.0].get(flags, &self.flags[flags.0], stats, |spec, use_flags| {
+            spec.evaluate(use_flags)
+        })
+    }
+
+    /// Lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Lookups that had to walk the expression.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Drop every cached result, keeping the registered handles and the
+    /// statistics.
+    pub fn clear(&mut self) {
+        self.required_use.iter_mut().for_each(Memo::clear);
+        self.dep_specs.iter_mut().for_each(Memo::clear);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portage_atom::DepEntry;
+
+    #[test]
+    fn dep_specs_by_relevant_flags() {
+        let entries = DepEntry::parse("ssl? ( dev-libs/openssl ) !test? ( dev-libs/a )").unwrap();
+        let spec = DepSpec::from(entries.as_slice());
+        let mut cache = EvalCache::new();
+        let id = cache.register_dep_spec(&spec);
+        for (flags, atoms) in [
+            ("ssl", vec!["dev-libs/openssl", "dev-libs/a"]),
+            ("ssl doc", vec!["dev-libs/openssl", "dev-libs/a"]),
+            ("test", vec![]),
+            ("test X", vec![]),
+            ("ssl test", vec!["dev-libs/openssl"]),
+        ] {
+            let config = cache.register_flags(&UseFlagSet::parse(flags));
+            let evaluated = cache.evaluate(id, config);
+            let leaves: Vec<String> = evaluated.leaves().map(ToString::to_string).collect();
+            assert_eq!(leaves, atoms, "{flags}");
+        }
+        assert_eq!((cache.hits(), cache.misses()), (2, 3));
+
+        // Identical expressions share entries.
+        let expr = RequiredUseExpr::parse("^^ ( a b )").unwrap();
+        let id = cache.register_required_use(&expr);
+        assert_eq!(cache.register_required_use(&expr.clone()), id);
+        let a = cache.register_flags(&UseFlagSet::parse("a"));
+        let ac = cache.register_flags(&UseFlagSet::parse("a c"));
+        assert!(cache.is_satisfied(id, a));
+        assert!(cache.is_satisfied(id, ac));
+        assert!(cache.is_satisfied(id, ac));
+        assert_eq!(cache.hits(), 4);
+
+        cache.clear();
+        let ab = cache.register_flags(&UseFlagSet::parse("a b"));
+        assert!(!cache.is_satisfied(id, ab));
+        assert!(cache.is_satisfied(id, a));
+        assert_eq!(cache.misses(), 6);
+    }
+
+    #[test]
+    fn many_flags_cached_by_configuration() {
+        let flags: Vec<String> = (0..70).map(|i| format!("f{i}")).collect();
+        let expr = RequiredUseExpr::parse(&format!("|| ( {} )", flags.join(" "))).unwrap();
+        let mut cache: EvalCache = EvalCache::new();
+        let id = cache.register_required_use(&expr);
+        let f69 = cache.register_flags(&UseFlagSet::parse("f69"));
+        let none = cache.register_flags(&UseFlagSet::new());
+        assert!(cache.is_satisfied(id, f69));
+        assert!(!cache.is_satisfied(id, none));
+        assert!(cache.is_satisfied(id, f69));
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use crate::fetch_plan::FetchPlan;
+use crate::manifest::Manifest;
+
+/// The layout written by [`FetchList`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FetchListFormat {
+    /// One URL per line, the first candidate of each file, for
+    /// `wget -i`. Renamed files keep the name from their URL.
+    #[default]
+    Urls,
+    /// An `aria2c -i` input file: every candidate URL of a file on one
+    /// tab-separated line, followed by indented `out=` and, given a
+    /// Manifest, `checksum=` options.
+    Aria2,
+}
+
+/// Plain-text download lists from a [`FetchPlan`], for distfile
+/// pre-mirroring scripts.
+///
+/// Fetch-restricted files are left out: they must be downloaded by hand.
+///
+/// See [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{
+///     FetchList, FetchListFormat, FetchPlan, Manifest, SrcUriEntry, ThirdPartyMirrors, UseFlagSet,
+/// };
+///
+/// let src_uri = SrcUriEntry::parse("mirror://gnu/hello/hello-2.12.tar.gz").unwrap();
+/// let mirrors = ThirdPartyMirrors::parse("gnu https://a.example/gnu https://b.example/gnu");
+/// let plan = FetchPlan::new(&src_uri, &[], &UseFlagSet::new(), &mirrors);
+/// let manifest = Manifest::parse("DIST hello-2.12.tar.gz 1024 BLAKE2B 00 SHA512 ab12\n").unwrap();
+///
+/// let mut out = Vec::new();
+/// FetchList::new(FetchListFormat::Aria2)
+///     .with_manifest(manifest)
+///     .write(&mut out, &plan)
+///     .unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "https://a.example/gnu/hello/hello-2.12.tar.gz\thttps://b.example/gnu/hello/hello-2.12.tar.gz\n\
+///      \x20 out=hello-2.12.tar.gz\n\
+///      \x20 checksum=sha-512=ab12\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchList {
+    format: FetchListFormat,
+    manifest: Option<Manifest>,
+}
+
+impl FetchList {
+    /// Create a list in `format`, without checksums.
+    pub fn new(format: FetchListFormat) -> Self {
+        FetchList {
+            format,
+            manifest: None,
+        }
+    }
+
+    /// Take checksums from `manifest`, for formats that support them.
+    pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Write the files of `plan` that may be fetched automatically.
+    pub fn write(&self, out: &mut impl Write, plan: &FetchPlan) -> io::Result<()> {
+        for file in plan.files().iter().filter(|file| file.fetch) {
+            let Some(first) = file.urls.first() else {
+                continue;
+            };
+            match self.format {
+                FetchListFormat::Urls => writeln!(out, "{first}")?,
+                FetchListFormat::Aria2 => {
+                    writeln!(out, "{}", file.urls.join("\t"))?;
+                    writeln!(out, "  out={}", file.filename)?;
+                    if let Some((kind, digest)) = self.checksum(&file.filename) {
+                        writeln!(out, "  checksum={kind}={digest}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The strongest Manifest digest of `filename` that aria2 can verify.
+    fn checksum(&self, filename: &str) -> Option<(&'static str, &str)> {
+        let entry = self.manifest.as_ref()?.get(filename)?;
+        [
+            ("SHA512", "sha-512"),
+            ("SHA256", "sha-256"),
+            ("SHA1", "sha-1"),
+        ]
+        .into_iter()
+        .find_map(|(algorithm, kind)| Some((kind, entry.hash(algorithm)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mirrors::ThirdPartyMirrors;
+    use crate::restrict::RestrictExpr;
+    use crate::src_uri::SrcUriEntry;
+    use crate::use_flags::UseFlagSet;
+
+    fn write(list: &FetchList, src_uri: &str, restrict: &str) -> String {
+        let plan = FetchPlan::new(
+            &SrcUriEntry::parse(src_uri).unwrap(),
+            &RestrictExpr::parse(restrict).unwrap(),
+            &UseFlagSet::new(),
+            &ThirdPartyMirrors::new(),
+        );
+        let mut out = Vec::new();
+        list.write(&mut out, &plan).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        let src_uri = "https://a.org/v1.tgz -> foo-1.tgz fetch+https://a.org/b.tgz";
+        assert_eq!(
+            write(&FetchList::default(), src_uri, ""),
+            "https://a.org/v1.tgz\nhttps://a.org/b.tgz\n"
+        );
+        let manifest = Manifest::parse("DIST b.tgz 1 BLAKE2B 00 SHA256 cd\n").unwrap();
+        assert_eq!(
+            write(
+                &FetchList::new(FetchListFormat::Aria2).with_manifest(manifest),
+                src_uri,
+                "fetch"
+            ),
+            "https://a.org/b.tgz\n  out=b.tgz\n  checksum=sha-256=cd\n"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use crate::distfile::DistfileAccess;
+use crate::mirrors::ThirdPartyMirrors;
+use crate::restrict::RestrictExpr;
+use crate::src_uri::SrcUriEntry;
+use crate::src_url::SrcUrl;
+use crate::use_flags::UseFlagSet;
+
+/// Ranks candidate download URLs for a [`FetchPlan`].
+///
+/// Higher scores are tried first; URLs with equal scores keep their
+/// `SRC_URI` order. Closures `Fn(&str) -> i64` implement this trait.
+pub trait MirrorScorer {
+    /// The score of `url`.
+    fn score(&self, url: &str) -> i64;
+}
+
+impl<F: Fn(&str) -> i64> MirrorScorer for F {
+    fn score(&self, url: &str) -> i64 {
+        self(url)
+    }
+}
+
+/// Prefers URL schemes in the given order (e.g. `https` before `http`).
+///
+/// Unlisted schemes score lowest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolPreference(pub Vec<String>);
+
+impl MirrorScorer for ProtocolPreference {
+    fn score(&self, url: &str) -> i64 {
+        let scheme = url.split_once("://").map_or("", |(scheme, _)| scheme);
+        match self.0.iter().position(|s| s.eq_ignore_ascii_case(scheme)) {
+            Some(i) => -(i as i64),
+            None => i64::MIN,
+        }
+    }
+}
+
+/// User-assigned weights per host, e.g. for mirrors in the user's region.
+///
+/// Unlisted hosts score 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostWeights(pub BTreeMap<String, i64>);
+
+impl MirrorScorer for HostWeights {
+    fn score(&self, url: &str) -> i64 {
+        match SrcUrl::parse(url).host() {
+            Some(host) => self.0.get(host).copied().unwrap_or(0),
+            None => 0,
+        }
+    }
+}
+
+/// A distfile to download and where to get it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedDistfile {
+    /// The local filename in `DISTDIR`.
+    pub filename: String,
+    /// Candidate URLs, `mirror://` URLs expanded, in the order to try.
+    pub urls: Vec<String>,
+    /// Whether any URI for the file may be fetched automatically.
+    pub fetch: bool,
+    /// Whether any URI for the file may be mirrored.
+    pub mirror: bool,
+}
+
+/// The distfiles of a package configuration with their candidate URLs.
+///
+/// Built from `SRC_URI` and `RESTRICT` evaluated under a set of USE flags.
+/// Several `SRC_URI` entries naming the same file are merged into one
+/// [`PlannedDistfile`].
+///
+/// See [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchPlan {
+    files: Vec<PlannedDistfile>,
+}
+
+impl FetchPlan {
+    /// Plan the downloads for `src_uri` under `use_flags`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{FetchPlan, SrcUriEntry, ThirdPartyMirrors, UseFlagSet};
+    ///
+    /// let src_uri = SrcUriEntry::parse(
+    ///     "mirror://gnu/hello/hello-2.12.tar.gz https://e.com/hello-2.12.tar.gz",
+    /// )
+    /// .unwrap();
+    /// let mirrors = ThirdPartyMirrors::parse("gnu https://ftp.gnu.org/gnu");
+    /// let plan = FetchPlan::new(&src_uri, &[], &UseFlagSet::new(), &mirrors);
+    /// assert_eq!(plan.files().len(), 1);
+    /// assert_eq!(plan.files()[0].urls.len(), 2);
+    /// ```
+    pub fn new(
+        src_uri: &[SrcUriEntry],
+        restrict: &[RestrictExpr],
+        use_flags: &UseFlagSet,
+        mirrors: &ThirdPartyMirrors,
+    ) -> Self {
+        let mut files: Vec<PlannedDistfile> = Vec::new();
+        for access in DistfileAccess::compute(src_uri, restrict, use_flags) {
+            let urls = mirrors.expand(access.url);
+            match files.iter_mut().find(|f| f.filename == access.filename) {
+                Some(file) => {
+                    file.urls.extend(urls);
+                    file.fetch |= access.fetch;
+                    file.mirror |= access.mirror;
+                }
+                None => files.push(PlannedDistfile {
+                    filename: access.filename.to_string(),
+                    urls,
+                    fetch: access.fetch,
+                    mirror: access.mirror,
+                }),
+            }
+        }
+        for file in &mut files {
+            let mut seen = Vec::new();
+            file.urls.retain(|url| {
+                let new = !seen.contains(url);
+                if new {
+                    seen.push(url.clone());
+                }
+                new
+            });
+        }
+        Self { files }
+    }
+
+    /// The planned distfiles, in `SRC_URI` order.
+    pub fn files(&self) -> &[PlannedDistfile] {
+        &self.files
+    }
+
+    /// Reorder every file's candidate URLs by descending `scorer` score.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{FetchPlan, ProtocolPreference, SrcUriEntry, ThirdPartyMirrors, UseFlagSet};
+    ///
+    /// let src_uri = SrcUriEntry::parse("mirror://gnu/a.tgz").unwrap();
+    /// let mirrors = ThirdPartyMirrors::parse("gnu ftp://ftp.gnu.org/gnu https://ftp.gnu.org/gnu");
+    /// let mut plan = FetchPlan::new(&src_uri, &[], &UseFlagSet::new(), &mirrors);
+    /// plan.order_by(&ProtocolPreference(vec!["https".into(), "ftp".into()]));
+    /// assert_eq!(plan.files()[0].urls[0], "https://ftp.gnu.org/gnu/a.tgz");
+    /// ```
+    pub fn order_by(&mut self, scorer: &impl MirrorScorer) {
+        for file in &mut self.files {
+            file.urls
+                .sort_by_cached_key(|url| std::cmp::Reverse(scorer.score(url)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_and_restrict() {
+        let src_uri = SrcUriEntry::parse(
+            "https://a.org/f.tgz mirror+https://b.org/f.tgz https://a.org/f.tgz https://a.org/g.tgz",
+        )
+        .unwrap();
+        let restrict = RestrictExpr::parse("mirror").unwrap();
+        let plan = FetchPlan::new(
+            &src_uri,
+            &restrict,
+            &UseFlagSet::new(),
+            &ThirdPartyMirrors::new(),
+        );
+        assert_eq!(plan.files().len(), 2);
+        assert_eq!(
+            plan.files()[0].urls,
+            vec!["https://a.org/f.tgz", "https://b.org/f.tgz"]
+        );
+        assert!(plan.files()[0].mirror);
+        assert!(!plan.files()[1].mirror);
+    }
+
+    #[test]
+    fn order_by_host_weights_and_closure() {
+        let src_uri = SrcUriEntry::parse("mirror://m/x.tgz").unwrap();
+        let mirrors = ThirdPartyMirrors::parse(
+            "m https://us.example/m https://eu.example/m http://old.example/m",
+        );
+        let mut plan = FetchPlan::new(&src_uri, &[], &UseFlagSet::new(), &mirrors);
+
+        let weights = HostWeights([("eu.example".to_string(), 10)].into_iter().collect());
+        plan.order_by(&weights);
+        assert_eq!(
+            plan.files()[0].urls,
+            vec![
+                "https://eu.example/m/x.tgz",
+                "https://us.example/m/x.tgz",
+                "http://old.example/m/x.tgz"
+            ]
+        );
+
+        plan.order_by(&|url: &str| i64::from(url.starts_with("http://")));
+        assert_eq!(plan.files()[0].urls[0], "http://old.example/m/x.tgz");
+    }
+}
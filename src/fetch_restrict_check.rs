@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::phase::Phase;
+use crate::restrict::{RestrictExpr, RestrictKind};
+use crate::src_uri::SrcUriEntry;
+use crate::src_url::SrcUrl;
+use crate::use_flags::UseFlagSet;
+
+/// USE flags beyond which not every combination is tried; the rest are
+/// taken as enabled.
+const MAX_FLAGS: usize = 10;
+
+/// Reports fetch-restricted entries that define no `pkg_nofetch`, and
+/// mirror-restricted entries fetching from `mirror://` URLs.
+///
+/// Without `pkg_nofetch` users get no instructions for downloading the
+/// files by hand. `mirror://` URLs point at the very mirrors a `mirror`
+/// (or `fetch`) restriction keeps the files off, unless an EAPI 8
+/// `mirror+` prefix lifts it for the URL. Restrictions count even when
+/// they depend on USE flags: a URL is reported if some combination of the
+/// flags `RESTRICT` and `SRC_URI` depend on leaves it both active and
+/// unmirrorable.
+///
+/// See [PMS 7.3.6](https://projects.gentoo.org/pms/9/pms.html#restrict).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, FetchRestrictCheck};
+///
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-1").unwrap(),
+///     CacheEntry::parse(
+///         "DESCRIPTION=x\nSLOT=0\nRESTRICT=mirror\nSRC_URI=mirror://gnu/foo-1.tgz\n",
+///     )
+///     .unwrap(),
+/// )]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(FetchRestrictCheck);
+/// let findings = runner.run(&repo);
+/// assert_eq!(findings[0].message, "RESTRICT=mirror with mirror://gnu/foo-1.tgz");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FetchRestrictCheck;
+
+impl MetadataCheck for FetchRestrictCheck {
+    fn name(&self) -> &'static str {
+        "fetch-restrict"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let metadata = &cx.entry.metadata;
+        let restricted = |kind| RestrictExpr::possibly_applies(&metadata.restrict, &kind);
+        let fetch = restricted(RestrictKind::Fetch);
+        if fetch && !metadata.defined_phases.contains(&Phase::PkgNofetch) {
+            let message = "RESTRICT=fetch without pkg_nofetch";
+            findings.push(cx.finding(self.name(), Severity::Warning, message));
+        }
+        if !fetch && !restricted(RestrictKind::Mirror) {
+            return;
+        }
+        let mut flags = BTreeSet::new();
+        RestrictExpr::collect_flags(&metadata.restrict, &mut flags);
+        SrcUriEntry::collect_flags(&metadata.src_uri, &mut flags);
+        let flags: Vec<&str> = flags.into_iter().collect();
+        let (tried, enabled) = flags.split_at(flags.len().min(MAX_FLAGS));
+        let mut reported: Vec<(&SrcUrl, RestrictKind)> = Vec::new();
+        for bits in 0..1u32 << tried.len() {
+            let use_flags: UseFlagSet = tried
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| bits >> i & 1 == 1)
+                .map(|(_, flag)| *flag)
+                .chain(enabled.iter().copied())
+                .collect();
+            let kind =
+                if RestrictExpr::applies(&metadata.restrict, &RestrictKind::Fetch, &use_flags) {
+                    RestrictKind::Fetch
+                } else {
+                    RestrictKind::Mirror
+                };
+            for access in metadata.distfile_access(&use_flags) {
+                let url = access.url;
+                if !access.mirror
+                    && matches!(url, SrcUrl::Mirror { .. })
+                    && !reported.iter().any(|(seen, _)| *seen == url)
+                {
+                    reported.push((url, kind.clone()));
+                }
+            }
+        }
+        for (url, kind) in reported {
+            let message = format!("RESTRICT={} with {url}", kind.as_str());
+            findings.push(cx.finding(self.name(), Severity::Error, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    fn messages(text: &str) -> Vec<String> {
+        let findings = run_check(FetchRestrictCheck, &[("app-misc/foo-1", text)]);
+        findings.into_iter().map(|f| f.message).collect()
+    }
+
+    #[test]
+    fn fetch_restriction() {
+        assert_eq!(
+            messages(
+                "EAPI=8\nDESCRIPTION=x\nSLOT=0\nRESTRICT=bindist? ( fetch )\n\
+                 SRC_URI=foo.tgz doc? ( mirror://sourceforge/foo/doc.tgz )\n"
+            ),
+            [
+                "RESTRICT=fetch without pkg_nofetch",
+                "RESTRICT=fetch with mirror://sourceforge/foo/doc.tgz"
+            ]
+        );
+        assert!(messages(
+            "EAPI=8\nDESCRIPTION=x\nSLOT=0\nRESTRICT=fetch\nSRC_URI=foo.tgz\n\
+             DEFINED_PHASES=nofetch install\n"
+        )
+        .is_empty());
+        assert!(
+            messages("EAPI=8\nDESCRIPTION=x\nSLOT=0\nSRC_URI=mirror://gnu/foo.tgz\n").is_empty()
+        );
+    }
+
+    #[test]
+    fn uri_prefixes_and_exclusive_conditionals() {
+        assert!(messages(
+            "EAPI=8\nDESCRIPTION=x\nSLOT=0\nRESTRICT=mirror\n\
+             SRC_URI=mirror+mirror://gnu/foo-1.tgz\n"
+        )
+        .is_empty());
+        assert_eq!(
+            messages(
+                "EAPI=8\nDESCRIPTION=x\nSLOT=0\nRESTRICT=mirror\n\
+                 SRC_URI=fetch+mirror://gnu/foo-1.tgz\n"
+            ),
+            ["RESTRICT=mirror with mirror://gnu/foo-1.tgz"]
+        );
+        assert!(messages(
+            "EAPI=8\nDESCRIPTION=x\nSLOT=0\nRESTRICT=bindist? ( mirror )\n\
+             SRC_URI=!bindist? ( mirror://gnu/foo-1.tgz )\n"
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Changed files of a git checkout, behind the `git` feature.
+//!
+//! Changes are read by running the `git` command rather than through a
+//! git library such as gitoxide or libgit2, which would add a large
+//! dependency tree for two read-only queries. `git` must therefore be
+//! installed and on `PATH`; if it is not, [`GitChanges::since`] fails with
+//! an [`Error::Io`] of kind [`io::ErrorKind::NotFound`] on the checkout
+//! root.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use portage_atom::Cpv;
+
+use crate::error::{Error, Result};
+use crate::metadata_repo::MetadataRepo;
+
+/// The ebuilds and eclasses of a git checkout changed since a commit.
+///
+/// Lets scans over a large repository stop at what a commit range could
+/// have affected: [`GitChanges::affected`] adds the package versions
+/// inheriting a changed eclass, and the result limits
+/// [`RegenPlan::for_cpvs`](crate::RegenPlan::for_cpvs) or any other
+/// per-entry work. Committed, staged and unstaged edits and untracked
+/// files all count.
+///
+/// Changes are read by running the `git` command, which must be on
+/// `PATH`.
+///
+/// Git checkouts are not specified by PMS.
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::GitChanges;
+///
+/// let changes = GitChanges::parse_name_status(
+///     "M\tapp-misc/foo/foo-1.ebuild\nD\tdev-libs/bar/bar-2.ebuild\n\
+///      M\teclass/cmake.eclass\nM\tapp-misc/foo/Manifest\n",
+/// );
+/// assert_eq!(changes.ebuilds.len(), 1);
+/// assert_eq!(changes.removed.iter().next().unwrap().to_string(), "dev-libs/bar-2");
+/// assert!(changes.eclasses.contains("cmake"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitChanges {
+    /// Package versions whose ebuild was added or modified.
+    pub ebuilds: BTreeSet<Cpv>,
+    /// Package versions whose ebuild was deleted.
+    pub removed: BTreeSet<Cpv>,
+    /// Eclasses added, modified or deleted, by name.
+    pub eclasses: BTreeSet<String>,
+}
+
+impl GitChanges {
+    /// The changes in the checkout at `root` since `commit`, which may be
+    /// any revision git accepts, such as a hash, tag or `HEAD~10`.
+    ///
+    /// `root` may be a subdirectory of the checkout; paths are taken
+    /// relative to it.
+    ///
+    /// A failure to run git is reported as [`Error::Io`] on `root`, as is
+    /// a git error, with git's message. If `git` is not on `PATH`, the
+    /// error kind is [`io::ErrorKind::NotFound`].
+    pub fn since(root: impl AsRef<Path>, commit: &str) -> Result<Self> {
+        let root = root.as_ref();
+        let diff = git(
+            root,
+            &[
+                "diff",
+                "--name-status",
+                "--no-renames",
+                "--relative",
+                commit,
+                "--",
+            ],
+        )?;
+        let untracked = git(root, &["ls-files", "--others", "--exclude-standard"])?;
+        let mut changes = Self::parse_name_status(&diff);
+        for path in untracked.lines() {
+            changes.add('A', path);
+        }
+        Ok(changes)
+    }
+
+    /// Read the output of `git diff --name-status --no-renames`.
+    ///
+    /// Paths outside `<category>/<package>/*.ebuild` and
+    /// `eclass/*.eclass` are ignored.
+    pub fn parse_name_status(output: &str) -> Self {
+        let mut changes = GitChanges::default();
+        for line in output.lines() {
+            if let Some((status, path)) = line.split_once('\t') {
+                changes.add(status.chars().next().unwrap_or('M'), path);
+            }
+        }
+        changes
+    }
+
+    /// Whether nothing relevant changed.
+    pub fn is_empty(&self) -> bool {
+        self.ebuilds.is_empty() && self.removed.is_empty() && self.eclasses.is_empty()
+    }
+
+    /// The package versions to look at again: changed and removed
+    /// ebuilds, plus every cache entry of `repo` inheriting a changed
+    /// eclass.
+    ///
+    /// The cache is only read if an eclass changed; entries that fail to
+    /// parse are reported as errors.
+    pub fn affected(&self, repo: &MetadataRepo) -> Result<BTreeSet<Cpv>> {
+        let mut out: BTreeSet<Cpv> = self.ebuilds.union(&self.removed).cloned().collect();
+        if self.eclasses.is_empty() {
+            return Ok(out);
+        }
+        for cpv in repo.cpvs()? {
+            let entry = repo.read(&cpv)?;
+            if entry
+                .eclasses
+                .iter()
+                .any(|(name, _)| self.eclasses.contains(name.as_str()))
+            {
+                out.insert(cpv);
+            }
+        }
+        Ok(out)
+    }
+
+    fn add(&mut self, status: char, path: &str) {
+        let parts: Vec<&str> = path.split('/').collect();
+        match parts.as_slice() {
+            ["eclass", file] => {
+                if let Some(name) = file.strip_suffix(".eclass") {
+                    self.eclasses.insert(name.to_string());
+                }
+            }
+            [category, _, file] => {
+                let Some(pf) = file.strip_suffix(".ebuild") else {
+                    return;
+                };
+                let Ok(cpv) = Cpv::parse(&format!("{category}/{pf}")) else {
+                    return;
+                };
+                if status == 'D' {
+                    self.removed.insert(cpv);
+                } else {
+                    self.ebuilds.insert(cpv);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Run git in `root`, returning its standard output.
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(|e| Error::io(root, e))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(Error::io(root, io::Error::other(message)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+    use std::fs;
+
+    fn cpv(s: &str) -> Cpv {
+        Cpv::parse(s).unwrap()
+    }
+
+    #[test]
+    fn since_commit() {
+        // The repository is a subdirectory of the checkout.
+        let top = TempDir::new("git-changes");
+        let root = top.join("repo");
+        let run = |args: &[&str]| git(&root, args).unwrap();
+        let write = |path: &str, text: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        fs::create_dir_all(&root).unwrap();
+        git(&top, &["init", "-q"]).unwrap();
+        write("app-misc/foo/foo-1.ebuild", "EAPI=8\n");
+        write("app-misc/bar/bar-1.ebuild", "EAPI=8\n");
+        write("dev-libs/baz/baz-1.ebuild", "EAPI=8\n");
+        write("eclass/local.eclass", "# local\n");
+        write(
+            "metadata/md5-cache/dev-libs/baz-1",
+            "DESCRIPTION=x\nSLOT=0\n_eclasses_=local\tabc\n",
+        );
+        write(
+            "metadata/md5-cache/app-misc/foo-1",
+            "DESCRIPTION=x\nSLOT=0\n",
+        );
+        run(&["add", "."]);
+        run(&[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.org",
+            "commit",
+            "-qm",
+            "initial",
+        ]);
+
+        assert!(GitChanges::since(&root, "HEAD").unwrap().is_empty());
+        write("app-misc/foo/foo-1.ebuild", "EAPI=8\nIUSE=x\n");
+        write("app-misc/foo/foo-2.ebuild", "EAPI=8\n");
+        write("eclass/local.eclass", "# changed\n");
+        fs::remove_file(root.join("app-misc/bar/bar-1.ebuild")).unwrap();
+
+        let changes = GitChanges::since(&root, "HEAD").unwrap();
+        assert_eq!(
+            changes.ebuilds,
+            BTreeSet::from([cpv("app-misc/foo-1"), cpv("app-misc/foo-2")])
+        );
+        assert_eq!(changes.removed, BTreeSet::from([cpv("app-misc/bar-1")]));
+        assert_eq!(
+            changes.affected(&MetadataRepo::new(&root)).unwrap(),
+            BTreeSet::from([
+                cpv("app-misc/bar-1"),
+                cpv("app-misc/foo-1"),
+                cpv("app-misc/foo-2"),
+                cpv("dev-libs/baz-1"),
+            ])
+        );
+        assert!(GitChanges::since(&root, "no-such-commit").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::src_url::SrcUrl;
+
+/// A `HOMEPAGE` entry.
+///
+/// Entries must be absolute `scheme://host/...` URLs. Values that are not
+/// are kept as [`Homepage::Invalid`] by the lenient [`Homepage::parse`] so a
+/// bad ebuild does not make the whole cache entry unreadable.
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Homepage {
+    /// A well-formed URL; always [`SrcUrl::Url`].
+    Url(SrcUrl),
+    /// A value that is not a valid URL, kept verbatim.
+    Invalid(String),
+}
+
+impl Homepage {
+    /// Parse one `HOMEPAGE` token, falling back to [`Homepage::Invalid`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Homepage;
+    ///
+    /// let hp = Homepage::parse("https://www.gentoo.org/");
+    /// assert!(hp.is_valid());
+    /// assert_eq!(hp.url().and_then(|u| u.host()), Some("www.gentoo.org"));
+    ///
+    /// assert!(!Homepage::parse("www.gentoo.org").is_valid());
+    /// ```
+    pub fn parse(input: &str) -> Self {
+        match SrcUrl::parse(input) {
+            url @ SrcUrl::Url { .. } if is_valid_url(&url) => Homepage::Url(url),
+            _ => Homepage::Invalid(input.to_string()),
+        }
+    }
+
+    /// Parse one `HOMEPAGE` token, rejecting anything but a valid URL.
+    pub fn parse_strict(input: &str) -> Result<Self> {
+        match Homepage::parse(input) {
+            Homepage::Invalid(value) => Err(Error::InvalidHomepage(value)),
+            hp => Ok(hp),
+        }
+    }
+
+    /// Parse a whitespace-separated `HOMEPAGE` value leniently.
+    pub fn parse_line(input: &str) -> Vec<Self> {
+        input.split_whitespace().map(Homepage::parse).collect()
+    }
+
+    /// Whether this entry is a well-formed URL.
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Homepage::Url(_))
+    }
+
+    /// The parsed URL, if valid.
+    pub fn url(&self) -> Option<&SrcUrl> {
+        match self {
+            Homepage::Url(url) => Some(url),
+            Homepage::Invalid(_) => None,
+        }
+    }
+}
+
+// RFC 3986 scheme characters and a non-empty host.
+fn is_valid_url(url: &SrcUrl) -> bool {
+    let SrcUrl::Url { scheme, host, .. } = url else {
+        return false;
+    };
+    let scheme_ok = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'));
+    let host_ok = !host.is_empty()
+        && !host.starts_with(['.', '-', ':'])
+        && host
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !matches!(b, b'/' | b'?' | b'#'));
+    scheme_ok && host_ok
+}
+
+impl fmt::Display for Homepage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Homepage::Url(url) => write!(f, "{url}"),
+            Homepage::Invalid(value) => f.write_str(value),
+        }
+    }
+}
+
+impl PartialEq<str> for Homepage {
+    fn eq(&self, other: &str) -> bool {
+        match self {
+            Homepage::Url(url) => url == other,
+            Homepage::Invalid(value) => value == other,
+        }
+    }
+}
+
+impl PartialEq<&str> for Homepage {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_urls() {
+        for url in [
+            "https://llvm.org/",
+            "http://example.com",
+            "https://github.com/foo/bar?tab=readme",
+            "git+https://host:8080/x",
+        ] {
+            let hp = Homepage::parse(url);
+            assert!(hp.is_valid(), "{url}");
+            assert_eq!(hp.to_string(), url);
+        }
+    }
+
+    #[test]
+    fn invalid_values_kept() {
+        for value in [
+            "example.com",
+            "https://",
+            "1http://x.org",
+            "mirror://gnu/x",
+            "https://-x/",
+        ] {
+            let hp = Homepage::parse(value);
+            assert_eq!(hp, Homepage::Invalid(value.to_string()));
+            assert_eq!(hp, value);
+        }
+    }
+
+    #[test]
+    fn strict_rejects_invalid() {
+        assert!(Homepage::parse_strict("https://gentoo.org").is_ok());
+        assert_eq!(
+            Homepage::parse_strict("gentoo.org"),
+            Err(Error::InvalidHomepage("gentoo.org".to_string()))
+        );
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 use crate::interner::{DefaultInterner, Interner};
 
 use crate::error::{Error, Result};
+use crate::names::{check_name, invalid_flag_char, Leaf};
+use crate::tokens::Tokens;
+use crate::use_expand::UseExpandConfig;
 
 /// Default state for an IUSE flag.
 ///
@@ -22,7 +27,7 @@ pub enum IUseDefault {
 /// A single USE flag entry from the `IUSE` variable.
 ///
 /// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IUse<I = DefaultInterner>
 where
     I: Interner,
@@ -33,15 +38,59 @@ where
     pub default: Option<IUseDefault>,
 }
 
-fn is_valid_use_flag_name(name: &str) -> bool {
-    !name.is_empty()
-        && name
-            .chars()
-            .next()
-            .is_some_and(|c| c.is_ascii_alphanumeric())
-        && name
-            .chars()
-            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '@' | '-'))
+/// A flag listed more than once, as reported by [`IUse::normalize`].
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IUseDuplicate {
+    /// The repeated flag name.
+    pub name: String,
+    /// Whether the occurrences gave different explicit defaults
+    /// (`+flag` and `-flag`).
+    pub conflicting: bool,
+}
+
+/// USE flag churn between two IUSE lists, as computed by [`IUse::diff`].
+///
+/// All lists are sorted by flag name.
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IUseDiff {
+    /// Flags only in the new list.
+    pub added: Vec<String>,
+    /// Flags only in the old list.
+    pub removed: Vec<String>,
+    /// Flags in both lists whose default changed.
+    pub default_changed: Vec<IUseDefaultChange>,
+}
+
+impl IUseDiff {
+    /// Whether the two lists declare the same flags with the same defaults.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.default_changed.is_empty()
+    }
+}
+
+/// A flag whose IUSE default changed between versions.
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IUseDefaultChange {
+    /// The flag name.
+    pub name: String,
+    /// Default in the old list.
+    pub old: Option<IUseDefault>,
+    /// Default in the new list.
+    pub new: Option<IUseDefault>,
+}
+
+/// Byte offset within the IUSE `token` and description of the first
+/// character violating PMS 3.1.4.
+fn check_token(token: &str) -> Option<(usize, String)> {
+    let name = token.strip_prefix(['+', '-']).unwrap_or(token);
+    let (i, what) = check_name(name, Leaf::Flag)?;
+    Some((token.len() - name.len() + i, what))
 }
 
 impl<I: Interner> IUse<I> {
@@ -50,6 +99,186 @@ impl<I: Interner> IUse<I> {
         I::resolve(&self.name)
     }
 
+    /// Split the flag into its `USE_EXPAND` variable and value.
+    ///
+    /// Returns `None` if the flag does not belong to any variable of
+    /// `config`. See [`UseExpandConfig::split_flag`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{IUse, UseExpandConfig};
+    ///
+    /// let config = UseExpandConfig::parse("PYTHON_TARGETS");
+    /// let flag: IUse = "+python_targets_python3_11".parse().unwrap();
+    /// assert_eq!(
+    ///     flag.expand_parts(&config),
+    ///     Some(("PYTHON_TARGETS", "python3_11")),
+    /// );
+    /// ```
+    pub fn expand_parts<'a>(&'a self, config: &'a UseExpandConfig) -> Option<(&'a str, &'a str)> {
+        config.split_flag(self.name())
+    }
+
+    /// Deduplicate and sort `flags` by name.
+    ///
+    /// For a repeated flag, an explicit default (`+flag`/`-flag`) overrides
+    /// a bare entry, and among explicit defaults the last one wins, as when
+    /// eclass and ebuild IUSE are stacked. Returns the repeated flags,
+    /// sorted by name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{IUse, IUseDefault};
+    ///
+    /// let mut flags = IUse::parse_line("ssl +doc -ssl doc").unwrap();
+    /// let dups = IUse::normalize(&mut flags);
+    /// let line: Vec<String> = flags.iter().map(ToString::to_string).collect();
+    /// assert_eq!(line.join(" "), "+doc -ssl");
+    /// assert_eq!(dups.len(), 2);
+    /// assert!(dups.iter().all(|d| !d.conflicting));
+    /// ```
+    pub fn normalize(flags: &mut Vec<Self>) -> Vec<IUseDuplicate> {
+        let mut out: Vec<Self> = Vec::with_capacity(flags.len());
+        let mut dups: Vec<IUseDuplicate> = Vec::new();
+        for flag in flags.drain(..) {
+            let Some(kept) = out.iter_mut().find(|f| f.name() == flag.name()) else {
+                out.push(flag);
+                continue;
+            };
+            let conflicting = matches!(
+                (kept.default, flag.default),
+                (Some(a), Some(b)) if a != b
+            );
+            match dups.iter_mut().find(|d| d.name == flag.name()) {
+                Some(dup) => dup.conflicting |= conflicting,
+                None => dups.push(IUseDuplicate {
+                    name: flag.name().to_string(),
+                    conflicting,
+                }),
+            }
+            if flag.default.is_some() {
+                kept.default = flag.default;
+            }
+        }
+        out.sort_by(|a, b| a.name().cmp(b.name()));
+        dups.sort_by(|a, b| a.name.cmp(&b.name));
+        *flags = out;
+        dups
+    }
+
+    /// Compare the IUSE of two package versions.
+    ///
+    /// Duplicates are resolved as in [`IUse::normalize`], so neither they
+    /// nor ordering show up as changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{IUse, IUseDefault};
+    ///
+    /// let old = IUse::parse_line("ssl doc gtk").unwrap();
+    /// let new = IUse::parse_line("+ssl gtk qt6").unwrap();
+    /// let diff = IUse::diff(&old, &new);
+    /// assert_eq!(diff.added, vec!["qt6"]);
+    /// assert_eq!(diff.removed, vec!["doc"]);
+    /// assert_eq!(diff.default_changed[0].name, "ssl");
+    /// assert_eq!(diff.default_changed[0].new, Some(IUseDefault::Enabled));
+    /// ```
+    pub fn diff(old: &[Self], new: &[Self]) -> IUseDiff {
+        let old = Self::defaults(old);
+        let new = Self::defaults(new);
+        let mut diff = IUseDiff::default();
+        for (name, old_default) in &old {
+            match new.get(name) {
+                None => diff.removed.push(name.to_string()),
+                Some(new_default) if new_default != old_default => {
+                    diff.default_changed.push(IUseDefaultChange {
+                        name: name.to_string(),
+                        old: *old_default,
+                        new: *new_default,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        diff.added = new
+            .keys()
+            .filter(|name| !old.contains_key(*name))
+            .map(|name| name.to_string())
+            .collect();
+        diff
+    }
+
+    /// Effective default per flag, with the precedence of [`IUse::normalize`].
+    fn defaults(flags: &[Self]) -> BTreeMap<&str, Option<IUseDefault>> {
+        let mut out = BTreeMap::new();
+        for flag in flags {
+            let default = out.entry(flag.name()).or_insert(None);
+            if flag.default.is_some() {
+                *default = flag.default;
+            }
+        }
+        out
+    }
+
+    /// Group `flags` by `USE_EXPAND` variable, the way `emerge` and
+    /// `equery uses` display them.
+    ///
+    /// Returns the expanded flags keyed by variable, in their original
+    /// order, and the remaining plain flags.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{IUse, UseExpandConfig};
+    ///
+    /// let config = UseExpandConfig::parse("PYTHON_TARGETS");
+    /// let flags = IUse::parse_line("ssl python_targets_python3_12 +python_targets_python3_13").unwrap();
+    /// let (groups, plain) = IUse::group_by_expand(&flags, &config);
+    /// let values: Vec<String> = groups["PYTHON_TARGETS"].iter().map(ToString::to_string).collect();
+    /// assert_eq!(values, ["python_targets_python3_12", "+python_targets_python3_13"]);
+    /// assert_eq!(plain[0].name(), "ssl");
+    /// ```
+    pub fn group_by_expand<'a>(
+        flags: &'a [Self],
+        config: &'a UseExpandConfig,
+    ) -> (BTreeMap<&'a str, Vec<&'a Self>>, Vec<&'a Self>) {
+        let mut groups: BTreeMap<&str, Vec<&Self>> = BTreeMap::new();
+        let mut plain = Vec::new();
+        for flag in flags {
+            match flag.expand_parts(config) {
+                Some((var, _)) => groups.entry(var).or_default().push(flag),
+                None => plain.push(flag),
+            }
+        }
+        (groups, plain)
+    }
+
+    /// Parse a single IUSE token, reporting the exact offending character
+    /// if the flag name violates
+    /// [PMS 3.1.4](https://projects.gentoo.org/pms/9/pms.html#use-flag-names).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::interner::DefaultInterner;
+    /// use portage_metadata::IUse;
+    ///
+    /// let err = IUse::<DefaultInterner>::parse_strict("+gtk.3").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "invalid IUSE entry: position 4: invalid character '.' in USE flag name 'gtk.3'",
+    /// );
+    /// ```
+    pub fn parse_strict(s: &str) -> Result<Self> {
+        if let Some((pos, what)) = check_token(s) {
+            return Err(Error::InvalidIUse(format!("position {pos}: {what}")));
+        }
+        Self::parse(s)
+    }
+
     /// Parse a single IUSE token.
     pub fn parse(s: &str) -> Result<Self> {
         if s.is_empty() {
@@ -57,7 +286,7 @@ impl<I: Interner> IUse<I> {
         }
 
         if let Some(name) = s.strip_prefix('+') {
-            if name.is_empty() || !is_valid_use_flag_name(name) {
+            if invalid_flag_char(name).is_some() {
                 return Err(Error::InvalidIUse(s.to_string()));
             }
             Ok(IUse {
@@ -65,7 +294,7 @@ impl<I: Interner> IUse<I> {
                 default: Some(IUseDefault::Enabled),
             })
         } else if let Some(name) = s.strip_prefix('-') {
-            if name.is_empty() || !is_valid_use_flag_name(name) {
+            if invalid_flag_char(name).is_some() {
                 return Err(Error::InvalidIUse(s.to_string()));
             }
             Ok(IUse {
@@ -73,7 +302,7 @@ impl<I: Interner> IUse<I> {
                 default: Some(IUseDefault::Disabled),
             })
         } else {
-            if !is_valid_use_flag_name(s) {
+            if invalid_flag_char(s).is_some() {
                 return Err(Error::InvalidIUse(s.to_string()));
             }
             Ok(IUse {
@@ -102,7 +331,40 @@ impl IUse<DefaultInterner> {
     /// assert_eq!(flags[2].default, None);
     /// ```
     pub fn parse_line(input: &str) -> Result<Vec<Self>> {
-        input.split_whitespace().map(Self::parse).collect()
+        let tokens = Tokens::new(input);
+        let mut parsed = Vec::with_capacity(tokens.len());
+        for token in tokens.iter() {
+            parsed.push(Self::parse(token)?);
+        }
+        Ok(parsed)
+    }
+
+    /// Parse an `IUSE` line like [`IUse::parse_line`], reporting the
+    /// position within `input` of the first invalid flag name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::IUse;
+    ///
+    /// let err = IUse::parse_line_strict("ssl +_doc").unwrap_err();
+    /// assert!(err.to_string().contains("position 5"));
+    /// ```
+    pub fn parse_line_strict(input: &str) -> Result<Vec<Self>> {
+        let mut offset = 0;
+        let mut flags = Vec::new();
+        for token in input.split_whitespace() {
+            let start = offset + input[offset..].find(token).unwrap_or(0);
+            offset = start + token.len();
+            if let Some((pos, what)) = check_token(token) {
+                return Err(Error::InvalidIUse(format!(
+                    "position {}: {what}",
+                    start + pos
+                )));
+            }
+            flags.push(Self::parse(token)?);
+        }
+        Ok(flags)
     }
 }
 
@@ -117,6 +379,13 @@ impl<I: Interner> fmt::Display for IUse<I> {
     }
 }
 
+impl<I: Interner> Hash for IUse<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.default.hash(state);
+    }
+}
+
 impl<I: Interner> FromStr for IUse<I> {
     type Err = Error;
 
@@ -185,6 +454,88 @@ mod tests {
         assert!("-".parse::<IUse>().is_err());
     }
 
+    #[test]
+    fn expand_parts() {
+        let config = UseExpandConfig::parse("L10N VIDEO_CARDS");
+        let flags = IUse::parse_line("l10n_pt-BR video_cards_amdgpu ssl").unwrap();
+        assert_eq!(flags[0].expand_parts(&config), Some(("L10N", "pt-BR")));
+        assert_eq!(
+            flags[1].expand_parts(&config),
+            Some(("VIDEO_CARDS", "amdgpu"))
+        );
+        assert_eq!(flags[2].expand_parts(&config), None);
+    }
+
+    #[test]
+    fn strict_errors() {
+        let err = IUse::parse_line_strict("  a -b@ +").unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidIUse("position 9: empty USE flag name".to_string())
+        );
+        let err = IUse::<DefaultInterner>::parse_strict("@x").unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidIUse(
+                "position 0: invalid character '@' in USE flag name '@x'".to_string()
+            )
+        );
+        assert_eq!(IUse::parse_line_strict("+ssl -b@ a").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn normalize_conflicting_defaults() {
+        let mut flags = IUse::parse_line("+x b a -x +x").unwrap();
+        let dups = IUse::normalize(&mut flags);
+        let line: Vec<String> = flags.iter().map(ToString::to_string).collect();
+        assert_eq!(line.join(" "), "a b +x");
+        assert_eq!(
+            dups,
+            vec![IUseDuplicate {
+                name: "x".to_string(),
+                conflicting: true
+            }]
+        );
+        let mut flags = IUse::parse_line("b a").unwrap();
+        assert!(IUse::normalize(&mut flags).is_empty());
+        assert_eq!(flags[0].name(), "a");
+    }
+
+    #[test]
+    fn diff_ignores_order_and_duplicates() {
+        let old = IUse::parse_line("b a a").unwrap();
+        let new = IUse::parse_line("a b").unwrap();
+        assert!(IUse::diff(&old, &new).is_empty());
+
+        let old = IUse::parse_line("-x y").unwrap();
+        let new = IUse::parse_line("x").unwrap();
+        let diff = IUse::diff(&old, &new);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed, vec!["y"]);
+        assert_eq!(
+            diff.default_changed,
+            vec![IUseDefaultChange {
+                name: "x".to_string(),
+                old: Some(IUseDefault::Disabled),
+                new: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn group_by_expand() {
+        let config = UseExpandConfig::parse("L10N VIDEO_CARDS");
+        let flags = IUse::parse_line("l10n_de doc video_cards_intel l10n_fr +ssl").unwrap();
+        let (groups, plain) = IUse::group_by_expand(&flags, &config);
+        assert_eq!(
+            groups.keys().copied().collect::<Vec<_>>(),
+            ["L10N", "VIDEO_CARDS"]
+        );
+        assert_eq!(groups["L10N"].len(), 2);
+        let plain: Vec<&str> = plain.iter().map(|f| f.name()).collect();
+        assert_eq!(plain, ["doc", "ssl"]);
+    }
+
     #[test]
     fn complex_flag_names() {
         let flag: IUse = "python_targets_python3_11".parse().unwrap();
@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::cache::CacheEntry;
+use crate::error::{Error, Result};
+use crate::metadata_key::MetadataKey;
+
+/// Cache keys with a field of their own besides the metadata variables.
+const CACHE_KEYS: &[&str] = &["_md5_", "_eclasses_"];
+
+/// Alternative key spellings and extra keys in the md5-cache of a
+/// Portage fork.
+///
+/// An alias is read as the standard key it names, e.g. a fork's
+/// `SUMMARY` as `DESCRIPTION` or its own checksum key as `_md5_`. Extra
+/// keys have no [`EbuildMetadata`](crate::EbuildMetadata) field and are
+/// kept verbatim in [`AliasedEntry::extras`]; other unknown keys are
+/// ignored, as [`CacheEntry::parse`] does.
+///
+/// Fork keys are not specified by PMS; aliases stand in for the keys of
+/// [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::KeyAliases;
+///
+/// let mut aliases = KeyAliases::new();
+/// aliases.alias("SUMMARY", "DESCRIPTION").unwrap();
+/// aliases.alias("_digest_", "_md5_").unwrap();
+/// aliases.extra("MAINTAINER");
+///
+/// let parsed = aliases
+///     .parse("SUMMARY=A tool\nSLOT=0\n_digest_=0123\nMAINTAINER=me@example.org\nX=y\n")
+///     .unwrap();
+/// assert_eq!(parsed.entry.metadata.description, "A tool");
+/// assert_eq!(parsed.entry.md5.as_deref(), Some("0123"));
+/// assert_eq!(parsed.extras["MAINTAINER"], "me@example.org");
+/// assert_eq!(parsed.extras.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyAliases {
+    aliases: BTreeMap<String, &'static str>,
+    extras: BTreeSet<String>,
+}
+
+/// A cache entry parsed with [`KeyAliases`], with its extra keys.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasedEntry {
+    /// The entry, with aliased values in their standard fields.
+    pub entry: CacheEntry,
+    /// The values of the registered extra keys present, by key.
+    pub extras: BTreeMap<String, String>,
+}
+
+impl KeyAliases {
+    /// No aliases or extra keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read `alias` as the standard cache key `key`, such as
+    /// `DESCRIPTION` or `_md5_`.
+    ///
+    /// Fails with [`Error::InvalidConfig`] if `key` is not a md5-cache
+    /// key.
+    pub fn alias(&mut self, alias: impl Into<String>, key: &str) -> Result<()> {
+        let key = MetadataKey::ALL
+            .iter()
+            .map(|key| key.as_str())
+            .filter(|&name| name != MetadataKey::Inherited.as_str())
+            .chain(CACHE_KEYS.iter().copied())
+            .find(|&name| name == key)
+            .ok_or_else(|| Error::InvalidConfig(format!("not a cache key: {key}")))?;
+        self.aliases.insert(alias.into(), key);
+        Ok(())
+    }
+
+    /// Keep the value of `key` in [`AliasedEntry::extras`].
+    pub fn extra(&mut self, key: impl Into<String>) {
+        self.extras.insert(key.into());
+    }
+
+    /// Parse a md5-cache file's contents, as [`CacheEntry::parse`] does,
+    /// honouring the aliases and extra keys.
+    pub fn parse(&self, input: &str) -> Result<AliasedEntry> {
+        let mut extras = BTreeMap::new();
+        let entry = CacheEntry::parse_mapped(input, |key, value| {
+            if self.extras.contains(key) {
+                extras.insert(key.to_string(), value.to_string());
+                return None;
+            }
+            Some(self.aliases.get(key).copied().unwrap_or(key))
+        })?;
+        Ok(AliasedEntry { entry, extras })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_and_extras() {
+        let mut aliases = KeyAliases::new();
+        aliases.alias("RUNDEPEND", "RDEPEND").unwrap();
+        aliases.alias("ECLASSES", "_eclasses_").unwrap();
+        aliases.extra("BUILD_ID");
+        assert_eq!(
+            aliases.alias("SUMMARY", "INHERITED").unwrap_err(),
+            Error::InvalidConfig("not a cache key: INHERITED".to_string())
+        );
+
+        let parsed = aliases
+            .parse(
+                "DESCRIPTION=x\nSLOT=0\nRUNDEPEND=dev-libs/a\nECLASSES=git-r3\tabc\n\
+                 BUILD_ID=7\nSUMMARY=y\n",
+            )
+            .unwrap();
+        let metadata = &parsed.entry.metadata;
+        assert_eq!(metadata.description, "x");
+        assert_eq!(metadata.rdepend.len(), 1);
+        assert_eq!(metadata.inherited[0].as_str(), "git-r3");
+        assert_eq!(
+            parsed.extras,
+            BTreeMap::from([("BUILD_ID".to_string(), "7".to_string())])
+        );
+
+        // Without aliases, only standard keys are read.
+        let plain = KeyAliases::new()
+            .parse("DESCRIPTION=x\nSLOT=0\nRUNDEPEND=dev-libs/a\n")
+            .unwrap();
+        assert!(plain.entry.metadata.rdepend.is_empty());
+    }
+}
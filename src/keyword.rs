@@ -1,12 +1,18 @@
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
-use crate::interner::{DefaultInterner, Interned, Interner};
+use crate::arch::Arch;
+use crate::interner::{DefaultInterner, Interner};
 
 use crate::error::{Error, Result};
+use crate::tokens::Tokens;
 
 /// Stability level for an architecture keyword.
 ///
+/// Levels order from least to most trusted: `DisabledAll < Disabled <
+/// Testing < Stable`, so the best keyword across versions is a plain `max`.
+///
 /// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Stability {
@@ -20,22 +26,95 @@ pub enum Stability {
     DisabledAll,
 }
 
+impl Stability {
+    fn rank(self) -> u8 {
+        match self {
+            Stability::DisabledAll => 0,
+            Stability::Disabled => 1,
+            Stability::Testing => 2,
+            Stability::Stable => 3,
+        }
+    }
+
+    /// Whether this level is `min` or better.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Stability;
+    ///
+    /// assert!(Stability::Stable.at_least(Stability::Testing));
+    /// assert!(!Stability::Disabled.at_least(Stability::Testing));
+    /// ```
+    pub fn at_least(self, min: Stability) -> bool {
+        self >= min
+    }
+}
+
+impl PartialOrd for Stability {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Stability {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
 /// A single architecture keyword entry from the `KEYWORDS` variable.
 ///
 /// Each keyword consists of an architecture name and a stability level.
 ///
 /// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Keyword<I = DefaultInterner>
 where
     I: Interner,
 {
-    /// Architecture (interned).
-    pub arch: Interned<I>,
+    /// Architecture.
+    pub arch: Arch<I>,
     /// Stability classification.
     pub stability: Stability,
 }
 
+/// A problem found by [`Keyword::check`].
+///
+/// Indices refer to positions in the checked keyword list.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeywordIssue {
+    /// The same keyword appears twice, e.g. `amd64 amd64`.
+    Duplicate {
+        /// Index of the first occurrence.
+        first: usize,
+        /// Index of the repeated occurrence.
+        second: usize,
+    },
+    /// The same arch appears with different stabilities, e.g. `amd64 -amd64`.
+    Conflict {
+        /// Index of the earlier keyword.
+        first: usize,
+        /// Index of the later keyword.
+        second: usize,
+    },
+}
+
+impl fmt::Display for KeywordIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeywordIssue::Duplicate { first, second } => {
+                write!(f, "keywords {first} and {second} are duplicates")
+            }
+            KeywordIssue::Conflict { first, second } => {
+                write!(f, "keywords {first} and {second} conflict")
+            }
+        }
+    }
+}
+
 fn is_valid_arch_name(name: &str) -> bool {
     !name.is_empty()
         && !name.starts_with('-')
@@ -52,7 +131,7 @@ impl<I: Interner> Keyword<I> {
 
         if s == "-*" {
             return Ok(Keyword {
-                arch: Interned::intern("*"),
+                arch: Arch::new("*"),
                 stability: Stability::DisabledAll,
             });
         }
@@ -62,7 +141,7 @@ impl<I: Interner> Keyword<I> {
                 return Err(Error::InvalidKeyword(s.to_string()));
             }
             Ok(Keyword {
-                arch: Interned::intern(arch),
+                arch: Arch::new(arch),
                 stability: Stability::Testing,
             })
         } else if let Some(arch) = s.strip_prefix('-') {
@@ -70,7 +149,7 @@ impl<I: Interner> Keyword<I> {
                 return Err(Error::InvalidKeyword(s.to_string()));
             }
             Ok(Keyword {
-                arch: Interned::intern(arch),
+                arch: Arch::new(arch),
                 stability: Stability::Disabled,
             })
         } else {
@@ -78,7 +157,7 @@ impl<I: Interner> Keyword<I> {
                 return Err(Error::InvalidKeyword(s.to_string()));
             }
             Ok(Keyword {
-                arch: Interned::intern(s),
+                arch: Arch::new(s),
                 stability: Stability::Stable,
             })
         }
@@ -88,6 +167,57 @@ impl<I: Interner> Keyword<I> {
     pub fn parse(s: &str) -> Result<Self> {
         Self::parse_impl(s)
     }
+
+    /// Sort `keywords` the way `ekeyword` writes them.
+    ///
+    /// `-*` comes first, then plain arches alphabetically, then prefix
+    /// arches grouped by operating system and sorted by CPU within it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Keyword;
+    ///
+    /// let mut kws = Keyword::parse_line("~x64-macos x86 ~amd64-linux -* ~arm64 amd64").unwrap();
+    /// Keyword::sort_canonical(&mut kws);
+    /// let line: Vec<String> = kws.iter().map(ToString::to_string).collect();
+    /// assert_eq!(line.join(" "), "-* amd64 ~arm64 x86 ~amd64-linux ~x64-macos");
+    /// ```
+    pub fn sort_canonical(keywords: &mut [Self]) {
+        keywords.sort_by(|a, b| a.canonical_key().cmp(&b.canonical_key()));
+    }
+
+    fn canonical_key(&self) -> (u8, &str, &str) {
+        if self.stability == Stability::DisabledAll {
+            return (0, "", "");
+        }
+        match self.arch.os() {
+            None => (1, "", self.arch.cpu()),
+            Some(os) => (2, os, self.arch.cpu()),
+        }
+    }
+
+    /// Find repeated keywords and arches listed with conflicting
+    /// stabilities.
+    ///
+    /// Each later entry is reported against the first entry for the same
+    /// arch.
+    pub fn check(keywords: &[Self]) -> Vec<KeywordIssue> {
+        let mut issues = Vec::new();
+        for (second, kw) in keywords.iter().enumerate() {
+            let first = keywords[..second]
+                .iter()
+                .position(|k| k.arch.as_str() == kw.arch.as_str());
+            if let Some(first) = first {
+                issues.push(if keywords[first].stability == kw.stability {
+                    KeywordIssue::Duplicate { first, second }
+                } else {
+                    KeywordIssue::Conflict { first, second }
+                });
+            }
+        }
+        issues
+    }
 }
 
 impl<I: Interner> fmt::Display for Keyword<I> {
@@ -102,6 +232,14 @@ impl<I: Interner> fmt::Display for Keyword<I> {
     }
 }
 
+// Written out since deriving would require `I: Hash`.
+impl<I: Interner> Hash for Keyword<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.arch.hash(state);
+        self.stability.hash(state);
+    }
+}
+
 impl<I: Interner> FromStr for Keyword<I> {
     type Err = Error;
 
@@ -126,7 +264,29 @@ impl Keyword<DefaultInterner> {
     /// assert_eq!(kws[3].stability, Stability::DisabledAll);
     /// ```
     pub fn parse_line(input: &str) -> Result<Vec<Self>> {
-        input.split_whitespace().map(Self::parse).collect()
+        let tokens = Tokens::new(input);
+        let mut parsed = Vec::with_capacity(tokens.len());
+        for token in tokens.iter() {
+            parsed.push(Self::parse(token)?);
+        }
+        Ok(parsed)
+    }
+
+    /// Parse a `KEYWORDS` line and [`check`](Keyword::check) it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Keyword, KeywordIssue};
+    ///
+    /// let (kws, issues) = Keyword::parse_line_checked("amd64 ~x86 -amd64").unwrap();
+    /// assert_eq!(kws.len(), 3);
+    /// assert_eq!(issues, vec![KeywordIssue::Conflict { first: 0, second: 2 }]);
+    /// ```
+    pub fn parse_line_checked(input: &str) -> Result<(Vec<Self>, Vec<KeywordIssue>)> {
+        let keywords = Self::parse_line(input)?;
+        let issues = Self::check(&keywords);
+        Ok((keywords, issues))
     }
 }
 
@@ -244,6 +404,58 @@ mod tests {
         assert_eq!(kw.stability, Stability::Stable);
     }
 
+    #[test]
+    fn check_duplicates_and_conflicts() {
+        let (_, issues) = Keyword::parse_line_checked("amd64 x86 amd64 ~x86 -* -*").unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                KeywordIssue::Duplicate {
+                    first: 0,
+                    second: 2
+                },
+                KeywordIssue::Conflict {
+                    first: 1,
+                    second: 3
+                },
+                KeywordIssue::Duplicate {
+                    first: 4,
+                    second: 5
+                },
+            ]
+        );
+        let (_, issues) = Keyword::parse_line_checked("amd64 ~arm64 -x86").unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn sort_canonical_prefix_by_os() {
+        let mut kws =
+            Keyword::parse_line("~x86-solaris ~ppc-macos ~x64-solaris ~arm-linux").unwrap();
+        Keyword::sort_canonical(&mut kws);
+        let line: Vec<String> = kws.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            line.join(" "),
+            "~arm-linux ~ppc-macos ~x64-solaris ~x86-solaris"
+        );
+    }
+
+    #[test]
+    fn stability_ordering() {
+        assert!(Stability::Stable > Stability::Testing);
+        assert!(Stability::Testing > Stability::Disabled);
+        assert!(Stability::Disabled > Stability::DisabledAll);
+        let best = [
+            Stability::Disabled,
+            Stability::Testing,
+            Stability::DisabledAll,
+        ]
+        .into_iter()
+        .max();
+        assert_eq!(best, Some(Stability::Testing));
+        assert!(Stability::Testing.at_least(Stability::Testing));
+    }
+
     #[test]
     fn invalid_double_star() {
         assert!("**".parse::<Keyword>().is_err());
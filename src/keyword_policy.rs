@@ -0,0 +1,301 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::error::Result;
+use crate::keyword::Keyword;
+use crate::keyword_set::KeywordSet;
+use crate::keyword_token::KeywordToken;
+use crate::license::LicenseExpr;
+use crate::license_dir::LicenseDir;
+
+/// A system's `ACCEPT_KEYWORDS` and `ACCEPT_LICENSE` settings for one
+/// arch.
+///
+/// By default only stable keywords of the arch and licenses of the
+/// `FREE` group are accepted, as in the Gentoo profiles.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords)
+/// and [PMS 4.6](https://projects.gentoo.org/pms/9/pms.html#the-licenses-directory).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::{BTreeMap, BTreeSet};
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, KeywordPolicy, LicenseDir};
+///
+/// let mut dir = LicenseDir::new();
+/// dir.add_groups("FREE MIT\n");
+/// let entry = |keywords, license| {
+///     CacheEntry::parse(&format!(
+///         "DESCRIPTION=x\nSLOT=0\nKEYWORDS={keywords}\nLICENSE={license}\n"
+///     ))
+///     .unwrap()
+/// };
+/// let repo = BTreeMap::from([
+///     (Cpv::parse("app-misc/foo-2").unwrap(), entry("~amd64", "MIT")),
+///     (Cpv::parse("dev-libs/bar-1").unwrap(), entry("amd64", "|| ( MIT unrar )")),
+///     (Cpv::parse("x11-drivers/baz-9").unwrap(), entry("", "nvidia")),
+/// ]);
+///
+/// let policy = KeywordPolicy::new("amd64");
+/// let snippets = policy.unmask(&repo, &repo.keys().cloned().collect(), &dir);
+/// assert_eq!(
+///     snippets.package_accept_keywords(),
+///     "=app-misc/foo-2 ~amd64\n=x11-drivers/baz-9 **\n"
+/// );
+/// assert_eq!(snippets.package_license(), "=x11-drivers/baz-9 nvidia\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordPolicy {
+    arch: String,
+    accept_keywords: Vec<KeywordToken>,
+    accept_license: Vec<String>,
+}
+
+impl KeywordPolicy {
+    /// Accept stable `arch` keywords and `@FREE` licenses.
+    pub fn new(arch: impl Into<String>) -> Self {
+        let arch = arch.into();
+        let accept_keywords = KeywordToken::parse_line(&arch).unwrap_or_default();
+        KeywordPolicy {
+            arch,
+            accept_keywords,
+            accept_license: vec!["-*".to_string(), "@FREE".to_string()],
+        }
+    }
+
+    /// Replace `ACCEPT_KEYWORDS` with the tokens of `input`.
+    pub fn with_accept_keywords(mut self, input: &str) -> Result<Self> {
+        self.accept_keywords = KeywordToken::parse_line(input)?;
+        Ok(self)
+    }
+
+    /// Replace `ACCEPT_LICENSE` with the tokens of `input`.
+    ///
+    /// Tokens are license names, `@group` names and `*`, each optionally
+    /// negated with `-`; later tokens override earlier ones.
+    pub fn with_accept_license(mut self, input: &str) -> Self {
+        self.accept_license = input.split_whitespace().map(str::to_string).collect();
+        self
+    }
+
+    /// The arch the policy is for.
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    /// Whether `ACCEPT_KEYWORDS` accepts a package with `keywords`.
+    pub fn accepts_keywords(&self, keywords: &[Keyword]) -> bool {
+        KeywordToken::accepts(&self.accept_keywords, keywords)
+    }
+
+    /// Whether `ACCEPT_LICENSE` accepts `license`, with groups resolved
+    /// through `dir`.
+    pub fn accepts_license(&self, license: &str, dir: &LicenseDir) -> bool {
+        let mut accepted = false;
+        for token in &self.accept_license {
+            let (negated, name) = match token.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, token.as_str()),
+            };
+            let matches = match name.strip_prefix('@') {
+                Some(group) => dir.expand_group(group).contains(&license),
+                None => name == "*" || name == license,
+            };
+            if matches {
+                accepted = !negated;
+            }
+        }
+        accepted
+    }
+
+    /// The `package.accept_keywords` and `package.license` entries that
+    /// make every version in `targets` visible.
+    ///
+    /// Each version needing an entry gets one for exactly that version:
+    /// `~arch` if it is testing on the arch, else `**`. Licenses are
+    /// needed as if every USE conditional held; a `||` group with an
+    /// accepted alternative needs nothing, else its first alternative is
+    /// taken. Targets missing from `repo` are listed in
+    /// [`AcceptSnippets::missing`].
+    pub fn unmask(
+        &self,
+        repo: &BTreeMap<Cpv, CacheEntry>,
+        targets: &BTreeSet<Cpv>,
+        dir: &LicenseDir,
+    ) -> AcceptSnippets {
+        let mut snippets = AcceptSnippets::default();
+        for cpv in targets {
+            let Some(entry) = repo.get(cpv) else {
+                snippets.missing.push(cpv.clone());
+                continue;
+            };
+            let metadata = &entry.metadata;
+            if !self.accepts_keywords(&metadata.keywords) {
+                let token =
+                    if KeywordSet::from(metadata.keywords.as_slice()).is_testing_on(&self.arch) {
+                        KeywordToken::Keyword(
+                            Keyword::parse(&format!("~{}", self.arch))
+                                .expect("arch is a valid keyword"),
+                        )
+                    } else {
+                        KeywordToken::Any
+                    };
+                snippets.accept_keywords.push((cpv.clone(), token));
+            }
+            if let Some(license) = &metadata.license {
+                let mut needed = Vec::new();
+                self.needed_licenses(license, dir, &mut needed);
+                let mut seen = BTreeSet::new();
+                needed.retain(|name| seen.insert(*name));
+                if !needed.is_empty() {
+                    let needed = needed.into_iter().map(str::to_string).collect();
+                    snippets.license.push((cpv.clone(), needed));
+                }
+            }
+        }
+        snippets
+    }
+
+    fn needed_licenses<'a>(&self, expr: &'a LicenseExpr, dir: &LicenseDir, out: &mut Vec<&'a str>) {
+        match expr {
+            LicenseExpr::License(name) => {
+                if !self.accepts_license(name, dir) {
+                    out.push(name);
+                }
+            }
+            LicenseExpr::AnyOf(entries) => {
+                let mut first = None;
+                for entry in entries {
+                    let mut needed = Vec::new();
+                    self.needed_licenses(entry, dir, &mut needed);
+                    if needed.is_empty() {
+                        return;
+                    }
+                    first.get_or_insert(needed);
+                }
+                out.extend(first.into_iter().flatten());
+            }
+            LicenseExpr::UseConditional { entries, .. } | LicenseExpr::All(entries) => {
+                for entry in entries {
+                    self.needed_licenses(entry, dir, out);
+                }
+            }
+        }
+    }
+}
+
+/// Configuration entries making packages visible under a
+/// [`KeywordPolicy`], from [`KeywordPolicy::unmask`].
+///
+/// Displays as both files, each under a comment naming it; files without
+/// entries are left out.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AcceptSnippets {
+    /// Versions to keyword, with the token to accept, sorted.
+    pub accept_keywords: Vec<(Cpv, KeywordToken)>,
+    /// Versions to accept licenses for, with the licenses, sorted.
+    pub license: Vec<(Cpv, Vec<String>)>,
+    /// Targets not found in the repository.
+    pub missing: Vec<Cpv>,
+}
+
+impl AcceptSnippets {
+    /// Whether no entries are needed.
+    pub fn is_empty(&self) -> bool {
+        self.accept_keywords.is_empty() && self.license.is_empty()
+    }
+
+    /// The `package.accept_keywords` lines, one `=cpv token` per version.
+    pub fn package_accept_keywords(&self) -> String {
+        self.accept_keywords
+            .iter()
+            .map(|(cpv, token)| format!("={cpv} {token}\n"))
+            .collect()
+    }
+
+    /// The `package.license` lines, one `=cpv licenses...` per version.
+    pub fn package_license(&self) -> String {
+        self.license
+            .iter()
+            .map(|(cpv, licenses)| format!("={cpv} {}\n", licenses.join(" ")))
+            .collect()
+    }
+}
+
+impl fmt::Display for AcceptSnippets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for (file, text) in [
+            ("package.accept_keywords", self.package_accept_keywords()),
+            ("package.license", self.package_license()),
+        ] {
+            if !text.is_empty() {
+                write!(f, "{separator}# {file}\n{text}")?;
+                separator = "\n";
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_and_licenses() {
+        let mut dir = LicenseDir::new();
+        dir.add_groups("FREE MIT GPL-2\nEULA nvidia\n");
+        let entry = |keywords: &str, license: &str| {
+            CacheEntry::parse(&format!(
+                "DESCRIPTION=x\nSLOT=0\nKEYWORDS={keywords}\nLICENSE={license}\n"
+            ))
+            .unwrap()
+        };
+        let cpv = |s| Cpv::parse(s).unwrap();
+        let repo = BTreeMap::from([
+            (
+                cpv("app-misc/a-1"),
+                entry("~amd64 arm64", "MIT doc? ( unrar )"),
+            ),
+            (
+                cpv("app-misc/b-1"),
+                entry("~arm64", "|| ( nvidia unrar ) GPL-2"),
+            ),
+            (cpv("app-misc/c-1"), entry("~amd64", "nvidia")),
+        ]);
+        let targets: BTreeSet<Cpv> = [
+            cpv("app-misc/a-1"),
+            cpv("app-misc/b-1"),
+            cpv("app-misc/z-1"),
+        ]
+        .into();
+
+        let snippets = KeywordPolicy::new("amd64").unmask(&repo, &targets, &dir);
+        assert_eq!(
+            snippets.to_string(),
+            "# package.accept_keywords\n=app-misc/a-1 ~amd64\n=app-misc/b-1 **\n\n\
+             # package.license\n=app-misc/a-1 unrar\n=app-misc/b-1 nvidia\n"
+        );
+        assert_eq!(snippets.missing, [cpv("app-misc/z-1")]);
+
+        let policy = KeywordPolicy::new("amd64")
+            .with_accept_keywords("amd64 ~amd64")
+            .unwrap()
+            .with_accept_license("* -@EULA");
+        let targets = repo.keys().cloned().collect();
+        let snippets = policy.unmask(&repo, &targets, &dir);
+        assert_eq!(snippets.package_accept_keywords(), "=app-misc/b-1 **\n");
+        assert_eq!(snippets.package_license(), "=app-misc/c-1 nvidia\n");
+        assert!(KeywordPolicy::new("amd64")
+            .with_accept_keywords("amd64 ~")
+            .is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::keyword::Stability;
+use crate::keyword_set::KeywordSet;
+
+/// Reports arches a version dropped or downgraded compared with the
+/// previous version of the same package.
+///
+/// Uses [`KeywordSet::diff`]: an arch regresses when it was stable or
+/// testing and is now less trusted or no longer keyworded. Versions
+/// without a previous version are not checked.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, KeywordRegressionCheck};
+///
+/// let entry = |keywords| {
+///     CacheEntry::parse(&format!("DESCRIPTION=x\nSLOT=0\nKEYWORDS={keywords}\n")).unwrap()
+/// };
+/// let repo = BTreeMap::from([
+///     (Cpv::parse("app-misc/foo-1").unwrap(), entry("amd64 ~x86")),
+///     (Cpv::parse("app-misc/foo-2").unwrap(), entry("~amd64")),
+/// ]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(KeywordRegressionCheck);
+/// let findings: Vec<_> = runner.run(&repo).into_iter().map(|f| f.message).collect();
+/// assert_eq!(
+///     findings,
+///     [
+///         "amd64 downgraded from stable to testing since app-misc/foo-1",
+///         "x86 dropped (testing in app-misc/foo-1)",
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeywordRegressionCheck;
+
+impl MetadataCheck for KeywordRegressionCheck {
+    fn name(&self) -> &'static str {
+        "keyword-regression"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let Some((previous_cpv, previous)) = cx.previous else {
+            return;
+        };
+        let old = KeywordSet::from(previous.metadata.keywords.as_slice());
+        let new = KeywordSet::from(cx.entry.metadata.keywords.as_slice());
+        for change in old.diff(&new) {
+            let Some(was) = change.old.filter(|_| change.is_regression()) else {
+                continue;
+            };
+            let message = match change.new {
+                Some(now @ (Stability::Stable | Stability::Testing)) => format!(
+                    "{} downgraded from {} to {} since {previous_cpv}",
+                    change.arch,
+                    describe(was),
+                    describe(now)
+                ),
+                _ => format!(
+                    "{} dropped ({} in {previous_cpv})",
+                    change.arch,
+                    describe(was)
+                ),
+            };
+            findings.push(cx.finding(self.name(), Severity::Warning, message));
+        }
+    }
+}
+
+fn describe(stability: Stability) -> &'static str {
+    match stability {
+        Stability::Stable => "stable",
+        Stability::Testing => "testing",
+        Stability::Disabled | Stability::DisabledAll => "disabled",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn only_within_a_package() {
+        let entry = |keywords: &str| format!("DESCRIPTION=x\nSLOT=0\nKEYWORDS={keywords}\n");
+        let entries = [
+            ("app-misc/bar-1", entry("amd64 x86")),
+            ("app-misc/foo-1", entry("~amd64")),
+            ("app-misc/foo-1.1", entry("amd64 ~x86")),
+            ("app-misc/foo-2", entry("-* -amd64")),
+        ];
+        let repo: Vec<_> = entries
+            .iter()
+            .map(|(cpv, text)| (*cpv, text.as_str()))
+            .collect();
+        let findings: Vec<_> = run_check(KeywordRegressionCheck, &repo)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                "app-misc/foo-2: warning [keyword-regression]: amd64 dropped (stable in app-misc/foo-1.1)",
+                "app-misc/foo-2: warning [keyword-regression]: x86 dropped (testing in app-misc/foo-1.1)",
+            ]
+        );
+    }
+}
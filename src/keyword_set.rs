@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::interner::Interner;
+use crate::keyword::{Keyword, Stability};
+
+/// The `KEYWORDS` of an ebuild, indexed by arch.
+///
+/// Holds one [`Stability`] per arch plus whether `-*` was given. When an
+/// arch is listed more than once, the last entry wins.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{Keyword, KeywordSet, Stability};
+///
+/// let set: KeywordSet = Keyword::parse_line("amd64 ~arm64 -x86").unwrap().iter().collect();
+/// assert!(set.is_stable_on("amd64"));
+/// assert!(set.is_testing_on("arm64"));
+/// assert_eq!(set.stability_for("x86"), Some(Stability::Disabled));
+/// assert_eq!(set.stability_for("ppc"), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeywordSet {
+    arches: BTreeMap<String, Stability>,
+    disabled_all: bool,
+}
+
+impl KeywordSet {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a keyword, replacing any earlier entry for the same arch.
+    pub fn insert<I: Interner>(&mut self, keyword: &Keyword<I>) {
+        match keyword.stability {
+            Stability::DisabledAll => self.disabled_all = true,
+            stability => {
+                self.arches
+                    .insert(keyword.arch.as_str().to_string(), stability);
+            }
+        }
+    }
+
+    /// The stability explicitly listed for `arch`, if any.
+    ///
+    /// `-*` is not taken into account.
+    pub fn stability_for(&self, arch: &str) -> Option<Stability> {
+        self.arches.get(arch).copied()
+    }
+
+    /// The stability that applies on `arch`, honouring `-*`.
+    ///
+    /// An explicit entry for `arch` always wins, so `-* ~amd64` is testing
+    /// on amd64. Otherwise `-*` makes every arch
+    /// [`DisabledAll`](Stability::DisabledAll). `None` means the package
+    /// is simply not keyworded there.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Keyword, KeywordSet, Stability};
+    ///
+    /// let set: KeywordSet = Keyword::parse_line("-* ~amd64").unwrap().iter().collect();
+    /// assert_eq!(set.effective_stability("amd64"), Some(Stability::Testing));
+    /// assert_eq!(set.effective_stability("x86"), Some(Stability::DisabledAll));
+    ///
+    /// let set: KeywordSet = Keyword::parse_line("~amd64").unwrap().iter().collect();
+    /// assert_eq!(set.effective_stability("x86"), None);
+    /// ```
+    pub fn effective_stability(&self, arch: &str) -> Option<Stability> {
+        self.stability_for(arch)
+            .or_else(|| self.disabled_all.then_some(Stability::DisabledAll))
+    }
+
+    /// Whether the package is keyworded stable on `arch`.
+    pub fn is_stable_on(&self, arch: &str) -> bool {
+        self.stability_for(arch) == Some(Stability::Stable)
+    }
+
+    /// Whether the package is keyworded testing (`~arch`) on `arch`.
+    pub fn is_testing_on(&self, arch: &str) -> bool {
+        self.stability_for(arch) == Some(Stability::Testing)
+    }
+
+    /// Whether `-*` is present.
+    pub fn has_disabled_all(&self) -> bool {
+        self.disabled_all
+    }
+
+    /// Iterate over the listed arches and their stabilities, sorted by arch.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Stability)> {
+        self.arches.iter().map(|(arch, s)| (arch.as_str(), *s))
+    }
+
+    /// Arches the package is stable on, sorted.
+    pub fn stable_arches(&self) -> impl Iterator<Item = &str> {
+        self.arches_with(Stability::Stable)
+    }
+
+    /// Arches the package is testing on, sorted.
+    pub fn testing_arches(&self) -> impl Iterator<Item = &str> {
+        self.arches_with(Stability::Testing)
+    }
+
+    /// Whether the package is stable or testing on at least one arch.
+    pub fn is_keyworded(&self) -> bool {
+        self.arches
+            .values()
+            .any(|s| matches!(s, Stability::Stable | Stability::Testing))
+    }
+
+    /// Number of listed arches, not counting `-*`.
+    pub fn len(&self) -> usize {
+        self.arches.len()
+    }
+
+    /// Whether no keyword, not even `-*`, is present.
+    pub fn is_empty(&self) -> bool {
+        self.arches.is_empty() && !self.disabled_all
+    }
+
+    /// Arches whose [effective stability](KeywordSet::effective_stability)
+    /// differs between `self` and `new`, sorted by arch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Keyword, KeywordSet, Stability};
+    ///
+    /// let old: KeywordSet = Keyword::parse_line("amd64 ~x86").unwrap().iter().collect();
+    /// let new: KeywordSet = Keyword::parse_line("~amd64 ~arm64").unwrap().iter().collect();
+    /// let changes = old.diff(&new);
+    /// assert_eq!(changes.len(), 3);
+    /// assert_eq!(changes[0].arch, "amd64");
+    /// assert_eq!(changes[0].old, Some(Stability::Stable));
+    /// assert_eq!(changes[0].new, Some(Stability::Testing));
+    /// assert_eq!(changes[2].new, None);
+    /// ```
+    pub fn diff(&self, new: &KeywordSet) -> Vec<ArchChange> {
+        let arches: BTreeSet<&str> = self
+            .arches
+            .keys()
+            .chain(new.arches.keys())
+            .map(String::as_str)
+            .collect();
+        arches
+            .into_iter()
+            .filter_map(|arch| {
+                let old_stability = self.effective_stability(arch);
+                let new_stability = new.effective_stability(arch);
+                (old_stability != new_stability).then(|| ArchChange {
+                    arch: arch.to_string(),
+                    old: old_stability,
+                    new: new_stability,
+                })
+            })
+            .collect()
+    }
+
+    fn arches_with(&self, stability: Stability) -> impl Iterator<Item = &str> {
+        self.iter()
+            .filter(move |(_, s)| *s == stability)
+            .map(|(arch, _)| arch)
+    }
+}
+
+/// One arch whose keyword differs between two [`KeywordSet`]s.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArchChange {
+    /// The arch.
+    pub arch: String,
+    /// Its effective stability in the old set, `None` if not keyworded.
+    pub old: Option<Stability>,
+    /// Its effective stability in the new set, `None` if not keyworded.
+    pub new: Option<Stability>,
+}
+
+impl ArchChange {
+    /// Whether the arch lost keywording: it was stable or testing and is
+    /// now less trusted or gone.
+    pub fn is_regression(&self) -> bool {
+        self.old
+            .is_some_and(|old| old.at_least(Stability::Testing) && self.new < Some(old))
+    }
+}
+
+impl<'a, I: Interner + 'a> FromIterator<&'a Keyword<I>> for KeywordSet {
+    fn from_iter<T: IntoIterator<Item = &'a Keyword<I>>>(iter: T) -> Self {
+        let mut set = Self::new();
+        for keyword in iter {
+            set.insert(keyword);
+        }
+        set
+    }
+}
+
+impl<I: Interner> From<&[Keyword<I>]> for KeywordSet {
+    fn from(keywords: &[Keyword<I>]) -> Self {
+        keywords.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries() {
+        let keywords = Keyword::parse_line("-* ~x86 amd64 arm64 ~ppc -sparc").unwrap();
+        let set = KeywordSet::from(keywords.as_slice());
+        assert_eq!(set.stable_arches().collect::<Vec<_>>(), ["amd64", "arm64"]);
+        assert_eq!(set.testing_arches().collect::<Vec<_>>(), ["ppc", "x86"]);
+        assert!(set.has_disabled_all());
+        assert!(set.is_keyworded());
+        assert_eq!(set.len(), 5);
+    }
+
+    #[test]
+    fn last_entry_wins() {
+        let keywords = Keyword::parse_line("amd64 ~amd64").unwrap();
+        let set: KeywordSet = keywords.iter().collect();
+        assert!(set.is_testing_on("amd64"));
+        assert!(!set.is_stable_on("amd64"));
+    }
+
+    #[test]
+    fn effective_stability_order_independent() {
+        for line in ["-* -x86 amd64", "amd64 -x86 -*"] {
+            let set: KeywordSet = Keyword::parse_line(line).unwrap().iter().collect();
+            assert_eq!(set.effective_stability("amd64"), Some(Stability::Stable));
+            assert_eq!(set.effective_stability("x86"), Some(Stability::Disabled));
+            assert_eq!(set.effective_stability("arm"), Some(Stability::DisabledAll));
+        }
+    }
+
+    #[test]
+    fn diff_regressions() {
+        let old: KeywordSet = Keyword::parse_line("amd64 ~arm ~ppc x86")
+            .unwrap()
+            .iter()
+            .collect();
+        let new: KeywordSet = Keyword::parse_line("-* amd64 ~x86 arm")
+            .unwrap()
+            .iter()
+            .collect();
+        let changes = old.diff(&new);
+        let arches: Vec<_> = changes.iter().map(|c| c.arch.as_str()).collect();
+        assert_eq!(arches, ["arm", "ppc", "x86"]);
+        let regressions: Vec<_> = changes
+            .iter()
+            .filter(|c| c.is_regression())
+            .map(|c| (c.arch.as_str(), c.new))
+            .collect();
+        assert_eq!(
+            regressions,
+            [
+                ("ppc", Some(Stability::DisabledAll)),
+                ("x86", Some(Stability::Testing))
+            ]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let set = KeywordSet::new();
+        assert!(set.is_empty());
+        assert!(!set.is_keyworded());
+        let disabled: KeywordSet = Keyword::parse_line("-*").unwrap().iter().collect();
+        assert!(!disabled.is_empty());
+        assert!(!disabled.is_keyworded());
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::interner::{DefaultInterner, Interner};
+use crate::keyword::{Keyword, Stability};
+
+/// A token of `ACCEPT_KEYWORDS` or `package.accept_keywords`.
+///
+/// Besides plain keywords, policy contexts allow wildcards that ebuild
+/// `KEYWORDS` do not: `*` accepts any stable keyword, `~*` any testing
+/// keyword, and `**` anything, including packages with no keywords.
+/// `-arch` and `-*` remove previously accepted tokens.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{Keyword, KeywordToken};
+///
+/// let accept = KeywordToken::parse_line("amd64 ~*").unwrap();
+/// let keywords = Keyword::parse_line("~arm64").unwrap();
+/// assert!(KeywordToken::accepts(&accept, &keywords));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeywordToken<I = DefaultInterner>
+where
+    I: Interner,
+{
+    /// A plain keyword such as `amd64`, `~amd64`, `-amd64` or `-*`.
+    Keyword(Keyword<I>),
+    /// `*` — any stable keyword.
+    AnyStable,
+    /// `~*` — any testing keyword.
+    AnyTesting,
+    /// `**` — any keywords, or none at all.
+    Any,
+}
+
+impl<I: Interner> KeywordToken<I> {
+    /// Parse a single token.
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "*" => Ok(KeywordToken::AnyStable),
+            "~*" => Ok(KeywordToken::AnyTesting),
+            "**" => Ok(KeywordToken::Any),
+            _ => Keyword::parse(s)
+                .map(KeywordToken::Keyword)
+                .map_err(|_| Error::InvalidKeyword(s.to_string())),
+        }
+    }
+
+    /// Whether this single token accepts a package with `keywords`.
+    ///
+    /// Plain keywords match exactly, so `~amd64` alone does not accept a
+    /// package keyworded `amd64`. Negative tokens accept nothing.
+    pub fn matches(&self, keywords: &[Keyword<I>]) -> bool {
+        match self {
+            KeywordToken::Any => true,
+            KeywordToken::AnyStable => keywords.iter().any(|k| k.stability == Stability::Stable),
+            KeywordToken::AnyTesting => keywords.iter().any(|k| k.stability == Stability::Testing),
+            KeywordToken::Keyword(token) => match token.stability {
+                Stability::Stable | Stability::Testing => keywords.iter().any(|k| {
+                    k.stability == token.stability && k.arch.as_str() == token.arch.as_str()
+                }),
+                Stability::Disabled | Stability::DisabledAll => false,
+            },
+        }
+    }
+
+    /// Whether the incremental token list `tokens` accepts a package with
+    /// `keywords`.
+    ///
+    /// Tokens are applied in order: `-arch` removes an earlier `arch`, and
+    /// `-*` clears everything accepted so far.
+    /// The package is accepted if any remaining token matches.
+    pub fn accepts(tokens: &[Self], keywords: &[Keyword<I>]) -> bool {
+        let mut active: Vec<&Self> = Vec::new();
+        for token in tokens {
+            match token {
+                KeywordToken::Keyword(kw) if kw.stability == Stability::DisabledAll => {
+                    active.clear()
+                }
+                KeywordToken::Keyword(kw) if kw.stability == Stability::Disabled => {
+                    active.retain(|t| match t {
+                        KeywordToken::Keyword(k) => {
+                            !(k.stability == Stability::Stable
+                                && k.arch.as_str() == kw.arch.as_str())
+                        }
+                        _ => true,
+                    })
+                }
+                _ => active.push(token),
+            }
+        }
+        active.iter().any(|token| token.matches(keywords))
+    }
+}
+
+impl KeywordToken<DefaultInterner> {
+    /// Parse a space-separated `ACCEPT_KEYWORDS` value or the keyword part
+    /// of a `package.accept_keywords` line.
+    pub fn parse_line(input: &str) -> Result<Vec<Self>> {
+        input.split_whitespace().map(Self::parse).collect()
+    }
+}
+
+impl<I: Interner> fmt::Display for KeywordToken<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeywordToken::Keyword(kw) => write!(f, "{kw}"),
+            KeywordToken::AnyStable => f.write_str("*"),
+            KeywordToken::AnyTesting => f.write_str("~*"),
+            KeywordToken::Any => f.write_str("**"),
+        }
+    }
+}
+
+impl<I: Interner> FromStr for KeywordToken<I> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wildcards() {
+        let tokens = KeywordToken::parse_line("* ~* ** ~amd64 -*").unwrap();
+        assert_eq!(tokens[0], KeywordToken::AnyStable);
+        assert_eq!(tokens[1], KeywordToken::AnyTesting);
+        assert_eq!(tokens[2], KeywordToken::Any);
+        let s: Vec<String> = tokens.iter().map(ToString::to_string).collect();
+        assert_eq!(s.join(" "), "* ~* ** ~amd64 -*");
+        assert!("~**".parse::<KeywordToken>().is_err());
+    }
+
+    #[test]
+    fn wildcard_semantics() {
+        let stable = Keyword::parse_line("amd64 ~x86").unwrap();
+        let testing = Keyword::parse_line("~amd64").unwrap();
+        let any: [KeywordToken; 1] = [KeywordToken::Any];
+        assert!(KeywordToken::accepts(&any, &[]));
+        assert!(KeywordToken::AnyStable.matches(&stable));
+        assert!(!KeywordToken::AnyStable.matches(&testing));
+        assert!(KeywordToken::AnyTesting.matches(&testing));
+        assert!(!KeywordToken::AnyStable.matches(&stable[..0]));
+    }
+
+    #[test]
+    fn incremental_negation() {
+        let keywords = Keyword::parse_line("amd64").unwrap();
+        let tokens = KeywordToken::parse_line("amd64 -amd64").unwrap();
+        assert!(!KeywordToken::accepts(&tokens, &keywords));
+        let tokens = KeywordToken::parse_line("* -* ~amd64").unwrap();
+        assert!(!KeywordToken::accepts(&tokens, &keywords));
+        let tokens = KeywordToken::parse_line("-* amd64").unwrap();
+        assert!(KeywordToken::accepts(&tokens, &keywords));
+    }
+}
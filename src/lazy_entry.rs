@@ -0,0 +1,234 @@
+use std::cell::OnceCell;
+use std::ops::Range;
+
+use portage_atom::DepEntry;
+
+use crate::cache::{parse_dep_field, CacheEntry};
+use crate::error::{Error, Result, Span};
+use crate::interner::{DefaultInterner, Interner};
+use crate::license::LicenseExpr;
+use crate::metadata_key::MetadataKey;
+use crate::required_use::RequiredUseExpr;
+use crate::src_uri::SrcUriEntry;
+
+/// A md5-cache entry whose expression-valued fields are parsed on first
+/// access.
+///
+/// `SRC_URI`, `LICENSE`, `REQUIRED_USE` and the dependency classes are
+/// kept as raw text and only turned into trees when asked for; the result
+/// is cached. Every other field is parsed up front, as by
+/// [`CacheEntry::parse`]. Errors in a deferred field are only reported by
+/// its accessor or by [`LazyCacheEntry::into_entry`].
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{LazyCacheEntry, MetadataKey};
+///
+/// let entry: LazyCacheEntry = LazyCacheEntry::parse(
+///     "DESCRIPTION=x\nSLOT=0\nLICENSE=|| ( MIT BSD )\nRDEPEND=dev-libs/foo\n",
+/// )
+/// .unwrap();
+/// assert_eq!(entry.entry().metadata.description, "x");
+/// assert_eq!(entry.raw(MetadataKey::License), "|| ( MIT BSD )");
+/// assert!(entry.license().unwrap().unwrap().contains("BSD"));
+/// assert_eq!(entry.deps(MetadataKey::Rdepend).unwrap().len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LazyCacheEntry<I = DefaultInterner>
+where
+    I: Interner,
+{
+    entry: CacheEntry<I>,
+    source: String,
+    raw: [Range<usize>; 8],
+    src_uri: OnceCell<Result<Vec<SrcUriEntry>>>,
+    license: OnceCell<Result<Option<LicenseExpr<I>>>>,
+    required_use: OnceCell<Result<Option<RequiredUseExpr>>>,
+    deps: [OnceCell<Result<Vec<DepEntry>>>; 5],
+}
+
+// The deferred fields, in `raw` order.
+const DEFERRED: [MetadataKey; 8] = [
+    MetadataKey::SrcUri,
+    MetadataKey::License,
+    MetadataKey::RequiredUse,
+    MetadataKey::Depend,
+    MetadataKey::Rdepend,
+    MetadataKey::Bdepend,
+    MetadataKey::Pdepend,
+    MetadataKey::Idepend,
+];
+
+impl<I: Interner> LazyCacheEntry<I> {
+    /// Parse a md5-cache entry, deferring the expression-valued fields.
+    ///
+    /// Fails on errors in the other fields only.
+    pub fn parse(input: &str) -> Result<Self> {
+        let (entry, raw) = CacheEntry::parse_eager(input)?;
+        let raw = raw.map(|text| match Span::of(input, text) {
+            Some(span) if !text.is_empty() => span.offset..span.offset + text.len(),
+            _ => 0..0,
+        });
+        Ok(LazyCacheEntry {
+            entry,
+            source: input.to_string(),
+            raw,
+            src_uri: OnceCell::new(),
+            license: OnceCell::new(),
+            required_use: OnceCell::new(),
+            deps: Default::default(),
+        })
+    }
+
+    /// The eagerly parsed fields; the deferred ones are left empty.
+    pub fn entry(&self) -> &CacheEntry<I> {
+        &self.entry
+    }
+
+    /// The raw text of a deferred field, or `""` for any other key.
+    pub fn raw(&self, key: MetadataKey) -> &str {
+        DEFERRED
+            .iter()
+            .position(|&deferred| deferred == key)
+            .map_or("", |i| &self.source[self.raw[i].clone()])
+    }
+
+    /// `SRC_URI`, parsed on first access.
+    pub fn src_uri(&self) -> Result<&[SrcUriEntry]> {
+        self.get(&self.src_uri, MetadataKey::SrcUri, |text| {
+            if text.is_empty() {
+                Ok(Vec::new())
+            } else {
+                SrcUriEntry::parse(text)
+            }
+        })
+        .map(Vec::as_slice)
+    }
+
+    /// `LICENSE`, parsed on first access.
+    pub fn license(&self) -> Result<Option<&LicenseExpr<I>>> {
+        self.get(&self.license, MetadataKey::License, |text| {
+            optional(text, LicenseExpr::parse_interned)
+        })
+        .map(Option::as_ref)
+    }
+
+    /// `REQUIRED_USE`, parsed on first access.
+    pub fn required_use(&self) -> Result<Option<&RequiredUseExpr>> {
+        self.get(&self.required_use, MetadataKey::RequiredUse, |text| {
+            optional(text, RequiredUseExpr::parse)
+        })
+        .map(Option::as_ref)
+    }
+
+    /// The dependency class `key`, parsed on first access; empty for keys
+    /// that are not dependency classes.
+    pub fn deps(&self, key: MetadataKey) -> Result<&[DepEntry]> {
+        match MetadataKey::DEPENDENCIES
+            .iter()
+            .position(|&class| class == key)
+        {
+            Some(i) => self
+                .get(&self.deps[i], key, parse_dep_field)
+                .map(Vec::as_slice),
+            None => Ok(&[]),
+        }
+    }
+
+    /// Parse every deferred field and return the complete entry.
+    pub fn into_entry(self) -> Result<CacheEntry<I>> {
+        self.src_uri()?;
+        self.license()?;
+        self.required_use()?;
+        for &class in MetadataKey::DEPENDENCIES {
+            self.deps(class)?;
+        }
+        let mut entry = self.entry;
+        let metadata = &mut entry.metadata;
+        metadata.src_uri = parsed(self.src_uri)?;
+        metadata.license = parsed(self.license)?;
+        metadata.required_use = parsed(self.required_use)?;
+        let [depend, rdepend, bdepend, pdepend, idepend] = self.deps;
+        metadata.depend = parsed(depend)?;
+        metadata.rdepend = parsed(rdepend)?;
+        metadata.bdepend = parsed(bdepend)?;
+        metadata.pdepend = parsed(pdepend)?;
+        metadata.idepend = parsed(idepend)?;
+        Ok(entry)
+    }
+
+    /// The cached value of `cell`, parsing the `key` field into it first
+    /// if needed. Errors carry the field and its location.
+    fn get<'s, T>(
+        &'s self,
+        cell: &'s OnceCell<Result<T>>,
+        key: MetadataKey,
+        parse: impl FnOnce(&str) -> Result<T>,
+    ) -> Result<&'s T> {
+        let value = cell.get_or_init(|| {
+            let text = self.raw(key);
+            parse(text).map_err(|error| Error::Field {
+                field: key.to_string(),
+                text: text.to_string(),
+                span: Span::of(&self.source, text),
+                source: Box::new(error),
+            })
+        });
+        value.as_ref().map_err(Clone::clone)
+    }
+}
+
+/// The value of a cell filled by an accessor.
+fn parsed<T>(cell: OnceCell<Result<T>>) -> Result<T> {
+    cell.into_inner().expect("parsed by its accessor")
+}
+
+fn optional<T>(text: &str, parse: impl FnOnce(&str) -> Result<T>) -> Result<Option<T>> {
+    if text.is_empty() {
+        Ok(None)
+    } else {
+        parse(text).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "EAPI=8\nDESCRIPTION=x\nSLOT=0\n\
+        SRC_URI=https://e.org/a.tar.gz\nLICENSE=MIT\nREQUIRED_USE=|| ( a b )\n\
+        DEPEND=dev-libs/a\nBDEPEND=dev-util/b\nIUSE=a b\n";
+
+    #[test]
+    fn matches_eager_parse() {
+        let lazy: LazyCacheEntry = LazyCacheEntry::parse(INPUT).unwrap();
+        assert!(lazy.entry().metadata.src_uri.is_empty());
+        assert_eq!(lazy.entry().metadata.iuse.len(), 2);
+        assert_eq!(lazy.src_uri().unwrap().len(), 1);
+        assert!(lazy.deps(MetadataKey::Rdepend).unwrap().is_empty());
+        assert!(lazy.deps(MetadataKey::Slot).unwrap().is_empty());
+        assert_eq!(lazy.raw(MetadataKey::Slot), "");
+        assert_eq!(
+            lazy.into_entry().unwrap(),
+            CacheEntry::parse(INPUT).unwrap()
+        );
+    }
+
+    #[test]
+    fn deferred_errors() {
+        let input = "DESCRIPTION=x\nSLOT=0\nLICENSE=|| (\nDEPEND=dev-libs/a\n";
+        assert!(CacheEntry::<DefaultInterner>::parse(input).is_err());
+        let lazy: LazyCacheEntry = LazyCacheEntry::parse(input).unwrap();
+        assert_eq!(lazy.deps(MetadataKey::Depend).unwrap().len(), 1);
+        let err = lazy.license().unwrap_err();
+        assert_eq!(err.field(), Some("LICENSE"));
+        assert_eq!(err.span().map(|span| span.line), Some(3));
+        assert_eq!(lazy.license().unwrap_err(), err);
+        assert_eq!(lazy.into_entry().unwrap_err(), err);
+
+        assert!(LazyCacheEntry::<DefaultInterner>::parse("DESCRIPTION=x\n").is_err());
+    }
+}
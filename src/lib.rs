@@ -30,31 +30,209 @@
 //! assert_eq!(entry.metadata.description, "Example package");
 //! assert_eq!(entry.metadata.eapi.to_string(), "7");
 //! ```
+//!
+//! # portage-atom types
+//!
+//! Package names, versions, slots and dependency atoms come from
+//! [portage-atom](https://crates.io/crates/portage-atom). The types used
+//! in this crate's API, such as [`Cpv`], [`Dep`] and [`DepEntry`], are
+//! re-exported here; use them rather than depending on portage-atom
+//! directly, so both always agree on its version. A release of this
+//! crate moving to a semver-incompatible portage-atom is itself
+//! semver-incompatible.
 
+mod arch;
+mod auto_enforce;
+mod bindist;
+mod bit_set;
 mod cache;
+#[cfg(feature = "fs")]
+mod cache_convert;
+mod cache_store;
+mod changed_use;
+mod check;
+mod check_config;
+#[cfg(feature = "fs")]
+mod conformance;
+mod csv_export;
+mod dep_graph;
+mod dep_spec;
+mod description_check;
+#[cfg(feature = "fs")]
+mod distdir;
+mod distfile;
 mod eapi;
+mod eapi_check;
+mod eapi_feature;
+mod eapi_policy;
+mod eapi_registry;
+mod effective_iuse;
 mod error;
+mod eval_cache;
+mod fetch_list;
+mod fetch_plan;
+mod fetch_restrict_check;
+#[cfg(feature = "git")]
+mod git_changes;
+mod homepage;
 mod iuse;
+mod key_aliases;
 mod keyword;
+mod keyword_policy;
+mod keyword_regression_check;
+mod keyword_set;
+mod keyword_token;
+mod lazy_entry;
 mod license;
+mod license_check;
+mod license_dir;
+mod live_check;
+mod manifest;
+#[cfg(feature = "fs")]
+mod md5;
 mod metadata;
+mod metadata_key;
+#[cfg(feature = "fs")]
+mod metadata_repo;
+mod mirrors;
+mod names;
+mod parse_report;
+mod parse_stats;
 mod phase;
+mod phase_set;
+pub mod prelude;
+mod pretty;
+mod profile;
+mod properties;
+mod property_set;
+mod raw_entry;
+mod redundant_dep_check;
+#[cfg(feature = "fs")]
+mod regen_plan;
+mod repo_diff;
+mod repo_stats;
+mod report;
 mod required_use;
+mod required_use_check;
 mod restrict;
+mod restrict_set;
+mod sbom;
+mod spdx;
+mod sql_export;
 mod src_uri;
+mod src_url;
+mod stabilization_batch;
+mod stable_candidate;
+mod subslot_rebuild;
+#[cfg(all(test, feature = "fs"))]
+mod temp_dir;
+#[cfg(feature = "fs")]
+mod timestamp;
+mod tokens;
+#[cfg(feature = "fs")]
+mod tree_writer;
+mod uri_lint;
+mod use_expand;
+mod use_flag_check;
+mod use_flags;
+mod use_resolver;
 
 // Re-export public types
-pub use cache::CacheEntry;
+pub use arch::Arch;
+pub use auto_enforce::{AutoEnforce, EnforceIssue, Implication, UseFlagState};
+pub use bindist::{BindistReport, LicenseClass, LicenseClasses};
+pub use cache::{BumpKeywords, CacheEntry};
+#[cfg(feature = "fs")]
+pub use cache_convert::{CacheConversion, CacheFormat};
+pub use cache_store::CacheStore;
+pub use changed_use::{ChangedUse, InstalledUse};
+pub use check::{
+    CheckContext, CheckRunner, Finding, FindingFormat, MetadataCheck, Severity, ValidateCheck,
+};
+pub use check_config::CheckConfig;
+#[cfg(feature = "fs")]
+pub use conformance::{ConformanceFailure, ConformanceIssue, ConformanceReport};
+pub use csv_export::CsvExport;
+pub use dep_graph::{DepGraph, DotOptions};
+pub use dep_spec::{DepSpec, SpecVisitor};
+pub use description_check::DescriptionCheck;
+#[cfg(feature = "fs")]
+pub use distdir::{Distdir, DistfileStatus};
+pub use distfile::DistfileAccess;
 pub use eapi::Eapi;
-pub use error::{Error, Result};
-pub use iuse::{IUse, IUseDefault};
-pub use keyword::{Keyword, Stability};
+pub use eapi_check::EapiCheck;
+pub use eapi_feature::EapiFeature;
+pub use eapi_policy::EapiPolicy;
+pub use eapi_registry::EapiRegistry;
+pub use effective_iuse::EffectiveIuse;
+pub use error::{Error, Result, Span};
+pub use eval_cache::{DepSpecId, EvalCache, FlagsId, RequiredUseId};
+pub use fetch_list::{FetchList, FetchListFormat};
+pub use fetch_plan::{FetchPlan, HostWeights, MirrorScorer, PlannedDistfile, ProtocolPreference};
+pub use fetch_restrict_check::FetchRestrictCheck;
+#[cfg(feature = "git")]
+pub use git_changes::GitChanges;
+pub use homepage::Homepage;
+pub use iuse::{IUse, IUseDefault, IUseDefaultChange, IUseDiff, IUseDuplicate};
+pub use key_aliases::{AliasedEntry, KeyAliases};
+pub use keyword::{Keyword, KeywordIssue, Stability};
+pub use keyword_policy::{AcceptSnippets, KeywordPolicy};
+pub use keyword_regression_check::KeywordRegressionCheck;
+pub use keyword_set::{ArchChange, KeywordSet};
+pub use keyword_token::KeywordToken;
+pub use lazy_entry::LazyCacheEntry;
 pub use license::LicenseExpr;
-pub use metadata::EbuildMetadata;
+pub use license_check::LicenseCheck;
+pub use license_dir::LicenseDir;
+pub use live_check::LiveCheck;
+pub use manifest::{Manifest, ManifestEntry};
+pub use metadata::{EbuildMetadata, MetadataIssue, MetadataRow};
+pub use metadata_key::{FieldValue, MetadataKey};
+#[cfg(feature = "fs")]
+pub use metadata_repo::{MetadataRepo, ValidateOptions};
+pub use mirrors::ThirdPartyMirrors;
+pub use parse_report::{ParseReport, Strictness};
+pub use parse_stats::ParseStats;
 pub use phase::Phase;
+pub use phase_set::PhaseSet;
+pub use profile::Profile;
+pub use properties::{PropertiesExpr, PropertyKind};
+pub use property_set::PropertySet;
+pub use raw_entry::RawCacheEntry;
+pub use redundant_dep_check::RedundantDepCheck;
+#[cfg(feature = "fs")]
+pub use regen_plan::{RegenPlan, StaleEntry, StaleReason};
+pub use repo_diff::{FieldChange, PackageDiff, RepoDiff};
+pub use repo_stats::{ArchCoverage, RepoStats};
+pub use report::Report;
 pub use required_use::RequiredUseExpr;
-pub use restrict::RestrictExpr;
+pub use required_use_check::RequiredUseCheck;
+pub use restrict::{RestrictExpr, RestrictKind};
+pub use restrict_set::RestrictSet;
+pub use sbom::Sbom;
+pub use spdx::SpdxMap;
+pub use sql_export::SqlExport;
 pub use src_uri::SrcUriEntry;
+pub use src_url::SrcUrl;
+pub use stabilization_batch::{KeywordChange, StabilizationBatch, StabilizationReport};
+pub use stable_candidate::StableCandidate;
+pub use subslot_rebuild::SubslotRebuild;
+#[cfg(feature = "fs")]
+pub use tree_writer::{TreeWriteReport, TreeWriter};
+pub use uri_lint::{UriFinding, UriIssue, UriLint};
+pub use use_expand::UseExpandConfig;
+pub use use_flag_check::UseFlagCheck;
+pub use use_flags::UseFlagSet;
+pub use use_resolver::{ChoiceKind, UseChoice, UseChooser, UseResolution};
 
 // Re-export interner module so downstream crates can use the same types
 pub use portage_atom::gentoo_interner as interner;
+
+// Re-export the portage-atom types that appear in this crate's API
+pub use portage_atom::{
+    Blocker, Cpn, Cpv, Dep, DepEntry, Operator, Revision, Slot, SlotDep, SlotOperator, Suffix,
+    SuffixKind, UseDefault, UseDep, UseDepKind, Version,
+};
+
+// Re-export smallvec, the type of `EbuildMetadata::keywords`
+pub use smallvec;
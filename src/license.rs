@@ -1,12 +1,19 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-use winnow::ascii::multispace0;
-use winnow::combinator::{alt, cut_err, delimited, dispatch, opt, peek, preceded, repeat};
-use winnow::error::StrContext;
 use winnow::prelude::*;
-use winnow::token::{any, take_while};
+use winnow::token::take_while;
 
+use crate::dep_spec::{
+    self, fmt_spec, parse_spec, walk, DepSpec, GroupKind, ParseSpec, Shape, SpecTree, SpecVisitor,
+};
 use crate::error::{Error, Result};
+use crate::interner::{DefaultInterner, Interned, Interner};
+use crate::license_dir::LicenseDir;
+use crate::names::{check_tokens, Leaf};
+use crate::spdx::SpdxMap;
 
 /// A node in a `LICENSE` expression tree.
 ///
@@ -16,11 +23,14 @@ use crate::error::{Error, Result};
 /// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables)
 /// and [PMS 8.2](https://projects.gentoo.org/pms/9/pms.html#dependency-specification-format).
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum LicenseExpr {
+pub enum LicenseExpr<I = DefaultInterner>
+where
+    I: Interner,
+{
     /// A single license identifier (e.g. `MIT`, `GPL-2+`).
-    License(String),
+    License(Interned<I>),
     /// `|| ( license1 license2 ... )` — any one license is acceptable.
-    AnyOf(Vec<LicenseExpr>),
+    AnyOf(Vec<LicenseExpr<I>>),
     /// `flag? ( licenses... )` or `!flag? ( licenses... )` conditional group.
     UseConditional {
         /// USE flag name.
@@ -28,13 +38,53 @@ pub enum LicenseExpr {
         /// `true` for `!flag?` (negated conditional).
         negated: bool,
         /// License entries guarded by this flag.
-        entries: Vec<LicenseExpr>,
+        entries: Vec<LicenseExpr<I>>,
     },
     /// Top-level grouping: all listed licenses apply.
-    All(Vec<LicenseExpr>),
+    All(Vec<LicenseExpr<I>>),
 }
 
-impl LicenseExpr {
+impl LicenseExpr<DefaultInterner> {
+    /// The license `name`.
+    pub fn license(name: &str) -> Self {
+        LicenseExpr::License(Interned::intern(name))
+    }
+
+    /// `|| ( entries... )`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::LicenseExpr;
+    ///
+    /// let expr = LicenseExpr::all([
+    ///     LicenseExpr::license("MIT"),
+    ///     LicenseExpr::any_of([LicenseExpr::license("GPL-2"), LicenseExpr::license("BSD")]),
+    /// ]);
+    /// assert_eq!(expr, LicenseExpr::parse("MIT || ( GPL-2 BSD )").unwrap());
+    /// ```
+    pub fn any_of(entries: impl IntoIterator<Item = Self>) -> Self {
+        LicenseExpr::AnyOf(entries.into_iter().collect())
+    }
+
+    /// `flag? ( entries... )`, or `!flag? ( entries... )` if `negated`.
+    pub fn conditional(
+        flag: impl Into<String>,
+        negated: bool,
+        entries: impl IntoIterator<Item = Self>,
+    ) -> Self {
+        LicenseExpr::UseConditional {
+            flag: flag.into(),
+            negated,
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    /// Licenses that all apply.
+    pub fn all(entries: impl IntoIterator<Item = Self>) -> Self {
+        LicenseExpr::All(entries.into_iter().collect())
+    }
+
     /// Parse a `LICENSE` expression string.
     ///
     /// # Examples
@@ -49,7 +99,38 @@ impl LicenseExpr {
     /// assert!(matches!(expr, LicenseExpr::License(_)));
     /// ```
     pub fn parse(input: &str) -> Result<Self> {
-        let entries: Vec<LicenseExpr> = parse_license_string
+        Self::parse_interned(input)
+    }
+
+    /// Parse a `LICENSE` expression, enforcing the PMS naming rules.
+    ///
+    /// License names must follow
+    /// [PMS 3.1.7](https://projects.gentoo.org/pms/9/pms.html#license-names)
+    /// and USE flags
+    /// [PMS 3.1.4](https://projects.gentoo.org/pms/9/pms.html#use-flag-names);
+    /// the error reports the byte offset of the first invalid character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::LicenseExpr;
+    ///
+    /// assert!(LicenseExpr::parse_strict("GPL-2+ doc? ( FDL-1.3 )").is_ok());
+    /// let err = LicenseExpr::parse_strict("MIT @doc? ( FDL-1.3 )").unwrap_err();
+    /// assert!(err.to_string().contains("position 4"));
+    /// ```
+    pub fn parse_strict(input: &str) -> Result<Self> {
+        if let Some((pos, what)) = check_tokens(input, Leaf::License) {
+            return Err(Error::InvalidLicense(format!("position {pos}: {what}")));
+        }
+        Self::parse(input)
+    }
+}
+
+impl<I: Interner> LicenseExpr<I> {
+    /// Parse a `LICENSE` expression, interning license names with `I`.
+    pub fn parse_interned(input: &str) -> Result<Self> {
+        let entries: Vec<LicenseExpr<I>> = parse_spec
             .parse(input)
             .map_err(|e| Error::InvalidLicense(format!("{e}")))?;
 
@@ -59,149 +140,375 @@ impl LicenseExpr {
             _ => LicenseExpr::All(entries),
         })
     }
-}
 
-impl fmt::Display for LicenseExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            LicenseExpr::License(name) => write!(f, "{name}"),
-            LicenseExpr::AnyOf(entries) => {
-                write!(f, "|| ( ")?;
-                for (i, entry) in entries.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{entry}")?;
-                }
-                write!(f, " )")
+    /// Iterate over every license name in the expression, in source order.
+    ///
+    /// USE-conditional and `||` structure is ignored; a license appearing
+    /// several times is yielded each time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::LicenseExpr;
+    ///
+    /// let expr = LicenseExpr::parse("MIT ssl? ( || ( GPL-2 BSD ) )").unwrap();
+    /// let names: Vec<&str> = expr.licenses().collect();
+    /// assert_eq!(names, vec!["MIT", "GPL-2", "BSD"]);
+    /// ```
+    pub fn licenses(&self) -> impl Iterator<Item = &str> {
+        self.iter().filter_map(|node| match node {
+            LicenseExpr::License(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Whether `license` appears anywhere in the expression.
+    ///
+    /// Conditions are not evaluated; see [`LicenseExpr::licenses`].
+    pub fn contains(&self, license: &str) -> bool {
+        self.licenses().any(|name| name == license)
+    }
+
+    /// Return a canonical form of the expression.
+    ///
+    /// Nested all-of groups are flattened into their parent, `||` groups
+    /// with a single choice are unwrapped, empty groups are dropped,
+    /// repeated entries are removed, and the entries of every group are
+    /// sorted. Semantically equal `LICENSE` strings therefore compare and
+    /// serialize identically after normalization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::LicenseExpr;
+    ///
+    /// let a = LicenseExpr::parse("MIT GPL-2 || ( BSD )").unwrap();
+    /// let b = LicenseExpr::parse("BSD MIT GPL-2 MIT").unwrap();
+    /// assert_eq!(a.normalize(), b.normalize());
+    /// assert_eq!(a.normalize().to_string(), "BSD GPL-2 MIT");
+    /// ```
+    pub fn normalize(&self) -> LicenseExpr<I> {
+        from_spec(DepSpec::from(self).normalize())
+    }
+
+    /// Render the expression as an SPDX license expression.
+    ///
+    /// License names are translated with `map`; all-of groups become `AND`
+    /// and `||` groups become `OR`. SPDX has no notion of USE-conditional
+    /// licensing, so the entries of conditional groups are included as if
+    /// their condition held, giving the licenses of the fullest build. An
+    /// empty expression renders as `NONE`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{LicenseExpr, SpdxMap};
+    ///
+    /// let expr = LicenseExpr::parse("|| ( GPL-2 BSD ) doc? ( FDL-1.3 )").unwrap();
+    /// assert_eq!(
+    ///     expr.to_spdx(&SpdxMap::default()),
+    ///     "(BSD-3-Clause OR GPL-2.0-only) AND GFDL-1.3-only",
+    /// );
+    /// ```
+    pub fn to_spdx(&self, map: &SpdxMap) -> String {
+        spdx_expr(&self.normalize(), map)
+            .map(|(s, _)| s)
+            .unwrap_or_else(|| "NONE".to_string())
+    }
+
+    /// License names not present in `dir`, in source order without duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{LicenseDir, LicenseExpr};
+    ///
+    /// let mut dir = LicenseDir::new();
+    /// dir.add_license("MIT");
+    /// let expr = LicenseExpr::parse("MIT || ( GPL-2 MIT ) GPL-2").unwrap();
+    /// assert_eq!(expr.validate_against(&dir), vec!["GPL-2"]);
+    /// ```
+    pub fn validate_against(&self, dir: &LicenseDir) -> Vec<&str> {
+        let mut unknown = Vec::new();
+        for name in self.licenses() {
+            if !dir.contains(name) && !unknown.contains(&name) {
+                unknown.push(name);
             }
-            LicenseExpr::UseConditional {
-                flag,
-                negated,
-                entries,
-            } => {
-                if *negated {
-                    write!(f, "!")?;
-                }
-                write!(f, "{flag}? ( ")?;
-                for (i, entry) in entries.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{entry}")?;
+        }
+        unknown
+    }
+
+    /// The direct children of a group, or an empty slice for a license.
+    pub fn children(&self) -> &[LicenseExpr<I>] {
+        dep_spec::children(self)
+    }
+
+    /// Iterate over this node and all its descendants, depth-first in
+    /// pre-order.
+    pub fn iter(&self) -> impl Iterator<Item = &LicenseExpr<I>> {
+        dep_spec::iter(self)
+    }
+
+    /// Walk the tree depth-first, calling `visitor` on entry and exit of
+    /// every node.
+    pub fn walk<V: SpecVisitor<Self> + ?Sized>(&self, visitor: &mut V) {
+        walk(self, LicenseExpr::children, visitor, 0);
+    }
+
+    /// Rewrite every license name in place.
+    ///
+    /// Conditional and `||` structure is preserved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::LicenseExpr;
+    ///
+    /// let mut expr = LicenseExpr::parse("|| ( GPL-2 Artistic ) doc? ( FDL-1.2 )").unwrap();
+    /// expr.map_licenses(|name| {
+    ///     if name == "Artistic" {
+    ///         *name = "Artistic-2".to_string();
+    ///     }
+    /// });
+    /// assert_eq!(expr.to_string(), "|| ( GPL-2 Artistic-2 ) doc? ( FDL-1.2 )");
+    /// ```
+    pub fn map_licenses<F: FnMut(&mut String)>(&mut self, mut f: F) {
+        self.map_licenses_with(&mut f);
+    }
+
+    fn map_licenses_with<F: FnMut(&mut String)>(&mut self, f: &mut F) {
+        match self {
+            LicenseExpr::License(name) => {
+                let mut mapped = name.to_string();
+                f(&mut mapped);
+                if *name != mapped.as_str() {
+                    *name = Interned::intern(&mapped);
                 }
-                write!(f, " )")
             }
-            LicenseExpr::All(entries) => {
-                for (i, entry) in entries.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{entry}")?;
+            LicenseExpr::AnyOf(entries)
+            | LicenseExpr::UseConditional { entries, .. }
+            | LicenseExpr::All(entries) => {
+                for entry in entries {
+                    entry.map_licenses_with(f);
                 }
-                Ok(())
             }
         }
     }
-}
 
-// Winnow parsers
+    /// Add every USE flag referenced by a conditional group to `out`.
+    pub(crate) fn collect_flags<'a>(&'a self, out: &mut BTreeSet<&'a str>) {
+        dep_spec::collect_flags(std::slice::from_ref(self), out);
+    }
+}
 
-fn is_license_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+')
+/// Convert a normalized [`DepSpec`] back; `LICENSE` has no `^^` or `??`
+/// groups, so none appear.
+fn from_spec<I: Interner>(spec: DepSpec<String>) -> LicenseExpr<I> {
+    let convert = |entries: Vec<DepSpec<String>>| entries.into_iter().map(from_spec).collect();
+    match spec {
+        DepSpec::Leaf(name) => LicenseExpr::License(Interned::intern(&name)),
+        DepSpec::AllOf(entries) => LicenseExpr::All(convert(entries)),
+        DepSpec::AnyOf(entries) => LicenseExpr::AnyOf(convert(entries)),
+        DepSpec::UseConditional {
+            flag,
+            negated,
+            entries,
+        } => LicenseExpr::UseConditional {
+            flag,
+            negated,
+            entries: convert(entries),
+        },
+        DepSpec::ExactlyOne(_) | DepSpec::AtMostOne(_) => {
+            unreachable!("normalization adds no ^^ or ?? groups")
+        }
+    }
 }
 
-fn is_flag_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+' || c == '@'
+/// Render `expr` as SPDX, along with its top-level operator if compound.
+fn spdx_expr<I: Interner>(
+    expr: &LicenseExpr<I>,
+    map: &SpdxMap,
+) -> Option<(String, Option<&'static str>)> {
+    match expr {
+        LicenseExpr::License(name) => Some((map.identifier(name), None)),
+        LicenseExpr::AnyOf(entries) => spdx_join(entries, "OR", map),
+        LicenseExpr::All(entries) | LicenseExpr::UseConditional { entries, .. } => {
+            spdx_join(entries, "AND", map)
+        }
+    }
 }
 
-fn parse_license_name(input: &mut &str) -> ModalResult<LicenseExpr> {
-    take_while(1.., is_license_char)
-        .verify(|name: &str| {
-            // Validate license name according to PMS 3.1.7
-            !name.starts_with(['-', '.', '+'])
+fn spdx_join<I: Interner>(
+    entries: &[LicenseExpr<I>],
+    op: &'static str,
+    map: &SpdxMap,
+) -> Option<(String, Option<&'static str>)> {
+    let mut parts: Vec<(String, Option<&str>)> =
+        entries.iter().filter_map(|e| spdx_expr(e, map)).collect();
+    if parts.len() <= 1 {
+        return parts.pop();
+    }
+    let joined: Vec<String> = parts
+        .into_iter()
+        .map(|(s, inner)| match inner {
+            Some(inner) if inner != op => format!("({s})"),
+            _ => s,
         })
-        .map(|name: &str| LicenseExpr::License(name.to_string()))
-        .parse_next(input)
+        .collect();
+    Some((joined.join(&format!(" {op} ")), Some(op)))
 }
 
-fn parse_any_of(input: &mut &str) -> ModalResult<LicenseExpr> {
-    preceded(
-        "||",
-        preceded(
-            multispace0,
-            cut_err(delimited('(', parse_license_entries, (multispace0, ')')))
-                .context(StrContext::Label("'||' group")),
-        ),
-    )
-    .map(LicenseExpr::AnyOf)
-    .parse_next(input)
+impl<I: Interner> LicenseExpr<I> {
+    /// The position of the variant, ordering nodes of different kinds.
+    fn rank(&self) -> u8 {
+        match self {
+            LicenseExpr::License(_) => 0,
+            LicenseExpr::AnyOf(_) => 1,
+            LicenseExpr::UseConditional { .. } => 2,
+            LicenseExpr::All(_) => 3,
+        }
+    }
 }
 
-fn parse_use_conditional(input: &mut &str) -> ModalResult<LicenseExpr> {
-    let negated = opt('!').parse_next(input)?.is_some();
-    let flag: String = take_while(1.., is_flag_char)
-        .map(|s: &str| s.to_string())
-        .parse_next(input)?;
-    '?'.parse_next(input)?;
-    multispace0.parse_next(input)?;
-    let entries = cut_err(delimited('(', parse_license_entries, (multispace0, ')')))
-        .context(StrContext::Label("USE conditional group"))
-        .parse_next(input)?;
-    Ok(LicenseExpr::UseConditional {
-        flag,
-        negated,
-        entries,
-    })
+impl<I: Interner> Hash for LicenseExpr<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            LicenseExpr::License(name) => name.hash(state),
+            LicenseExpr::AnyOf(entries) | LicenseExpr::All(entries) => entries.hash(state),
+            LicenseExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => (flag, negated, entries).hash(state),
+        }
+    }
 }
 
-fn parse_paren_group(input: &mut &str) -> ModalResult<Vec<LicenseExpr>> {
-    delimited(
-        '(',
-        parse_license_entries,
-        cut_err((multispace0, ')')).context(StrContext::Label("closing ')'")),
-    )
-    .parse_next(input)
+/// License names compare as strings, so ordering does not depend on the
+/// order in which they were interned.
+impl<I: Interner + Eq> Ord for LicenseExpr<I> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (LicenseExpr::License(a), LicenseExpr::License(b)) => a.as_str().cmp(b.as_str()),
+            (LicenseExpr::AnyOf(a), LicenseExpr::AnyOf(b))
+            | (LicenseExpr::All(a), LicenseExpr::All(b)) => a.cmp(b),
+            (
+                LicenseExpr::UseConditional {
+                    flag,
+                    negated,
+                    entries,
+                },
+                LicenseExpr::UseConditional {
+                    flag: other_flag,
+                    negated: other_negated,
+                    entries: other_entries,
+                },
+            ) => (flag, negated, entries).cmp(&(other_flag, other_negated, other_entries)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
 }
 
-fn parse_license_entry(input: &mut &str) -> ModalResult<Vec<LicenseExpr>> {
-    dispatch! {peek(any);
-        '|' => parse_any_of.map(|e| vec![e]),
-        '(' => parse_paren_group,
-        _ => alt((
-            parse_use_conditional.map(|e| vec![e]),
-            parse_license_name.map(|e| vec![e]),
-        )),
+impl<I: Interner + Eq> PartialOrd for LicenseExpr<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I: Interner> fmt::Display for LicenseExpr<I> {
+    /// `{:#}` prints an indented multi-line tree.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_spec(f, self, true)
     }
-    .parse_next(input)
 }
 
-fn parse_license_entries(input: &mut &str) -> ModalResult<Vec<LicenseExpr>> {
-    repeat(0.., preceded(multispace0, parse_license_entry))
-        .fold(
-            Vec::new,
-            |mut acc: Vec<LicenseExpr>, batch: Vec<LicenseExpr>| {
-                acc.extend(batch);
-                acc
+impl<I: Interner> SpecTree for LicenseExpr<I> {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            LicenseExpr::License(_) => Shape::Leaf,
+            LicenseExpr::AnyOf(entries) => Shape::Group(GroupKind::AnyOf, entries),
+            LicenseExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => Shape::UseConditional {
+                flag,
+                negated: *negated,
+                entries,
             },
-        )
-        .parse_next(input)
+            LicenseExpr::All(entries) => Shape::Group(GroupKind::AllOf, entries),
+        }
+    }
+
+    fn fmt_leaf(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LicenseExpr::License(name) => write!(f, "{name}"),
+            _ => Ok(()),
+        }
+    }
 }
 
-pub(crate) fn parse_license_string(input: &mut &str) -> ModalResult<Vec<LicenseExpr>> {
-    let entries = parse_license_entries(input)?;
-    multispace0.parse_next(input)?;
-    Ok(entries)
+impl<I: Interner> ParseSpec for LicenseExpr<I> {
+    const CHOICES: &'static [GroupKind] = &[GroupKind::AnyOf];
+
+    fn parse_leaf(input: &mut &str) -> ModalResult<Self> {
+        take_while(1.., is_license_char)
+            .verify(|name: &str| {
+                // Validate license name according to PMS 3.1.7
+                !name.starts_with(['-', '.', '+'])
+            })
+            .map(|name: &str| LicenseExpr::License(Interned::intern(name)))
+            .parse_next(input)
+    }
+
+    fn choice(_: GroupKind, entries: Vec<Self>) -> Self {
+        LicenseExpr::AnyOf(entries)
+    }
+
+    fn conditional(flag: String, negated: bool, entries: Vec<Self>) -> Self {
+        LicenseExpr::UseConditional {
+            flag,
+            negated,
+            entries,
+        }
+    }
+}
+
+// Winnow parsers
+
+fn is_license_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+')
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn names_interned_and_ordered_as_strings() {
+        use crate::interner::NoInterner;
+
+        let expr = LicenseExpr::parse("MIT || ( MIT BSD )").unwrap();
+        let names: Vec<_> = expr
+            .iter()
+            .filter_map(|node| match node {
+                LicenseExpr::License(name) => Some(*name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names[0], names[1]);
+
+        let a = LicenseExpr::<NoInterner>::parse_interned("zlib").unwrap();
+        let b = LicenseExpr::<NoInterner>::parse_interned("MIT").unwrap();
+        assert!(b < a);
+        assert_eq!(a.normalize().to_string(), "zlib");
+    }
+
     #[test]
     fn parse_single_license() {
         let expr = LicenseExpr::parse("MIT").unwrap();
-        assert_eq!(expr, LicenseExpr::License("MIT".to_string()));
+        assert_eq!(expr, LicenseExpr::license("MIT"));
     }
 
     #[test]
@@ -210,8 +517,8 @@ mod tests {
         match expr {
             LicenseExpr::All(entries) => {
                 assert_eq!(entries.len(), 2);
-                assert_eq!(entries[0], LicenseExpr::License("MIT".to_string()));
-                assert_eq!(entries[1], LicenseExpr::License("BSD-2".to_string()));
+                assert_eq!(entries[0], LicenseExpr::license("MIT"));
+                assert_eq!(entries[1], LicenseExpr::license("BSD-2"));
             }
             _ => unreachable!("expected All"),
         }
@@ -253,9 +560,9 @@ mod tests {
                 assert_eq!(entries.len(), 2);
                 assert_eq!(
                     entries[0],
-                    LicenseExpr::License("Apache-2.0-with-LLVM-exceptions".to_string())
+                    LicenseExpr::license("Apache-2.0-with-LLVM-exceptions")
                 );
-                assert_eq!(entries[1], LicenseExpr::License("UoI-NCSA".to_string()));
+                assert_eq!(entries[1], LicenseExpr::license("UoI-NCSA"));
             }
             _ => unreachable!("expected All"),
         }
@@ -269,15 +576,15 @@ mod tests {
 
     #[test]
     fn display_single() {
-        let expr = LicenseExpr::License("MIT".to_string());
+        let expr = LicenseExpr::license("MIT");
         assert_eq!(expr.to_string(), "MIT");
     }
 
     #[test]
     fn display_any_of() {
         let expr = LicenseExpr::AnyOf(vec![
-            LicenseExpr::License("MIT".to_string()),
-            LicenseExpr::License("Apache-2.0".to_string()),
+            LicenseExpr::license("MIT"),
+            LicenseExpr::license("Apache-2.0"),
         ]);
         assert_eq!(expr.to_string(), "|| ( MIT Apache-2.0 )");
     }
@@ -308,16 +615,13 @@ mod tests {
     #[test]
     fn valid_license_with_underscore() {
         let expr = LicenseExpr::parse("MIT_with_underscore").unwrap();
-        assert_eq!(
-            expr,
-            LicenseExpr::License("MIT_with_underscore".to_string())
-        );
+        assert_eq!(expr, LicenseExpr::license("MIT_with_underscore"));
     }
 
     #[test]
     fn valid_license_with_hyphen_not_first() {
         let expr = LicenseExpr::parse("GPL-2+").unwrap();
-        assert_eq!(expr, LicenseExpr::License("GPL-2+".to_string()));
+        assert_eq!(expr, LicenseExpr::license("GPL-2+"));
     }
 
     #[test]
@@ -336,4 +640,137 @@ mod tests {
             _ => unreachable!("expected UseConditional"),
         }
     }
+
+    #[test]
+    fn licenses_and_contains() {
+        let expr = LicenseExpr::parse("GPL-2 || ( MIT doc? ( CC-BY-4.0 ) ) GPL-2").unwrap();
+        assert_eq!(
+            expr.licenses().collect::<Vec<_>>(),
+            vec!["GPL-2", "MIT", "CC-BY-4.0", "GPL-2"]
+        );
+        assert!(expr.contains("CC-BY-4.0"));
+        assert!(!expr.contains("GPL-3"));
+        assert_eq!(LicenseExpr::parse("").unwrap().licenses().count(), 0);
+    }
+
+    #[test]
+    fn normalize_flattens_and_sorts() {
+        let expr = LicenseExpr::All(vec![
+            LicenseExpr::license("MIT"),
+            LicenseExpr::All(vec![
+                LicenseExpr::license("BSD"),
+                LicenseExpr::All(Vec::new()),
+            ]),
+            LicenseExpr::license("MIT"),
+        ]);
+        assert_eq!(expr.normalize().to_string(), "BSD MIT");
+    }
+
+    #[test]
+    fn normalize_any_of() {
+        let a = LicenseExpr::parse("|| ( MIT || ( BSD MIT ) ) doc? ( )").unwrap();
+        let b = LicenseExpr::parse("|| ( BSD MIT )").unwrap();
+        assert_eq!(a.normalize(), b.normalize());
+        assert_eq!(a.normalize().to_string(), "|| ( BSD MIT )");
+    }
+
+    #[test]
+    fn normalize_conditional() {
+        let expr = LicenseExpr::parse("ssl? ( MIT GPL-2 MIT ) GPL-2").unwrap();
+        assert_eq!(expr.normalize().to_string(), "GPL-2 ssl? ( GPL-2 MIT )");
+    }
+
+    #[test]
+    fn normalize_empty() {
+        let expr = LicenseExpr::parse("doc? ( || ( ) )").unwrap();
+        assert_eq!(expr.normalize(), LicenseExpr::All(Vec::new()));
+    }
+
+    #[test]
+    fn to_spdx_nested() {
+        let map = SpdxMap::default();
+        let expr = LicenseExpr::parse("MIT || ( Apache-2.0 ( GPL-2+ LGPL-2.1 ) )").unwrap();
+        assert_eq!(
+            expr.to_spdx(&map),
+            "MIT AND (Apache-2.0 OR GPL-2.0-or-later OR LGPL-2.1-only)"
+        );
+        let expr = LicenseExpr::AnyOf(vec![
+            LicenseExpr::license("MIT"),
+            LicenseExpr::All(vec![
+                LicenseExpr::license("GPL-2"),
+                LicenseExpr::license("custom"),
+            ]),
+        ]);
+        assert_eq!(
+            expr.to_spdx(&map),
+            "MIT OR (GPL-2.0-only AND LicenseRef-custom)"
+        );
+    }
+
+    #[test]
+    fn to_spdx_empty() {
+        let expr = LicenseExpr::parse("").unwrap();
+        assert_eq!(expr.to_spdx(&SpdxMap::default()), "NONE");
+    }
+
+    #[test]
+    fn display_alternate() {
+        let expr = LicenseExpr::parse("MIT doc? ( || ( CC-BY-4.0 FDL-1.3 ) )").unwrap();
+        assert_eq!(
+            format!("{expr:#}"),
+            "MIT\ndoc? (\n  || (\n    CC-BY-4.0\n    FDL-1.3\n  )\n)"
+        );
+    }
+
+    #[test]
+    fn display_nested_all_of() {
+        let expr = LicenseExpr::all([
+            LicenseExpr::license("MIT"),
+            LicenseExpr::all([LicenseExpr::license("BSD"), LicenseExpr::license("ZLIB")]),
+        ]);
+        assert_eq!(expr.to_string(), "MIT ( BSD ZLIB )");
+        assert_eq!(format!("{expr:#}"), "MIT\n(\n  BSD\n  ZLIB\n)");
+    }
+
+    #[test]
+    fn walk_depths() {
+        struct Depths(Vec<(String, usize)>);
+        impl SpecVisitor<LicenseExpr> for Depths {
+            fn leave(&mut self, expr: &LicenseExpr, depth: usize) {
+                if let LicenseExpr::License(name) = expr {
+                    self.0.push((name.to_string(), depth));
+                }
+            }
+        }
+        let expr = LicenseExpr::parse("MIT a? ( || ( BSD ISC ) )").unwrap();
+        let mut depths = Depths(Vec::new());
+        expr.walk(&mut depths);
+        assert_eq!(
+            depths.0,
+            vec![
+                ("MIT".to_string(), 1),
+                ("BSD".to_string(), 3),
+                ("ISC".to_string(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn map_licenses_to_spdx_names() {
+        let map = SpdxMap::default();
+        let mut expr = LicenseExpr::parse("GPL-2+ || ( MIT BSD )").unwrap();
+        expr.map_licenses(|name| *name = map.identifier(name));
+        assert_eq!(expr.to_string(), "GPL-2.0-or-later || ( MIT BSD-3-Clause )");
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(
+            LicenseExpr::all([
+                LicenseExpr::license("MIT"),
+                LicenseExpr::conditional("doc", false, [LicenseExpr::license("FDL-1.3")]),
+            ]),
+            LicenseExpr::parse("MIT doc? ( FDL-1.3 )").unwrap()
+        );
+    }
 }
@@ -0,0 +1,103 @@
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::error::closest;
+use crate::license_dir::LicenseDir;
+
+/// Reports `LICENSE` names missing from the repository's `licenses/`
+/// directory, suggesting a known license differing only in case or else
+/// the closest one by spelling.
+///
+/// Names of groups from `profiles/license_groups` are reported as such:
+/// groups are only meaningful in `ACCEPT_LICENSE`, so `LICENSE` must list
+/// their members instead.
+///
+/// See [PMS 4.6](https://projects.gentoo.org/pms/9/pms.html#the-licenses-directory).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, LicenseCheck, LicenseDir};
+///
+/// let mut dir = LicenseDir::new();
+/// dir.add_license("GPL-2+");
+/// dir.add_license("MIT");
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-1").unwrap(),
+///     CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nLICENSE=MIT || ( GPL2+ Foo )\n").unwrap(),
+/// )]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(LicenseCheck::new(dir));
+/// let findings: Vec<_> = runner.run(&repo).into_iter().map(|f| f.message).collect();
+/// assert_eq!(
+///     findings,
+///     ["unknown license GPL2+ (did you mean `GPL-2+`?)", "unknown license Foo"]
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LicenseCheck {
+    dir: LicenseDir,
+}
+
+impl LicenseCheck {
+    /// Check against the licenses and groups of `dir`.
+    pub fn new(dir: LicenseDir) -> Self {
+        LicenseCheck { dir }
+    }
+}
+
+impl MetadataCheck for LicenseCheck {
+    fn name(&self) -> &'static str {
+        "unknown-license"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let Some(license) = &cx.entry.metadata.license else {
+            return;
+        };
+        for name in license.validate_against(&self.dir) {
+            let message = if self.dir.group(name).is_some() {
+                format!("{name} is a license group, not a license")
+            } else {
+                let suggestion = self
+                    .dir
+                    .licenses()
+                    .find(|license| license.eq_ignore_ascii_case(name))
+                    .or_else(|| closest(name, self.dir.licenses()));
+                match suggestion {
+                    Some(suggestion) => {
+                        format!("unknown license {name} (did you mean `{suggestion}`?)")
+                    }
+                    None => format!("unknown license {name}"),
+                }
+            };
+            findings.push(cx.finding(self.name(), Severity::Error, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn groups_and_conditionals() {
+        let mut dir = LicenseDir::new();
+        dir.add_license("MIT");
+        dir.add_groups("FREE MIT\n");
+        let text = "DESCRIPTION=x\nSLOT=0\nLICENSE=FREE doc? ( MIT mit )\n";
+        let findings: Vec<_> = run_check(LicenseCheck::new(dir), &[("app-misc/foo-1", text)])
+            .into_iter()
+            .map(|f| f.message)
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                "FREE is a license group, not a license",
+                "unknown license mit (did you mean `MIT`?)"
+            ]
+        );
+    }
+}
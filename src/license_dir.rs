@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use crate::error::{Error, Result};
+
+/// The licenses known to an ebuild repository.
+///
+/// Built from the file names in the repository's `licenses/` directory and
+/// the groups defined in `profiles/license_groups`. Used to check that
+/// every name in a `LICENSE` expression refers to an existing license.
+///
+/// See [PMS 4.6](https://projects.gentoo.org/pms/9/pms.html#the-licenses-directory)
+/// and [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LicenseDir {
+    licenses: BTreeSet<String>,
+    groups: BTreeMap<String, Vec<String>>,
+}
+
+impl LicenseDir {
+    /// Create an empty license directory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load `licenses/` and `profiles/license_groups` from a repository root.
+    ///
+    /// A missing `license_groups` file is not an error.
+    #[cfg(feature = "fs")]
+    pub fn from_repo(repo: impl AsRef<Path>) -> Result<Self> {
+        let repo = repo.as_ref();
+        let mut dir = Self::new();
+        let licenses = repo.join("licenses");
+        let io_err = |e| Error::io(&licenses, e);
+        for entry in fs::read_dir(&licenses).map_err(io_err)? {
+            let entry = entry.map_err(io_err)?;
+            if entry.file_type().map_err(io_err)?.is_file() {
+                if let Some(name) = entry.file_name().to_str() {
+                    dir.add_license(name);
+                }
+            }
+        }
+        let groups = repo.join("profiles/license_groups");
+        match fs::read_to_string(&groups) {
+            Ok(text) => dir.add_groups(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(Error::io(&groups, e)),
+        }
+        Ok(dir)
+    }
+
+    /// Add a license name.
+    pub fn add_license(&mut self, name: impl Into<String>) {
+        self.licenses.insert(name.into());
+    }
+
+    /// Add the groups defined in a `license_groups` file.
+    ///
+    /// Each non-comment line holds a group name followed by its members;
+    /// members prefixed with `@` refer to other groups.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::LicenseDir;
+    ///
+    /// let mut dir = LicenseDir::new();
+    /// dir.add_groups("# comment\nGPL-COMPATIBLE MIT @FSF-APPROVED\nFSF-APPROVED GPL-2\n");
+    /// assert_eq!(dir.expand_group("GPL-COMPATIBLE"), vec!["GPL-2", "MIT"]);
+    /// ```
+    pub fn add_groups(&mut self, input: &str) {
+        for line in input.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut tokens = line.split_whitespace();
+            if let Some(group) = tokens.next() {
+                self.groups
+                    .insert(group.to_string(), tokens.map(str::to_string).collect());
+            }
+        }
+    }
+
+    /// Whether `name` is a known license.
+    pub fn contains(&self, name: &str) -> bool {
+        self.licenses.contains(name)
+    }
+
+    /// Iterate over the known licenses in sorted order.
+    pub fn licenses(&self) -> impl Iterator<Item = &str> {
+        self.licenses.iter().map(String::as_str)
+    }
+
+    /// The direct members of a license group (without the `@`).
+    pub fn group(&self, name: &str) -> Option<&[String]> {
+        self.groups.get(name).map(Vec::as_slice)
+    }
+
+    /// All licenses in a group, resolving nested `@group` references.
+    ///
+    /// The result is sorted and free of duplicates; unknown groups and
+    /// reference cycles are ignored.
+    pub fn expand_group(&self, name: &str) -> Vec<&str> {
+        let mut seen = BTreeSet::new();
+        let mut out = BTreeSet::new();
+        self.expand_into(name, &mut seen, &mut out);
+        out.into_iter().collect()
+    }
+
+    fn expand_into<'a>(
+        &'a self,
+        name: &str,
+        seen: &mut BTreeSet<&'a str>,
+        out: &mut BTreeSet<&'a str>,
+    ) {
+        let Some((group, members)) = self.groups.get_key_value(name) else {
+            return;
+        };
+        if !seen.insert(group) {
+            return;
+        }
+        for member in members {
+            match member.strip_prefix('@') {
+                Some(nested) => self.expand_into(nested, seen, out),
+                None => {
+                    out.insert(member);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_nested_and_cyclic_groups() {
+        let mut dir = LicenseDir::new();
+        dir.add_groups("A MIT @B\nB GPL-2 @A @MISSING\n");
+        assert_eq!(dir.expand_group("A"), vec!["GPL-2", "MIT"]);
+        assert_eq!(dir.expand_group("B"), vec!["GPL-2", "MIT"]);
+        assert!(dir.expand_group("MISSING").is_empty());
+        assert_eq!(dir.group("B").unwrap(), ["GPL-2", "@A", "@MISSING"]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn from_repo() {
+        use crate::temp_dir::TempDir;
+        let root = TempDir::new("license-dir");
+        fs::create_dir_all(root.join("licenses")).unwrap();
+        fs::write(root.join("licenses/MIT"), "").unwrap();
+        fs::write(root.join("licenses/GPL-2"), "").unwrap();
+        let dir = LicenseDir::from_repo(&root).unwrap();
+        assert_eq!(dir.licenses().collect::<Vec<_>>(), vec!["GPL-2", "MIT"]);
+        assert!(dir.group("FREE").is_none());
+
+        fs::create_dir_all(root.join("profiles")).unwrap();
+        fs::write(root.join("profiles/license_groups"), "FREE MIT\n").unwrap();
+        let dir = LicenseDir::from_repo(&root).unwrap();
+        assert_eq!(dir.expand_group("FREE"), vec!["MIT"]);
+    }
+}
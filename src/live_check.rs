@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::keyword::Stability;
+use crate::properties::{PropertiesExpr, PropertyKind};
+
+/// Eclasses that fetch from version control in `src_unpack`.
+const LIVE_ECLASSES: &[&str] = &[
+    "bzr",
+    "cvs",
+    "darcs",
+    "git-r3",
+    "golang-vcs",
+    "mercurial",
+    "subversion",
+];
+
+/// Reports live ebuilds that declare stable or testing `KEYWORDS`.
+///
+/// An ebuild counts as live if `PROPERTIES` contains `live`, or if it has
+/// an empty `SRC_URI` and inherits a VCS eclass such as `git-r3`. Gentoo
+/// policy forbids keywording live ebuilds since their content changes
+/// without a version bump.
+///
+/// See [PMS 7.3.7](https://projects.gentoo.org/pms/9/pms.html#properties).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, LiveCheck};
+///
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-9999").unwrap(),
+///     CacheEntry::parse(
+///         "DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64\n_eclasses_=git-r3\t0123456789abcdef0123456789abcdef\n",
+///     )
+///     .unwrap(),
+/// )]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(LiveCheck::default());
+/// let findings = runner.run(&repo);
+/// assert_eq!(findings[0].message, "live ebuild (inherits git-r3) has KEYWORDS ~amd64");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveCheck {
+    eclasses: BTreeSet<String>,
+}
+
+impl LiveCheck {
+    /// Treat entries inheriting any of `eclasses` as live, instead of the
+    /// default VCS eclasses of the Gentoo repository.
+    pub fn with_eclasses<S: Into<String>>(eclasses: impl IntoIterator<Item = S>) -> Self {
+        LiveCheck {
+            eclasses: eclasses.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Default for LiveCheck {
+    fn default() -> Self {
+        Self::with_eclasses(LIVE_ECLASSES.iter().copied())
+    }
+}
+
+impl MetadataCheck for LiveCheck {
+    fn name(&self) -> &'static str {
+        "live-keywords"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let metadata = &cx.entry.metadata;
+        let keywords: Vec<String> = metadata
+            .keywords
+            .iter()
+            .filter(|k| k.stability.at_least(Stability::Testing))
+            .map(ToString::to_string)
+            .collect();
+        if keywords.is_empty() {
+            return;
+        }
+        let reason =
+            if PropertiesExpr::flat_tokens(&metadata.properties).contains(&&PropertyKind::Live) {
+                "PROPERTIES=live".to_string()
+            } else if let Some(eclass) = metadata
+                .inherited
+                .iter()
+                .find(|eclass| self.eclasses.contains(eclass.as_str()))
+                .filter(|_| metadata.src_uri.is_empty())
+            {
+                format!("inherits {}", eclass.as_str())
+            } else {
+                return;
+            };
+        let message = format!("live ebuild ({reason}) has KEYWORDS {}", keywords.join(" "));
+        findings.push(cx.finding(self.name(), Severity::Error, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn live_detection() {
+        let messages = |check: LiveCheck, text: &str| -> Vec<String> {
+            let text = format!("DESCRIPTION=x\nSLOT=0\n{text}");
+            run_check(check, &[("app-misc/foo-9999", &text)])
+                .into_iter()
+                .map(|f| f.message)
+                .collect()
+        };
+        let git = "_eclasses_=git-r3\t0123456789abcdef0123456789abcdef\n";
+        assert_eq!(
+            messages(
+                LiveCheck::default(),
+                "EAPI=8\nPROPERTIES=live\nKEYWORDS=amd64 -x86\n"
+            ),
+            ["live ebuild (PROPERTIES=live) has KEYWORDS amd64"]
+        );
+        assert!(messages(LiveCheck::default(), &format!("KEYWORDS=-*\n{git}")).is_empty());
+        assert!(messages(
+            LiveCheck::default(),
+            &format!("KEYWORDS=~amd64\nSRC_URI=https://e.com/a.tgz\n{git}")
+        )
+        .is_empty());
+        assert!(messages(
+            LiveCheck::with_eclasses(["myvcs"]),
+            &format!("KEYWORDS=~amd64\n{git}")
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+
+/// A `DIST` entry of a package `Manifest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// File size in bytes.
+    pub size: u64,
+    /// `(algorithm, hex digest)` pairs in file order, e.g. `("BLAKE2B", ...)`.
+    pub hashes: Vec<(String, String)>,
+}
+
+impl ManifestEntry {
+    /// The digest recorded for `algorithm`, if any.
+    pub fn hash(&self, algorithm: &str) -> Option<&str> {
+        self.hashes
+            .iter()
+            .find(|(algo, _)| algo == algorithm)
+            .map(|(_, digest)| digest.as_str())
+    }
+}
+
+/// The distfile entries of a package `Manifest`.
+///
+/// Only `DIST` lines are kept; the `EBUILD`, `AUX` and `MISC` entries of
+/// thick Manifests are skipped, as are blank lines and `#` comments. The
+/// format is defined by GLEP 74 rather than PMS; it complements the
+/// `SRC_URI` handling of
+/// [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    dist: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Parse the contents of a `Manifest` file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Manifest;
+    ///
+    /// let manifest = Manifest::parse(
+    ///     "DIST foo-1.0.tar.gz 1234 BLAKE2B abcd SHA512 ef01\n",
+    /// )
+    /// .unwrap();
+    /// let entry = manifest.get("foo-1.0.tar.gz").unwrap();
+    /// assert_eq!(entry.size, 1234);
+    /// assert_eq!(entry.hash("SHA512"), Some("ef01"));
+    /// ```
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut manifest = Self::default();
+        for line in input.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            if tokens.next() != Some("DIST") {
+                continue;
+            }
+            let (Some(name), Some(size)) = (tokens.next(), tokens.next()) else {
+                return Err(Error::InvalidManifest(line.to_string()));
+            };
+            let size = size
+                .parse()
+                .map_err(|_| Error::InvalidManifest(line.to_string()))?;
+            let rest: Vec<&str> = tokens.collect();
+            let pairs = rest.chunks_exact(2);
+            if !pairs.remainder().is_empty() {
+                return Err(Error::InvalidManifest(line.to_string()));
+            }
+            let hashes = pairs
+                .map(|pair| (pair[0].to_string(), pair[1].to_string()))
+                .collect();
+            manifest
+                .dist
+                .insert(name.to_string(), ManifestEntry { size, hashes });
+        }
+        Ok(manifest)
+    }
+
+    /// The entry for distfile `filename`.
+    pub fn get(&self, filename: &str) -> Option<&ManifestEntry> {
+        self.dist.get(filename)
+    }
+
+    /// Add or replace the entry for `filename`.
+    pub fn insert(&mut self, filename: impl Into<String>, entry: ManifestEntry) {
+        self.dist.insert(filename.into(), entry);
+    }
+
+    /// Iterate over `(filename, entry)` pairs in filename order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ManifestEntry)> {
+        self.dist.iter().map(|(name, entry)| (name.as_str(), entry))
+    }
+
+    /// Number of distfile entries.
+    pub fn len(&self) -> usize {
+        self.dist.len()
+    }
+
+    /// Whether there are no distfile entries.
+    pub fn is_empty(&self) -> bool {
+        self.dist.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_non_dist_lines() {
+        let manifest = Manifest::parse(
+            "# comment\n\nEBUILD foo-1.ebuild 10 SHA512 aa\nDIST a.tgz 5 SHA512 bb\nAUX x.patch 3 SHA512 cc\n",
+        )
+        .unwrap();
+        assert_eq!(manifest.len(), 1);
+        assert_eq!(manifest.iter().next().unwrap().0, "a.tgz");
+    }
+
+    #[test]
+    fn invalid_lines() {
+        assert!(matches!(
+            Manifest::parse("DIST a.tgz"),
+            Err(Error::InvalidManifest(_))
+        ));
+        assert!(Manifest::parse("DIST a.tgz big SHA512 aa").is_err());
+        assert!(Manifest::parse("DIST a.tgz 5 SHA512").is_err());
+    }
+
+    #[test]
+    fn entry_without_hashes() {
+        let manifest = Manifest::parse("DIST a.tgz 5\n").unwrap();
+        assert_eq!(manifest.get("a.tgz").unwrap().hash("SHA512"), None);
+    }
+}
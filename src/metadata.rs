@@ -1,14 +1,32 @@
-use crate::interner::{DefaultInterner, Interner};
-use portage_atom::{DepEntry, Slot};
+use std::collections::BTreeSet;
+use std::fmt::{self, Write as _};
+use std::hash::{Hash, Hasher};
 
+use crate::interner::{DefaultInterner, Interned, Interner};
+use portage_atom::{Dep, DepEntry, Slot, UseDepKind};
+use smallvec::SmallVec;
+
+use crate::cache::{is_valid_slot_name, parse_dep_field, parse_slot};
+use crate::dep_spec::DepSpec;
+use crate::distfile::DistfileAccess;
 use crate::eapi::Eapi;
+use crate::error::{Error, Result};
+use crate::homepage::Homepage;
 use crate::iuse::IUse;
-use crate::keyword::Keyword;
+use crate::keyword::{Keyword, KeywordIssue, Stability};
+use crate::keyword_set::KeywordSet;
 use crate::license::LicenseExpr;
+use crate::manifest::Manifest;
+use crate::metadata_key::{FieldValue, MetadataKey};
+use crate::parse_report::Strictness;
 use crate::phase::Phase;
+use crate::phase_set::PhaseSet;
+use crate::properties::{PropertiesExpr, PropertyKind};
 use crate::required_use::RequiredUseExpr;
-use crate::restrict::RestrictExpr;
+use crate::restrict::{RestrictExpr, RestrictKind};
 use crate::src_uri::SrcUriEntry;
+use crate::tokens::Tokens;
+use crate::use_flags::UseFlagSet;
 
 /// Metadata for a single ebuild, as produced by the metadata cache.
 ///
@@ -38,16 +56,16 @@ where
     pub slot: Slot,
 
     /// Homepage URL(s).
-    pub homepage: Vec<String>,
+    pub homepage: Vec<Homepage>,
 
     /// Source URI expression.
     pub src_uri: Vec<SrcUriEntry>,
 
     /// License expression.
-    pub license: Option<LicenseExpr>,
+    pub license: Option<LicenseExpr<I>>,
 
-    /// Architecture keywords.
-    pub keywords: Vec<Keyword<I>>,
+    /// Architecture keywords; up to eight are stored inline.
+    pub keywords: SmallVec<[Keyword<I>; 8]>,
 
     /// USE flags declared by the ebuild.
     pub iuse: Vec<IUse<I>>,
@@ -59,7 +77,7 @@ where
     pub restrict: Vec<RestrictExpr>,
 
     /// PROPERTIES entries.
-    pub properties: Vec<RestrictExpr>,
+    pub properties: Vec<PropertiesExpr>,
 
     /// Build-time dependencies (`DEPEND`).
     ///
@@ -84,7 +102,7 @@ where
     /// auxdb extension; it is not specified by PMS.
     ///
     /// See [PMS 10.1](https://projects.gentoo.org/pms/latest/pms.html#the-inherit-command).
-    pub inherit: Vec<String>,
+    pub inherit: Vec<Interned<I>>,
 
     /// All transitively inherited eclass names (direct + nested).
     ///
@@ -93,9 +111,1178 @@ where
     /// this key is excluded; the names are derived from `_eclasses_` instead.
     ///
     /// See [PMS 10.1](https://projects.gentoo.org/pms/latest/pms.html#the-inherit-command)
-    /// and [PMS 14.3](https://projects.gentoo.org/pms/latest/pms.html#md5-dict-cache-file-format).
-    pub inherited: Vec<String>,
+    /// and [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+    pub inherited: Vec<Interned<I>>,
 
     /// Defined phase functions.
     pub defined_phases: Vec<Phase>,
 }
+
+impl<I: Interner> EbuildMetadata<I> {
+    /// Rewrite every field into a canonical form, so that equivalent
+    /// metadata compares equal and serializes identically.
+    ///
+    /// Keywords are deduplicated and sorted with
+    /// [`Keyword::sort_canonical`], IUSE with [`IUse::normalize`], phases
+    /// into execution order, and eclass names alphabetically. `LICENSE` and
+    /// `REQUIRED_USE` trees are replaced by their `normalize()` form; the
+    /// top-level entries of `HOMEPAGE`, `SRC_URI`, `RESTRICT`, `PROPERTIES`
+    /// and the dependency classes are deduplicated and sorted by their
+    /// text, leaving nested groups as written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let mut a = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=x86 ~amd64\nIUSE=ssl doc\n").unwrap();
+    /// let mut b = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64 x86 x86\nIUSE=doc ssl\n").unwrap();
+    /// assert_ne!(a.metadata, b.metadata);
+    /// a.metadata.normalize();
+    /// b.metadata.normalize();
+    /// assert_eq!(a.metadata, b.metadata);
+    /// ```
+    pub fn normalize(&mut self) {
+        Keyword::sort_canonical(&mut self.keywords);
+        self.keywords
+            .dedup_by(|a, b| a.arch.as_str() == b.arch.as_str() && a.stability == b.stability);
+        IUse::normalize(&mut self.iuse);
+        Phase::sort_execution_order(&mut self.defined_phases);
+        self.defined_phases.dedup();
+        for names in [&mut self.inherit, &mut self.inherited] {
+            names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+            names.dedup();
+        }
+        self.license = self.license.as_ref().map(LicenseExpr::normalize);
+        self.required_use = self.required_use.as_ref().map(RequiredUseExpr::normalize);
+        sort_by_text(&mut self.homepage);
+        sort_by_text(&mut self.src_uri);
+        sort_by_text(&mut self.restrict);
+        sort_by_text(&mut self.properties);
+        for deps in [
+            &mut self.depend,
+            &mut self.rdepend,
+            &mut self.bdepend,
+            &mut self.pdepend,
+            &mut self.idepend,
+        ] {
+            sort_by_text(deps);
+        }
+    }
+
+    /// Check consistency across fields and return every problem found.
+    ///
+    /// Reports fields the EAPI does not allow (`REQUIRED_USE`, `PROPERTIES`,
+    /// `BDEPEND`, `IDEPEND`), defined phases the EAPI does not have, live
+    /// ebuilds with stable or testing keywords, invalid `SLOT` names, an
+    /// empty `DESCRIPTION`, `HOMEPAGE` entries that are not URLs, and
+    /// duplicate or conflicting keywords. An empty
+    /// result means the metadata is consistent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, MetadataIssue, MetadataKey};
+    ///
+    /// let entry = CacheEntry::parse(
+    ///     "EAPI=3\nDESCRIPTION=x\nSLOT=0\nIUSE=a b\nREQUIRED_USE=|| ( a b )\n",
+    /// )
+    /// .unwrap();
+    /// let issues = entry.metadata.validate();
+    /// assert!(matches!(
+    ///     issues[..],
+    ///     [MetadataIssue::UnsupportedInEapi { key: MetadataKey::RequiredUse, .. }]
+    /// ));
+    /// ```
+    pub fn validate(&self) -> Vec<MetadataIssue> {
+        let mut issues = Vec::new();
+        if self.description.trim().is_empty() {
+            issues.push(MetadataIssue::EmptyDescription);
+        }
+        let subslot_ok = match &self.slot.subslot {
+            Some(sub) => is_valid_slot_name(sub.as_str()),
+            None => true,
+        };
+        let slot_ok = is_valid_slot_name(self.slot.slot.as_str()) && subslot_ok;
+        if !slot_ok {
+            issues.push(MetadataIssue::InvalidSlot(self.slot.to_string()));
+        }
+        issues.extend(
+            self.homepage
+                .iter()
+                .filter(|hp| !hp.is_valid())
+                .map(|hp| MetadataIssue::InvalidHomepage(hp.to_string())),
+        );
+        let gated = [
+            (
+                MetadataKey::RequiredUse,
+                self.required_use.is_some(),
+                self.eapi.has_required_use(),
+            ),
+            (
+                MetadataKey::Properties,
+                !self.properties.is_empty(),
+                self.eapi.has_properties(),
+            ),
+            (
+                MetadataKey::Bdepend,
+                !self.bdepend.is_empty(),
+                self.eapi.has_bdepend(),
+            ),
+            (
+                MetadataKey::Idepend,
+                !self.idepend.is_empty(),
+                self.eapi.has_idepend(),
+            ),
+        ];
+        for (key, present, supported) in gated {
+            if present && !supported {
+                issues.push(MetadataIssue::UnsupportedInEapi {
+                    key,
+                    eapi: self.eapi.clone(),
+                });
+            }
+        }
+        for phase in &self.defined_phases {
+            if !phase.is_valid_in(&self.eapi) {
+                issues.push(MetadataIssue::PhaseNotInEapi {
+                    phase: phase.clone(),
+                    eapi: self.eapi.clone(),
+                });
+            }
+        }
+        let live = PropertiesExpr::flat_tokens(&self.properties).contains(&&PropertyKind::Live);
+        let keyworded = self
+            .keywords
+            .iter()
+            .any(|k| matches!(k.stability, Stability::Stable | Stability::Testing));
+        if live && keyworded {
+            issues.push(MetadataIssue::LiveWithKeywords);
+        }
+        for token in RestrictExpr::flat_tokens(&self.restrict) {
+            if let Some(suggestion) = token.suggestion() {
+                issues.push(MetadataIssue::MisspelledRestrict {
+                    token: token.to_string(),
+                    suggestion,
+                });
+            }
+        }
+        issues.extend(
+            Keyword::check(&self.keywords)
+                .into_iter()
+                .map(MetadataIssue::Keyword),
+        );
+        issues
+    }
+
+    /// A compact one-line summary for tabular listings.
+    ///
+    /// Columns are `EAPI`, `SLOT`, `KEYWORDS` and `DESCRIPTION`, padded to
+    /// line up with [`MetadataRow::HEADER`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, MetadataRow};
+    ///
+    /// let entry = CacheEntry::parse("EAPI=8\nDESCRIPTION=A tool\nSLOT=0\nKEYWORDS=~amd64\n").unwrap();
+    /// assert_eq!(
+    ///     entry.metadata.row().to_string(),
+    ///     "8    0          ~amd64                    A tool"
+    /// );
+    /// println!("{}", MetadataRow::HEADER);
+    /// ```
+    pub fn row(&self) -> MetadataRow<'_, I> {
+        MetadataRow { metadata: self }
+    }
+
+    /// The field for `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, MetadataKey};
+    ///
+    /// let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64 x86\n").unwrap();
+    /// let keywords = entry.metadata.get(MetadataKey::Keywords);
+    /// assert_eq!(keywords.to_string(), "~amd64 x86");
+    /// assert!(entry.metadata.get(MetadataKey::License).is_empty());
+    /// ```
+    pub fn get(&self, key: MetadataKey) -> FieldValue<'_, I> {
+        match key {
+            MetadataKey::Eapi => FieldValue::Eapi(&self.eapi),
+            MetadataKey::Description => FieldValue::Text(&self.description),
+            MetadataKey::Slot => FieldValue::Slot(&self.slot),
+            MetadataKey::Homepage => FieldValue::Homepage(&self.homepage),
+            MetadataKey::SrcUri => FieldValue::SrcUri(&self.src_uri),
+            MetadataKey::License => FieldValue::License(self.license.as_ref()),
+            MetadataKey::Keywords => FieldValue::Keywords(&self.keywords),
+            MetadataKey::Iuse => FieldValue::Iuse(&self.iuse),
+            MetadataKey::RequiredUse => FieldValue::RequiredUse(self.required_use.as_ref()),
+            MetadataKey::Restrict => FieldValue::Restrict(&self.restrict),
+            MetadataKey::Properties => FieldValue::Properties(&self.properties),
+            MetadataKey::Depend => FieldValue::Deps(&self.depend),
+            MetadataKey::Rdepend => FieldValue::Deps(&self.rdepend),
+            MetadataKey::Bdepend => FieldValue::Deps(&self.bdepend),
+            MetadataKey::Pdepend => FieldValue::Deps(&self.pdepend),
+            MetadataKey::Idepend => FieldValue::Deps(&self.idepend),
+            MetadataKey::Inherit => FieldValue::Names(&self.inherit),
+            MetadataKey::Inherited => FieldValue::Names(&self.inherited),
+            MetadataKey::DefinedPhases => FieldValue::Phases(&self.defined_phases),
+        }
+    }
+
+    /// Parse `value` in md5-cache form and store it in the field for `key`.
+    ///
+    /// An empty value clears optional fields; `EAPI` falls back to 0. On
+    /// error the field is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, MetadataKey};
+    ///
+    /// let mut entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\n").unwrap();
+    /// entry.metadata.set(MetadataKey::Slot, "2/2.1").unwrap();
+    /// assert_eq!(entry.metadata.get(MetadataKey::Slot).to_string(), "2/2.1");
+    /// assert!(entry.metadata.set(MetadataKey::Slot, "").is_err());
+    /// ```
+    pub fn set(&mut self, key: MetadataKey, value: &str) -> Result<()> {
+        let invalid = |error| field_error(key, value, error);
+        let optional = (!value.is_empty()).then_some(value);
+        match key {
+            MetadataKey::Eapi => {
+                self.eapi = match optional {
+                    Some(value) => value.parse().map_err(invalid)?,
+                    None => Eapi::Zero,
+                }
+            }
+            MetadataKey::Description => self.description = value.to_string(),
+            MetadataKey::Slot => self.slot = parse_slot(value).map_err(invalid)?,
+            MetadataKey::Homepage => self.homepage = Homepage::parse_line(value),
+            MetadataKey::SrcUri => self.src_uri = SrcUriEntry::parse(value).map_err(invalid)?,
+            MetadataKey::License => {
+                self.license = optional
+                    .map(LicenseExpr::parse_interned)
+                    .transpose()
+                    .map_err(invalid)?
+            }
+            MetadataKey::Keywords => self.keywords = parse_all(key, value, Keyword::parse)?,
+            MetadataKey::Iuse => self.iuse = parse_all(key, value, IUse::parse)?,
+            MetadataKey::RequiredUse => {
+                self.required_use = optional
+                    .map(RequiredUseExpr::parse)
+                    .transpose()
+                    .map_err(invalid)?
+            }
+            MetadataKey::Restrict => self.restrict = RestrictExpr::parse(value).map_err(invalid)?,
+            MetadataKey::Properties => {
+                self.properties = PropertiesExpr::parse(value).map_err(invalid)?
+            }
+            MetadataKey::Depend => self.depend = parse_dep_field(value).map_err(invalid)?,
+            MetadataKey::Rdepend => self.rdepend = parse_dep_field(value).map_err(invalid)?,
+            MetadataKey::Bdepend => self.bdepend = parse_dep_field(value).map_err(invalid)?,
+            MetadataKey::Pdepend => self.pdepend = parse_dep_field(value).map_err(invalid)?,
+            MetadataKey::Idepend => self.idepend = parse_dep_field(value).map_err(invalid)?,
+            MetadataKey::Inherit => {
+                self.inherit = value.split_whitespace().map(Interned::intern).collect()
+            }
+            MetadataKey::Inherited => {
+                self.inherited = value.split_whitespace().map(Interned::intern).collect()
+            }
+            MetadataKey::DefinedPhases => {
+                self.defined_phases = Phase::parse_line(value).map_err(invalid)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse `value` in md5-cache form into the field for `key`, checked
+    /// as `strictness` asks against the current `EAPI`.
+    ///
+    /// Every error is passed to `report` with the slice of `value` it
+    /// concerns. A failed field takes the fallback documented on
+    /// [`ParseReport`](crate::ParseReport); invalid tokens of a list are
+    /// dropped.
+    pub(crate) fn parse_field<'a>(
+        &mut self,
+        key: MetadataKey,
+        value: &'a str,
+        strictness: Strictness,
+        report: &mut impl FnMut(&'a str, Error),
+    ) {
+        let strict = strictness.is_strict();
+        let eapi = self.eapi.clone();
+        let rules = strictness.rules(&eapi);
+        let names = || value.split_whitespace().map(Interned::intern).collect();
+        match key {
+            MetadataKey::Eapi => {
+                self.eapi = if value.is_empty() {
+                    Eapi::Zero
+                } else {
+                    keep(value, value.parse(), report).unwrap_or(Eapi::Zero)
+                }
+            }
+            MetadataKey::Description => self.description = value.to_string(),
+            MetadataKey::Slot => {
+                self.slot = keep(value, parse_slot(value), report).unwrap_or_else(|| Slot::new("0"))
+            }
+            MetadataKey::Homepage => {
+                self.homepage = if strict {
+                    parse_tokens(value, Homepage::parse_strict, report)
+                } else {
+                    Homepage::parse_line(value)
+                }
+            }
+            MetadataKey::SrcUri => {
+                self.src_uri = if value.is_empty() {
+                    Vec::new()
+                } else if strict {
+                    keep(value, SrcUriEntry::parse_in(value, &rules), report).unwrap_or_default()
+                } else {
+                    keep(value, SrcUriEntry::parse(value), report).unwrap_or_default()
+                }
+            }
+            MetadataKey::License => {
+                self.license = if value.is_empty() {
+                    None
+                } else {
+                    keep(value, LicenseExpr::parse_interned(value), report)
+                }
+            }
+            MetadataKey::Keywords => self.keywords = parse_tokens(value, Keyword::parse, report),
+            MetadataKey::Iuse => {
+                let parse = if strict {
+                    IUse::parse_strict
+                } else {
+                    IUse::parse
+                };
+                self.iuse = parse_tokens(value, parse, report)
+            }
+            MetadataKey::RequiredUse => {
+                self.required_use = if value.is_empty() {
+                    None
+                } else {
+                    keep(
+                        value,
+                        RequiredUseExpr::parse(value).and_then(|expr| {
+                            if strict {
+                                expr.validate_in(&rules)?;
+                            }
+                            Ok(expr)
+                        }),
+                        report,
+                    )
+                }
+            }
+            MetadataKey::Restrict => {
+                self.restrict = if value.is_empty() {
+                    Vec::new()
+                } else if strict {
+                    keep(value, RestrictExpr::parse_in(value, &rules), report).unwrap_or_default()
+                } else {
+                    keep(value, RestrictExpr::parse(value), report).unwrap_or_default()
+                }
+            }
+            MetadataKey::Properties => {
+                self.properties = if value.is_empty() {
+                    Vec::new()
+                } else if strict {
+                    keep(value, PropertiesExpr::parse_in(value, &rules), report).unwrap_or_default()
+                } else {
+                    keep(value, PropertiesExpr::parse(value), report).unwrap_or_default()
+                }
+            }
+            MetadataKey::Depend => {
+                self.depend = keep(value, parse_dep_field(value), report).unwrap_or_default()
+            }
+            MetadataKey::Rdepend => {
+                self.rdepend = keep(value, parse_dep_field(value), report).unwrap_or_default()
+            }
+            MetadataKey::Bdepend => {
+                self.bdepend = keep(value, parse_dep_field(value), report).unwrap_or_default()
+            }
+            MetadataKey::Pdepend => {
+                self.pdepend = keep(value, parse_dep_field(value), report).unwrap_or_default()
+            }
+            MetadataKey::Idepend => {
+                self.idepend = keep(value, parse_dep_field(value), report).unwrap_or_default()
+            }
+            MetadataKey::Inherit => self.inherit = names(),
+            MetadataKey::Inherited => self.inherited = names(),
+            MetadataKey::DefinedPhases => {
+                self.defined_phases =
+                    keep(value, Phase::parse_line(value), report).unwrap_or_default();
+                if strict {
+                    let tokens = Tokens::new(value);
+                    for (phase, token) in self.defined_phases.iter().zip(tokens.iter()) {
+                        let error = match phase {
+                            Phase::Other(name) => Phase::invalid(name),
+                            _ if !rules.phases().contains(phase) => {
+                                rules.unsupported(format!("{phase} phase"))
+                            }
+                            _ => continue,
+                        };
+                        report(token, error);
+                    }
+                }
+            }
+        }
+    }
+
+    /// USE flags referenced by the metadata but not declared in `IUSE`.
+    ///
+    /// Collects the flags used by USE-conditional groups in `SRC_URI`,
+    /// `LICENSE`, `RESTRICT`, `PROPERTIES` and the dependency classes,
+    /// conditional USE dependencies (`[flag?]`, `[flag=]`), and every flag
+    /// mentioned in `REQUIRED_USE`. Flags listed in `implicit` (IUSE_IMPLICIT,
+    /// USE_EXPAND_IMPLICIT expansions, etc.) count as declared.
+    ///
+    /// The result is sorted and free of duplicates.
+    ///
+    /// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let entry = CacheEntry::parse(
+    ///     "DESCRIPTION=x\nSLOT=0\nIUSE=ssl\nRDEPEND=ssl? ( dev-libs/openssl ) doc? ( app-doc/x )\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(entry.metadata.undeclared_flags(&[]), vec!["doc"]);
+    /// ```
+    pub fn undeclared_flags(&self, implicit: &[&str]) -> Vec<&str> {
+        let declared: BTreeSet<&str> = self.iuse.iter().map(|i| i.name()).collect();
+        self.referenced_flags()
+            .into_iter()
+            .filter(|flag| !declared.contains(flag) && !implicit.contains(flag))
+            .collect()
+    }
+
+    /// `IUSE` flags not referenced anywhere in the metadata, in `IUSE`
+    /// order.
+    ///
+    /// References are collected as for [`EbuildMetadata::undeclared_flags`].
+    /// A flag may still be used by the ebuild's phase functions, which the
+    /// cache does not record.
+    ///
+    /// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let entry = CacheEntry::parse(
+    ///     "DESCRIPTION=x\nSLOT=0\nIUSE=ssl +doc\nRDEPEND=ssl? ( dev-libs/openssl )\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(entry.metadata.unused_flags(), vec!["doc"]);
+    /// ```
+    pub fn unused_flags(&self) -> Vec<&str> {
+        let referenced = self.referenced_flags();
+        self.iuse
+            .iter()
+            .map(|i| i.name())
+            .filter(|flag| !referenced.contains(flag))
+            .collect()
+    }
+
+    fn referenced_flags(&self) -> BTreeSet<&str> {
+        let mut referenced = BTreeSet::new();
+        SrcUriEntry::collect_flags(&self.src_uri, &mut referenced);
+        if let Some(license) = &self.license {
+            license.collect_flags(&mut referenced);
+        }
+        if let Some(required_use) = &self.required_use {
+            required_use.collect_flags(&mut referenced);
+        }
+        RestrictExpr::collect_flags(&self.restrict, &mut referenced);
+        PropertiesExpr::collect_flags(&self.properties, &mut referenced);
+        for (_, deps) in self.dependencies() {
+            DepSpec::from_deps(deps).visit(&mut |node| match *node {
+                DepSpec::Leaf(dep) => referenced.extend(conditional_use_deps(dep)),
+                DepSpec::UseConditional { flag, .. } => {
+                    referenced.insert(flag);
+                }
+                _ => {}
+            });
+        }
+        referenced
+    }
+
+    /// Total size in bytes of the distfiles needed under `use_flags`.
+    ///
+    /// Each distinct filename is counted once, using its size from
+    /// `manifest`; files without a Manifest entry count as zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, Manifest, UseFlagSet};
+    ///
+    /// let entry = CacheEntry::parse(
+    ///     "DESCRIPTION=x\nSLOT=0\nSRC_URI=https://e.com/a.tgz doc? ( https://e.com/d.tgz )\n",
+    /// )
+    /// .unwrap();
+    /// let manifest = Manifest::parse("DIST a.tgz 100 SHA512 0\nDIST d.tgz 20 SHA512 1\n").unwrap();
+    /// assert_eq!(entry.metadata.download_size(&UseFlagSet::new(), &manifest), 100);
+    /// assert_eq!(entry.metadata.download_size(&UseFlagSet::parse("doc"), &manifest), 120);
+    /// ```
+    pub fn download_size(&self, use_flags: &UseFlagSet, manifest: &Manifest) -> u64 {
+        let files: BTreeSet<&str> = self
+            .distfile_access(use_flags)
+            .into_iter()
+            .map(|access| access.filename)
+            .collect();
+        files
+            .into_iter()
+            .filter_map(|file| manifest.get(file))
+            .map(|entry| entry.size)
+            .sum()
+    }
+
+    /// Mark the package stable on `arch`, as `ekeyword arch` does.
+    ///
+    /// Replaces any `~arch` or `-arch` entry, adding one if the arch is not
+    /// listed, and leaves the keywords in [`Keyword::sort_canonical`] order.
+    /// Returns `false`, touching nothing, if the arch already had exactly
+    /// that keyword; fails if `arch` is not a plain arch name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let mut entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~x86 ~amd64\n").unwrap();
+    /// assert!(entry.metadata.stabilize("amd64").unwrap());
+    /// assert!(!entry.metadata.stabilize("amd64").unwrap());
+    /// assert!(entry.serialize().contains("KEYWORDS=amd64 ~x86\n"));
+    /// ```
+    pub fn stabilize(&mut self, arch: &str) -> Result<bool> {
+        self.edit_keyword(arch, Some(Stability::Stable))
+    }
+
+    /// Keyword the package for testing on `arch`, as `ekeyword ~arch` does.
+    ///
+    /// Replaces any existing entry for the arch, including a stable one.
+    /// See [`EbuildMetadata::stabilize`] for ordering and the return value.
+    pub fn add_testing_keyword(&mut self, arch: &str) -> Result<bool> {
+        self.edit_keyword(arch, Some(Stability::Testing))
+    }
+
+    /// Remove every entry for `arch`, as `ekeyword ^arch` does.
+    ///
+    /// See [`EbuildMetadata::stabilize`] for the return value.
+    pub fn drop_keyword(&mut self, arch: &str) -> Result<bool> {
+        self.edit_keyword(arch, None)
+    }
+
+    fn edit_keyword(&mut self, arch: &str, stability: Option<Stability>) -> Result<bool> {
+        let keyword = Keyword::<I>::parse(arch)?;
+        if keyword.stability != Stability::Stable {
+            return Err(Error::InvalidKeyword(arch.to_string()));
+        }
+        let current: Vec<Stability> = self
+            .keywords
+            .iter()
+            .filter(|k| k.arch.as_str() == arch)
+            .map(|k| k.stability)
+            .collect();
+        if current == Vec::from_iter(stability) {
+            return Ok(false);
+        }
+        self.keywords.retain(|k| k.arch.as_str() != arch);
+        if let Some(stability) = stability {
+            self.keywords.push(Keyword {
+                stability,
+                ..keyword
+            });
+        }
+        Keyword::sort_canonical(&mut self.keywords);
+        Ok(true)
+    }
+
+    /// A fingerprint of the metadata, stable across runs and platforms.
+    ///
+    /// Computed with 64-bit FNV-1a over the md5-cache form of every
+    /// non-empty field, so it can be stored to detect changes between
+    /// repository snapshots. Unlike [`Hash`], which suits in-process maps,
+    /// the value does not depend on the hasher or the Rust version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::CacheEntry;
+    ///
+    /// let a = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64\n").unwrap();
+    /// let mut b = a.clone();
+    /// assert_eq!(a.metadata.content_digest(), b.metadata.content_digest());
+    /// b.metadata.stabilize("amd64").unwrap();
+    /// assert_ne!(a.metadata.content_digest(), b.metadata.content_digest());
+    /// ```
+    pub fn content_digest(&self) -> u64 {
+        let mut text = String::new();
+        for &key in MetadataKey::ALL {
+            let value = self.get(key);
+            if !value.is_empty() {
+                // Writing to a String cannot fail.
+                let _ = writeln!(text, "{key}={value}");
+            }
+        }
+        fnv1a(text.as_bytes())
+    }
+
+    /// `KEYWORDS` indexed by arch.
+    pub fn keyword_set(&self) -> KeywordSet {
+        self.keywords.iter().collect()
+    }
+
+    /// Every dependency class with its entries, in
+    /// [`MetadataKey::DEPENDENCIES`] order.
+    ///
+    /// See [PMS 8.1](https://projects.gentoo.org/pms/9/pms.html#dependency-classes).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{CacheEntry, MetadataKey};
+    ///
+    /// let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nRDEPEND=dev-libs/a\n").unwrap();
+    /// let classes = entry.metadata.dependencies();
+    /// assert_eq!(classes[1].0, MetadataKey::Rdepend);
+    /// assert_eq!(classes[1].1.len(), 1);
+    /// ```
+    pub fn dependencies(&self) -> [(MetadataKey, &[DepEntry]); 5] {
+        [
+            (MetadataKey::Depend, &self.depend),
+            (MetadataKey::Rdepend, &self.rdepend),
+            (MetadataKey::Bdepend, &self.bdepend),
+            (MetadataKey::Pdepend, &self.pdepend),
+            (MetadataKey::Idepend, &self.idepend),
+        ]
+    }
+
+    /// `DEFINED_PHASES` as a [`PhaseSet`], ignoring order and duplicates.
+    ///
+    /// Unknown phases ([`Phase::Other`]) are dropped.
+    pub fn phase_set(&self) -> PhaseSet {
+        self.defined_phases.iter().collect()
+    }
+
+    /// Fetch and mirror permissions of the distfiles active under `use_flags`.
+    ///
+    /// See [`DistfileAccess::compute`].
+    pub fn distfile_access(&self, use_flags: &UseFlagSet) -> Vec<DistfileAccess<'_>> {
+        DistfileAccess::compute(&self.src_uri, &self.restrict, use_flags)
+    }
+}
+
+/// The flags of the conditional USE deps of `dep`, such as `ssl?` or `doc=`.
+fn conditional_use_deps(dep: &Dep) -> impl Iterator<Item = &str> {
+    dep.use_deps
+        .iter()
+        .flatten()
+        .filter(|use_dep| {
+            matches!(
+                use_dep.kind,
+                UseDepKind::Conditional
+                    | UseDepKind::ConditionalInverse
+                    | UseDepKind::Equal
+                    | UseDepKind::EqualInverse
+            )
+        })
+        .map(|use_dep| use_dep.flag.as_str())
+}
+
+/// A problem found by [`EbuildMetadata::validate`].
+///
+/// See [PMS 7](https://projects.gentoo.org/pms/9/pms.html#ebuilddefined-variables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataIssue {
+    /// `DESCRIPTION` is empty.
+    EmptyDescription,
+    /// `SLOT` or its sub-slot is not a valid slot name.
+    ///
+    /// See [PMS 3.1.3](https://projects.gentoo.org/pms/9/pms.html#slot-names).
+    InvalidSlot(String),
+    /// A `HOMEPAGE` entry is not a valid URL.
+    InvalidHomepage(String),
+    /// A field is set that the EAPI does not support.
+    UnsupportedInEapi {
+        /// The offending field.
+        key: MetadataKey,
+        /// The ebuild's EAPI.
+        eapi: Eapi,
+    },
+    /// `DEFINED_PHASES` lists a phase the EAPI does not have.
+    PhaseNotInEapi {
+        /// The offending phase.
+        phase: Phase,
+        /// The ebuild's EAPI.
+        eapi: Eapi,
+    },
+    /// `PROPERTIES` contains `live` but the ebuild has stable or testing
+    /// keywords.
+    LiveWithKeywords,
+    /// A `RESTRICT` token that looks like a misspelled known token.
+    MisspelledRestrict {
+        /// The token as written.
+        token: String,
+        /// The known token it most likely means.
+        suggestion: RestrictKind,
+    },
+    /// A problem within `KEYWORDS`.
+    Keyword(KeywordIssue),
+}
+
+impl fmt::Display for MetadataIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetadataIssue::EmptyDescription => f.write_str("DESCRIPTION is empty"),
+            MetadataIssue::InvalidSlot(slot) => write!(f, "invalid SLOT: {slot}"),
+            MetadataIssue::InvalidHomepage(value) => write!(f, "invalid HOMEPAGE: {value}"),
+            MetadataIssue::UnsupportedInEapi { key, eapi } => {
+                write!(f, "{key} is not supported in EAPI {eapi}")
+            }
+            MetadataIssue::PhaseNotInEapi { phase, eapi } => {
+                write!(f, "phase {phase} is not available in EAPI {eapi}")
+            }
+            MetadataIssue::LiveWithKeywords => {
+                f.write_str("live ebuild has stable or testing KEYWORDS")
+            }
+            MetadataIssue::MisspelledRestrict { token, suggestion } => {
+                write!(
+                    f,
+                    "unknown RESTRICT token {token} (did you mean `{suggestion}`?)"
+                )
+            }
+            MetadataIssue::Keyword(issue) => write!(f, "KEYWORDS: {issue}"),
+        }
+    }
+}
+
+impl<I: Interner> Hash for EbuildMetadata<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let EbuildMetadata {
+            eapi,
+            description,
+            slot,
+            homepage,
+            src_uri,
+            license,
+            keywords,
+            iuse,
+            required_use,
+            restrict,
+            properties,
+            depend,
+            rdepend,
+            bdepend,
+            pdepend,
+            idepend,
+            inherit,
+            inherited,
+            defined_phases,
+        } = self;
+        eapi.hash(state);
+        description.hash(state);
+        slot.hash(state);
+        homepage.hash(state);
+        src_uri.hash(state);
+        license.hash(state);
+        keywords.hash(state);
+        iuse.hash(state);
+        required_use.hash(state);
+        restrict.hash(state);
+        properties.hash(state);
+        depend.hash(state);
+        rdepend.hash(state);
+        bdepend.hash(state);
+        pdepend.hash(state);
+        idepend.hash(state);
+        inherit.hash(state);
+        inherited.hash(state);
+        defined_phases.hash(state);
+    }
+}
+
+/// 64-bit FNV-1a, used for [`EbuildMetadata::content_digest`] and
+/// [`CacheEntry::content_digest`](crate::CacheEntry::content_digest).
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Sort entries by their `Display` text and drop exact repeats.
+fn sort_by_text<C, T>(entries: &mut C)
+where
+    C: Default + IntoIterator<Item = T> + Extend<T>,
+    T: fmt::Display,
+{
+    let mut keyed: Vec<(String, T)> = std::mem::take(entries)
+        .into_iter()
+        .map(|e| (e.to_string(), e))
+        .collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.dedup_by(|a, b| a.0 == b.0);
+    entries.extend(keyed.into_iter().map(|(_, e)| e));
+}
+
+/// Multi-line, human-readable rendering: one `KEY: value` line per
+/// non-empty field, in [`MetadataKey::ALL`] order.
+///
+/// For the md5-cache form use [`CacheEntry::serialize`](crate::CacheEntry::serialize).
+impl<I: Interner> fmt::Display for EbuildMetadata<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &key in MetadataKey::ALL {
+            let value = self.get(key);
+            if value.is_empty() {
+                continue;
+            }
+            let pad = LABEL_WIDTH - key.as_str().len();
+            writeln!(f, "{key}:{:pad$}{value}", "")?;
+        }
+        Ok(())
+    }
+}
+
+// Wide enough for `DEFINED_PHASES:` plus one space.
+const LABEL_WIDTH: usize = 15;
+
+/// One-line summary of an [`EbuildMetadata`], from [`EbuildMetadata::row`].
+///
+/// See [PMS 7.3.1](https://projects.gentoo.org/pms/9/pms.html#eapi) for `EAPI`,
+/// [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables)
+/// for `SLOT` and `DESCRIPTION`, and
+/// [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords) for `KEYWORDS`.
+#[derive(Debug, Clone, Copy)]
+pub struct MetadataRow<'a, I = DefaultInterner>
+where
+    I: Interner,
+{
+    metadata: &'a EbuildMetadata<I>,
+}
+
+impl MetadataRow<'_> {
+    /// Column titles matching the `Display` layout.
+    pub const HEADER: &'static str = "EAPI SLOT       KEYWORDS                  DESCRIPTION";
+}
+
+impl<I: Interner> fmt::Display for MetadataRow<'_, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = self.metadata;
+        let keywords = m.get(MetadataKey::Keywords).to_string();
+        write!(
+            f,
+            "{:<4} {:<10} {:<25} {}",
+            m.eapi.to_string(),
+            m.slot.to_string(),
+            keywords,
+            m.description
+        )
+    }
+}
+
+// Report the error of `result`, if any, against all of `value`, and
+// return its value.
+fn keep<'a, T>(
+    value: &'a str,
+    result: Result<T>,
+    report: &mut impl FnMut(&'a str, Error),
+) -> Option<T> {
+    result.map_err(|error| report(value, error)).ok()
+}
+
+/// `error` for the slice `text` of the value of `key`.
+fn field_error(key: MetadataKey, text: &str, error: Error) -> Error {
+    Error::Field {
+        field: key.to_string(),
+        text: text.to_string(),
+        span: None,
+        source: Box::new(error),
+    }
+}
+
+// Parse each token of `value` with `parse`, failing on the first invalid
+// one.
+fn parse_all<'a, T, C: FromIterator<T>>(
+    key: MetadataKey,
+    value: &'a str,
+    parse: impl Fn(&'a str) -> Result<T>,
+) -> Result<C> {
+    Tokens::new(value)
+        .iter()
+        .map(|token| parse(token).map_err(|error| field_error(key, token, error)))
+        .collect()
+}
+
+// Parse each token of `value` with `parse`, reporting and dropping the
+// invalid ones.
+fn parse_tokens<'a, T, C: FromIterator<T>>(
+    value: &'a str,
+    parse: impl Fn(&'a str) -> Result<T>,
+    report: &mut impl FnMut(&'a str, Error),
+) -> C {
+    Tokens::new(value)
+        .iter()
+        .filter_map(|token| parse(token).map_err(|error| report(token, error)).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::CacheEntry;
+    use crate::eapi::Eapi;
+    use crate::error::Error;
+    use crate::keyword::KeywordIssue;
+    use crate::manifest::Manifest;
+    use crate::metadata::MetadataIssue;
+    use crate::metadata_key::MetadataKey;
+    use crate::phase::Phase;
+    use crate::restrict::RestrictKind;
+    use crate::use_flags::UseFlagSet;
+    use portage_atom::Slot;
+
+    #[test]
+    fn undeclared_flags_all_fields() {
+        let input = "\
+DESCRIPTION=Test
+SLOT=0
+IUSE=ssl test
+SRC_URI=doc? ( https://example.com/doc.tar.gz )
+LICENSE=bindist? ( MIT )
+REQUIRED_USE=|| ( ssl gnutls )
+RESTRICT=!test? ( test )
+PROPERTIES=live? ( live )
+DEPEND=dev-libs/foo[ssl?,examples=]
+RDEPEND=gnutls? ( net-libs/gnutls )
+";
+        let entry = CacheEntry::parse(input).unwrap();
+        assert_eq!(
+            entry.metadata.undeclared_flags(&[]),
+            vec!["bindist", "doc", "examples", "gnutls", "live"]
+        );
+    }
+
+    #[test]
+    fn undeclared_flags_honours_implicit() {
+        let input = "DESCRIPTION=Test\nSLOT=0\nRDEPEND=elibc_glibc? ( sys-libs/glibc )\n";
+        let entry = CacheEntry::parse(input).unwrap();
+        assert_eq!(entry.metadata.undeclared_flags(&[]), vec!["elibc_glibc"]);
+        assert!(entry.metadata.undeclared_flags(&["elibc_glibc"]).is_empty());
+    }
+
+    #[test]
+    fn unconditional_use_deps_ignored() {
+        let input = "DESCRIPTION=Test\nSLOT=0\nRDEPEND=dev-libs/foo[bar,-baz]\n";
+        let entry = CacheEntry::parse(input).unwrap();
+        assert!(entry.metadata.undeclared_flags(&[]).is_empty());
+    }
+
+    #[test]
+    fn display_skips_empty_fields() {
+        let entry =
+            CacheEntry::parse("EAPI=8\nDESCRIPTION=Test\nSLOT=0\nIUSE=+ssl\nDEFINED_PHASES=-\n")
+                .unwrap();
+        assert_eq!(
+            entry.metadata.to_string(),
+            "\
+EAPI:           8
+DESCRIPTION:    Test
+SLOT:           0
+IUSE:           +ssl
+"
+        );
+    }
+
+    #[test]
+    fn normalize_is_canonical() {
+        let a = "\
+DESCRIPTION=Test
+SLOT=0
+HOMEPAGE=https://b.org https://a.org
+KEYWORDS=~x86 amd64 -*
+IUSE=test +ssl
+LICENSE=MIT || ( BSD )
+RDEPEND=dev-libs/b ssl? ( dev-libs/openssl ) dev-libs/a
+DEFINED_PHASES=install compile
+";
+        let b = "\
+DESCRIPTION=Test
+SLOT=0
+HOMEPAGE=https://a.org https://b.org https://a.org
+KEYWORDS=-* amd64 ~x86 amd64
+IUSE=ssl +ssl test
+LICENSE=BSD MIT
+RDEPEND=dev-libs/a dev-libs/b ssl? ( dev-libs/openssl ) dev-libs/a
+DEFINED_PHASES=compile install install
+";
+        let mut a = CacheEntry::parse(a).unwrap();
+        let mut b = CacheEntry::parse(b).unwrap();
+        a.metadata.normalize();
+        b.metadata.normalize();
+        assert_eq!(a.metadata, b.metadata);
+        assert_eq!(
+            a.metadata.get(MetadataKey::Rdepend).to_string(),
+            "dev-libs/a dev-libs/b ssl? ( dev-libs/openssl )"
+        );
+        assert_eq!(
+            a.metadata.get(MetadataKey::Keywords).to_string(),
+            "-* amd64 ~x86"
+        );
+        assert_eq!(a.serialize(), b.serialize());
+    }
+
+    #[test]
+    fn validate_cross_field() {
+        let input = "\
+EAPI=7
+DESCRIPTION=
+SLOT=0
+HOMEPAGE=https://ok.org example.org
+KEYWORDS=~amd64 ~amd64
+PROPERTIES=live
+RESTRICT=mirrors bindist
+IDEPEND=dev-libs/a
+DEFINED_PHASES=compile pretend frobnicate
+";
+        let mut entry = CacheEntry::parse(input).unwrap();
+        entry.metadata.slot = Slot::new("-bad");
+        let issues = entry.metadata.validate();
+        assert_eq!(
+            issues,
+            vec![
+                MetadataIssue::EmptyDescription,
+                MetadataIssue::InvalidSlot("-bad".to_string()),
+                MetadataIssue::InvalidHomepage("example.org".to_string()),
+                MetadataIssue::UnsupportedInEapi {
+                    key: MetadataKey::Idepend,
+                    eapi: Eapi::Seven,
+                },
+                MetadataIssue::PhaseNotInEapi {
+                    phase: Phase::Other("frobnicate".to_string()),
+                    eapi: Eapi::Seven,
+                },
+                MetadataIssue::LiveWithKeywords,
+                MetadataIssue::MisspelledRestrict {
+                    token: "mirrors".to_string(),
+                    suggestion: RestrictKind::Mirror,
+                },
+                MetadataIssue::Keyword(KeywordIssue::Duplicate {
+                    first: 0,
+                    second: 1
+                }),
+            ]
+        );
+
+        let ok = CacheEntry::parse("EAPI=8\nDESCRIPTION=x\nSLOT=0/1\nKEYWORDS=~amd64\n").unwrap();
+        assert!(ok.metadata.validate().is_empty());
+    }
+
+    #[test]
+    fn keyword_edits() {
+        let mut entry =
+            CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~x86 -* ~amd64 ~amd64\n").unwrap();
+        let m = &mut entry.metadata;
+        assert!(m.stabilize("amd64").unwrap());
+        assert_eq!(m.get(MetadataKey::Keywords).to_string(), "-* amd64 ~x86");
+        assert!(m.add_testing_keyword("arm64").unwrap());
+        assert!(m.add_testing_keyword("amd64").unwrap());
+        assert_eq!(
+            m.get(MetadataKey::Keywords).to_string(),
+            "-* ~amd64 ~arm64 ~x86"
+        );
+        assert!(m.drop_keyword("x86").unwrap());
+        assert!(!m.drop_keyword("x86").unwrap());
+        assert!(!m.add_testing_keyword("arm64").unwrap());
+        assert_eq!(m.get(MetadataKey::Keywords).to_string(), "-* ~amd64 ~arm64");
+        assert!(m.stabilize("~amd64").is_err());
+        assert!(m.drop_keyword("*").is_err());
+    }
+
+    #[test]
+    fn hash_and_digest() {
+        use std::collections::HashSet;
+
+        let a = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nIUSE=ssl\nKEYWORDS=~amd64\n").unwrap();
+        let b = CacheEntry::parse("KEYWORDS=~amd64\nIUSE=ssl\nSLOT=0\nDESCRIPTION=x\n").unwrap();
+        let c = CacheEntry::parse("DESCRIPTION=x\nSLOT=1\nIUSE=ssl\nKEYWORDS=~amd64\n").unwrap();
+        let set: HashSet<_> = [&a.metadata, &b.metadata, &c.metadata]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 2);
+        assert_eq!(a.metadata.content_digest(), b.metadata.content_digest());
+        assert_ne!(a.metadata.content_digest(), c.metadata.content_digest());
+        // Pinned so that stored digests stay valid across releases.
+        assert_eq!(super::fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(super::fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn get_set_round_trip() {
+        let input = "\
+EAPI=8
+DESCRIPTION=Test
+SLOT=0/1
+IUSE=+ssl test
+RESTRICT=!test? ( test )
+RDEPEND=ssl? ( dev-libs/openssl:= )
+DEFINED_PHASES=compile install
+";
+        let entry = CacheEntry::parse(input).unwrap();
+        let mut copy = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\n").unwrap();
+        for key in MetadataKey::ALL {
+            let value = entry.metadata.get(*key).to_string();
+            copy.metadata.set(*key, &value).unwrap();
+        }
+        assert_eq!(copy.metadata, entry.metadata);
+
+        let err = copy.metadata.set(MetadataKey::License, "|| (").unwrap_err();
+        assert_eq!(err.field(), Some("LICENSE"));
+        assert_eq!(copy.metadata.license, entry.metadata.license);
+
+        let err = copy
+            .metadata
+            .set(MetadataKey::Keywords, "amd64 %x86")
+            .unwrap_err();
+        assert!(matches!(err, Error::Field { ref text, .. } if text == "%x86"));
+        assert_eq!(copy.metadata.keywords, entry.metadata.keywords);
+    }
+
+    #[test]
+    fn download_size_counts_each_file_once() {
+        let input = "\
+DESCRIPTION=Test
+SLOT=0
+SRC_URI=https://a.org/x.tgz mirror://gnu/x.tgz https://a.org/unlisted.tgz
+";
+        let entry = CacheEntry::parse(input).unwrap();
+        let manifest = Manifest::parse("DIST x.tgz 42 SHA512 0\n").unwrap();
+        assert_eq!(
+            entry.metadata.download_size(&UseFlagSet::new(), &manifest),
+            42
+        );
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn inline_lists_stay_small() {
+        use crate::keyword::Keyword;
+        use crate::metadata::EbuildMetadata;
+        use smallvec::SmallVec;
+        use std::mem::size_of;
+
+        // Inline keywords cost at most their own size over a `Vec`;
+        // homepages and phases are too large to store inline.
+        assert!(
+            size_of::<SmallVec<[Keyword; 8]>>()
+                <= size_of::<Vec<Keyword>>() + 8 * size_of::<Keyword>()
+        );
+        assert!(size_of::<EbuildMetadata>() <= 576);
+        assert!(size_of::<CacheEntry>() <= 624);
+    }
+}
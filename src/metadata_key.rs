@@ -0,0 +1,251 @@
+use std::fmt;
+use std::str::FromStr;
+
+use portage_atom::{DepEntry, Slot};
+
+use crate::eapi::Eapi;
+use crate::error::{Error, Result};
+use crate::homepage::Homepage;
+use crate::interner::{DefaultInterner, Interned, Interner};
+use crate::iuse::IUse;
+use crate::keyword::Keyword;
+use crate::license::LicenseExpr;
+use crate::phase::Phase;
+use crate::properties::PropertiesExpr;
+use crate::required_use::RequiredUseExpr;
+use crate::restrict::RestrictExpr;
+use crate::src_uri::SrcUriEntry;
+
+/// A metadata variable of [`EbuildMetadata`](crate::EbuildMetadata).
+///
+/// Used with [`EbuildMetadata::get`](crate::EbuildMetadata::get) and
+/// [`EbuildMetadata::set`](crate::EbuildMetadata::set) to handle every
+/// field uniformly. Converts to and from the variable name.
+///
+/// See [PMS 7](https://projects.gentoo.org/pms/9/pms.html#ebuilddefined-variables).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MetadataKey {
+    /// `EAPI`
+    Eapi,
+    /// `DESCRIPTION`
+    Description,
+    /// `SLOT`
+    Slot,
+    /// `HOMEPAGE`
+    Homepage,
+    /// `SRC_URI`
+    SrcUri,
+    /// `LICENSE`
+    License,
+    /// `KEYWORDS`
+    Keywords,
+    /// `IUSE`
+    Iuse,
+    /// `REQUIRED_USE`
+    RequiredUse,
+    /// `RESTRICT`
+    Restrict,
+    /// `PROPERTIES`
+    Properties,
+    /// `DEPEND`
+    Depend,
+    /// `RDEPEND`
+    Rdepend,
+    /// `BDEPEND`
+    Bdepend,
+    /// `PDEPEND`
+    Pdepend,
+    /// `IDEPEND`
+    Idepend,
+    /// `INHERIT`
+    Inherit,
+    /// `INHERITED`
+    Inherited,
+    /// `DEFINED_PHASES`
+    DefinedPhases,
+}
+
+impl MetadataKey {
+    /// Every key, in declaration order.
+    pub const ALL: &'static [MetadataKey] = &[
+        MetadataKey::Eapi,
+        MetadataKey::Description,
+        MetadataKey::Slot,
+        MetadataKey::Homepage,
+        MetadataKey::SrcUri,
+        MetadataKey::License,
+        MetadataKey::Keywords,
+        MetadataKey::Iuse,
+        MetadataKey::RequiredUse,
+        MetadataKey::Restrict,
+        MetadataKey::Properties,
+        MetadataKey::Depend,
+        MetadataKey::Rdepend,
+        MetadataKey::Bdepend,
+        MetadataKey::Pdepend,
+        MetadataKey::Idepend,
+        MetadataKey::Inherit,
+        MetadataKey::Inherited,
+        MetadataKey::DefinedPhases,
+    ];
+
+    /// The dependency classes, in declaration order.
+    ///
+    /// See [PMS 8.1](https://projects.gentoo.org/pms/9/pms.html#dependency-classes).
+    pub const DEPENDENCIES: &'static [MetadataKey] = &[
+        MetadataKey::Depend,
+        MetadataKey::Rdepend,
+        MetadataKey::Bdepend,
+        MetadataKey::Pdepend,
+        MetadataKey::Idepend,
+    ];
+
+    /// The variable name, e.g. `REQUIRED_USE` (same as `Display`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MetadataKey::Eapi => "EAPI",
+            MetadataKey::Description => "DESCRIPTION",
+            MetadataKey::Slot => "SLOT",
+            MetadataKey::Homepage => "HOMEPAGE",
+            MetadataKey::SrcUri => "SRC_URI",
+            MetadataKey::License => "LICENSE",
+            MetadataKey::Keywords => "KEYWORDS",
+            MetadataKey::Iuse => "IUSE",
+            MetadataKey::RequiredUse => "REQUIRED_USE",
+            MetadataKey::Restrict => "RESTRICT",
+            MetadataKey::Properties => "PROPERTIES",
+            MetadataKey::Depend => "DEPEND",
+            MetadataKey::Rdepend => "RDEPEND",
+            MetadataKey::Bdepend => "BDEPEND",
+            MetadataKey::Pdepend => "PDEPEND",
+            MetadataKey::Idepend => "IDEPEND",
+            MetadataKey::Inherit => "INHERIT",
+            MetadataKey::Inherited => "INHERITED",
+            MetadataKey::DefinedPhases => "DEFINED_PHASES",
+        }
+    }
+}
+
+impl fmt::Display for MetadataKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MetadataKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        MetadataKey::ALL
+            .iter()
+            .copied()
+            .find(|key| key.as_str() == s)
+            .ok_or_else(|| Error::InvalidCacheEntry(format!("unknown metadata key: {s}")))
+    }
+}
+
+/// A borrowed view of one field of [`EbuildMetadata`](crate::EbuildMetadata).
+///
+/// `Display` renders the value the way the md5-cache stores it.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValue<'a, I = DefaultInterner>
+where
+    I: Interner,
+{
+    /// `EAPI`.
+    Eapi(&'a Eapi),
+    /// Free text: `DESCRIPTION`.
+    Text(&'a str),
+    /// `SLOT`.
+    Slot(&'a Slot),
+    /// `HOMEPAGE`.
+    Homepage(&'a [Homepage]),
+    /// Interned names: `INHERIT`, `INHERITED`.
+    Names(&'a [Interned<I>]),
+    /// `SRC_URI`.
+    SrcUri(&'a [SrcUriEntry]),
+    /// `LICENSE`.
+    License(Option<&'a LicenseExpr<I>>),
+    /// `KEYWORDS`.
+    Keywords(&'a [Keyword<I>]),
+    /// `IUSE`.
+    Iuse(&'a [IUse<I>]),
+    /// `REQUIRED_USE`.
+    RequiredUse(Option<&'a RequiredUseExpr>),
+    /// `RESTRICT`.
+    Restrict(&'a [RestrictExpr]),
+    /// `PROPERTIES`.
+    Properties(&'a [PropertiesExpr]),
+    /// A dependency class.
+    Deps(&'a [DepEntry]),
+    /// `DEFINED_PHASES`.
+    Phases(&'a [Phase]),
+}
+
+impl<I: Interner> FieldValue<'_, I> {
+    /// Whether the field is unset or empty.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            FieldValue::Eapi(_) | FieldValue::Slot(_) => false,
+            FieldValue::Text(s) => s.is_empty(),
+            FieldValue::Homepage(v) => v.is_empty(),
+            FieldValue::Names(v) => v.is_empty(),
+            FieldValue::SrcUri(v) => v.is_empty(),
+            FieldValue::License(v) => v.is_none(),
+            FieldValue::Keywords(v) => v.is_empty(),
+            FieldValue::Iuse(v) => v.is_empty(),
+            FieldValue::RequiredUse(v) => v.is_none(),
+            FieldValue::Restrict(v) => v.is_empty(),
+            FieldValue::Properties(v) => v.is_empty(),
+            FieldValue::Deps(v) => v.is_empty(),
+            FieldValue::Phases(v) => v.is_empty(),
+        }
+    }
+}
+
+fn write_joined<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(" ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    Ok(())
+}
+
+impl<I: Interner> fmt::Display for FieldValue<'_, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldValue::Eapi(eapi) => write!(f, "{eapi}"),
+            FieldValue::Text(s) => f.write_str(s),
+            FieldValue::Slot(slot) => write!(f, "{slot}"),
+            FieldValue::Homepage(v) => write_joined(f, v),
+            FieldValue::Names(v) => write_joined(f, v),
+            FieldValue::SrcUri(v) => write_joined(f, v),
+            FieldValue::License(v) => v.map_or(Ok(()), |l| write!(f, "{l}")),
+            FieldValue::Keywords(v) => write_joined(f, v),
+            FieldValue::Iuse(v) => write_joined(f, v),
+            FieldValue::RequiredUse(v) => v.map_or(Ok(()), |r| write!(f, "{r}")),
+            FieldValue::Restrict(v) => write_joined(f, v),
+            FieldValue::Properties(v) => write_joined(f, v),
+            FieldValue::Deps(v) => write_joined(f, v),
+            FieldValue::Phases([]) => f.write_str("-"),
+            FieldValue::Phases(v) => write_joined(f, v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        for key in MetadataKey::ALL {
+            assert_eq!(key.as_str().parse::<MetadataKey>().unwrap(), *key);
+        }
+        assert!("_md5_".parse::<MetadataKey>().is_err());
+    }
+}
@@ -0,0 +1,645 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::error::{Error, Result};
+use crate::parse_report::{ParseReport, Strictness};
+use crate::parse_stats::ParseStats;
+use crate::regen_plan::find_ebuilds;
+use crate::timestamp::parse_timestamp;
+
+/// The metadata cache of an ebuild repository.
+///
+/// Entries live in `metadata/md5-cache/<category>/<package>-<version>`
+/// under the repository root. Nothing is read until an entry is asked
+/// for.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataRepo {
+    root: PathBuf,
+}
+
+impl MetadataRepo {
+    /// Use the repository rooted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        MetadataRepo { root: root.into() }
+    }
+
+    /// The repository root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The `metadata/md5-cache` directory.
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("metadata/md5-cache")
+    }
+
+    /// The path of the cache entry for `cpv`.
+    pub fn entry_path(&self, cpv: &Cpv) -> PathBuf {
+        self.cache_dir().join(cpv.to_string())
+    }
+
+    /// Every package version in the cache, sorted.
+    pub fn cpvs(&self) -> Result<Vec<Cpv>> {
+        let mut cpvs: Vec<Cpv> = list_cache_dir(&self.cache_dir())?
+            .into_iter()
+            .map(|(cpv, _)| cpv)
+            .collect();
+        cpvs.sort();
+        Ok(cpvs)
+    }
+
+    /// Read and parse the cache entry for `cpv`.
+    ///
+    /// Parse errors carry `cpv` as [`Error::Package`] context; file access
+    /// failures are reported as [`Error::Io`].
+    pub fn read(&self, cpv: &Cpv) -> Result<CacheEntry> {
+        CacheEntry::parse(&self.read_to_string(cpv)?).map_err(|e| e.in_package(cpv))
+    }
+
+    /// Read the cache entry for `cpv`, collecting every error as
+    /// [`CacheEntry::parse_report`] does.
+    ///
+    /// Only file access failures are returned as errors.
+    pub fn read_report(&self, cpv: &Cpv, strictness: Strictness) -> Result<ParseReport> {
+        Ok(CacheEntry::parse_report(
+            &self.read_to_string(cpv)?,
+            strictness,
+        ))
+    }
+
+    /// Parse every cache entry as [`MetadataRepo::read_report`] does,
+    /// counting the outcomes.
+    ///
+    /// Only file access failures are returned as errors.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use portage_metadata::{MetadataRepo, Strictness};
+    ///
+    /// let repo = MetadataRepo::new("/var/db/repos/gentoo");
+    /// println!("{}", repo.parse_stats(Strictness::Lenient).unwrap());
+    /// ```
+    pub fn parse_stats(&self, strictness: Strictness) -> Result<ParseStats> {
+        let mut stats = ParseStats::new();
+        for cpv in self.cpvs()? {
+            let text = self.read_to_string(&cpv)?;
+            stats.record(text.len(), &CacheEntry::parse_report(&text, strictness));
+        }
+        Ok(stats)
+    }
+
+    /// Read and parse every cache entry.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use portage_metadata::MetadataRepo;
+    ///
+    /// let repo = MetadataRepo::new("/var/db/repos/gentoo");
+    /// for (cpv, entry) in repo.load().unwrap() {
+    ///     println!("{cpv}: {}", entry.metadata.description);
+    /// }
+    /// ```
+    pub fn load(&self) -> Result<BTreeMap<Cpv, CacheEntry>> {
+        self.cpvs()?
+            .into_iter()
+            .map(|cpv| {
+                let entry = self.read(&cpv)?;
+                Ok((cpv, entry))
+            })
+            .collect()
+    }
+
+    /// [`Load`](MetadataRepo::load) every entry and pass them to `write`
+    /// together with a new file at `path`, returning the number of
+    /// entries.
+    ///
+    /// Failures to create or write the file are reported against `path`.
+    pub(crate) fn export(
+        &self,
+        path: &Path,
+        write: impl FnOnce(&mut BufWriter<File>, &BTreeMap<Cpv, CacheEntry>) -> io::Result<()>,
+    ) -> Result<usize> {
+        let entries = self.load()?;
+        let io_error = |e| Error::io(path, e);
+        let mut out = BufWriter::new(File::create(path).map_err(io_error)?);
+        write(&mut out, &entries).map_err(io_error)?;
+        out.flush().map_err(io_error)?;
+        Ok(entries.len())
+    }
+
+    /// Serialize `entry` to the cache entry for `cpv`, creating its
+    /// category directory if needed.
+    ///
+    /// The file is replaced through a hidden temporary file and a rename,
+    /// so readers never see a partial entry.
+    pub fn write(&self, cpv: &Cpv, entry: &CacheEntry) -> Result<()> {
+        write_atomic(&self.entry_path(cpv), &entry.serialize(), None)
+    }
+
+    /// Write every entry across `threads` worker threads, or one per
+    /// available core if `threads` is 0.
+    ///
+    /// Each file's content depends only on its entry, so the resulting
+    /// tree is the same for any thread count. Entries are split into
+    /// contiguous runs in package version order; if writes fail, the error
+    /// for the first failing package version is returned.
+    pub fn write_all(&self, entries: &BTreeMap<Cpv, CacheEntry>, threads: usize) -> Result<()> {
+        let entries: Vec<_> = entries.iter().collect();
+        let chunk = entries.len().div_ceil(worker_count(threads)).max(1);
+        thread::scope(|scope| {
+            let workers: Vec<_> = entries
+                .chunks(chunk)
+                .map(|run| {
+                    scope.spawn(move || {
+                        run.iter()
+                            .try_for_each(|(cpv, entry)| self.write(cpv, entry))
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().expect("cache writer panicked"))
+        })
+    }
+
+    /// Validate every cache entry across worker threads, as
+    /// [`MetadataRepo::read_report`] does for one.
+    ///
+    /// Only entries in `options.categories` are read, if any are given.
+    /// Once `options.max_errors` errors have been found, workers stop
+    /// picking up entries and the reports gathered so far are returned;
+    /// which entries those are then depends on scheduling. File access
+    /// failures abort the run and are returned as errors.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use portage_metadata::{MetadataRepo, ValidateOptions};
+    ///
+    /// let repo = MetadataRepo::new("/var/db/repos/gentoo");
+    /// let options = ValidateOptions {
+    ///     max_errors: Some(10),
+    ///     categories: vec!["dev-libs".to_string()],
+    ///     ..ValidateOptions::default()
+    /// };
+    /// for (cpv, report) in repo.validate_parallel(&options).unwrap() {
+    ///     for error in &report.errors {
+    ///         println!("{cpv}: {error}");
+    ///     }
+    /// }
+    /// ```
+    pub fn validate_parallel(
+        &self,
+        options: &ValidateOptions<'_>,
+    ) -> Result<BTreeMap<Cpv, ParseReport>> {
+        let mut cpvs = self.cpvs()?;
+        if !options.categories.is_empty() {
+            cpvs.retain(|cpv| {
+                options
+                    .categories
+                    .iter()
+                    .any(|category| category == cpv.cpn.category.as_str())
+            });
+        }
+        let max_errors = options.max_errors.unwrap_or(usize::MAX);
+        let (next, errors, abort) = (
+            AtomicUsize::new(0),
+            AtomicUsize::new(0),
+            AtomicBool::new(false),
+        );
+        let work = || {
+            let mut reports = Vec::new();
+            while !abort.load(Ordering::Relaxed) {
+                let Some(cpv) = cpvs.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                    break;
+                };
+                let report = self.read_report(cpv, options.strictness).inspect_err(|_| {
+                    abort.store(true, Ordering::Relaxed);
+                })?;
+                let found = report.errors.len();
+                if found > 0 && errors.fetch_add(found, Ordering::Relaxed) + found >= max_errors {
+                    abort.store(true, Ordering::Relaxed);
+                }
+                reports.push((cpv.clone(), report));
+            }
+            Ok(reports)
+        };
+        let results: Vec<Result<Vec<_>>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..worker_count(options.threads))
+                .map(|_| scope.spawn(work))
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("validation worker panicked"))
+                .collect()
+        });
+        let mut out = BTreeMap::new();
+        for reports in results {
+            out.extend(reports?);
+        }
+        Ok(out)
+    }
+
+    /// When the repository was last updated upstream, as recorded in
+    /// `metadata/timestamp.chk`.
+    ///
+    /// A missing file is reported as [`Error::Io`], an unrecognized date
+    /// as [`Error::InvalidTimestamp`].
+    pub fn timestamp(&self) -> Result<SystemTime> {
+        let path = self.root.join("metadata/timestamp.chk");
+        let text = fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+        parse_timestamp(&text)
+    }
+
+    /// How long ago the repository was last updated upstream; zero if
+    /// [`MetadataRepo::timestamp`] is in the future.
+    pub fn age(&self) -> Result<Duration> {
+        Ok(SystemTime::now()
+            .duration_since(self.timestamp()?)
+            .unwrap_or_default())
+    }
+
+    /// Whether the repository is older than `threshold`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use portage_metadata::MetadataRepo;
+    ///
+    /// let repo = MetadataRepo::new("/var/db/repos/gentoo");
+    /// if repo.is_stale(Duration::from_secs(2 * 24 * 3600)).unwrap() {
+    ///     eprintln!("snapshot is more than two days old");
+    /// }
+    /// ```
+    pub fn is_stale(&self, threshold: Duration) -> Result<bool> {
+        Ok(self.age()? > threshold)
+    }
+
+    /// The cache entries with no ebuild in `ebuild_tree`, sorted; with
+    /// `delete` set, they are also removed from the cache.
+    ///
+    /// `ebuild_tree` is usually [`MetadataRepo::root`], but may be a
+    /// separate checkout when the cache is kept elsewhere. Category
+    /// directories left empty are removed as well.
+    ///
+    /// Deleting is refused with an [`Error::Io`] on `ebuild_tree` if it
+    /// holds no ebuild at all, as a wrong path would otherwise empty the
+    /// whole cache.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use portage_metadata::MetadataRepo;
+    ///
+    /// let repo = MetadataRepo::new("/var/db/repos/overlay");
+    /// for cpv in repo.prune(repo.root(), true).unwrap() {
+    ///     println!("removed {cpv}");
+    /// }
+    /// ```
+    pub fn prune(&self, ebuild_tree: impl AsRef<Path>, delete: bool) -> Result<Vec<Cpv>> {
+        let ebuild_tree = ebuild_tree.as_ref();
+        let ebuilds = find_ebuilds(ebuild_tree)?;
+        if delete && ebuilds.is_empty() {
+            let error = io::Error::new(
+                io::ErrorKind::NotFound,
+                "no ebuilds found, refusing to prune the cache",
+            );
+            return Err(Error::io(ebuild_tree, error));
+        }
+        let orphans: Vec<Cpv> = self
+            .cpvs()?
+            .into_iter()
+            .filter(|cpv| !ebuilds.contains_key(cpv))
+            .collect();
+        if delete {
+            for cpv in &orphans {
+                let path = self.entry_path(cpv);
+                fs::remove_file(&path).map_err(|e| Error::io(&path, e))?;
+                if let Some(dir) = path.parent() {
+                    if read_dir(dir)?.is_empty() {
+                        fs::remove_dir(dir).map_err(|e| Error::io(dir, e))?;
+                    }
+                }
+            }
+        }
+        Ok(orphans)
+    }
+
+    fn read_to_string(&self, cpv: &Cpv) -> Result<String> {
+        let path = self.entry_path(cpv);
+        fs::read_to_string(&path).map_err(|e| Error::io(path, e))
+    }
+}
+
+/// How [`MetadataRepo::validate_parallel`] runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidateOptions<'a> {
+    /// Worker threads, or 0 for one per available core.
+    pub threads: usize,
+    /// Stop after this many errors; `None` validates every entry.
+    pub max_errors: Option<usize>,
+    /// Categories to validate; empty for all.
+    pub categories: Vec<String>,
+    /// How strictly entries are checked, as for
+    /// [`CacheEntry::parse_report`].
+    pub strictness: Strictness<'a>,
+}
+
+/// `threads`, or the available parallelism if it is 0.
+fn worker_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// The entries of a md5-cache directory with their package versions, in
+/// directory order.
+///
+/// Hidden files, such as the temporary files of [`write_atomic`], are
+/// skipped.
+pub(crate) fn list_cache_dir(cache_dir: &Path) -> Result<Vec<(Cpv, PathBuf)>> {
+    scan_cache_dir(cache_dir, &mut Vec::new())
+}
+
+/// [`list_cache_dir`], also adding the hidden files of the categories to
+/// `hidden`.
+pub(crate) fn scan_cache_dir(
+    cache_dir: &Path,
+    hidden: &mut Vec<PathBuf>,
+) -> Result<Vec<(Cpv, PathBuf)>> {
+    let mut entries = Vec::new();
+    for category in read_dir(cache_dir)? {
+        if !category.is_dir() || is_hidden(&category) {
+            continue;
+        }
+        for path in read_dir(&category)? {
+            if is_hidden(&path) {
+                hidden.push(path);
+                continue;
+            }
+            let name = path.strip_prefix(cache_dir).unwrap_or(&path);
+            let name = name.to_string_lossy();
+            let cpv = Cpv::parse(&name)
+                .map_err(|_| Error::InvalidCacheEntry(format!("not a package version: {name}")))?;
+            entries.push((cpv, path));
+        }
+    }
+    Ok(entries)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
+}
+
+/// Write `text` to `path`, creating its directory if needed.
+///
+/// The text goes to a hidden temporary file next to `path`, which is
+/// given `mtime` if set and then renamed over `path`, so readers never see
+/// a partial file. The temporary file is removed if any step fails.
+pub(crate) fn write_atomic(path: &Path, text: &str, mtime: Option<SystemTime>) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = dir.join(format!(".{name}.{}.tmp", process::id()));
+    let result = write_file(&tmp, text, mtime)
+        .and_then(|()| fs::rename(&tmp, path).map_err(|e| Error::io(path, e)));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn write_file(path: &Path, text: &str, mtime: Option<SystemTime>) -> Result<()> {
+    let io_error = |e| Error::io(path, e);
+    let mut file = File::create(path).map_err(io_error)?;
+    file.write_all(text.as_bytes()).map_err(io_error)?;
+    if let Some(mtime) = mtime {
+        file.set_modified(mtime).map_err(io_error)?;
+    }
+    Ok(())
+}
+
+/// The paths in `dir`, in directory order.
+pub(crate) fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let io_error = |e| Error::io(dir, e);
+    fs::read_dir(dir)
+        .map_err(io_error)?
+        .map(|entry| entry.map(|entry| entry.path()).map_err(io_error))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn cpv(s: &str) -> Cpv {
+        Cpv::parse(s).unwrap()
+    }
+
+    #[test]
+    fn read_cache() {
+        let root = TempDir::new("metadata-repo");
+        let repo = MetadataRepo::new(&root);
+        let cache = repo.cache_dir();
+        fs::create_dir_all(cache.join("app-misc")).unwrap();
+        fs::create_dir_all(cache.join("dev-libs")).unwrap();
+        fs::write(cache.join("dev-libs/bar-2"), "DESCRIPTION=bar\nSLOT=2\n").unwrap();
+        fs::write(cache.join("app-misc/foo-1.10"), "DESCRIPTION=foo\nSLOT=0\n").unwrap();
+        fs::write(cache.join("app-misc/foo-1.9"), "DESCRIPTION=foo\n").unwrap();
+
+        assert_eq!(
+            repo.cpvs().unwrap(),
+            [
+                cpv("app-misc/foo-1.9"),
+                cpv("app-misc/foo-1.10"),
+                cpv("dev-libs/bar-2")
+            ]
+        );
+        assert_eq!(
+            repo.read(&cpv("dev-libs/bar-2"))
+                .unwrap()
+                .metadata
+                .description,
+            "bar"
+        );
+
+        let err = repo.read(&cpv("app-misc/foo-1.9")).unwrap_err();
+        assert_eq!(err.cpv(), Some("app-misc/foo-1.9"));
+        assert_eq!(err.root(), &Error::MissingField("SLOT".to_string()));
+        assert!(repo.load().is_err());
+
+        let report = repo
+            .read_report(&cpv("app-misc/foo-1.9"), Strictness::Lenient)
+            .unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(
+            repo.read(&cpv("app-misc/foo-2")).unwrap_err().io_kind(),
+            Some(std::io::ErrorKind::NotFound)
+        );
+
+        fs::write(cache.join("app-misc/foo-1.9"), "DESCRIPTION=foo\nSLOT=0\n").unwrap();
+        assert_eq!(repo.load().unwrap().len(), 3);
+
+        fs::write(cache.join("app-misc/README"), "").unwrap();
+        assert!(repo.cpvs().is_err());
+    }
+
+    #[test]
+    fn write_cache() {
+        let base = TempDir::new("metadata-repo-write");
+        let entries: BTreeMap<Cpv, CacheEntry> = (1..=20)
+            .map(|i| {
+                let category = if i % 2 == 0 { "app-misc" } else { "dev-libs" };
+                let entry = CacheEntry::parse(&format!("DESCRIPTION=v{i}\nSLOT=0\n")).unwrap();
+                (cpv(&format!("{category}/foo-{i}")), entry)
+            })
+            .collect();
+
+        let serial = MetadataRepo::new(base.join("serial"));
+        let parallel = MetadataRepo::new(base.join("parallel"));
+        serial.write_all(&entries, 1).unwrap();
+        parallel.write_all(&entries, 0).unwrap();
+        assert_eq!(parallel.load().unwrap(), entries);
+        for cpv in entries.keys() {
+            assert_eq!(
+                fs::read(serial.entry_path(cpv)).unwrap(),
+                fs::read(parallel.entry_path(cpv)).unwrap()
+            );
+        }
+
+        fs::write(base.join("file"), "").unwrap();
+        let blocked = MetadataRepo::new(base.join("file"));
+        assert!(blocked
+            .write_all(&entries, 4)
+            .unwrap_err()
+            .io_kind()
+            .is_some());
+    }
+
+    #[test]
+    fn validate() {
+        let root = TempDir::new("metadata-repo-validate");
+        let repo = MetadataRepo::new(&root);
+        for (name, text) in [
+            ("app-misc/a-1", "DESCRIPTION=x\nSLOT=0\n"),
+            ("app-misc/b-1", "DESCRIPTION=x\n"),
+            ("app-misc/c-1", "SLOT=0\n"),
+            ("dev-libs/d-1", "DESCRIPTION=x\n"),
+        ] {
+            let path = repo.entry_path(&cpv(name));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        let failed = |reports: &BTreeMap<Cpv, ParseReport>| {
+            reports
+                .iter()
+                .filter(|(_, report)| !report.is_ok())
+                .map(|(cpv, _)| cpv.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let reports = repo.validate_parallel(&ValidateOptions::default()).unwrap();
+        assert_eq!(reports.len(), 4);
+        assert_eq!(
+            failed(&reports),
+            ["app-misc/b-1", "app-misc/c-1", "dev-libs/d-1"]
+        );
+
+        let options = ValidateOptions {
+            categories: vec!["dev-libs".to_string()],
+            ..ValidateOptions::default()
+        };
+        assert_eq!(
+            failed(&repo.validate_parallel(&options).unwrap()),
+            ["dev-libs/d-1"]
+        );
+
+        let options = ValidateOptions {
+            threads: 1,
+            max_errors: Some(1),
+            ..ValidateOptions::default()
+        };
+        let reports = repo.validate_parallel(&options).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(failed(&reports), ["app-misc/b-1"]);
+    }
+
+    #[test]
+    fn prune_orphans() {
+        let root = TempDir::new("metadata-repo-prune");
+        let repo = MetadataRepo::new(&root);
+        for name in ["app-misc/foo-1", "app-misc/foo-2", "dev-libs/gone-1"] {
+            let path = repo.entry_path(&cpv(name));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "DESCRIPTION=x\nSLOT=0\n").unwrap();
+        }
+        fs::create_dir_all(root.join("app-misc/foo")).unwrap();
+        fs::write(root.join("app-misc/foo/foo-2.ebuild"), "EAPI=8\n").unwrap();
+
+        let orphans = [cpv("app-misc/foo-1"), cpv("dev-libs/gone-1")];
+        let empty = root.join("empty");
+        fs::create_dir_all(&empty).unwrap();
+        assert_eq!(repo.prune(&empty, false).unwrap().len(), 3);
+        let err = repo.prune(&empty, true).unwrap_err();
+        assert_eq!(err.io_kind(), Some(std::io::ErrorKind::NotFound));
+        assert_eq!(repo.cpvs().unwrap().len(), 3);
+        assert_eq!(repo.prune(&root, false).unwrap(), orphans);
+        assert_eq!(repo.cpvs().unwrap().len(), 3);
+        assert_eq!(repo.prune(&root, true).unwrap(), orphans);
+        assert_eq!(repo.cpvs().unwrap(), [cpv("app-misc/foo-2")]);
+        assert!(!repo.cache_dir().join("dev-libs").exists());
+    }
+
+    #[test]
+    fn timestamp_chk() {
+        let root = TempDir::new("metadata-repo-ts");
+        let repo = MetadataRepo::new(&root);
+        fs::create_dir_all(root.join("metadata")).unwrap();
+        assert_eq!(
+            repo.age().unwrap_err().io_kind(),
+            Some(std::io::ErrorKind::NotFound)
+        );
+
+        fs::write(
+            root.join("metadata/timestamp.chk"),
+            "Thu, 01 Jan 2015 00:00:00 +0000\n",
+        )
+        .unwrap();
+        assert_eq!(
+            repo.timestamp().unwrap(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1420070400)
+        );
+        assert!(repo.is_stale(Duration::from_secs(3600)).unwrap());
+
+        fs::write(
+            root.join("metadata/timestamp.chk"),
+            "Fri, 01 Jan 9999 00:00:00 +0000",
+        )
+        .unwrap();
+        assert_eq!(repo.age().unwrap(), Duration::ZERO);
+        assert!(!repo.is_stale(Duration::ZERO).unwrap());
+
+        fs::write(root.join("metadata/timestamp.chk"), "soon").unwrap();
+        assert_eq!(
+            repo.timestamp().unwrap_err(),
+            Error::InvalidTimestamp("soon".to_string())
+        );
+    }
+}
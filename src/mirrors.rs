@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use crate::src_url::SrcUrl;
+
+/// The `profiles/thirdpartymirrors` table of a repository.
+///
+/// Maps each mirror group to its base URLs, used to expand
+/// `mirror://group/path` URLs in `SRC_URI`.
+///
+/// See [PMS 4.4](https://projects.gentoo.org/pms/9/pms.html#the-profiles-directory)
+/// and [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThirdPartyMirrors {
+    groups: BTreeMap<String, Vec<String>>,
+}
+
+impl ThirdPartyMirrors {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a `thirdpartymirrors` file: one group per line, the group
+    /// name followed by its base URLs. Blank lines and `#` comments are
+    /// skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::ThirdPartyMirrors;
+    ///
+    /// let mirrors = ThirdPartyMirrors::parse("gnu https://ftp.gnu.org/gnu https://mirror.example/gnu/\n");
+    /// assert_eq!(mirrors.get("gnu").unwrap().len(), 2);
+    /// ```
+    pub fn parse(input: &str) -> Self {
+        let mut mirrors = Self::new();
+        for line in input.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut tokens = line.split_whitespace();
+            if let Some(group) = tokens.next() {
+                mirrors.insert(group, tokens.map(str::to_string).collect());
+            }
+        }
+        mirrors
+    }
+
+    /// Set the base URLs of `group`, replacing any previous ones.
+    pub fn insert(&mut self, group: impl Into<String>, urls: Vec<String>) {
+        self.groups.insert(group.into(), urls);
+    }
+
+    /// The base URLs of `group`.
+    pub fn get(&self, group: &str) -> Option<&[String]> {
+        self.groups.get(group).map(Vec::as_slice)
+    }
+
+    /// The concrete URLs `url` may be fetched from.
+    ///
+    /// A `mirror://` URL expands to one URL per base URL of its group (none
+    /// if the group is unknown); any other URL is returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{SrcUrl, ThirdPartyMirrors};
+    ///
+    /// let mirrors = ThirdPartyMirrors::parse("gnu https://ftp.gnu.org/gnu/");
+    /// assert_eq!(
+    ///     mirrors.expand(&SrcUrl::parse("mirror://gnu/hello/hello-2.12.tar.gz")),
+    ///     vec!["https://ftp.gnu.org/gnu/hello/hello-2.12.tar.gz"],
+    /// );
+    /// ```
+    pub fn expand(&self, url: &SrcUrl) -> Vec<String> {
+        match url {
+            SrcUrl::Mirror { group, path } => self
+                .get(group)
+                .unwrap_or_default()
+                .iter()
+                .map(|base| format!("{}/{path}", base.trim_end_matches('/')))
+                .collect(),
+            SrcUrl::Url { .. } | SrcUrl::Plain(_) => vec![url.to_string()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_unknown_group_and_plain() {
+        let mirrors = ThirdPartyMirrors::parse(
+            "# comment\n\nsourceforge https://downloads.sourceforge.net\n",
+        );
+        assert!(mirrors
+            .expand(&SrcUrl::parse("mirror://gnu/a.tgz"))
+            .is_empty());
+        assert_eq!(
+            mirrors.expand(&SrcUrl::parse("mirror://sourceforge/p/a.tgz")),
+            vec!["https://downloads.sourceforge.net/p/a.tgz"]
+        );
+        assert_eq!(
+            mirrors.expand(&SrcUrl::parse("https://e.com/a.tgz")),
+            vec!["https://e.com/a.tgz"]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+// Validation of USE flag and license names against the PMS naming rules.
+//
+// The winnow parsers accept a permissive character set so that real-world
+// metadata parses; the strict parse functions run these checks on top to
+// report the exact offending position.
+
+/// Which kind of leaf a token stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Leaf {
+    /// A license name (LICENSE).
+    License,
+    /// A possibly `!`-negated USE flag (REQUIRED_USE).
+    Flag,
+    /// Anything else, checked elsewhere (SRC_URI, RESTRICT).
+    Other,
+}
+
+/// Byte offset within `name` of the first character violating
+/// [PMS 3.1.4](https://projects.gentoo.org/pms/9/pms.html#use-flag-names).
+pub(crate) fn invalid_flag_char(name: &str) -> Option<usize> {
+    invalid_char(
+        name,
+        |c| c.is_ascii_alphanumeric(),
+        |c| c.is_ascii_alphanumeric() || matches!(c, '+' | '_' | '@' | '-'),
+    )
+}
+
+/// Byte offset within `name` of the first character violating
+/// [PMS 3.1.7](https://projects.gentoo.org/pms/9/pms.html#license-names).
+pub(crate) fn invalid_license_char(name: &str) -> Option<usize> {
+    invalid_char(
+        name,
+        |c| c.is_ascii_alphanumeric() || c == '_',
+        |c| c.is_ascii_alphanumeric() || matches!(c, '+' | '_' | '.' | '-'),
+    )
+}
+
+/// Byte offset within `name` and description of the first character
+/// violating the naming rules of a [`Leaf::Flag`] or [`Leaf::License`].
+pub(crate) fn check_name(name: &str, leaf: Leaf) -> Option<(usize, String)> {
+    let (i, kind) = match leaf {
+        Leaf::Flag => (invalid_flag_char(name)?, "USE flag"),
+        Leaf::License => (invalid_license_char(name)?, "license"),
+        Leaf::Other => return None,
+    };
+    let what = match name[i..].chars().next() {
+        Some(c) => format!("invalid character '{c}' in {kind} name '{name}'"),
+        None => format!("empty {kind} name"),
+    };
+    Some((i, what))
+}
+
+fn invalid_char(name: &str, first: fn(char) -> bool, rest: fn(char) -> bool) -> Option<usize> {
+    let mut chars = name.char_indices();
+    match chars.next() {
+        None => return Some(0),
+        Some((_, c)) if !first(c) => return Some(0),
+        Some(_) => {}
+    }
+    chars.find(|&(_, c)| !rest(c)).map(|(i, _)| i)
+}
+
+/// Check every flag and `leaf` name in a dependency-specification string.
+///
+/// Returns the byte offset into `input` and a description of the first
+/// invalid name.
+pub(crate) fn check_tokens(input: &str, leaf: Leaf) -> Option<(usize, String)> {
+    let mut offset = 0;
+    for token in input.split_whitespace() {
+        let start = offset + input[offset..].find(token).unwrap_or(0);
+        offset = start + token.len();
+        if matches!(token, "(" | ")" | "||" | "^^" | "??") {
+            continue;
+        }
+        // A URI may end in `?` without being a conditional.
+        if leaf == Leaf::Other && token.contains("://") {
+            continue;
+        }
+        let (name, name_start, is_flag) = if let Some(cond) = token.strip_suffix('?') {
+            let negated = cond.starts_with('!');
+            let name = cond.strip_prefix('!').unwrap_or(cond);
+            (name, start + usize::from(negated), true)
+        } else {
+            match leaf {
+                Leaf::License => (token, start, false),
+                Leaf::Flag => {
+                    let negated = token.starts_with('!');
+                    let name = token.strip_prefix('!').unwrap_or(token);
+                    (name, start + usize::from(negated), true)
+                }
+                Leaf::Other => continue,
+            }
+        };
+        let kind = if is_flag { Leaf::Flag } else { Leaf::License };
+        if let Some((i, what)) = check_name(name, kind) {
+            return Some((name_start + i, what));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_names() {
+        assert_eq!(invalid_flag_char("python_targets_python3_12"), None);
+        assert_eq!(invalid_flag_char("gtk+"), None);
+        assert_eq!(invalid_flag_char("_foo"), Some(0));
+        assert_eq!(invalid_flag_char("foo.bar"), Some(3));
+        assert_eq!(invalid_flag_char(""), Some(0));
+    }
+
+    #[test]
+    fn license_names() {
+        assert_eq!(invalid_license_char("GPL-2+"), None);
+        assert_eq!(invalid_license_char("_x"), None);
+        assert_eq!(invalid_license_char(".hidden"), Some(0));
+        assert_eq!(invalid_license_char("a@b"), Some(1));
+    }
+
+    #[test]
+    fn token_positions() {
+        assert_eq!(
+            check_tokens("MIT  !fo.o? ( BSD )", Leaf::License),
+            Some((
+                8,
+                "invalid character '.' in USE flag name 'fo.o'".to_string()
+            ))
+        );
+        assert_eq!(
+            check_tokens("|| ( a !-b )", Leaf::Flag),
+            Some((8, "invalid character '-' in USE flag name '-b'".to_string()))
+        );
+        assert_eq!(
+            check_tokens("https://e.com/a?x ssl? ( b )", Leaf::Other),
+            None
+        );
+        assert_eq!(check_tokens("https://e.com/a? ( b )", Leaf::Other), None);
+        assert!(check_tokens("!f.o? ( https://e.com/a? )", Leaf::Other).is_some());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::cache::CacheEntry;
+use crate::eapi::Eapi;
+use crate::eapi_registry::{EapiRegistry, EapiRules};
+use crate::error::Error;
+use crate::interner::{DefaultInterner, Interner};
+use crate::metadata::MetadataIssue;
+
+/// The outcome of parsing a md5-cache entry without stopping at the first
+/// error, as returned by [`CacheEntry::parse_report`].
+///
+/// Fields that failed to parse take a fallback in `entry`, so the rest of
+/// the metadata can still be inspected: a missing or invalid `SLOT`
+/// becomes `0`, an invalid `EAPI` is read as EAPI 0, invalid `KEYWORDS`,
+/// `IUSE` and `HOMEPAGE` tokens are dropped, and any other field that
+/// fails is left empty.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseReport<I = DefaultInterner>
+where
+    I: Interner,
+{
+    /// The entry, with every field that parsed.
+    pub entry: CacheEntry<I>,
+    /// Every parse error, in the order fields are checked.
+    pub errors: Vec<Error>,
+    /// Cross-field issues found by [`EbuildMetadata::validate`](crate::EbuildMetadata::validate).
+    pub warnings: Vec<MetadataIssue>,
+}
+
+impl<I: Interner> ParseReport<I> {
+    /// Whether the entry parsed without errors.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Whether the entry parsed without errors or warnings.
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty() && self.warnings.is_empty()
+    }
+
+    /// The entry, or the first error if there was any.
+    pub fn into_result(self) -> Result<CacheEntry<I>, Error> {
+        match self.errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(self.entry),
+        }
+    }
+}
+
+/// How strictly [`CacheEntry::parse_report`] checks an entry.
+///
+/// See [PMS 2](https://projects.gentoo.org/pms/9/pms.html#eapis).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness<'a> {
+    /// Check the syntax only, as [`CacheEntry::parse`] does.
+    #[default]
+    Lenient,
+    /// Also reject syntax the entry's EAPI does not allow, as
+    /// [`CacheEntry::parse_strict`] does.
+    Strict,
+    /// Like [`Strictness::Strict`], taking the features of custom EAPIs
+    /// from a registry, as [`CacheEntry::parse_strict_with`] does.
+    StrictWith(&'a EapiRegistry),
+}
+
+impl<'a> Strictness<'a> {
+    /// Whether fields are checked against the entry's EAPI.
+    pub fn is_strict(self) -> bool {
+        self != Strictness::Lenient
+    }
+
+    /// The features of `eapi` as this mode sees them.
+    pub(crate) fn rules(self, eapi: &'a Eapi) -> EapiRules<'a> {
+        match self {
+            Strictness::StrictWith(registry) => EapiRules::new(eapi, Some(registry)),
+            Strictness::Lenient | Strictness::Strict => EapiRules::new(eapi, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_all_errors() {
+        let input = "EAPI=8\nSLOT=0/\nKEYWORDS=amd64 ~x8@6\nLICENSE=|| (\nDESCRIPTION=x\n";
+        let report = CacheEntry::parse_report(input, Strictness::Lenient);
+        let fields: Vec<_> = report.errors.iter().map(|e| e.field()).collect();
+        assert_eq!(fields, [Some("SLOT"), Some("LICENSE"), Some("KEYWORDS")]);
+        assert_eq!(report.entry.metadata.slot.slot, "0");
+        assert_eq!(report.entry.metadata.keywords.len(), 1);
+        assert!(report.entry.metadata.license.is_none());
+        assert_eq!(report.entry.metadata.description, "x");
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn first_error_matches_parse() {
+        let input = "EAPI=8\nDESCRIPTION=x\nSLOT=0\nKEYWORDS=a@ b@\n";
+        let report = CacheEntry::parse_report(input, Strictness::Lenient);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.into_result(), CacheEntry::parse(input));
+    }
+
+    #[test]
+    fn missing_fields() {
+        let report = CacheEntry::parse_report("EAPI=8\n", Strictness::Lenient);
+        assert_eq!(
+            report.errors,
+            [
+                Error::MissingField("DESCRIPTION".to_string()),
+                Error::MissingField("SLOT".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strict_and_warnings() {
+        let input = "EAPI=7\nDESCRIPTION=\nSLOT=0\nRESTRICT=!test? ( test )\n\
+                     DEFINED_PHASES=compile bogus\n";
+        let report = CacheEntry::parse_report(input, Strictness::Strict);
+        let fields: Vec<_> = report.errors.iter().map(|e| e.field()).collect();
+        assert_eq!(fields, [Some("RESTRICT"), Some("DEFINED_PHASES")]);
+        assert_eq!(report.warnings[0], MetadataIssue::EmptyDescription);
+
+        let report = CacheEntry::parse_report(input, Strictness::Lenient);
+        assert!(report.is_ok());
+        assert!(!report.is_clean());
+
+        let mut registry = EapiRegistry::new();
+        registry.register_like("7-fork", Eapi::Seven);
+        let input = input.replace("EAPI=7", "EAPI=7-fork");
+        let fields = |strictness| {
+            let report = CacheEntry::parse_report(&input, strictness);
+            report
+                .errors
+                .iter()
+                .map(|e| e.field().map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fields(Strictness::Strict), [Some("DEFINED_PHASES".into())]);
+        assert_eq!(
+            fields(Strictness::StrictWith(&registry)),
+            [Some("RESTRICT".into()), Some("DEFINED_PHASES".into())]
+        );
+    }
+
+    #[test]
+    fn clean() {
+        let report =
+            CacheEntry::parse_report("EAPI=8\nDESCRIPTION=x\nSLOT=0\n", Strictness::Lenient);
+        assert!(report.is_clean());
+        assert!(report.into_result().is_ok());
+    }
+}
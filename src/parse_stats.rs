@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::dep_spec::DepSpec;
+use crate::error::Error;
+use crate::metadata::EbuildMetadata;
+use crate::metadata_key::MetadataKey;
+use crate::parse_report::ParseReport;
+
+/// Counters describing how a set of cache entries parsed.
+///
+/// Fed one [`ParseReport`] at a time with [`ParseStats::record`], or
+/// gathered for a whole repository by
+/// [`MetadataRepo::parse_stats`](crate::MetadataRepo::parse_stats).
+/// Collected over time, the numbers show a tree's health: failure counts
+/// by field, and how deeply the expression-valued fields nest, which
+/// bounds the work of every tree walk.
+///
+/// See [PMS 7](https://projects.gentoo.org/pms/9/pms.html#ebuilddefined-variables).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{CacheEntry, MetadataKey, ParseStats, Strictness};
+///
+/// let mut stats = ParseStats::new();
+/// for input in [
+///     "DESCRIPTION=x\nSLOT=0\nLICENSE=MIT ssl? ( || ( BSD ISC ) )\n",
+///     "DESCRIPTION=x\nSLOT=0\nLICENSE=MIT\nKEYWORDS=~amd64 @\n",
+/// ] {
+///     stats.record(input.len(), &CacheEntry::parse_report(input, Strictness::Lenient));
+/// }
+/// assert_eq!(stats.entries, 2);
+/// assert_eq!(stats.failed, 1);
+/// assert_eq!(stats.field_failures["KEYWORDS"], 1);
+/// assert_eq!(stats.depths[&MetadataKey::License], [1, 0, 1]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// Entries recorded.
+    pub entries: usize,
+    /// Entries with at least one parse error.
+    pub failed: usize,
+    /// Total size of the recorded input, in bytes.
+    pub bytes: u64,
+    /// Parse errors by field name; errors not tied to a field are
+    /// counted under the empty name.
+    pub field_failures: BTreeMap<String, usize>,
+    /// For each expression-valued field, how many entries set it with
+    /// each nesting depth: `[3, 1]` means three values without groups and
+    /// one with a single level of groups. The top-level list of entries is
+    /// not a group.
+    pub depths: BTreeMap<MetadataKey, Vec<usize>>,
+}
+
+impl ParseStats {
+    /// No entries recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count an entry parsed from `bytes` bytes of input.
+    pub fn record(&mut self, bytes: usize, report: &ParseReport) {
+        self.entries += 1;
+        self.bytes += bytes as u64;
+        if !report.is_ok() {
+            self.failed += 1;
+        }
+        for error in &report.errors {
+            let field = match (error.field(), error.root()) {
+                (Some(field), _) => field,
+                (None, Error::MissingField(field)) => field.as_str(),
+                _ => "",
+            };
+            *self.field_failures.entry(field.to_string()).or_default() += 1;
+        }
+        for (key, depth) in field_depths(&report.entry.metadata) {
+            let histogram = self.depths.entry(key).or_default();
+            if histogram.len() <= depth {
+                histogram.resize(depth + 1, 0);
+            }
+            histogram[depth] += 1;
+        }
+    }
+
+    /// Add the counts of `other`, e.g. from another worker thread.
+    pub fn merge(&mut self, other: &ParseStats) {
+        self.entries += other.entries;
+        self.failed += other.failed;
+        self.bytes += other.bytes;
+        for (field, count) in &other.field_failures {
+            *self.field_failures.entry(field.clone()).or_default() += count;
+        }
+        for (key, counts) in &other.depths {
+            let histogram = self.depths.entry(*key).or_default();
+            if histogram.len() < counts.len() {
+                histogram.resize(counts.len(), 0);
+            }
+            for (total, count) in histogram.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+    }
+
+    /// The deepest nesting seen in `key`, if any entry set it.
+    pub fn max_depth(&self, key: MetadataKey) -> Option<usize> {
+        self.depths
+            .get(&key)
+            .and_then(|histogram| histogram.iter().rposition(|&count| count > 0))
+    }
+}
+
+/// The nesting depth of each non-empty expression-valued field.
+fn field_depths(metadata: &EbuildMetadata) -> Vec<(MetadataKey, usize)> {
+    let mut out = Vec::new();
+    if !metadata.src_uri.is_empty() {
+        out.push((
+            MetadataKey::SrcUri,
+            nesting(&DepSpec::from(metadata.src_uri.as_slice())),
+        ));
+    }
+    if let Some(license) = &metadata.license {
+        out.push((MetadataKey::License, nesting(&DepSpec::from(license))));
+    }
+    if let Some(required_use) = &metadata.required_use {
+        out.push((
+            MetadataKey::RequiredUse,
+            nesting(&DepSpec::from(required_use)),
+        ));
+    }
+    if !metadata.restrict.is_empty() {
+        out.push((
+            MetadataKey::Restrict,
+            nesting(&DepSpec::from(metadata.restrict.as_slice())),
+        ));
+    }
+    if !metadata.properties.is_empty() {
+        out.push((
+            MetadataKey::Properties,
+            nesting(&DepSpec::from(metadata.properties.as_slice())),
+        ));
+    }
+    for (key, entries) in metadata.dependencies() {
+        if !entries.is_empty() {
+            out.push((key, nesting(&DepSpec::from(entries))));
+        }
+    }
+    out
+}
+
+/// The depth of a field's value, not counting the top-level list.
+fn nesting<L>(spec: &DepSpec<L>) -> usize {
+    match spec {
+        DepSpec::AllOf(entries) => entries.iter().map(DepSpec::depth).max().unwrap_or(0),
+        spec => spec.depth(),
+    }
+}
+
+impl fmt::Display for ParseStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} entries ({} failed), {} bytes",
+            self.entries, self.failed, self.bytes
+        )?;
+        for (field, count) in &self.field_failures {
+            let field = if field.is_empty() { "other" } else { field };
+            write!(f, "\n{field}: {count} errors")?;
+        }
+        for key in self.depths.keys() {
+            if let Some(depth) = self.max_depth(*key) {
+                write!(f, "\n{key}: max depth {depth}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheEntry;
+    use crate::parse_report::Strictness;
+
+    fn stats(inputs: &[&str]) -> ParseStats {
+        let mut stats = ParseStats::new();
+        for input in inputs {
+            stats.record(
+                input.len(),
+                &CacheEntry::parse_report(input, Strictness::Lenient),
+            );
+        }
+        stats
+    }
+
+    #[test]
+    fn record_and_merge() {
+        let mut a = stats(&[
+            "DESCRIPTION=x\nSLOT=0\nRDEPEND=dev-libs/a ssl? ( || ( dev-libs/b dev-libs/c ) )\n",
+            "DESCRIPTION=x\n",
+        ]);
+        let b = stats(&["DESCRIPTION=x\nSLOT=0/\nRDEPEND=dev-libs/a\n"]);
+        assert_eq!(a.field_failures["SLOT"], 1);
+        assert_eq!(a.max_depth(MetadataKey::Rdepend), Some(2));
+        assert_eq!(a.max_depth(MetadataKey::Depend), None);
+
+        a.merge(&b);
+        assert_eq!((a.entries, a.failed), (3, 2));
+        assert_eq!(a.field_failures["SLOT"], 2);
+        assert_eq!(a.depths[&MetadataKey::Rdepend], [1, 0, 1]);
+        assert_eq!(
+            a.to_string(),
+            format!(
+                "3 entries (2 failed), {} bytes\nSLOT: 2 errors\nRDEPEND: max depth 2",
+                a.bytes
+            )
+        );
+    }
+}
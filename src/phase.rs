@@ -1,15 +1,23 @@
 use std::fmt;
 use std::str::FromStr;
 
+use crate::eapi::Eapi;
 use crate::error::{Error, Result};
+use crate::tokens::Tokens;
 
 /// Ebuild phase function.
 ///
 /// Phase functions are called by the package manager in a defined order
 /// during package build and installation.
 ///
-/// See [PMS 9](https://projects.gentoo.org/pms/9/pms.html#ebuilddefined-functions).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Phases order by when they run: the install sequence from `pkg_pretend`
+/// to `pkg_postinst`, then `pkg_prerm` and `pkg_postrm`, then the phases
+/// invoked on demand (`pkg_config`, `pkg_info`, `pkg_nofetch`).
+/// [`Phase::Other`] sorts last, by name.
+///
+/// See [PMS 9](https://projects.gentoo.org/pms/9/pms.html#ebuilddefined-functions)
+/// and [PMS 9.2](https://projects.gentoo.org/pms/9/pms.html#call-order).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Phase {
     /// `pkg_pretend` — pre-flight checks (EAPI 4+).
     PkgPretend,
@@ -41,11 +49,33 @@ pub enum Phase {
     PkgInfo,
     /// `pkg_nofetch` — handle fetch-restricted sources.
     PkgNofetch,
+    /// A phase unknown to this crate, e.g. from a newer or forked package
+    /// manager. Holds the name as written.
+    Other(String),
 }
 
 impl Phase {
-    /// Return the short phase name as a `&'static str` (same as `Display`).
-    pub fn as_str(self) -> &'static str {
+    /// Every phase defined by PMS, in execution order.
+    pub const ALL: &'static [Phase] = &[
+        Phase::PkgPretend,
+        Phase::PkgSetup,
+        Phase::SrcUnpack,
+        Phase::SrcPrepare,
+        Phase::SrcConfigure,
+        Phase::SrcCompile,
+        Phase::SrcTest,
+        Phase::SrcInstall,
+        Phase::PkgPreinst,
+        Phase::PkgPostinst,
+        Phase::PkgPrerm,
+        Phase::PkgPostrm,
+        Phase::PkgConfig,
+        Phase::PkgInfo,
+        Phase::PkgNofetch,
+    ];
+
+    /// Return the short phase name (same as `Display`).
+    pub fn as_str(&self) -> &str {
         match self {
             Phase::PkgPretend => "pretend",
             Phase::PkgSetup => "setup",
@@ -62,13 +92,14 @@ impl Phase {
             Phase::PkgConfig => "config",
             Phase::PkgInfo => "info",
             Phase::PkgNofetch => "nofetch",
+            Phase::Other(name) => name,
         }
     }
 
     /// Parse a space-separated `DEFINED_PHASES` line into a list of phases.
     ///
     /// The special value `-` (used in the cache to mean "no phases defined")
-    /// returns an empty list.
+    /// returns an empty list. Unknown phase names become [`Phase::Other`].
     ///
     /// # Examples
     ///
@@ -84,14 +115,56 @@ impl Phase {
     /// ```
     pub fn parse_line(input: &str) -> Result<Vec<Phase>> {
         let trimmed = input.trim();
-        if trimmed.is_empty() || trimmed == "-" {
+        if trimmed == "-" {
             return Ok(Vec::new());
         }
-        trimmed
-            .split_whitespace()
+        Tokens::new(trimmed)
+            .iter()
             .map(|token| token.parse())
             .collect()
     }
+
+    /// Whether an ebuild may define this phase in `eapi`.
+    ///
+    /// Always `false` for [`Phase::Other`]. See [`Eapi::phases`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Eapi, Phase};
+    ///
+    /// assert!(Phase::SrcConfigure.is_valid_in(&Eapi::Two));
+    /// assert!(!Phase::PkgPretend.is_valid_in(&Eapi::Three));
+    /// ```
+    pub fn is_valid_in(&self, eapi: &Eapi) -> bool {
+        eapi.phases().contains(self)
+    }
+
+    /// An [`Error::InvalidPhase`] for `name`, suggesting the closest known
+    /// phase.
+    pub(crate) fn invalid(name: &str) -> Error {
+        Error::InvalidPhase(name.to_string()).suggest(name, Phase::ALL.iter().map(Phase::as_str))
+    }
+
+    /// Sort `phases` into execution order, e.g. to normalize a
+    /// `DEFINED_PHASES` list before comparing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Phase;
+    ///
+    /// let mut phases = Phase::parse_line("install configure prepare").unwrap();
+    /// Phase::sort_execution_order(&mut phases);
+    /// assert_eq!(
+    ///     phases,
+    ///     [Phase::SrcPrepare, Phase::SrcConfigure, Phase::SrcInstall]
+    /// );
+    /// assert!(Phase::SrcPrepare < Phase::SrcConfigure);
+    /// ```
+    pub fn sort_execution_order(phases: &mut [Phase]) {
+        phases.sort();
+    }
 }
 
 impl FromStr for Phase {
@@ -99,7 +172,8 @@ impl FromStr for Phase {
 
     fn from_str(s: &str) -> Result<Self> {
         // DEFINED_PHASES uses short names (without pkg_/src_ prefix)
-        // but we also accept full names for convenience.
+        // but we also accept full names for convenience. Anything else that
+        // looks like a phase name is kept verbatim.
         match s {
             "pretend" | "pkg_pretend" => Ok(Phase::PkgPretend),
             "setup" | "pkg_setup" => Ok(Phase::PkgSetup),
@@ -116,11 +190,18 @@ impl FromStr for Phase {
             "config" | "pkg_config" => Ok(Phase::PkgConfig),
             "info" | "pkg_info" => Ok(Phase::PkgInfo),
             "nofetch" | "pkg_nofetch" => Ok(Phase::PkgNofetch),
-            _ => Err(Error::InvalidPhase(s.to_string())),
+            _ if is_phase_name(s) => Ok(Phase::Other(s.to_string())),
+            _ => Err(Phase::invalid(s)),
         }
     }
 }
 
+fn is_phase_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
 impl fmt::Display for Phase {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(self.as_str())
@@ -202,10 +283,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unknown_phase_preserved() {
+        assert_eq!(
+            "foo".parse::<Phase>().unwrap(),
+            Phase::Other("foo".to_string())
+        );
+        let phases = Phase::parse_line("compile src_frobnicate").unwrap();
+        assert_eq!(phases[1], Phase::Other("src_frobnicate".to_string()));
+        assert_eq!(phases[1].to_string(), "src_frobnicate");
+    }
+
+    #[test]
+    fn execution_order() {
+        let mut phases =
+            Phase::parse_line("nofetch zzz postinst compile pretend unpack aaa").unwrap();
+        Phase::sort_execution_order(&mut phases);
+        let names: Vec<&str> = phases.iter().map(Phase::as_str).collect();
+        assert_eq!(
+            names,
+            ["pretend", "unpack", "compile", "postinst", "nofetch", "aaa", "zzz"]
+        );
+    }
+
+    #[test]
+    fn validity_per_eapi() {
+        assert!(!Phase::SrcPrepare.is_valid_in(&Eapi::One));
+        assert!(Phase::SrcPrepare.is_valid_in(&Eapi::Two));
+        assert!(Phase::PkgPretend.is_valid_in(&Eapi::Four));
+        assert!(Phase::SrcUnpack.is_valid_in(&Eapi::Zero));
+        assert!(!Phase::Other("frobnicate".to_string()).is_valid_in(&Eapi::Nine));
+    }
+
     #[test]
     fn invalid_phase() {
-        assert!("foo".parse::<Phase>().is_err());
         assert!("".parse::<Phase>().is_err());
+        assert!("foo=bar".parse::<Phase>().is_err());
     }
 
     #[test]
@@ -0,0 +1,92 @@
+use crate::bit_set::{bit_set, BitIndex};
+use crate::phase::Phase;
+
+bit_set! {
+    /// A compact set of the PMS-defined phase functions.
+    ///
+    /// `DEFINED_PHASES` is a set: neither order nor duplicates carry meaning.
+    /// Each known [`Phase`] maps to one bit, so membership tests and set
+    /// operations are O(1). [`Phase::Other`] cannot be stored and is ignored.
+    ///
+    /// Iteration and `Display` follow execution order; an empty set displays
+    /// as `-`, like the md5-cache.
+    ///
+    /// See [PMS 9.1](https://projects.gentoo.org/pms/9/pms.html#list-of-phase-functions).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Phase, PhaseSet};
+    ///
+    /// let phases = Phase::parse_line("install compile install").unwrap();
+    /// let set: PhaseSet = phases.iter().collect();
+    /// assert!(set.contains(&Phase::SrcCompile));
+    /// assert_eq!(set.to_string(), "compile install");
+    /// assert_eq!(PhaseSet::new().to_string(), "-");
+    /// ```
+    PhaseSet(u16), Phase, empty = "-"
+}
+
+impl BitIndex for Phase {
+    const KNOWN: &'static [Self] = Phase::ALL;
+
+    fn bit_index(&self) -> Option<u8> {
+        Some(match self {
+            Phase::PkgPretend => 0,
+            Phase::PkgSetup => 1,
+            Phase::SrcUnpack => 2,
+            Phase::SrcPrepare => 3,
+            Phase::SrcConfigure => 4,
+            Phase::SrcCompile => 5,
+            Phase::SrcTest => 6,
+            Phase::SrcInstall => 7,
+            Phase::PkgPreinst => 8,
+            Phase::PkgPostinst => 9,
+            Phase::PkgPrerm => 10,
+            Phase::PkgPostrm => 11,
+            Phase::PkgConfig => 12,
+            Phase::PkgInfo => 13,
+            Phase::PkgNofetch => 14,
+            Phase::Other(_) => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eapi::Eapi;
+
+    #[test]
+    fn set_operations() {
+        let a: PhaseSet = [Phase::SrcCompile, Phase::SrcInstall].iter().collect();
+        let b: PhaseSet = [Phase::SrcInstall, Phase::PkgSetup].iter().collect();
+        assert_eq!((a | b).to_string(), "setup compile install");
+        assert_eq!((a & b).to_string(), "install");
+        assert_eq!((a - b).to_string(), "compile");
+        assert_eq!(PhaseSet::all().len(), 15);
+    }
+
+    #[test]
+    fn all_matches_latest_eapi() {
+        let latest: PhaseSet = Eapi::Nine.phases().iter().collect();
+        assert_eq!(latest, PhaseSet::all());
+    }
+
+    #[test]
+    fn other_ignored() {
+        let mut set = PhaseSet::new();
+        assert!(!set.insert(&Phase::Other("frobnicate".to_string())));
+        assert!(set.is_empty());
+        assert!(set.insert(&Phase::PkgNofetch));
+        assert!(set.remove(&Phase::PkgNofetch));
+        assert!(!set.remove(&Phase::PkgNofetch));
+    }
+
+    #[test]
+    fn bit_indices_follow_known() {
+        for (i, phase) in Phase::KNOWN.iter().enumerate() {
+            assert_eq!(phase.bit_index(), Some(i as u8));
+        }
+    }
+}
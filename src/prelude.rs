@@ -0,0 +1,32 @@
+//! The commonly used types and traits, for glob import.
+//!
+//! ```
+//! use portage_metadata::prelude::*;
+//!
+//! let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nIUSE=ssl\nREQUIRED_USE=ssl\n").unwrap();
+//! let required_use = entry.metadata.required_use.as_ref().unwrap();
+//! assert!(required_use.is_satisfied(&UseFlagSet::parse("ssl")));
+//! ```
+//!
+//! `Result` is left out so that it does not shadow the standard library's;
+//! use [`crate::Result`] explicitly.
+
+pub use crate::cache::CacheEntry;
+pub use crate::check::{CheckRunner, Finding, MetadataCheck, Severity};
+pub use crate::dep_spec::SpecVisitor;
+pub use crate::eapi::Eapi;
+pub use crate::error::Error;
+pub use crate::iuse::IUse;
+pub use crate::keyword::{Keyword, Stability};
+pub use crate::keyword_set::KeywordSet;
+pub use crate::license::LicenseExpr;
+pub use crate::metadata::EbuildMetadata;
+pub use crate::metadata_key::MetadataKey;
+#[cfg(feature = "fs")]
+pub use crate::metadata_repo::MetadataRepo;
+pub use crate::profile::Profile;
+pub use crate::properties::PropertiesExpr;
+pub use crate::required_use::RequiredUseExpr;
+pub use crate::restrict::RestrictExpr;
+pub use crate::src_uri::SrcUriEntry;
+pub use crate::use_flags::UseFlagSet;
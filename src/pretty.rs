@@ -0,0 +1,46 @@
+use std::fmt;
+
+use crate::dep_spec::{GroupKind, Shape, SpecTree};
+
+/// Write `tree` as indented lines, two spaces per level.
+///
+/// Groups print as `prefix(`, their indented children, then `)`. The
+/// entries of the `root` all-of group are printed at the current depth,
+/// one per line.
+pub(crate) fn write_pretty<T: SpecTree + fmt::Display>(
+    f: &mut fmt::Formatter,
+    tree: &T,
+    root: bool,
+) -> fmt::Result {
+    let mut lines = Vec::new();
+    collect_lines(tree, 0, root, &mut lines);
+    f.write_str(&lines.join("\n"))
+}
+
+fn collect_lines<T: SpecTree + fmt::Display>(
+    tree: &T,
+    depth: usize,
+    root: bool,
+    out: &mut Vec<String>,
+) {
+    let indent = "  ".repeat(depth);
+    let shape = tree.shape();
+    match shape {
+        Shape::Leaf => out.push(format!("{indent}{tree}")),
+        Shape::Group(GroupKind::AllOf, children) if root => {
+            for child in children {
+                collect_lines(child, depth, false, out);
+            }
+        }
+        Shape::Group(_, children)
+        | Shape::UseConditional {
+            entries: children, ..
+        } => {
+            out.push(format!("{indent}{}(", shape.prefix()));
+            for child in children {
+                collect_lines(child, depth + 1, false, out);
+            }
+            out.push(format!("{indent})"));
+        }
+    }
+}
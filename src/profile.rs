@@ -0,0 +1,129 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::use_expand::UseExpandConfig;
+
+/// The IUSE-related variables of a stacked profile.
+///
+/// Profiles inject flags into every ebuild's effective IUSE without them
+/// appearing in the metadata cache: `IUSE_IMPLICIT` directly, and the
+/// values of the `USE_EXPAND_IMPLICIT` variables listed in
+/// `USE_EXPAND_VALUES_<VAR>`. Values are taken as final, i.e. after
+/// incremental stacking of the profile directories.
+///
+/// See [PMS 5.3](https://projects.gentoo.org/pms/9/pms.html#profile-variables).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    iuse_implicit: BTreeSet<String>,
+    use_expand: UseExpandConfig,
+    use_expand_unprefixed: BTreeSet<String>,
+    use_expand_implicit: BTreeSet<String>,
+    use_expand_values: BTreeMap<String, Vec<String>>,
+}
+
+impl Profile {
+    /// Create a profile with no variables set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a profile from `(variable, value)` pairs, e.g. the output of
+    /// `portageq envvar`. Unknown variables are ignored and a repeated
+    /// variable replaces the earlier value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::Profile;
+    ///
+    /// let profile = Profile::from_vars([
+    ///     ("IUSE_IMPLICIT", "prefix test"),
+    ///     ("USE_EXPAND", "ELIBC"),
+    ///     ("USE_EXPAND_IMPLICIT", "ELIBC ARCH"),
+    ///     ("USE_EXPAND_UNPREFIXED", "ARCH"),
+    ///     ("USE_EXPAND_VALUES_ELIBC", "glibc musl"),
+    ///     ("USE_EXPAND_VALUES_ARCH", "amd64 arm64"),
+    /// ]);
+    /// let flags: Vec<String> = profile.implicit_flags().into_iter().collect();
+    /// assert_eq!(
+    ///     flags,
+    ///     ["amd64", "arm64", "elibc_glibc", "elibc_musl", "prefix", "test"]
+    /// );
+    /// ```
+    pub fn from_vars<'a>(vars: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut profile = Self::new();
+        for (key, value) in vars {
+            profile.set(key, value);
+        }
+        profile
+    }
+
+    /// Set a profile variable. Unknown variables are ignored.
+    pub fn set(&mut self, key: &str, value: &str) {
+        let words = || value.split_whitespace().map(str::to_string);
+        match key {
+            "IUSE_IMPLICIT" => self.iuse_implicit = words().collect(),
+            "USE_EXPAND" => self.use_expand = UseExpandConfig::parse(value),
+            "USE_EXPAND_UNPREFIXED" => self.use_expand_unprefixed = words().collect(),
+            "USE_EXPAND_IMPLICIT" => self.use_expand_implicit = words().collect(),
+            _ => {
+                if let Some(var) = key.strip_prefix("USE_EXPAND_VALUES_") {
+                    self.use_expand_values
+                        .insert(var.to_string(), words().collect());
+                }
+            }
+        }
+    }
+
+    /// The profile's `USE_EXPAND` variables.
+    pub fn use_expand(&self) -> &UseExpandConfig {
+        &self.use_expand
+    }
+
+    /// Flags of `IUSE_IMPLICIT`.
+    pub fn iuse_implicit(&self) -> impl Iterator<Item = &str> {
+        self.iuse_implicit.iter().map(String::as_str)
+    }
+
+    /// Every flag the profile adds to the effective IUSE.
+    ///
+    /// `USE_EXPAND_IMPLICIT` variables contribute their values prefixed
+    /// with the lowercased variable name if listed in `USE_EXPAND`, and
+    /// unprefixed if listed in `USE_EXPAND_UNPREFIXED`.
+    pub fn implicit_flags(&self) -> BTreeSet<String> {
+        let mut flags = self.iuse_implicit.clone();
+        for var in &self.use_expand_implicit {
+            let values = self.use_expand_values.get(var).into_iter().flatten();
+            if self.use_expand.contains(var) {
+                flags.extend(values.map(|v| UseExpandConfig::flag(var, v)));
+            } else if self.use_expand_unprefixed.contains(var) {
+                flags.extend(values.cloned());
+            }
+        }
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn implicit_requires_expand_listing() {
+        let profile = Profile::from_vars([
+            ("USE_EXPAND_IMPLICIT", "KERNEL USERLAND"),
+            ("USE_EXPAND_VALUES_KERNEL", "linux"),
+            ("USE_EXPAND_VALUES_USERLAND", "GNU"),
+            ("USE_EXPAND", "KERNEL"),
+        ]);
+        assert_eq!(
+            profile.implicit_flags().into_iter().collect::<Vec<_>>(),
+            ["kernel_linux"]
+        );
+    }
+
+    #[test]
+    fn repeated_variable_replaces() {
+        let profile = Profile::from_vars([("IUSE_IMPLICIT", "a b"), ("IUSE_IMPLICIT", "c")]);
+        assert_eq!(profile.iuse_implicit().collect::<Vec<_>>(), ["c"]);
+    }
+}
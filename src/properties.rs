@@ -0,0 +1,295 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use winnow::prelude::*;
+
+use crate::dep_spec::{self, fmt_spec, parse_spec, ParseSpec, Shape, SpecTree};
+use crate::eapi::Eapi;
+use crate::eapi_feature::EapiFeature;
+use crate::eapi_registry::EapiRules;
+use crate::error::{Error, Result};
+use crate::restrict::parse_token;
+use crate::use_flags::UseFlagSet;
+
+/// A single `PROPERTIES` token.
+///
+/// Tokens defined by PMS get their own variant; anything else is preserved
+/// verbatim in [`PropertyKind::Other`].
+///
+/// See [PMS 7.3.7](https://projects.gentoo.org/pms/9/pms.html#properties).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PropertyKind {
+    /// `live` — the package is built from a live source (e.g. VCS).
+    Live,
+    /// `interactive` — the build requires user interaction.
+    Interactive,
+    /// `test_network` — tests require network access.
+    TestNetwork,
+    /// Any other token.
+    Other(String),
+}
+
+impl PropertyKind {
+    /// The token as it appears in `PROPERTIES`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            PropertyKind::Live => "live",
+            PropertyKind::Interactive => "interactive",
+            PropertyKind::TestNetwork => "test_network",
+            PropertyKind::Other(s) => s,
+        }
+    }
+}
+
+impl From<&str> for PropertyKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "live" => PropertyKind::Live,
+            "interactive" => PropertyKind::Interactive,
+            "test_network" => PropertyKind::TestNetwork,
+            _ => PropertyKind::Other(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for PropertyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A node in a `PROPERTIES` expression.
+///
+/// Shares the `RESTRICT` grammar: tokens and `flag? ( ... )` groups.
+///
+/// See [PMS 7.3.7](https://projects.gentoo.org/pms/9/pms.html#properties).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PropertiesExpr {
+    /// A single property token (e.g. `live`).
+    Token(PropertyKind),
+    /// `flag? ( ... )` or `!flag? ( ... )` conditional group.
+    UseConditional {
+        /// USE flag name.
+        flag: String,
+        /// `true` for `!flag?` (negated conditional).
+        negated: bool,
+        /// Entries guarded by this flag.
+        entries: Vec<PropertiesExpr>,
+    },
+}
+
+impl PropertiesExpr {
+    /// The token `kind`, e.g. `"live"`.
+    pub fn token(kind: &str) -> Self {
+        PropertiesExpr::Token(PropertyKind::from(kind))
+    }
+
+    /// `flag? ( entries... )`, or `!flag? ( entries... )` if `negated`.
+    pub fn conditional(
+        flag: impl Into<String>,
+        negated: bool,
+        entries: impl IntoIterator<Item = Self>,
+    ) -> Self {
+        PropertiesExpr::UseConditional {
+            flag: flag.into(),
+            negated,
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    /// Parse a `PROPERTIES` expression string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{PropertiesExpr, PropertyKind};
+    ///
+    /// let entries = PropertiesExpr::parse("live test? ( test_network )").unwrap();
+    /// assert_eq!(
+    ///     PropertiesExpr::flat_tokens(&entries),
+    ///     vec![&PropertyKind::Live, &PropertyKind::TestNetwork],
+    /// );
+    /// ```
+    pub fn parse(input: &str) -> Result<Vec<PropertiesExpr>> {
+        parse_spec
+            .parse(input)
+            .map_err(|e| Error::InvalidRestrict(format!("{e}")))
+    }
+
+    /// Parse a `PROPERTIES` expression, rejecting syntax not allowed by `eapi`.
+    ///
+    /// USE-conditional groups are only allowed from EAPI 8.
+    pub fn parse_with_eapi(input: &str, eapi: &Eapi) -> Result<Vec<PropertiesExpr>> {
+        Self::parse_in(input, &EapiRules::new(eapi, None))
+    }
+
+    /// [`PropertiesExpr::parse_with_eapi`] against the features of `rules`.
+    pub(crate) fn parse_in(input: &str, rules: &EapiRules<'_>) -> Result<Vec<PropertiesExpr>> {
+        let entries = Self::parse(input)?;
+        if !rules.supports(EapiFeature::UseConditionalRestrict) {
+            if let Some(PropertiesExpr::UseConditional { flag, .. }) = entries
+                .iter()
+                .find(|e| matches!(e, PropertiesExpr::UseConditional { .. }))
+            {
+                return Err(rules.unsupported(format!("conditional PROPERTIES '{flag}? ( ... )'")));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Collect all plain token values, ignoring USE-conditional structure.
+    pub fn flat_tokens(entries: &[PropertiesExpr]) -> Vec<&PropertyKind> {
+        dep_spec::leaves(entries)
+            .into_iter()
+            .filter_map(Self::kind)
+            .collect()
+    }
+
+    /// The tokens active under `use_flags`.
+    pub fn evaluate<'a>(
+        entries: &'a [PropertiesExpr],
+        use_flags: &UseFlagSet,
+    ) -> Vec<&'a PropertyKind> {
+        dep_spec::active_leaves(entries, use_flags)
+            .into_iter()
+            .filter_map(Self::kind)
+            .collect()
+    }
+
+    /// Add every USE flag referenced by a conditional group to `out`.
+    pub(crate) fn collect_flags<'a>(entries: &'a [PropertiesExpr], out: &mut BTreeSet<&'a str>) {
+        dep_spec::collect_flags(entries, out);
+    }
+
+    /// The token of a [`PropertiesExpr::Token`].
+    fn kind(&self) -> Option<&PropertyKind> {
+        match self {
+            PropertiesExpr::Token(kind) => Some(kind),
+            PropertiesExpr::UseConditional { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for PropertiesExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_spec(f, self, false)
+    }
+}
+
+impl SpecTree for PropertiesExpr {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            PropertiesExpr::Token(_) => Shape::Leaf,
+            PropertiesExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => Shape::UseConditional {
+                flag,
+                negated: *negated,
+                entries,
+            },
+        }
+    }
+
+    fn fmt_leaf(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertiesExpr::Token(kind) => write!(f, "{kind}"),
+            PropertiesExpr::UseConditional { .. } => Ok(()),
+        }
+    }
+}
+
+impl ParseSpec for PropertiesExpr {
+    fn parse_leaf(input: &mut &str) -> ModalResult<Self> {
+        parse_token.map(PropertiesExpr::token).parse_next(input)
+    }
+
+    fn conditional(flag: String, negated: bool, entries: Vec<Self>) -> Self {
+        PropertiesExpr::UseConditional {
+            flag,
+            negated,
+            entries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_known_tokens() {
+        let entries = PropertiesExpr::parse("live interactive test_network").unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                PropertiesExpr::Token(PropertyKind::Live),
+                PropertiesExpr::Token(PropertyKind::Interactive),
+                PropertiesExpr::Token(PropertyKind::TestNetwork),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_other_token() {
+        let entries = PropertiesExpr::parse("set").unwrap();
+        assert_eq!(
+            entries,
+            vec![PropertiesExpr::Token(PropertyKind::Other(
+                "set".to_string()
+            ))]
+        );
+    }
+
+    #[test]
+    fn parse_conditional() {
+        let entries = PropertiesExpr::parse("!test? ( interactive )").unwrap();
+        assert_eq!(
+            entries,
+            vec![PropertiesExpr::UseConditional {
+                flag: "test".to_string(),
+                negated: true,
+                entries: vec![PropertiesExpr::Token(PropertyKind::Interactive)],
+            }]
+        );
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let input = "live test? ( test_network )";
+        let entries = PropertiesExpr::parse(input).unwrap();
+        let s: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        assert_eq!(s.join(" "), input);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(PropertiesExpr::parse("test? ( live").is_err());
+    }
+
+    #[test]
+    fn parse_with_eapi_gating() {
+        assert!(PropertiesExpr::parse_with_eapi("test? ( test_network )", &Eapi::Eight).is_ok());
+        assert!(matches!(
+            PropertiesExpr::parse_with_eapi("test? ( test_network )", &Eapi::Seven),
+            Err(Error::UnsupportedInEapi {
+                eapi: Eapi::Seven,
+                ..
+            })
+        ));
+        assert!(PropertiesExpr::parse_with_eapi("live", &Eapi::Zero).is_ok());
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(
+            vec![PropertiesExpr::conditional(
+                "test",
+                false,
+                [PropertiesExpr::token("test_network")]
+            )],
+            PropertiesExpr::parse("test? ( test_network )").unwrap()
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::bit_set::{bit_set, BitIndex};
+use crate::properties::{PropertiesExpr, PropertyKind};
+use crate::use_flags::UseFlagSet;
+
+bit_set! {
+    /// A compact set of the known `PROPERTIES` tokens.
+    ///
+    /// Each [`PropertyKind`] variant other than [`PropertyKind::Other`] maps to one bit, so
+    /// membership tests and set operations are O(1) and the set is `Copy`.
+    /// Tokens without a dedicated variant cannot be stored and are ignored;
+    /// keep the [`PropertiesExpr`] list around if they matter.
+    ///
+    /// See [PMS 7.3.7](https://projects.gentoo.org/pms/9/pms.html#properties).
+    PropertySet(u8), PropertyKind, empty = ""
+}
+
+impl PropertySet {
+    /// The tokens of `entries` active under `use_flags`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{PropertiesExpr, PropertyKind, PropertySet, UseFlagSet};
+    ///
+    /// let entries = PropertiesExpr::parse("live test? ( test_network )").unwrap();
+    /// let set = PropertySet::from_expr(&entries, &UseFlagSet::new());
+    /// assert!(set.contains(&PropertyKind::Live));
+    /// assert!(!set.contains(&PropertyKind::TestNetwork));
+    /// ```
+    pub fn from_expr(entries: &[PropertiesExpr], use_flags: &UseFlagSet) -> Self {
+        PropertiesExpr::evaluate(entries, use_flags)
+            .into_iter()
+            .collect()
+    }
+}
+
+impl BitIndex for PropertyKind {
+    const KNOWN: &'static [Self] = &[
+        PropertyKind::Live,
+        PropertyKind::Interactive,
+        PropertyKind::TestNetwork,
+    ];
+
+    fn bit_index(&self) -> Option<u8> {
+        Some(match self {
+            PropertyKind::Live => 0,
+            PropertyKind::Interactive => 1,
+            PropertyKind::TestNetwork => 2,
+            PropertyKind::Other(_) => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_conditional_expr() {
+        let entries = PropertiesExpr::parse("interactive test? ( test_network )").unwrap();
+        let set = PropertySet::from_expr(&entries, &UseFlagSet::parse("test"));
+        assert_eq!(set.to_string(), "interactive test_network");
+        assert_eq!(
+            PropertySet::all() - set,
+            [PropertyKind::Live].iter().collect()
+        );
+    }
+
+    #[test]
+    fn bit_indices_follow_known() {
+        for (i, kind) in PropertyKind::KNOWN.iter().enumerate() {
+            assert_eq!(kind.bit_index(), Some(i as u8));
+        }
+    }
+}
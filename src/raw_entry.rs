@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use crate::cache::CacheEntry;
+use crate::error::Result;
+
+/// A md5-cache entry that keeps the text of each field as written.
+///
+/// Parsing normalizes values: whitespace is collapsed, groups are
+/// reformatted and so on. Alongside the parsed [`CacheEntry`], this keeps
+/// every `KEY=VALUE` line's original value, so tools can show or re-emit
+/// the upstream text while analysing the typed fields.
+/// [`RawCacheEntry::serialize`] writes the original text of each field
+/// whose parsed value is unchanged, and the normalized form of the
+/// others.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::RawCacheEntry;
+///
+/// let input = "DESCRIPTION=x\nLICENSE=|| (  MIT BSD )\nSLOT=0\n";
+/// let mut raw = RawCacheEntry::parse(input).unwrap();
+/// assert_eq!(raw.raw("LICENSE"), Some("|| (  MIT BSD )"));
+/// assert_eq!(raw.entry().metadata.license.as_ref().unwrap().to_string(), "|| ( MIT BSD )");
+///
+/// raw.entry_mut().metadata.description = "y".to_string();
+/// assert_eq!(
+///     raw.serialize(),
+///     "DEFINED_PHASES=-\nDESCRIPTION=y\nEAPI=0\nLICENSE=|| (  MIT BSD )\nSLOT=0\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawCacheEntry {
+    entry: CacheEntry,
+    /// The value of every line, by key as written.
+    raw: BTreeMap<String, String>,
+    /// The normalized fields right after parsing.
+    parsed: Vec<(&'static str, String)>,
+}
+
+impl RawCacheEntry {
+    /// Parse a md5-cache entry as [`CacheEntry::parse`] does, keeping the
+    /// raw values.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut raw = BTreeMap::new();
+        let entry = CacheEntry::parse_mapped(input, |key, value| {
+            raw.insert(key.to_string(), value.to_string());
+            Some(key)
+        })?;
+        let parsed = entry.fields();
+        Ok(RawCacheEntry { entry, raw, parsed })
+    }
+
+    /// The parsed entry.
+    pub fn entry(&self) -> &CacheEntry {
+        &self.entry
+    }
+
+    /// The parsed entry, for editing; [`RawCacheEntry::serialize`] then
+    /// writes the changed fields in normalized form.
+    pub fn entry_mut(&mut self) -> &mut CacheEntry {
+        &mut self.entry
+    }
+
+    /// The parsed entry, dropping the raw text.
+    pub fn into_entry(self) -> CacheEntry {
+        self.entry
+    }
+
+    /// The value of `key` as written, e.g. `raw("RDEPEND")` or
+    /// `raw("_md5_")`. Keys the parser ignores are kept too.
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.raw.get(key).map(String::as_str)
+    }
+
+    /// Serialize to md5-cache format like [`CacheEntry::serialize`],
+    /// keeping the original text of every field not changed since
+    /// parsing.
+    ///
+    /// Only the keys [`CacheEntry::serialize`] writes are emitted.
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for (key, value) in self.entry.fields() {
+            let unchanged = self.parsed.contains(&(key, value.clone()));
+            let value = match self.raw.get(key) {
+                Some(raw) if unchanged => raw,
+                _ => &value,
+            };
+            out.push_str(key);
+            out.push('=');
+            out.push_str(value);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_untouched_text() {
+        let input = "DEFINED_PHASES=compile  install\nDESCRIPTION=x\nEAPI=8\n\
+                     RDEPEND=dev-libs/a\tdev-libs/b\nSLOT=0\nX-FORK=1\n_md5_=abc\n";
+        let mut raw = RawCacheEntry::parse(input).unwrap();
+        assert_eq!(raw.entry(), &CacheEntry::parse(input).unwrap());
+        assert_eq!(raw.raw("X-FORK"), Some("1"));
+        assert_eq!(raw.serialize(), input.replace("X-FORK=1\n", ""));
+
+        raw.entry_mut().metadata.rdepend.pop();
+        raw.entry_mut().md5 = None;
+        assert_eq!(
+            raw.serialize(),
+            "DEFINED_PHASES=compile  install\nDESCRIPTION=x\nEAPI=8\n\
+             RDEPEND=dev-libs/a\nSLOT=0\n"
+        );
+        assert!(raw.into_entry().md5.is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::dep_spec::DepSpec;
+use crate::metadata_key::MetadataKey;
+
+/// Reports redundant and self-referencing dependencies.
+///
+/// Using [`EbuildMetadata::dependencies`](crate::EbuildMetadata::dependencies),
+/// flags an atom listed twice in one class under the same USE
+/// conditionals, an atom in `PDEPEND` that `RDEPEND` already requires
+/// (both are runtime dependencies, so the post-merge one adds nothing),
+/// and a package depending on itself. Atoms inside `||`, `^^` and `??`
+/// groups are alternatives and are not compared; blockers may name the
+/// package itself.
+///
+/// See [PMS 8.1](https://projects.gentoo.org/pms/9/pms.html#dependency-classes).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, RedundantDepCheck};
+///
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-1").unwrap(),
+///     CacheEntry::parse(
+///         "DESCRIPTION=x\nSLOT=0\nRDEPEND=dev-libs/a app-misc/foo\nPDEPEND=dev-libs/a\n",
+///     )
+///     .unwrap(),
+/// )]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(RedundantDepCheck);
+/// let findings: Vec<_> = runner.run(&repo).into_iter().map(|f| f.message).collect();
+/// assert_eq!(
+///     findings,
+///     [
+///         "RDEPEND depends on the package itself: app-misc/foo",
+///         "PDEPEND repeats RDEPEND: dev-libs/a",
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RedundantDepCheck;
+
+impl MetadataCheck for RedundantDepCheck {
+    fn name(&self) -> &'static str {
+        "redundant-deps"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let mut report = |severity, message| {
+            findings.push(cx.finding(self.name(), severity, message));
+        };
+        let mut rdepend = Vec::new();
+        for (class, entries) in cx.entry.metadata.dependencies() {
+            let spec = DepSpec::from_deps(entries);
+            let mut seen = Vec::new();
+            for (guards, dep) in spec.required_leaves() {
+                let context: String = guards
+                    .iter()
+                    .map(|&(flag, negated)| format!("{}{flag}? ", if negated { "!" } else { "" }))
+                    .collect();
+                if dep.cpn == cx.cpv.cpn && dep.blocker.is_none() {
+                    report(
+                        Severity::Warning,
+                        format!("{class} depends on the package itself: {dep}"),
+                    );
+                }
+                let key = (context.clone(), dep.to_string());
+                if seen.contains(&key) {
+                    report(
+                        Severity::Warning,
+                        format!("{class} lists {context}{dep} twice"),
+                    );
+                } else if class == MetadataKey::Pdepend && rdepend.contains(&key) {
+                    report(
+                        Severity::Warning,
+                        format!("{class} repeats {}: {context}{dep}", MetadataKey::Rdepend),
+                    );
+                }
+                seen.push(key);
+            }
+            if class == MetadataKey::Rdepend {
+                rdepend = seen;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn conditionals_and_groups() {
+        let messages = |deps: &str| -> Vec<String> {
+            let text = format!("EAPI=8\nDESCRIPTION=x\nSLOT=0\n{deps}");
+            run_check(RedundantDepCheck, &[("app-misc/foo-1", &text)])
+                .into_iter()
+                .map(|f| f.message)
+                .collect()
+        };
+        assert_eq!(
+            messages(
+                "DEPEND=ssl? ( dev-libs/a ( dev-libs/a ) ) dev-libs/a \
+                 || ( dev-libs/b dev-libs/b )\n\
+                 RDEPEND=!test? ( dev-libs/c )\nPDEPEND=dev-libs/c !test? ( dev-libs/c )\n"
+            ),
+            [
+                "DEPEND lists ssl? dev-libs/a twice",
+                "PDEPEND repeats RDEPEND: !test? dev-libs/c",
+            ]
+        );
+        assert!(messages(
+            "DEPEND=dev-libs/a\nBDEPEND=dev-libs/a\nRDEPEND=!<app-misc/foo-1 dev-libs/a\n"
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use portage_atom::Cpv;
+
+use crate::error::{Error, Result};
+use crate::md5::md5_hex;
+use crate::metadata_repo::{read_dir, MetadataRepo};
+
+/// Why a cache entry needs regenerating.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaleReason {
+    /// The ebuild has no cache entry.
+    Missing,
+    /// The cache entry has no ebuild.
+    Orphaned,
+    /// The entry records no `_md5_`.
+    NoChecksum,
+    /// The ebuild's MD5 differs from `_md5_`.
+    EbuildChanged,
+    /// An inherited eclass's MD5 differs from `_eclasses_`.
+    EclassChanged(String),
+    /// An inherited eclass was not found in any eclass directory.
+    EclassMissing(String),
+}
+
+/// A cache entry that needs regenerating, found by [`RegenPlan`].
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleEntry {
+    /// The package version.
+    pub cpv: Cpv,
+    /// Why the entry is stale.
+    pub reason: StaleReason,
+}
+
+/// The out-of-date entries of a repository's md5-cache.
+///
+/// Each entry's `_md5_` is checked against its ebuild, and each
+/// `_eclasses_` checksum against the eclass file. Eclasses are looked up
+/// in the repository's `eclass/` directory, then in those of its masters.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegenPlan {
+    /// Every stale entry, sorted by package version.
+    pub stale: Vec<StaleEntry>,
+}
+
+impl RegenPlan {
+    /// Check the cache of `repo`, resolving eclasses through `masters`.
+    ///
+    /// Entries that fail to parse are reported as errors.
+    pub fn new(repo: &MetadataRepo, masters: &[MetadataRepo]) -> Result<Self> {
+        Self::scan(repo, masters, None)
+    }
+
+    /// Like [`RegenPlan::new`], checking only the entries and ebuilds of
+    /// `only`, e.g. the package versions a commit touched.
+    pub fn for_cpvs(
+        repo: &MetadataRepo,
+        masters: &[MetadataRepo],
+        only: &BTreeSet<Cpv>,
+    ) -> Result<Self> {
+        Self::scan(repo, masters, Some(only))
+    }
+
+    fn scan(
+        repo: &MetadataRepo,
+        masters: &[MetadataRepo],
+        only: Option<&BTreeSet<Cpv>>,
+    ) -> Result<Self> {
+        let (ebuilds, cpvs) = match only {
+            None => (find_ebuilds(repo.root())?, repo.cpvs()?),
+            Some(only) => (
+                only.iter()
+                    .map(|cpv| (cpv.clone(), ebuild_path(repo.root(), cpv)))
+                    .filter(|(_, path)| path.is_file())
+                    .collect(),
+                only.iter()
+                    .filter(|cpv| repo.entry_path(cpv).is_file())
+                    .cloned()
+                    .collect(),
+            ),
+        };
+        let mut eclasses = EclassDigests::new(repo, masters);
+        let mut stale = Vec::new();
+
+        for cpv in &cpvs {
+            let Some(ebuild) = ebuilds.get(cpv) else {
+                stale.push(StaleEntry {
+                    cpv: cpv.clone(),
+                    reason: StaleReason::Orphaned,
+                });
+                continue;
+            };
+            let entry = repo.read(cpv)?;
+            let reason = match &entry.md5 {
+                None => Some(StaleReason::NoChecksum),
+                Some(md5) if !md5.eq_ignore_ascii_case(&digest(ebuild)?) => {
+                    Some(StaleReason::EbuildChanged)
+                }
+                Some(_) => entry
+                    .eclasses
+                    .iter()
+                    .find_map(|(name, md5)| match eclasses.get(name.as_str()) {
+                        Ok(Some(digest)) if digest.eq_ignore_ascii_case(md5.as_str()) => None,
+                        Ok(Some(_)) => Some(Ok(StaleReason::EclassChanged(name.to_string()))),
+                        Ok(None) => Some(Ok(StaleReason::EclassMissing(name.to_string()))),
+                        Err(e) => Some(Err(e)),
+                    })
+                    .transpose()?,
+            };
+            if let Some(reason) = reason {
+                stale.push(StaleEntry {
+                    cpv: cpv.clone(),
+                    reason,
+                });
+            }
+        }
+
+        let cached: BTreeSet<&Cpv> = cpvs.iter().collect();
+        stale.extend(
+            ebuilds
+                .keys()
+                .filter(|cpv| !cached.contains(cpv))
+                .map(|cpv| StaleEntry {
+                    cpv: cpv.clone(),
+                    reason: StaleReason::Missing,
+                }),
+        );
+        stale.sort_by(|a, b| a.cpv.cmp(&b.cpv));
+        Ok(RegenPlan { stale })
+    }
+
+    /// Whether the cache is up to date.
+    pub fn is_empty(&self) -> bool {
+        self.stale.is_empty()
+    }
+}
+
+impl fmt::Display for StaleReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StaleReason::Missing => f.write_str("no cache entry"),
+            StaleReason::Orphaned => f.write_str("no ebuild"),
+            StaleReason::NoChecksum => f.write_str("no _md5_"),
+            StaleReason::EbuildChanged => f.write_str("ebuild changed"),
+            StaleReason::EclassChanged(name) => write!(f, "eclass {name} changed"),
+            StaleReason::EclassMissing(name) => write!(f, "eclass {name} not found"),
+        }
+    }
+}
+
+impl fmt::Display for StaleEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.cpv, self.reason)
+    }
+}
+
+/// Eclass digests, computed once per eclass.
+pub(crate) struct EclassDigests {
+    dirs: Vec<PathBuf>,
+    digests: BTreeMap<String, Option<String>>,
+}
+
+impl EclassDigests {
+    pub(crate) fn new(repo: &MetadataRepo, masters: &[MetadataRepo]) -> Self {
+        EclassDigests {
+            dirs: std::iter::once(repo)
+                .chain(masters)
+                .map(|repo| repo.root().join("eclass"))
+                .collect(),
+            digests: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn get(&mut self, name: &str) -> Result<Option<&str>> {
+        if !self.digests.contains_key(name) {
+            let mut found = None;
+            for dir in &self.dirs {
+                let path = dir.join(format!("{name}.eclass"));
+                match digest(&path) {
+                    Ok(digest) => {
+                        found = Some(digest);
+                        break;
+                    }
+                    Err(e) if e.io_kind() == Some(io::ErrorKind::NotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            self.digests.insert(name.to_string(), found);
+        }
+        Ok(self.digests[name].as_deref())
+    }
+}
+
+pub(crate) fn digest(path: &Path) -> Result<String> {
+    fs::read(path)
+        .map(|data| md5_hex(&data))
+        .map_err(|e| Error::io(path, e))
+}
+
+/// The ebuilds of a repository by package version, from
+/// `<category>/<package>/<package>-<version>.ebuild`.
+pub(crate) fn find_ebuilds(root: &Path) -> Result<BTreeMap<Cpv, PathBuf>> {
+    let mut ebuilds = BTreeMap::new();
+    for category in subdirs(root)? {
+        let Some(category_name) = category.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if matches!(
+            category_name,
+            "eclass" | "licenses" | "metadata" | "profiles"
+        ) || category_name.starts_with('.')
+        {
+            continue;
+        }
+        for package in subdirs(&category)? {
+            for path in read_dir(&package)? {
+                let Some(pf) = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_suffix(".ebuild"))
+                else {
+                    continue;
+                };
+                if let Ok(cpv) = Cpv::parse(&format!("{category_name}/{pf}")) {
+                    ebuilds.insert(cpv, path);
+                }
+            }
+        }
+    }
+    Ok(ebuilds)
+}
+
+/// The path of the ebuild of `cpv` under `root`.
+fn ebuild_path(root: &Path, cpv: &Cpv) -> PathBuf {
+    let name = cpv.to_string();
+    let pf = name.rsplit('/').next().unwrap_or(&name);
+    root.join(cpv.cpn.category.as_str())
+        .join(cpv.cpn.package.as_str())
+        .join(format!("{pf}.ebuild"))
+}
+
+fn subdirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(read_dir(dir)?.into_iter().filter(|p| p.is_dir()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn cpv(s: &str) -> Cpv {
+        Cpv::parse(s).unwrap()
+    }
+
+    #[test]
+    fn stale_entries() {
+        let base = TempDir::new("regen-plan");
+        let (root, master_root) = (base.join("overlay"), base.join("gentoo"));
+        let repo = MetadataRepo::new(&root);
+        let master = MetadataRepo::new(&master_root);
+        let cache = repo.cache_dir();
+        fs::create_dir_all(cache.join("app-misc")).unwrap();
+        fs::create_dir_all(root.join("app-misc/foo")).unwrap();
+        fs::create_dir_all(root.join("eclass")).unwrap();
+        fs::create_dir_all(master_root.join("eclass")).unwrap();
+
+        let ebuild = "EAPI=8\n";
+        let md5 = md5_hex(ebuild.as_bytes());
+        let (local, upstream) = ("local eclass\n", "upstream eclass\n");
+        fs::write(root.join("eclass/local.eclass"), local).unwrap();
+        fs::write(master_root.join("eclass/upstream.eclass"), upstream).unwrap();
+        for version in ["1", "2", "3", "4", "5"] {
+            fs::write(
+                root.join(format!("app-misc/foo/foo-{version}.ebuild")),
+                ebuild,
+            )
+            .unwrap();
+        }
+        let entry = |md5: &str, eclasses: &[(&str, String)]| {
+            let eclasses: Vec<_> = eclasses
+                .iter()
+                .map(|(name, md5)| format!("{name}\t{md5}"))
+                .collect();
+            format!(
+                "DESCRIPTION=x\nSLOT=0\n_md5_={md5}\n_eclasses_={}\n",
+                eclasses.join("\t")
+            )
+        };
+        let local_md5 = md5_hex(local.as_bytes());
+        let upstream_md5 = md5_hex(upstream.as_bytes());
+        let fresh = entry(
+            &md5,
+            &[("local", local_md5.clone()), ("upstream", upstream_md5)],
+        );
+        fs::write(cache.join("app-misc/foo-1"), &fresh).unwrap();
+        fs::write(cache.join("app-misc/foo-2"), entry("0", &[])).unwrap();
+        fs::write(
+            cache.join("app-misc/foo-3"),
+            entry(&md5, &[("local", "0".into())]),
+        )
+        .unwrap();
+        fs::write(
+            cache.join("app-misc/foo-4"),
+            entry(&md5, &[("gone", local_md5)]),
+        )
+        .unwrap();
+        fs::write(cache.join("app-misc/foo-6"), &fresh).unwrap();
+
+        let plan = RegenPlan::new(&repo, std::slice::from_ref(&master)).unwrap();
+        let stale: Vec<_> = plan.stale.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            stale,
+            [
+                "app-misc/foo-2: ebuild changed",
+                "app-misc/foo-3: eclass local changed",
+                "app-misc/foo-4: eclass gone not found",
+                "app-misc/foo-5: no cache entry",
+                "app-misc/foo-6: no ebuild",
+            ]
+        );
+
+        let only = BTreeSet::from([cpv("app-misc/foo-1"), cpv("app-misc/foo-5")]);
+        let plan = RegenPlan::for_cpvs(&repo, &[master], &only).unwrap();
+        assert_eq!(plan.stale.len(), 1);
+        assert_eq!(plan.stale[0].to_string(), "app-misc/foo-5: no cache entry");
+
+        let plan = RegenPlan::new(&repo, &[]).unwrap();
+        assert_eq!(
+            plan.stale[0],
+            StaleEntry {
+                cpv: cpv("app-misc/foo-1"),
+                reason: StaleReason::EclassMissing("upstream".to_string()),
+            }
+        );
+    }
+}
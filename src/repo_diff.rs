@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::metadata::EbuildMetadata;
+use crate::metadata_key::MetadataKey;
+
+/// The metadata differences between two snapshots of a repository.
+///
+/// Fields are compared after [`EbuildMetadata::normalize`], so reordering
+/// `KEYWORDS` or `IUSE` is not a change. Only metadata variables are
+/// compared; `_md5_` and `_eclasses_` are ignored.
+///
+/// See [PMS 7](https://projects.gentoo.org/pms/9/pms.html#ebuilddefined-variables).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, MetadataKey, RepoDiff};
+///
+/// let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+/// let old = BTreeMap::from([(
+///     cpv.clone(),
+///     CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64 ~x86\n").unwrap(),
+/// )]);
+/// let new = BTreeMap::from([(
+///     cpv.clone(),
+///     CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~x86 amd64\n").unwrap(),
+/// )]);
+///
+/// let diff = RepoDiff::new(&old, &new);
+/// assert_eq!(diff.changed[0].changes[0].key, MetadataKey::Keywords);
+/// assert_eq!(
+///     diff.to_string(),
+///     "~ app-misc/foo-1.0\n    KEYWORDS: ~amd64 ~x86 -> amd64 ~x86\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoDiff {
+    /// Package versions only in the new snapshot.
+    pub added: Vec<Cpv>,
+    /// Package versions only in the old snapshot.
+    pub removed: Vec<Cpv>,
+    /// Package versions in both whose metadata differs.
+    pub changed: Vec<PackageDiff>,
+}
+
+/// The changed fields of one package version in a [`RepoDiff`].
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageDiff {
+    /// The package version.
+    pub cpv: Cpv,
+    /// Every changed field, in [`MetadataKey::ALL`] order.
+    pub changes: Vec<FieldChange>,
+}
+
+/// One changed metadata variable.
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// The variable.
+    pub key: MetadataKey,
+    /// The old value, in md5-cache form; empty if unset.
+    pub old: String,
+    /// The new value, in md5-cache form; empty if unset.
+    pub new: String,
+}
+
+impl RepoDiff {
+    /// Compare two snapshots, as loaded by
+    /// [`MetadataRepo::load`](crate::MetadataRepo::load).
+    pub fn new(old: &BTreeMap<Cpv, CacheEntry>, new: &BTreeMap<Cpv, CacheEntry>) -> Self {
+        let mut diff = RepoDiff::default();
+        for (cpv, old_entry) in old {
+            match new.get(cpv) {
+                Some(new_entry) => {
+                    let changes = FieldChange::between(&old_entry.metadata, &new_entry.metadata);
+                    if !changes.is_empty() {
+                        diff.changed.push(PackageDiff {
+                            cpv: cpv.clone(),
+                            changes,
+                        });
+                    }
+                }
+                None => diff.removed.push(cpv.clone()),
+            }
+        }
+        diff.added = new
+            .keys()
+            .filter(|cpv| !old.contains_key(*cpv))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Whether the snapshots have the same metadata.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl FieldChange {
+    /// The fields that differ between `old` and `new`, once both are
+    /// normalized.
+    pub fn between(old: &EbuildMetadata, new: &EbuildMetadata) -> Vec<FieldChange> {
+        let (mut old, mut new) = (old.clone(), new.clone());
+        old.normalize();
+        new.normalize();
+        MetadataKey::ALL
+            .iter()
+            .filter_map(|&key| {
+                let text = |metadata: &EbuildMetadata| {
+                    let value = metadata.get(key);
+                    if value.is_empty() {
+                        String::new()
+                    } else {
+                        value.to_string()
+                    }
+                };
+                let (old, new) = (text(&old), text(&new));
+                (old != new).then_some(FieldChange { key, old, new })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for RepoDiff {
+    /// `+` added, `-` removed and `~` changed package versions, one per
+    /// line, with changed fields indented below.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for cpv in &self.added {
+            writeln!(f, "+ {cpv}")?;
+        }
+        for cpv in &self.removed {
+            writeln!(f, "- {cpv}")?;
+        }
+        for package in &self.changed {
+            writeln!(f, "~ {}", package.cpv)?;
+            for change in &package.changes {
+                writeln!(f, "    {change}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.key, self.old, self.new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(entries: &[(&str, &str)]) -> BTreeMap<Cpv, CacheEntry> {
+        entries
+            .iter()
+            .map(|(cpv, text)| (Cpv::parse(cpv).unwrap(), CacheEntry::parse(text).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn added_removed_changed() {
+        let old = snapshot(&[
+            ("app-misc/foo-1", "DESCRIPTION=foo\nSLOT=0\n"),
+            ("app-misc/foo-2", "DESCRIPTION=foo\nSLOT=0\nIUSE=a b\n"),
+        ]);
+        let new = snapshot(&[
+            (
+                "app-misc/foo-2",
+                "DESCRIPTION=Foo\nSLOT=0\nIUSE=b a\n_md5_=x\n",
+            ),
+            ("app-misc/foo-3", "DESCRIPTION=foo\nSLOT=0\n"),
+        ]);
+        let diff = RepoDiff::new(&old, &new);
+        assert_eq!(diff.added, [Cpv::parse("app-misc/foo-3").unwrap()]);
+        assert_eq!(diff.removed, [Cpv::parse("app-misc/foo-1").unwrap()]);
+        assert_eq!(
+            diff.changed[0].changes,
+            [FieldChange {
+                key: MetadataKey::Description,
+                old: "foo".to_string(),
+                new: "Foo".to_string(),
+            }]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "+ app-misc/foo-3\n- app-misc/foo-1\n~ app-misc/foo-2\n    DESCRIPTION: foo -> Foo\n"
+        );
+    }
+
+    #[test]
+    fn unset_fields() {
+        let old = snapshot(&[("app-misc/foo-1", "DESCRIPTION=foo\nSLOT=0\n")]);
+        let new = snapshot(&[("app-misc/foo-1", "DESCRIPTION=foo\nSLOT=0\nLICENSE=MIT\n")]);
+        let diff = RepoDiff::new(&old, &new);
+        assert_eq!(diff.changed[0].changes[0].old, "");
+        assert_eq!(diff.changed[0].changes[0].new, "MIT");
+        assert!(RepoDiff::new(&old, &old).is_empty());
+    }
+}
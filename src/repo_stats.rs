@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::cache::CacheEntry;
+use crate::interner::Interner;
+use crate::keyword::Stability;
+
+/// Summary counts over the cache entries of a repository.
+///
+/// Repository statistics are not specified by PMS.
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{CacheEntry, RepoStats};
+///
+/// let entries = [
+///     CacheEntry::parse("EAPI=8\nDESCRIPTION=x\nSLOT=0\nKEYWORDS=amd64 ~arm64\nLICENSE=MIT\n"),
+///     CacheEntry::parse("EAPI=7\nDESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64\nLICENSE=|| ( MIT GPL-2 )\n"),
+/// ]
+/// .map(Result::unwrap);
+///
+/// let stats = RepoStats::new(&entries);
+/// assert_eq!(stats.entries, 2);
+/// assert_eq!(stats.eapis["8"], 1);
+/// assert_eq!(stats.keywords["amd64"].stable, 1);
+/// assert_eq!(stats.keywords["amd64"].testing, 1);
+/// assert_eq!(stats.licenses["MIT"], 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoStats {
+    /// Number of entries counted.
+    pub entries: usize,
+    /// Entries per EAPI.
+    pub eapis: BTreeMap<String, usize>,
+    /// Keyword coverage per arch.
+    pub keywords: BTreeMap<String, ArchCoverage>,
+    /// Entries per license name, counting each name once per entry.
+    pub licenses: BTreeMap<String, usize>,
+}
+
+/// How many entries are keyworded on an arch, in [`RepoStats`].
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchCoverage {
+    /// Entries stable on the arch.
+    pub stable: usize,
+    /// Entries in testing on the arch.
+    pub testing: usize,
+}
+
+impl RepoStats {
+    /// Count `entries`.
+    pub fn new<'a, I: Interner + 'a>(entries: impl IntoIterator<Item = &'a CacheEntry<I>>) -> Self {
+        let mut stats = RepoStats::default();
+        for entry in entries {
+            stats.add(entry);
+        }
+        stats
+    }
+
+    /// Count one more entry.
+    pub fn add<I: Interner>(&mut self, entry: &CacheEntry<I>) {
+        let metadata = &entry.metadata;
+        self.entries += 1;
+        *self.eapis.entry(metadata.eapi.to_string()).or_default() += 1;
+        for keyword in &metadata.keywords {
+            let stable = match keyword.stability {
+                Stability::Stable => true,
+                Stability::Testing => false,
+                _ => continue,
+            };
+            let coverage = self.keywords.entry(keyword.arch.to_string()).or_default();
+            if stable {
+                coverage.stable += 1;
+            } else {
+                coverage.testing += 1;
+            }
+        }
+        if let Some(license) = &metadata.license {
+            for name in license.licenses().collect::<BTreeSet<_>>() {
+                *self.licenses.entry(name.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    /// License names by descending count, then by name.
+    pub fn licenses_by_count(&self) -> Vec<(&str, usize)> {
+        let mut licenses: Vec<_> = self
+            .licenses
+            .iter()
+            .map(|(name, &count)| (name.as_str(), count))
+            .collect();
+        licenses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        licenses
+    }
+}
+
+impl fmt::Display for RepoStats {
+    /// Plain-text tables of the EAPI breakdown, keyword coverage and
+    /// license histogram.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} entries", self.entries)?;
+        writeln!(f, "\nEAPI  ENTRIES")?;
+        for (eapi, count) in &self.eapis {
+            writeln!(f, "{eapi:<5} {count:>7}")?;
+        }
+        writeln!(f, "\nARCH           STABLE TESTING")?;
+        for (arch, coverage) in &self.keywords {
+            writeln!(
+                f,
+                "{arch:<14} {:>6} {:>7}",
+                coverage.stable, coverage.testing
+            )?;
+        }
+        writeln!(f, "\nLICENSE                  ENTRIES")?;
+        for (name, count) in self.licenses_by_count() {
+            writeln!(f, "{name:<24} {count:>7}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        let entries = [
+            "EAPI=8\nDESCRIPTION=x\nSLOT=0\nKEYWORDS=amd64 -x86\nLICENSE=MIT\n",
+            "DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64\nLICENSE=MIT foo? ( MIT BSD )\n",
+        ]
+        .map(|text| CacheEntry::parse(text).unwrap());
+        let stats = RepoStats::new(&entries);
+        assert_eq!(stats.eapis["0"], 1);
+        assert!(!stats.keywords.contains_key("x86"));
+        assert_eq!(stats.licenses_by_count(), [("MIT", 2), ("BSD", 1)]);
+        assert_eq!(
+            stats.to_string(),
+            "2 entries\n\
+             \n\
+             EAPI  ENTRIES\n\
+             0           1\n\
+             8           1\n\
+             \n\
+             ARCH           STABLE TESTING\n\
+             amd64               1       1\n\
+             \n\
+             LICENSE                  ENTRIES\n\
+             MIT                            2\n\
+             BSD                            1\n"
+        );
+    }
+}
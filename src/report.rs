@@ -0,0 +1,368 @@
+use portage_atom::Cpv;
+
+use crate::interner::Interner;
+use crate::parse_report::ParseReport;
+use crate::repo_diff::RepoDiff;
+use crate::repo_stats::RepoStats;
+
+/// A human-readable report, rendered as Markdown or self-contained HTML.
+///
+/// Reports are built from a [`RepoDiff`], [`RepoStats`] or a set of
+/// [`ParseReport`]s, or assembled by hand from headings, paragraphs,
+/// lists and tables. The Markdown form suits PR comments; the HTML form
+/// is a complete page for CI artifacts.
+///
+/// Reports are not specified by PMS.
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{CacheEntry, RepoStats, Report};
+///
+/// let entry = CacheEntry::parse("EAPI=8\nDESCRIPTION=x\nSLOT=0\nLICENSE=MIT\n").unwrap();
+/// let report = Report::stats(&RepoStats::new([&entry]));
+/// assert!(report.to_markdown().contains("| 8 | 1 |\n"));
+/// assert!(report.to_html().contains("<td>MIT</td><td>1</td>"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    title: String,
+    blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Block {
+    Heading(String),
+    Paragraph(String),
+    List(Vec<String>),
+    Table {
+        header: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+}
+
+impl Report {
+    /// Create an empty report.
+    pub fn new(title: impl Into<String>) -> Self {
+        Report {
+            title: title.into(),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Append a section heading.
+    pub fn heading(&mut self, text: impl Into<String>) -> &mut Self {
+        self.blocks.push(Block::Heading(text.into()));
+        self
+    }
+
+    /// Append a paragraph.
+    pub fn paragraph(&mut self, text: impl Into<String>) -> &mut Self {
+        self.blocks.push(Block::Paragraph(text.into()));
+        self
+    }
+
+    /// Append a bulleted list.
+    pub fn list<T: Into<String>>(&mut self, items: impl IntoIterator<Item = T>) -> &mut Self {
+        self.blocks
+            .push(Block::List(items.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Append a table.
+    pub fn table<H, R, C>(
+        &mut self,
+        header: impl IntoIterator<Item = H>,
+        rows: impl IntoIterator<Item = R>,
+    ) -> &mut Self
+    where
+        H: Into<String>,
+        R: IntoIterator<Item = C>,
+        C: Into<String>,
+    {
+        self.blocks.push(Block::Table {
+            header: header.into_iter().map(Into::into).collect(),
+            rows: rows
+                .into_iter()
+                .map(|row| row.into_iter().map(Into::into).collect())
+                .collect(),
+        });
+        self
+    }
+
+    /// The added, removed and changed package versions of `diff`.
+    pub fn diff(diff: &RepoDiff) -> Self {
+        let mut report = Report::new("Metadata changes");
+        report.paragraph(format!(
+            "{} added, {} removed, {} changed.",
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        ));
+        if !diff.added.is_empty() {
+            report
+                .heading("Added")
+                .list(diff.added.iter().map(ToString::to_string));
+        }
+        if !diff.removed.is_empty() {
+            report
+                .heading("Removed")
+                .list(diff.removed.iter().map(ToString::to_string));
+        }
+        if !diff.changed.is_empty() {
+            let rows = diff.changed.iter().flat_map(|package| {
+                package.changes.iter().map(|change| {
+                    [
+                        package.cpv.to_string(),
+                        change.key.to_string(),
+                        change.old.clone(),
+                        change.new.clone(),
+                    ]
+                })
+            });
+            report
+                .heading("Changed")
+                .table(["Package", "Variable", "Old", "New"], rows);
+        }
+        report
+    }
+
+    /// The EAPI breakdown, keyword coverage and license histogram of
+    /// `stats`.
+    pub fn stats(stats: &RepoStats) -> Self {
+        let mut report = Report::new("Repository statistics");
+        report.paragraph(format!("{} entries.", stats.entries));
+        report.heading("EAPI").table(
+            ["EAPI", "Entries"],
+            stats
+                .eapis
+                .iter()
+                .map(|(eapi, count)| [eapi.clone(), count.to_string()]),
+        );
+        report.heading("Keywords").table(
+            ["Arch", "Stable", "Testing"],
+            stats.keywords.iter().map(|(arch, coverage)| {
+                [
+                    arch.clone(),
+                    coverage.stable.to_string(),
+                    coverage.testing.to_string(),
+                ]
+            }),
+        );
+        report.heading("Licenses").table(
+            ["License", "Entries"],
+            stats
+                .licenses_by_count()
+                .into_iter()
+                .map(|(name, count)| [name.to_string(), count.to_string()]),
+        );
+        report
+    }
+
+    /// The errors and warnings of each entry; clean entries are only
+    /// counted.
+    pub fn validation<'a, I: Interner + 'a>(
+        reports: impl IntoIterator<Item = (&'a Cpv, &'a ParseReport<I>)>,
+    ) -> Self {
+        let (mut entries, mut rows) = (0, Vec::new());
+        for (cpv, report) in reports {
+            entries += 1;
+            for error in &report.errors {
+                rows.push([
+                    cpv.to_string(),
+                    "error".to_string(),
+                    error.code().to_string(),
+                    error.to_string(),
+                ]);
+            }
+            for warning in &report.warnings {
+                rows.push([
+                    cpv.to_string(),
+                    "warning".to_string(),
+                    String::new(),
+                    warning.to_string(),
+                ]);
+            }
+        }
+        let errors = rows.iter().filter(|row| row[1] == "error").count();
+        let mut report = Report::new("Validation");
+        report.paragraph(format!(
+            "{entries} entries, {errors} errors, {} warnings.",
+            rows.len() - errors
+        ));
+        if !rows.is_empty() {
+            report
+                .heading("Findings")
+                .table(["Package", "Level", "Code", "Message"], rows);
+        }
+        report
+    }
+
+    /// Render as GitHub-flavoured Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.title);
+        for block in &self.blocks {
+            out.push('\n');
+            match block {
+                Block::Heading(text) => out.push_str(&format!("## {text}\n")),
+                Block::Paragraph(text) => out.push_str(&format!("{text}\n")),
+                Block::List(items) => {
+                    for item in items {
+                        out.push_str(&format!("- {item}\n"));
+                    }
+                }
+                Block::Table { header, rows } => {
+                    let line = |cells: &[String]| {
+                        let cells: Vec<_> = cells.iter().map(|cell| markdown_cell(cell)).collect();
+                        format!("| {} |\n", cells.join(" | "))
+                    };
+                    out.push_str(&line(header));
+                    out.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+                    for row in rows {
+                        out.push_str(&line(row));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Render as a complete HTML page.
+    pub fn to_html(&self) -> String {
+        let title = html_escape(&self.title);
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n"
+        );
+        for block in &self.blocks {
+            match block {
+                Block::Heading(text) => {
+                    out.push_str(&format!("<h2>{}</h2>\n", html_escape(text)));
+                }
+                Block::Paragraph(text) => {
+                    out.push_str(&format!("<p>{}</p>\n", html_escape(text)));
+                }
+                Block::List(items) => {
+                    out.push_str("<ul>\n");
+                    for item in items {
+                        out.push_str(&format!("<li>{}</li>\n", html_escape(item)));
+                    }
+                    out.push_str("</ul>\n");
+                }
+                Block::Table { header, rows } => {
+                    let line = |tag: &str, cells: &[String]| {
+                        let cells: String = cells
+                            .iter()
+                            .map(|cell| format!("<{tag}>{}</{tag}>", html_escape(cell)))
+                            .collect();
+                        format!("<tr>{cells}</tr>\n")
+                    };
+                    out.push_str("<table>\n");
+                    out.push_str(&line("th", header));
+                    for row in rows {
+                        out.push_str(&line("td", row));
+                    }
+                    out.push_str("</table>\n");
+                }
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif}\
+table{border-collapse:collapse}\
+th,td{border:1px solid #ccc;padding:2px 6px;text-align:left}";
+
+// Table cells cannot hold `|` or line breaks.
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheEntry;
+    use crate::metadata_key::MetadataKey;
+    use crate::parse_report::Strictness;
+    use crate::repo_diff::{FieldChange, PackageDiff};
+
+    fn cpv(s: &str) -> Cpv {
+        Cpv::parse(s).unwrap()
+    }
+
+    #[test]
+    fn markdown() {
+        let diff = RepoDiff {
+            added: vec![cpv("app-misc/foo-2")],
+            removed: Vec::new(),
+            changed: vec![PackageDiff {
+                cpv: cpv("app-misc/foo-1"),
+                changes: vec![FieldChange {
+                    key: MetadataKey::License,
+                    old: "MIT".to_string(),
+                    new: "|| ( MIT BSD )".to_string(),
+                }],
+            }],
+        };
+        assert_eq!(
+            Report::diff(&diff).to_markdown(),
+            "# Metadata changes\n\
+             \n\
+             1 added, 0 removed, 1 changed.\n\
+             \n\
+             ## Added\n\
+             \n\
+             - app-misc/foo-2\n\
+             \n\
+             ## Changed\n\
+             \n\
+             | Package | Variable | Old | New |\n\
+             |---|---|---|---|\n\
+             | app-misc/foo-1 | LICENSE | MIT | \\|\\| ( MIT BSD ) |\n"
+        );
+    }
+
+    #[test]
+    fn html() {
+        let mut report = Report::new("A & B");
+        report
+            .paragraph("<script>")
+            .list(["x"])
+            .table(["K"], [["\"v\""]]);
+        let html = report.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<p>&lt;script&gt;</p>\n<ul>\n<li>x</li>\n</ul>\n"));
+        assert!(html.contains("<tr><th>K</th></tr>\n<tr><td>&quot;v&quot;</td></tr>\n"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn validation() {
+        let (good, bad) = (cpv("app-misc/foo-1"), cpv("app-misc/foo-2"));
+        let good_report = CacheEntry::parse_report("DESCRIPTION=x\nSLOT=0\n", Strictness::Lenient);
+        let bad_report = CacheEntry::parse_report("DESCRIPTION=\nSLOT=0/\n", Strictness::Lenient);
+        let report = Report::validation([(&good, &good_report), (&bad, &bad_report)]);
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("2 entries, 1 errors, 1 warnings.\n"));
+        assert!(markdown.contains("| app-misc/foo-2 | error | PM"));
+        assert!(markdown.contains("| app-misc/foo-2 | warning |  | "));
+    }
+}
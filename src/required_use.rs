@@ -1,12 +1,19 @@
+use std::collections::BTreeSet;
 use std::fmt;
 
-use winnow::ascii::multispace0;
-use winnow::combinator::{alt, cut_err, delimited, dispatch, opt, peek, preceded, repeat};
-use winnow::error::StrContext;
+use winnow::combinator::opt;
 use winnow::prelude::*;
-use winnow::token::{any, take_while};
 
+use crate::dep_spec::{
+    self, fmt_spec, parse_flag_name, parse_spec, walk, DepSpec, GroupKind, ParseSpec, Shape,
+    SpecTree, SpecVisitor,
+};
+use crate::eapi::Eapi;
+use crate::eapi_feature::EapiFeature;
+use crate::eapi_registry::EapiRules;
 use crate::error::{Error, Result};
+use crate::names::{check_tokens, Leaf};
+use crate::use_flags::UseFlagSet;
 
 /// A node in a `REQUIRED_USE` expression tree.
 ///
@@ -14,7 +21,7 @@ use crate::error::{Error, Result};
 /// Introduced in EAPI 4. The `AtMostOne` (`??`) operator was added in EAPI 5.
 ///
 /// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum RequiredUseExpr {
     /// A single USE flag (possibly negated with `!`).
     Flag {
@@ -43,6 +50,71 @@ pub enum RequiredUseExpr {
 }
 
 impl RequiredUseExpr {
+    /// The flag `name`.
+    pub fn flag(name: impl Into<String>) -> Self {
+        RequiredUseExpr::Flag {
+            name: name.into(),
+            negated: false,
+        }
+    }
+
+    /// The negated flag `!name`.
+    pub fn not_flag(name: impl Into<String>) -> Self {
+        RequiredUseExpr::Flag {
+            name: name.into(),
+            negated: true,
+        }
+    }
+
+    /// `|| ( entries... )`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RequiredUseExpr;
+    ///
+    /// let expr = RequiredUseExpr::conditional(
+    ///     "gui",
+    ///     false,
+    ///     [RequiredUseExpr::any_of([
+    ///         RequiredUseExpr::flag("qt6"),
+    ///         RequiredUseExpr::not_flag("minimal"),
+    ///     ])],
+    /// );
+    /// assert_eq!(expr, RequiredUseExpr::parse("gui? ( || ( qt6 !minimal ) )").unwrap());
+    /// ```
+    pub fn any_of(entries: impl IntoIterator<Item = Self>) -> Self {
+        RequiredUseExpr::AnyOf(entries.into_iter().collect())
+    }
+
+    /// `^^ ( entries... )`.
+    pub fn exactly_one(entries: impl IntoIterator<Item = Self>) -> Self {
+        RequiredUseExpr::ExactlyOne(entries.into_iter().collect())
+    }
+
+    /// `?? ( entries... )`.
+    pub fn at_most_one(entries: impl IntoIterator<Item = Self>) -> Self {
+        RequiredUseExpr::AtMostOne(entries.into_iter().collect())
+    }
+
+    /// `flag? ( entries... )`, or `!flag? ( entries... )` if `negated`.
+    pub fn conditional(
+        flag: impl Into<String>,
+        negated: bool,
+        entries: impl IntoIterator<Item = Self>,
+    ) -> Self {
+        RequiredUseExpr::UseConditional {
+            flag: flag.into(),
+            negated,
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    /// Entries that must all hold.
+    pub fn all(entries: impl IntoIterator<Item = Self>) -> Self {
+        RequiredUseExpr::All(entries.into_iter().collect())
+    }
+
     /// Parse a `REQUIRED_USE` expression string.
     ///
     /// # Examples
@@ -57,7 +129,7 @@ impl RequiredUseExpr {
     /// assert!(matches!(expr, RequiredUseExpr::ExactlyOne(_)));
     /// ```
     pub fn parse(input: &str) -> Result<Self> {
-        let entries: Vec<RequiredUseExpr> = parse_required_use_string
+        let entries: Vec<RequiredUseExpr> = parse_spec
             .parse(input)
             .map_err(|e| Error::InvalidRequiredUse(format!("{e}")))?;
 
@@ -67,191 +139,385 @@ impl RequiredUseExpr {
             _ => RequiredUseExpr::All(entries),
         })
     }
-}
 
-impl fmt::Display for RequiredUseExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// Check whether this constraint holds for the given USE configuration.
+    ///
+    /// Empty `||` and `??` groups count as satisfied, as do conditional
+    /// groups whose condition does not apply. An empty `^^` group is never
+    /// satisfied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{RequiredUseExpr, UseFlagSet};
+    ///
+    /// let expr = RequiredUseExpr::parse("^^ ( gtk qt5 )").unwrap();
+    /// assert!(expr.is_satisfied(&UseFlagSet::parse("gtk")));
+    /// assert!(!expr.is_satisfied(&UseFlagSet::parse("gtk qt5")));
+    /// ```
+    pub fn is_satisfied(&self, use_flags: &UseFlagSet) -> bool {
         match self {
-            RequiredUseExpr::Flag { name, negated } => {
-                if *negated {
-                    write!(f, "!{name}")
-                } else {
-                    write!(f, "{name}")
-                }
-            }
+            RequiredUseExpr::Flag { name, negated } => use_flags.is_enabled(name) != *negated,
             RequiredUseExpr::AnyOf(entries) => {
-                write!(f, "|| ( ")?;
-                fmt_entries(f, entries)?;
-                write!(f, " )")
-            }
-            RequiredUseExpr::ExactlyOne(entries) => {
-                write!(f, "^^ ( ")?;
-                fmt_entries(f, entries)?;
-                write!(f, " )")
-            }
-            RequiredUseExpr::AtMostOne(entries) => {
-                write!(f, "?? ( ")?;
-                fmt_entries(f, entries)?;
-                write!(f, " )")
+                entries.is_empty() || entries.iter().any(|e| e.is_satisfied(use_flags))
             }
+            RequiredUseExpr::ExactlyOne(entries) => count_satisfied(entries, use_flags) == 1,
+            RequiredUseExpr::AtMostOne(entries) => count_satisfied(entries, use_flags) <= 1,
             RequiredUseExpr::UseConditional {
                 flag,
                 negated,
                 entries,
             } => {
-                if *negated {
-                    write!(f, "!")?;
-                }
-                write!(f, "{flag}? ( ")?;
-                fmt_entries(f, entries)?;
-                write!(f, " )")
+                use_flags.is_enabled(flag) == *negated
+                    || entries.iter().all(|e| e.is_satisfied(use_flags))
             }
-            RequiredUseExpr::All(entries) => fmt_entries(f, entries),
+            RequiredUseExpr::All(entries) => entries.iter().all(|e| e.is_satisfied(use_flags)),
         }
     }
-}
 
-fn fmt_entries(f: &mut fmt::Formatter, entries: &[RequiredUseExpr]) -> fmt::Result {
-    for (i, entry) in entries.iter().enumerate() {
-        if i > 0 {
-            write!(f, " ")?;
+    /// Parse a `REQUIRED_USE` expression, enforcing the PMS flag naming rules.
+    ///
+    /// Every flag must follow
+    /// [PMS 3.1.4](https://projects.gentoo.org/pms/9/pms.html#use-flag-names);
+    /// the error reports the byte offset of the first invalid character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RequiredUseExpr;
+    ///
+    /// assert!(RequiredUseExpr::parse_strict("^^ ( gtk qt5 )").is_ok());
+    /// assert!(RequiredUseExpr::parse_strict("^^ ( gtk @qt5 )").is_err());
+    /// ```
+    pub fn parse_strict(input: &str) -> Result<Self> {
+        if let Some((pos, what)) = check_tokens(input, Leaf::Flag) {
+            return Err(Error::InvalidRequiredUse(format!("position {pos}: {what}")));
         }
-        write!(f, "{entry}")?;
+        Self::parse(input)
+    }
+
+    /// Check the expression against the rules of `eapi`.
+    ///
+    /// Rejects `REQUIRED_USE` before EAPI 4 and `??` groups before EAPI 5
+    /// with [`Error::UnsupportedInEapi`], and empty `||`, `^^`, `??` or
+    /// USE-conditional groups with [`Error::EmptyGroup`]. An empty
+    /// expression is always valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Eapi, Error, RequiredUseExpr};
+    ///
+    /// let expr = RequiredUseExpr::parse("?? ( a b )").unwrap();
+    /// assert!(expr.validate(&Eapi::Five).is_ok());
+    /// assert!(matches!(expr.validate(&Eapi::Four), Err(Error::UnsupportedInEapi { .. })));
+    /// ```
+    pub fn validate(&self, eapi: &Eapi) -> Result<()> {
+        self.validate_in(&EapiRules::new(eapi, None))
     }
-    Ok(())
-}
 
-// Winnow parsers
+    /// [`RequiredUseExpr::validate`] against the features of `rules`.
+    pub(crate) fn validate_in(&self, rules: &EapiRules<'_>) -> Result<()> {
+        if matches!(self, RequiredUseExpr::All(entries) if entries.is_empty()) {
+            return Ok(());
+        }
+        if !rules.supports(EapiFeature::RequiredUse) {
+            return Err(rules.unsupported("REQUIRED_USE"));
+        }
+        for node in self.iter() {
+            match node {
+                RequiredUseExpr::AtMostOne(_) if !rules.supports(EapiFeature::AtMostOneOf) => {
+                    return Err(rules.unsupported("?? ( ... )"));
+                }
+                RequiredUseExpr::AnyOf(entries)
+                | RequiredUseExpr::ExactlyOne(entries)
+                | RequiredUseExpr::AtMostOne(entries)
+                | RequiredUseExpr::UseConditional { entries, .. }
+                    if entries.is_empty() =>
+                {
+                    return Err(Error::EmptyGroup(node.to_string()));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
 
-fn is_flag_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+' || c == '@'
-}
+    /// Return a canonical form of the expression.
+    ///
+    /// Nested all-of groups are flattened into their parent, `||` and `^^`
+    /// groups with a single child are replaced by that child, `??` groups
+    /// with at most one child and other empty groups are dropped (they are
+    /// always satisfied), identical siblings are removed where that
+    /// preserves meaning (not inside `^^` or `??`, where each occurrence
+    /// counts), and children are sorted. Semantically equal expressions
+    /// then compare and hash equal.
+    ///
+    /// Sorting does not preserve the left-to-right preference used by
+    /// GLEP 73 auto-enforcement; normalize for comparison, not before
+    /// [`AutoEnforce::analyze`](crate::AutoEnforce::analyze).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RequiredUseExpr;
+    ///
+    /// let a = RequiredUseExpr::parse("|| ( qt5 gtk ) ssl? ( || ( a ) )").unwrap();
+    /// let b = RequiredUseExpr::parse("ssl? ( a a ) || ( gtk qt5 )").unwrap();
+    /// assert_eq!(a.normalize(), b.normalize());
+    /// assert_eq!(a.normalize().to_string(), "|| ( gtk qt5 ) ssl? ( a )");
+    /// ```
+    pub fn normalize(&self) -> RequiredUseExpr {
+        DepSpec::from(self).normalize().into()
+    }
 
-fn parse_any_of(input: &mut &str) -> ModalResult<RequiredUseExpr> {
-    "||".parse_next(input)?;
-    multispace0.parse_next(input)?;
-    cut_err(delimited(
-        '(',
-        parse_required_use_entries,
-        (multispace0, ')'),
-    ))
-    .context(StrContext::Label("'||' group"))
-    .map(RequiredUseExpr::AnyOf)
-    .parse_next(input)
-}
+    /// The direct children of a group, or an empty slice for a flag.
+    pub fn children(&self) -> &[RequiredUseExpr] {
+        dep_spec::children(self)
+    }
 
-fn parse_exactly_one(input: &mut &str) -> ModalResult<RequiredUseExpr> {
-    "^^".parse_next(input)?;
-    multispace0.parse_next(input)?;
-    cut_err(delimited(
-        '(',
-        parse_required_use_entries,
-        (multispace0, ')'),
-    ))
-    .context(StrContext::Label("'^^' group"))
-    .map(RequiredUseExpr::ExactlyOne)
-    .parse_next(input)
-}
+    /// Iterate over this node and all its descendants, depth-first in
+    /// pre-order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RequiredUseExpr;
+    ///
+    /// let expr = RequiredUseExpr::parse("ssl? ( || ( gnutls openssl ) )").unwrap();
+    /// let leaves: Vec<String> = expr
+    ///     .iter()
+    ///     .filter(|node| matches!(node, RequiredUseExpr::Flag { .. }))
+    ///     .map(|node| node.to_string())
+    ///     .collect();
+    /// assert_eq!(leaves, vec!["gnutls", "openssl"]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = &RequiredUseExpr> {
+        dep_spec::iter(self)
+    }
 
-fn parse_at_most_one(input: &mut &str) -> ModalResult<RequiredUseExpr> {
-    "??".parse_next(input)?;
-    multispace0.parse_next(input)?;
-    cut_err(delimited(
-        '(',
-        parse_required_use_entries,
-        (multispace0, ')'),
-    ))
-    .context(StrContext::Label("'??' group"))
-    .map(RequiredUseExpr::AtMostOne)
-    .parse_next(input)
-}
+    /// Walk the tree depth-first, calling `visitor` on entry and exit of
+    /// every node.
+    pub fn walk<V: SpecVisitor<Self> + ?Sized>(&self, visitor: &mut V) {
+        walk(self, RequiredUseExpr::children, visitor, 0);
+    }
 
-fn parse_use_conditional(input: &mut &str) -> ModalResult<RequiredUseExpr> {
-    let negated = opt('!').parse_next(input)?.is_some();
-    let flag: String = take_while(1.., is_flag_char)
-        .verify(|name: &str| {
-            // Validate flag name according to PMS 3.1.4
-            name.chars()
-                .next()
-                .is_some_and(|c| c.is_ascii_alphanumeric())
-        })
-        .map(|s: &str| s.to_string())
-        .parse_next(input)?;
-    '?'.parse_next(input)?;
-    multispace0.parse_next(input)?;
-    let entries = cut_err(delimited(
-        '(',
-        parse_required_use_entries,
-        (multispace0, ')'),
-    ))
-    .context(StrContext::Label("USE conditional group"))
-    .parse_next(input)?;
-    Ok(RequiredUseExpr::UseConditional {
-        flag,
-        negated,
-        entries,
-    })
-}
+    /// Rebuild the tree bottom-up, replacing every node with `f(node)`.
+    ///
+    /// Children are rewritten before their parent, so `f` sees groups
+    /// whose entries have already been transformed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RequiredUseExpr;
+    ///
+    /// let expr = RequiredUseExpr::parse("|| ( qt5 gtk )").unwrap();
+    /// let renamed = expr.rewrite(&mut |node| match node {
+    ///     RequiredUseExpr::Flag { name, negated } if name == "qt5" => RequiredUseExpr::Flag {
+    ///         name: "qt6".to_string(),
+    ///         negated,
+    ///     },
+    ///     other => other,
+    /// });
+    /// assert_eq!(renamed.to_string(), "|| ( qt6 gtk )");
+    /// ```
+    pub fn rewrite<F: FnMut(RequiredUseExpr) -> RequiredUseExpr>(self, f: &mut F) -> Self {
+        let map = |entries: Vec<RequiredUseExpr>, f: &mut F| -> Vec<RequiredUseExpr> {
+            entries.into_iter().map(|e| e.rewrite(f)).collect()
+        };
+        let node = match self {
+            RequiredUseExpr::Flag { .. } => self,
+            RequiredUseExpr::AnyOf(entries) => RequiredUseExpr::AnyOf(map(entries, f)),
+            RequiredUseExpr::ExactlyOne(entries) => RequiredUseExpr::ExactlyOne(map(entries, f)),
+            RequiredUseExpr::AtMostOne(entries) => RequiredUseExpr::AtMostOne(map(entries, f)),
+            RequiredUseExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => RequiredUseExpr::UseConditional {
+                flag,
+                negated,
+                entries: map(entries, f),
+            },
+            RequiredUseExpr::All(entries) => RequiredUseExpr::All(map(entries, f)),
+        };
+        f(node)
+    }
 
-/// Parse a bare flag: `flag` or `!flag`.
-fn parse_flag(input: &mut &str) -> ModalResult<RequiredUseExpr> {
-    (
-        opt('!'),
-        take_while(1.., is_flag_char)
-            .verify(|name: &str| {
-                // Validate flag name according to PMS 3.1.4
-                name.chars()
-                    .next()
-                    .is_some_and(|c| c.is_ascii_alphanumeric())
-            })
-            .map(|s: &str| s.to_string()),
-    )
-        .map(|(neg, name)| RequiredUseExpr::Flag {
-            name,
-            negated: neg.is_some(),
-        })
-        .parse_next(input)
+    /// The number of clauses in the conjunctive normal form of this
+    /// expression, as produced by distributing `||` over `&&` without
+    /// simplification; saturates at `u64::MAX`.
+    ///
+    /// This measures how hard the constraint is for users to reason about:
+    /// `^^` and `??` groups grow quadratically with their size, and nesting
+    /// them inside `||` or under negation multiplies the count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RequiredUseExpr;
+    ///
+    /// assert_eq!(RequiredUseExpr::parse("a? ( b c )").unwrap().cnf_clauses(), 2);
+    /// // At least one, plus one clause per pair.
+    /// assert_eq!(RequiredUseExpr::parse("^^ ( a b c d )").unwrap().cnf_clauses(), 7);
+    /// let nested = RequiredUseExpr::parse("|| ( ^^ ( a b ) ^^ ( c d ) )").unwrap();
+    /// assert_eq!(nested.cnf_clauses(), 4);
+    /// ```
+    pub fn cnf_clauses(&self) -> u64 {
+        self.cnf_sizes().0
+    }
+
+    /// Clause counts of the CNF of this expression and of its negation.
+    fn cnf_sizes(&self) -> (u64, u64) {
+        let sizes = |entries: &[RequiredUseExpr]| -> Vec<(u64, u64)> {
+            entries.iter().map(RequiredUseExpr::cnf_sizes).collect()
+        };
+        let sum = |values: &mut dyn Iterator<Item = u64>| values.fold(0, u64::saturating_add);
+        let product = |values: &mut dyn Iterator<Item = u64>| values.fold(1, u64::saturating_mul);
+        // `??`: no two children hold, i.e. `!a || !b` for every pair; its
+        // negation is the disjunction of `a && b` over every pair.
+        let at_most_one = |sizes: &[(u64, u64)]| -> (u64, u64) {
+            let mut pos = 0u64;
+            let mut neg = 1u64;
+            for (i, &(pos_i, neg_i)) in sizes.iter().enumerate() {
+                for &(pos_j, neg_j) in &sizes[..i] {
+                    pos = pos.saturating_add(neg_i.saturating_mul(neg_j));
+                    neg = neg.saturating_mul(pos_i.saturating_add(pos_j));
+                }
+            }
+            (pos, neg)
+        };
+        match self {
+            RequiredUseExpr::Flag { .. } => (1, 1),
+            RequiredUseExpr::All(entries) => {
+                let sizes = sizes(entries);
+                (
+                    sum(&mut sizes.iter().map(|s| s.0)),
+                    product(&mut sizes.iter().map(|s| s.1)),
+                )
+            }
+            RequiredUseExpr::AnyOf(entries) if entries.is_empty() => (0, 1),
+            RequiredUseExpr::AnyOf(entries) => {
+                let sizes = sizes(entries);
+                (
+                    product(&mut sizes.iter().map(|s| s.0)),
+                    sum(&mut sizes.iter().map(|s| s.1)),
+                )
+            }
+            RequiredUseExpr::ExactlyOne(entries) => {
+                let sizes = sizes(entries);
+                let (pairs_pos, pairs_neg) = at_most_one(&sizes);
+                (
+                    product(&mut sizes.iter().map(|s| s.0)).saturating_add(pairs_pos),
+                    sum(&mut sizes.iter().map(|s| s.1)).saturating_mul(pairs_neg),
+                )
+            }
+            RequiredUseExpr::AtMostOne(entries) => at_most_one(&sizes(entries)),
+            // `flag? ( e... )` is `!flag || ( e... )`.
+            RequiredUseExpr::UseConditional { entries, .. } => {
+                let sizes = sizes(entries);
+                (
+                    sum(&mut sizes.iter().map(|s| s.0)),
+                    product(&mut sizes.iter().map(|s| s.1)).saturating_add(1),
+                )
+            }
+        }
+    }
+
+    /// Add every flag name referenced by this expression to `out`.
+    pub(crate) fn collect_flags<'a>(&'a self, out: &mut BTreeSet<&'a str>) {
+        for node in self.iter() {
+            match node {
+                RequiredUseExpr::Flag { name, .. } => {
+                    out.insert(name);
+                }
+                RequiredUseExpr::UseConditional { flag, .. } => {
+                    out.insert(flag);
+                }
+                _ => {}
+            }
+        }
+    }
 }
 
-fn parse_paren_group(input: &mut &str) -> ModalResult<Vec<RequiredUseExpr>> {
-    delimited(
-        '(',
-        parse_required_use_entries,
-        cut_err((multispace0, ')')).context(StrContext::Label("closing ')'")),
-    )
-    .parse_next(input)
+fn count_satisfied(entries: &[RequiredUseExpr], use_flags: &UseFlagSet) -> usize {
+    entries.iter().filter(|e| e.is_satisfied(use_flags)).count()
 }
 
-fn parse_required_use_entry(input: &mut &str) -> ModalResult<Vec<RequiredUseExpr>> {
-    dispatch! {peek(any);
-        '|' => parse_any_of.map(|e| vec![e]),
-        '^' => parse_exactly_one.map(|e| vec![e]),
-        '(' => parse_paren_group,
-        '?' => parse_at_most_one.map(|e| vec![e]),
-        _ => alt((
-            parse_use_conditional.map(|e| vec![e]),
-            parse_flag.map(|e| vec![e]),
-        )),
-    }
-    .parse_next(input)
+impl fmt::Display for RequiredUseExpr {
+    /// `{:#}` prints an indented multi-line tree.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_spec(f, self, true)
+    }
 }
 
-fn parse_required_use_entries(input: &mut &str) -> ModalResult<Vec<RequiredUseExpr>> {
-    repeat(0.., preceded(multispace0, parse_required_use_entry))
-        .fold(
-            Vec::new,
-            |mut acc: Vec<RequiredUseExpr>, batch: Vec<RequiredUseExpr>| {
-                acc.extend(batch);
-                acc
+impl SpecTree for RequiredUseExpr {
+    fn shape(&self) -> Shape<'_, Self> {
+        match self {
+            RequiredUseExpr::Flag { .. } => Shape::Leaf,
+            RequiredUseExpr::AnyOf(entries) => Shape::Group(GroupKind::AnyOf, entries),
+            RequiredUseExpr::ExactlyOne(entries) => Shape::Group(GroupKind::ExactlyOne, entries),
+            RequiredUseExpr::AtMostOne(entries) => Shape::Group(GroupKind::AtMostOne, entries),
+            RequiredUseExpr::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => Shape::UseConditional {
+                flag,
+                negated: *negated,
+                entries,
             },
-        )
-        .parse_next(input)
+            RequiredUseExpr::All(entries) => Shape::Group(GroupKind::AllOf, entries),
+        }
+    }
+
+    fn fmt_leaf(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequiredUseExpr::Flag { name, negated } => {
+                if *negated {
+                    write!(f, "!")?;
+                }
+                write!(f, "{name}")
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
-pub(crate) fn parse_required_use_string(input: &mut &str) -> ModalResult<Vec<RequiredUseExpr>> {
-    let entries = parse_required_use_entries(input)?;
-    multispace0.parse_next(input)?;
-    Ok(entries)
+impl ParseSpec for RequiredUseExpr {
+    const CHOICES: &'static [GroupKind] = &[
+        GroupKind::AnyOf,
+        GroupKind::ExactlyOne,
+        GroupKind::AtMostOne,
+    ];
+
+    /// Parse a bare flag: `flag` or `!flag`.
+    fn parse_leaf(input: &mut &str) -> ModalResult<Self> {
+        (opt('!'), parse_flag_name)
+            .map(|(neg, name)| RequiredUseExpr::Flag {
+                name: name.to_string(),
+                negated: neg.is_some(),
+            })
+            .parse_next(input)
+    }
+
+    fn choice(kind: GroupKind, entries: Vec<Self>) -> Self {
+        match kind {
+            GroupKind::AnyOf => RequiredUseExpr::AnyOf(entries),
+            GroupKind::ExactlyOne => RequiredUseExpr::ExactlyOne(entries),
+            GroupKind::AtMostOne => RequiredUseExpr::AtMostOne(entries),
+            GroupKind::AllOf => RequiredUseExpr::All(entries),
+        }
+    }
+
+    fn conditional(flag: String, negated: bool, entries: Vec<Self>) -> Self {
+        RequiredUseExpr::UseConditional {
+            flag,
+            negated,
+            entries,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -426,4 +692,196 @@ mod tests {
     fn invalid_use_conditional_flag_starting_with_hyphen() {
         assert!(RequiredUseExpr::parse("-flag? ( ssl )").is_err());
     }
+
+    #[test]
+    fn satisfied_any_of() {
+        let expr = RequiredUseExpr::parse("|| ( a b )").unwrap();
+        assert!(expr.is_satisfied(&UseFlagSet::parse("a")));
+        assert!(expr.is_satisfied(&UseFlagSet::parse("a b")));
+        assert!(!expr.is_satisfied(&UseFlagSet::new()));
+    }
+
+    #[test]
+    fn satisfied_at_most_one() {
+        let expr = RequiredUseExpr::parse("?? ( a b )").unwrap();
+        assert!(expr.is_satisfied(&UseFlagSet::new()));
+        assert!(expr.is_satisfied(&UseFlagSet::parse("b")));
+        assert!(!expr.is_satisfied(&UseFlagSet::parse("a b")));
+    }
+
+    #[test]
+    fn satisfied_conditional() {
+        let expr = RequiredUseExpr::parse("ssl? ( !gnutls ) !ssl? ( gnutls )").unwrap();
+        assert!(expr.is_satisfied(&UseFlagSet::parse("ssl")));
+        assert!(expr.is_satisfied(&UseFlagSet::parse("gnutls")));
+        assert!(!expr.is_satisfied(&UseFlagSet::parse("ssl gnutls")));
+        assert!(!expr.is_satisfied(&UseFlagSet::new()));
+    }
+
+    #[test]
+    fn satisfied_empty_groups() {
+        for input in ["|| ( )", "?? ( )", ""] {
+            let expr = RequiredUseExpr::parse(input).unwrap();
+            assert!(expr.is_satisfied(&UseFlagSet::new()), "{input}");
+        }
+        let expr = RequiredUseExpr::parse("^^ ( )").unwrap();
+        assert!(!expr.is_satisfied(&UseFlagSet::new()));
+        assert_eq!(expr.cnf_clauses(), 1);
+    }
+
+    #[test]
+    fn iter_pre_order() {
+        let expr = RequiredUseExpr::parse("a ^^ ( b c ) d? ( e )").unwrap();
+        let nodes: Vec<String> = expr.iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                "a ^^ ( b c ) d? ( e )",
+                "a",
+                "^^ ( b c )",
+                "b",
+                "c",
+                "d? ( e )",
+                "e"
+            ]
+        );
+    }
+
+    #[test]
+    fn walk_enter_leave_order() {
+        struct Trace(Vec<String>);
+        impl SpecVisitor<RequiredUseExpr> for Trace {
+            fn enter(&mut self, expr: &RequiredUseExpr, depth: usize) {
+                self.0.push(format!("+{depth}:{expr}"));
+            }
+            fn leave(&mut self, expr: &RequiredUseExpr, depth: usize) {
+                self.0.push(format!("-{depth}:{expr}"));
+            }
+        }
+        let expr = RequiredUseExpr::parse("|| ( a b )").unwrap();
+        let mut trace = Trace(Vec::new());
+        expr.walk(&mut trace);
+        assert_eq!(
+            trace.0,
+            vec![
+                "+0:|| ( a b )",
+                "+1:a",
+                "-1:a",
+                "+1:b",
+                "-1:b",
+                "-0:|| ( a b )"
+            ]
+        );
+    }
+
+    #[test]
+    fn rewrite_bottom_up() {
+        let expr = RequiredUseExpr::parse("a? ( b !c )").unwrap();
+        let negated = expr.rewrite(&mut |node| match node {
+            RequiredUseExpr::Flag { name, negated } => RequiredUseExpr::Flag {
+                name,
+                negated: !negated,
+            },
+            other => other,
+        });
+        assert_eq!(negated.to_string(), "a? ( !b c )");
+    }
+
+    #[test]
+    fn validate_eapi_and_structure() {
+        let expr = RequiredUseExpr::parse("|| ( a b )").unwrap();
+        assert!(expr.validate(&Eapi::Four).is_ok());
+        assert_eq!(
+            expr.validate(&Eapi::Three),
+            Err(Error::UnsupportedInEapi {
+                feature: "REQUIRED_USE".to_string(),
+                eapi: Eapi::Three,
+            })
+        );
+        assert!(RequiredUseExpr::parse("")
+            .unwrap()
+            .validate(&Eapi::Zero)
+            .is_ok());
+        let nested = RequiredUseExpr::parse("a? ( ?? ( b c ) )").unwrap();
+        assert!(nested.validate(&Eapi::Four).is_err());
+        let empty = RequiredUseExpr::parse("a? ( ^^ ( ) )").unwrap();
+        assert_eq!(
+            empty.validate(&Eapi::Eight),
+            Err(Error::EmptyGroup("^^ ( )".to_string()))
+        );
+    }
+
+    #[test]
+    fn normalize_single_child_groups() {
+        let expr = RequiredUseExpr::parse("^^ ( a ) ?? ( b ) || ( c ) d? ( )").unwrap();
+        assert_eq!(expr.normalize().to_string(), "a c");
+    }
+
+    #[test]
+    fn normalize_keeps_counted_duplicates() {
+        let expr = RequiredUseExpr::parse("^^ ( b a b ) || ( b a b )").unwrap();
+        assert_eq!(expr.normalize().to_string(), "|| ( a b ) ^^ ( a b b )");
+    }
+
+    #[test]
+    fn normalize_preserves_satisfaction() {
+        let expr = RequiredUseExpr::parse("a? ( ^^ ( b c? ( d ) ) ) ?? ( e f )").unwrap();
+        let norm = expr.normalize();
+        for flags in ["", "a", "a b", "a c", "a c d", "a b c d", "e f", "a e"] {
+            let flags = UseFlagSet::parse(flags);
+            assert_eq!(expr.is_satisfied(&flags), norm.is_satisfied(&flags));
+        }
+    }
+
+    #[test]
+    fn cnf_clause_counts() {
+        for (input, clauses) in [
+            ("", 0),
+            ("a !b", 2),
+            ("|| ( )", 0),
+            ("?? ( a b c )", 3),
+            ("!a? ( ^^ ( b c ) )", 2),
+            // Two clauses for each `^^`, distributed: 2 * 2 * 1.
+            ("|| ( ^^ ( a b ) ^^ ( c d ) e )", 4),
+            // Negating `^^ ( a b )` inside `||`: 2 * 1 clauses.
+            ("|| ( ^^ ( a b ) c )", 2),
+            ("|| ( x? ( a b ) c )", 2),
+        ] {
+            assert_eq!(
+                RequiredUseExpr::parse(input).unwrap().cnf_clauses(),
+                clauses,
+                "{input}"
+            );
+        }
+        let huge = format!("|| ( {} )", "^^ ( a b c d e f g h ) ".repeat(30));
+        assert_eq!(
+            RequiredUseExpr::parse(&huge).unwrap().cnf_clauses(),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn display_alternate() {
+        let expr = RequiredUseExpr::parse("a !b? ( || ( c d ) )").unwrap();
+        assert_eq!(
+            format!("{expr:#}"),
+            "a\n!b? (\n  || (\n    c\n    d\n  )\n)"
+        );
+    }
+
+    #[test]
+    fn constructors() {
+        use RequiredUseExpr as R;
+        assert_eq!(
+            R::all([
+                R::exactly_one([R::flag("a"), R::flag("b")]),
+                R::conditional(
+                    "c",
+                    true,
+                    [R::at_most_one([R::flag("d"), R::not_flag("e")])]
+                ),
+            ]),
+            R::parse("^^ ( a b ) !c? ( ?? ( d !e ) )").unwrap()
+        );
+    }
 }
@@ -0,0 +1,96 @@
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+
+/// Reports `REQUIRED_USE` constraints too complex for users to satisfy by
+/// hand.
+///
+/// Complexity is measured by
+/// [`RequiredUseExpr::cnf_clauses`](crate::RequiredUseExpr::cnf_clauses):
+/// every clause is a condition users may have to untangle from a
+/// package manager error. Large `^^` and `??` groups and groups nested
+/// inside `||` make the count grow combinatorially.
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, RequiredUseCheck};
+///
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-1").unwrap(),
+///     CacheEntry::parse(
+///         "EAPI=8\nDESCRIPTION=x\nSLOT=0\nREQUIRED_USE=|| ( ^^ ( a b c ) ^^ ( d e f ) )\n",
+///     )
+///     .unwrap(),
+/// )]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(RequiredUseCheck { max_clauses: 10 });
+/// let findings = runner.run(&repo);
+/// assert_eq!(findings[0].message, "REQUIRED_USE expands to 16 clauses (limit 10)");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequiredUseCheck {
+    /// Most CNF clauses accepted; 100 by default.
+    pub max_clauses: u64,
+}
+
+impl Default for RequiredUseCheck {
+    fn default() -> Self {
+        RequiredUseCheck { max_clauses: 100 }
+    }
+}
+
+impl MetadataCheck for RequiredUseCheck {
+    fn name(&self) -> &'static str {
+        "required-use-complexity"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let Some(required_use) = &cx.entry.metadata.required_use else {
+            return;
+        };
+        let clauses = required_use.cnf_clauses();
+        if clauses > self.max_clauses {
+            let clauses = match clauses {
+                u64::MAX => "too many".to_string(),
+                n => n.to_string(),
+            };
+            let message = format!(
+                "REQUIRED_USE expands to {clauses} clauses (limit {})",
+                self.max_clauses
+            );
+            findings.push(cx.finding(self.name(), Severity::Warning, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn limits() {
+        let messages = |required_use: &str| -> Vec<String> {
+            let text = format!("EAPI=8\nDESCRIPTION=x\nSLOT=0\nREQUIRED_USE={required_use}\n");
+            run_check(RequiredUseCheck::default(), &[("media-video/foo-1", &text)])
+                .into_iter()
+                .map(|f| f.message)
+                .collect()
+        };
+        let targets: Vec<String> = (0..15).map(|n| format!("python3_{n}")).collect();
+        assert!(messages(&format!("^^ ( {} )", targets[..12].join(" "))).is_empty());
+        assert_eq!(
+            messages(&format!("^^ ( {} )", targets.join(" "))),
+            ["REQUIRED_USE expands to 106 clauses (limit 100)"]
+        );
+        let huge = format!("|| ( {} )", "^^ ( a b c d e f g h ) ".repeat(30));
+        assert_eq!(
+            messages(&huge),
+            ["REQUIRED_USE expands to too many clauses (limit 100)"]
+        );
+    }
+}
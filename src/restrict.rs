@@ -1,23 +1,120 @@
+use std::collections::BTreeSet;
 use std::fmt;
 
-use winnow::ascii::multispace0;
-use winnow::combinator::{alt, cut_err, delimited, dispatch, opt, peek, preceded, repeat};
-use winnow::error::StrContext;
 use winnow::prelude::*;
-use winnow::token::{any, take_while};
+use winnow::token::take_while;
 
-use crate::error::{Error, Result};
+use crate::dep_spec::{self, fmt_spec, parse_spec, ParseSpec, Shape, SpecTree};
+use crate::eapi::Eapi;
+use crate::eapi_feature::EapiFeature;
+use crate::eapi_registry::EapiRules;
+use crate::error::{closest, Error, Result};
+use crate::use_flags::UseFlagSet;
 
-/// A node in a `RESTRICT` or `PROPERTIES` expression.
+/// A single `RESTRICT` token.
 ///
-/// Before EAPI 8, these are simple space-separated token lists.
-/// In EAPI 8, they support USE-conditional groups (`flag? ( ... )`).
+/// Tokens defined by PMS and the common Portage extensions get their own
+/// variant; anything else is preserved verbatim in [`RestrictKind::Other`].
 ///
 /// See [PMS 7.3.6](https://projects.gentoo.org/pms/9/pms.html#restrict).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RestrictKind {
+    /// `fetch` — distfiles must be fetched manually.
+    Fetch,
+    /// `mirror` — distfiles must not be mirrored.
+    Mirror,
+    /// `test` — the test phase must not be run.
+    Test,
+    /// `strip` — installed files must not be stripped.
+    Strip,
+    /// `binchecks` — skip QA checks on binaries (Portage extension).
+    Binchecks,
+    /// `userpriv` — do not drop privileges during the build.
+    Userpriv,
+    /// `splitdebug` — do not split debug information (Portage extension).
+    Splitdebug,
+    /// Any other token.
+    Other(String),
+}
+
+impl RestrictKind {
+    /// The token as it appears in `RESTRICT`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            RestrictKind::Fetch => "fetch",
+            RestrictKind::Mirror => "mirror",
+            RestrictKind::Test => "test",
+            RestrictKind::Strip => "strip",
+            RestrictKind::Binchecks => "binchecks",
+            RestrictKind::Userpriv => "userpriv",
+            RestrictKind::Splitdebug => "splitdebug",
+            RestrictKind::Other(s) => s,
+        }
+    }
+
+    /// For a [`RestrictKind::Other`] token, the known token it is most
+    /// likely a misspelling of.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RestrictKind;
+    ///
+    /// assert_eq!(RestrictKind::from("mirrors").suggestion(), Some(RestrictKind::Mirror));
+    /// assert_eq!(RestrictKind::from("bindist").suggestion(), None);
+    /// assert_eq!(RestrictKind::Test.suggestion(), None);
+    /// ```
+    pub fn suggestion(&self) -> Option<RestrictKind> {
+        match self {
+            RestrictKind::Other(token) => {
+                closest(token, KNOWN.iter().map(RestrictKind::as_str)).map(RestrictKind::from)
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(crate) const KNOWN: [RestrictKind; 7] = [
+    RestrictKind::Fetch,
+    RestrictKind::Mirror,
+    RestrictKind::Test,
+    RestrictKind::Strip,
+    RestrictKind::Binchecks,
+    RestrictKind::Userpriv,
+    RestrictKind::Splitdebug,
+];
+
+impl From<&str> for RestrictKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "fetch" => RestrictKind::Fetch,
+            "mirror" => RestrictKind::Mirror,
+            "test" => RestrictKind::Test,
+            "strip" => RestrictKind::Strip,
+            "binchecks" => RestrictKind::Binchecks,
+            "userpriv" => RestrictKind::Userpriv,
+            "splitdebug" => RestrictKind::Splitdebug,
+            _ => RestrictKind::Other(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for RestrictKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A node in a `RESTRICT` expression.
+///
+/// Before EAPI 8, this is a simple space-separated token list.
+/// In EAPI 8, it supports USE-conditional groups (`flag? ( ... )`).
+///
+/// See [PMS 7.3.6](https://projects.gentoo.org/pms/9/pms.html#restrict).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RestrictExpr {
-    /// A single restriction/property token (e.g. `mirror`, `test`, `live`).
-    Token(String),
+    /// A single restriction token (e.g. `mirror`, `test`).
+    Token(RestrictKind),
     /// `flag? ( ... )` or `!flag? ( ... )` conditional group (EAPI 8+).
     UseConditional {
         /// USE flag name.
@@ -30,7 +127,37 @@ pub enum RestrictExpr {
 }
 
 impl RestrictExpr {
-    /// Parse a `RESTRICT` or `PROPERTIES` expression string.
+    /// The token `kind`, e.g. `"mirror"`.
+    pub fn token(kind: &str) -> Self {
+        RestrictExpr::Token(RestrictKind::from(kind))
+    }
+
+    /// `flag? ( entries... )`, or `!flag? ( entries... )` if `negated`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::RestrictExpr;
+    ///
+    /// let entries = vec![
+    ///     RestrictExpr::token("mirror"),
+    ///     RestrictExpr::conditional("test", true, [RestrictExpr::token("test")]),
+    /// ];
+    /// assert_eq!(entries, RestrictExpr::parse("mirror !test? ( test )").unwrap());
+    /// ```
+    pub fn conditional(
+        flag: impl Into<String>,
+        negated: bool,
+        entries: impl IntoIterator<Item = Self>,
+    ) -> Self {
+        RestrictExpr::UseConditional {
+            flag: flag.into(),
+            negated,
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    /// Parse a `RESTRICT` expression string.
     ///
     /// Handles both the simple space-separated format (EAPI <8) and
     /// the USE-conditional format (EAPI 8).
@@ -49,116 +176,164 @@ impl RestrictExpr {
     /// assert_eq!(entries.len(), 1);
     /// ```
     pub fn parse(input: &str) -> Result<Vec<RestrictExpr>> {
-        parse_restrict_string
+        parse_spec
             .parse(input)
             .map_err(|e| Error::InvalidRestrict(format!("{e}")))
     }
 
+    /// Parse a `RESTRICT` expression, rejecting syntax not allowed by `eapi`.
+    ///
+    /// USE-conditional groups are only allowed from EAPI 8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Eapi, RestrictExpr};
+    ///
+    /// assert!(RestrictExpr::parse_with_eapi("!test? ( test )", &Eapi::Eight).is_ok());
+    /// assert!(RestrictExpr::parse_with_eapi("!test? ( test )", &Eapi::Seven).is_err());
+    /// assert!(RestrictExpr::parse_with_eapi("mirror test", &Eapi::Zero).is_ok());
+    /// ```
+    pub fn parse_with_eapi(input: &str, eapi: &Eapi) -> Result<Vec<RestrictExpr>> {
+        Self::parse_in(input, &EapiRules::new(eapi, None))
+    }
+
+    /// [`RestrictExpr::parse_with_eapi`] against the features of `rules`.
+    pub(crate) fn parse_in(input: &str, rules: &EapiRules<'_>) -> Result<Vec<RestrictExpr>> {
+        let entries = Self::parse(input)?;
+        if !rules.supports(EapiFeature::UseConditionalRestrict) {
+            if let Some(RestrictExpr::UseConditional { flag, .. }) = entries
+                .iter()
+                .find(|e| matches!(e, RestrictExpr::UseConditional { .. }))
+            {
+                return Err(rules.unsupported(format!("conditional RESTRICT '{flag}? ( ... )'")));
+            }
+        }
+        Ok(entries)
+    }
+
     /// Collect all plain token values, ignoring USE-conditional structure.
     ///
     /// Useful for simple queries like "does RESTRICT contain `test`?"
     /// when you don't need to evaluate USE conditions.
-    pub fn flat_tokens(entries: &[RestrictExpr]) -> Vec<&str> {
-        let mut out = Vec::new();
-        for entry in entries {
-            match entry {
-                RestrictExpr::Token(t) => out.push(t.as_str()),
-                RestrictExpr::UseConditional { entries, .. } => {
-                    out.extend(Self::flat_tokens(entries));
-                }
-            }
+    pub fn flat_tokens(entries: &[RestrictExpr]) -> Vec<&RestrictKind> {
+        dep_spec::leaves(entries)
+            .into_iter()
+            .filter_map(Self::kind)
+            .collect()
+    }
+
+    /// The tokens active under `use_flags`.
+    pub fn evaluate<'a>(
+        entries: &'a [RestrictExpr],
+        use_flags: &UseFlagSet,
+    ) -> Vec<&'a RestrictKind> {
+        dep_spec::active_leaves(entries, use_flags)
+            .into_iter()
+            .filter_map(Self::kind)
+            .collect()
+    }
+
+    /// Whether `kind` is restricted under `use_flags`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{RestrictExpr, RestrictKind, UseFlagSet};
+    ///
+    /// let entries = RestrictExpr::parse("mirror !test? ( test )").unwrap();
+    /// assert!(RestrictExpr::applies(&entries, &RestrictKind::Test, &UseFlagSet::new()));
+    /// assert!(!RestrictExpr::applies(&entries, &RestrictKind::Test, &UseFlagSet::parse("test")));
+    /// assert!(RestrictExpr::possibly_applies(&entries, &RestrictKind::Test));
+    /// assert!(!RestrictExpr::always_applies(&entries, &RestrictKind::Test));
+    /// assert!(RestrictExpr::always_applies(&entries, &RestrictKind::Mirror));
+    /// ```
+    pub fn applies(entries: &[RestrictExpr], kind: &RestrictKind, use_flags: &UseFlagSet) -> bool {
+        Self::evaluate(entries, use_flags).contains(&kind)
+    }
+
+    /// Whether `kind` is restricted under at least one USE configuration.
+    pub fn possibly_applies(entries: &[RestrictExpr], kind: &RestrictKind) -> bool {
+        Self::flat_tokens(entries).contains(&kind)
+    }
+
+    /// Whether `kind` is restricted regardless of USE flags.
+    ///
+    /// Only unconditional tokens count; a token present under both `flag?`
+    /// and `!flag?` is not detected.
+    pub fn always_applies(entries: &[RestrictExpr], kind: &RestrictKind) -> bool {
+        entries
+            .iter()
+            .any(|entry| matches!(entry, RestrictExpr::Token(t) if t == kind))
+    }
+
+    /// Add every USE flag referenced by a conditional group to `out`.
+    pub(crate) fn collect_flags<'a>(entries: &'a [RestrictExpr], out: &mut BTreeSet<&'a str>) {
+        dep_spec::collect_flags(entries, out);
+    }
+
+    /// The token of a [`RestrictExpr::Token`].
+    fn kind(&self) -> Option<&RestrictKind> {
+        match self {
+            RestrictExpr::Token(kind) => Some(kind),
+            RestrictExpr::UseConditional { .. } => None,
         }
-        out
     }
 }
 
 impl fmt::Display for RestrictExpr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_spec(f, self, false)
+    }
+}
+
+impl SpecTree for RestrictExpr {
+    fn shape(&self) -> Shape<'_, Self> {
         match self {
-            RestrictExpr::Token(t) => write!(f, "{t}"),
+            RestrictExpr::Token(_) => Shape::Leaf,
             RestrictExpr::UseConditional {
                 flag,
                 negated,
                 entries,
-            } => {
-                if *negated {
-                    write!(f, "!")?;
-                }
-                write!(f, "{flag}? ( ")?;
-                for (i, entry) in entries.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
-                    }
-                    write!(f, "{entry}")?;
-                }
-                write!(f, " )")
-            }
+            } => Shape::UseConditional {
+                flag,
+                negated: *negated,
+                entries,
+            },
         }
     }
-}
-
-// Winnow parsers
-
-fn is_token_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+')
-}
-
-fn is_flag_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+'
-}
-
-fn parse_token(input: &mut &str) -> ModalResult<RestrictExpr> {
-    take_while(1.., is_token_char)
-        .map(|s: &str| RestrictExpr::Token(s.to_string()))
-        .parse_next(input)
-}
 
-fn parse_use_conditional(input: &mut &str) -> ModalResult<RestrictExpr> {
-    let negated = opt('!').parse_next(input)?.is_some();
-    let flag: String = take_while(1.., is_flag_char)
-        .map(|s: &str| s.to_string())
-        .parse_next(input)?;
-    '?'.parse_next(input)?;
-    multispace0.parse_next(input)?;
-    let entries = cut_err(delimited('(', parse_restrict_entries, (multispace0, ')')))
-        .context(StrContext::Label("USE conditional group"))
-        .parse_next(input)?;
-    Ok(RestrictExpr::UseConditional {
-        flag,
-        negated,
-        entries,
-    })
+    fn fmt_leaf(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RestrictExpr::Token(kind) => write!(f, "{kind}"),
+            RestrictExpr::UseConditional { .. } => Ok(()),
+        }
+    }
 }
 
-fn parse_restrict_entry(input: &mut &str) -> ModalResult<RestrictExpr> {
-    dispatch! {peek(any);
-        _ => alt((
-            parse_use_conditional,
-            parse_token,
-        )),
+impl ParseSpec for RestrictExpr {
+    fn parse_leaf(input: &mut &str) -> ModalResult<Self> {
+        parse_token.map(RestrictExpr::token).parse_next(input)
     }
-    .parse_next(input)
-}
 
-fn parse_paren_or_entry(input: &mut &str) -> ModalResult<Vec<RestrictExpr>> {
-    dispatch! {peek(any);
-        '(' => cut_err(delimited('(', parse_restrict_entries, (multispace0, ')')))
-            .context(StrContext::Label("paren group")),
-        _ => parse_restrict_entry.map(|e| vec![e]),
+    fn conditional(flag: String, negated: bool, entries: Vec<Self>) -> Self {
+        RestrictExpr::UseConditional {
+            flag,
+            negated,
+            entries,
+        }
     }
-    .parse_next(input)
 }
 
-fn parse_restrict_entries(input: &mut &str) -> ModalResult<Vec<RestrictExpr>> {
-    repeat(0.., preceded(multispace0, parse_paren_or_entry))
-        .map(|vecs: Vec<Vec<RestrictExpr>>| vecs.into_iter().flatten().collect())
-        .parse_next(input)
+// Winnow parsers
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+')
 }
 
-pub(crate) fn parse_restrict_string(input: &mut &str) -> ModalResult<Vec<RestrictExpr>> {
-    let entries = parse_restrict_entries(input)?;
-    multispace0.parse_next(input)?;
-    Ok(entries)
+/// Parse a `RESTRICT` or `PROPERTIES` token.
+pub(crate) fn parse_token<'s>(input: &mut &'s str) -> ModalResult<&'s str> {
+    take_while(1.., is_token_char).parse_next(input)
 }
 
 #[cfg(test)]
@@ -169,8 +344,8 @@ mod tests {
     fn parse_simple_tokens() {
         let entries = RestrictExpr::parse("mirror test").unwrap();
         assert_eq!(entries.len(), 2);
-        assert_eq!(entries[0], RestrictExpr::Token("mirror".to_string()));
-        assert_eq!(entries[1], RestrictExpr::Token("test".to_string()));
+        assert_eq!(entries[0], RestrictExpr::Token(RestrictKind::Mirror));
+        assert_eq!(entries[1], RestrictExpr::Token(RestrictKind::Test));
     }
 
     #[test]
@@ -186,7 +361,7 @@ mod tests {
                 assert_eq!(flag, "test");
                 assert!(negated);
                 assert_eq!(entries.len(), 1);
-                assert_eq!(entries[0], RestrictExpr::Token("test".to_string()));
+                assert_eq!(entries[0], RestrictExpr::Token(RestrictKind::Test));
             }
             _ => unreachable!("expected UseConditional"),
         }
@@ -196,7 +371,10 @@ mod tests {
     fn parse_mixed() {
         let entries = RestrictExpr::parse("mirror !test? ( test )").unwrap();
         assert_eq!(entries.len(), 2);
-        assert!(matches!(&entries[0], RestrictExpr::Token(t) if t == "mirror"));
+        assert!(matches!(
+            &entries[0],
+            RestrictExpr::Token(RestrictKind::Mirror)
+        ));
         assert!(matches!(&entries[1], RestrictExpr::UseConditional { .. }));
     }
 
@@ -210,12 +388,12 @@ mod tests {
     fn flat_tokens() {
         let entries = RestrictExpr::parse("mirror !test? ( test )").unwrap();
         let tokens = RestrictExpr::flat_tokens(&entries);
-        assert_eq!(tokens, vec!["mirror", "test"]);
+        assert_eq!(tokens, vec![&RestrictKind::Mirror, &RestrictKind::Test]);
     }
 
     #[test]
     fn display_token() {
-        let entry = RestrictExpr::Token("test".to_string());
+        let entry = RestrictExpr::Token(RestrictKind::Test);
         assert_eq!(entry.to_string(), "test");
     }
 
@@ -224,7 +402,7 @@ mod tests {
         let entry = RestrictExpr::UseConditional {
             flag: "test".to_string(),
             negated: true,
-            entries: vec![RestrictExpr::Token("test".to_string())],
+            entries: vec![RestrictExpr::Token(RestrictKind::Test)],
         };
         assert_eq!(entry.to_string(), "!test? ( test )");
     }
@@ -232,7 +410,7 @@ mod tests {
     #[test]
     fn parse_bare_paren_single() {
         let entries = RestrictExpr::parse("( test )").unwrap();
-        assert_eq!(entries, vec![RestrictExpr::Token("test".to_string())]);
+        assert_eq!(entries, vec![RestrictExpr::Token(RestrictKind::Test)]);
     }
 
     #[test]
@@ -241,8 +419,8 @@ mod tests {
         assert_eq!(
             entries,
             vec![
-                RestrictExpr::Token("mirror".to_string()),
-                RestrictExpr::Token("test".to_string()),
+                RestrictExpr::Token(RestrictKind::Mirror),
+                RestrictExpr::Token(RestrictKind::Test),
             ]
         );
     }
@@ -267,4 +445,69 @@ mod tests {
         let reparsed = RestrictExpr::parse(&rejoined).unwrap();
         assert_eq!(entries, reparsed);
     }
+
+    #[test]
+    fn known_and_other_kinds() {
+        let entries = RestrictExpr::parse("fetch strip network-sandbox").unwrap();
+        assert_eq!(
+            RestrictExpr::flat_tokens(&entries),
+            vec![
+                &RestrictKind::Fetch,
+                &RestrictKind::Strip,
+                &RestrictKind::Other("network-sandbox".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn kind_round_trip() {
+        for s in [
+            "fetch",
+            "mirror",
+            "test",
+            "strip",
+            "binchecks",
+            "userpriv",
+            "splitdebug",
+            "bindist",
+        ] {
+            assert_eq!(RestrictKind::from(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn evaluate_conditionals() {
+        let entries = RestrictExpr::parse("mirror !test? ( test ) bindist? ( fetch )").unwrap();
+        assert_eq!(
+            RestrictExpr::evaluate(&entries, &UseFlagSet::new()),
+            vec![&RestrictKind::Mirror, &RestrictKind::Test]
+        );
+        assert_eq!(
+            RestrictExpr::evaluate(&entries, &UseFlagSet::parse("test bindist")),
+            vec![&RestrictKind::Mirror, &RestrictKind::Fetch]
+        );
+    }
+
+    #[test]
+    fn conditional_queries() {
+        let entries = RestrictExpr::parse("a? ( b? ( strip ) ) fetch").unwrap();
+        let strip = RestrictKind::Strip;
+        assert!(!RestrictExpr::applies(
+            &entries,
+            &strip,
+            &UseFlagSet::parse("a")
+        ));
+        assert!(RestrictExpr::applies(
+            &entries,
+            &strip,
+            &UseFlagSet::parse("a b")
+        ));
+        assert!(RestrictExpr::possibly_applies(&entries, &strip));
+        assert!(!RestrictExpr::always_applies(&entries, &strip));
+        assert!(RestrictExpr::always_applies(&entries, &RestrictKind::Fetch));
+        assert!(!RestrictExpr::possibly_applies(
+            &entries,
+            &RestrictKind::Test
+        ));
+    }
 }
@@ -0,0 +1,87 @@
+use crate::bit_set::{bit_set, BitIndex};
+use crate::restrict::{RestrictExpr, RestrictKind, KNOWN};
+use crate::use_flags::UseFlagSet;
+
+bit_set! {
+    /// A compact set of the known `RESTRICT` tokens.
+    ///
+    /// Each [`RestrictKind`] variant other than [`RestrictKind::Other`] maps to one bit, so
+    /// membership tests and set operations are O(1) and the set is `Copy`.
+    /// Tokens without a dedicated variant cannot be stored and are ignored;
+    /// keep the [`RestrictExpr`] list around if they matter.
+    ///
+    /// See [PMS 7.3.6](https://projects.gentoo.org/pms/9/pms.html#restrict).
+    RestrictSet(u8), RestrictKind, empty = ""
+}
+
+impl RestrictSet {
+    /// The tokens of `entries` active under `use_flags`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{RestrictExpr, RestrictKind, RestrictSet, UseFlagSet};
+    ///
+    /// let entries = RestrictExpr::parse("mirror bindist? ( fetch )").unwrap();
+    /// let set = RestrictSet::from_expr(&entries, &UseFlagSet::new());
+    /// assert!(set.contains(&RestrictKind::Mirror));
+    /// assert!(!set.contains(&RestrictKind::Fetch));
+    /// ```
+    pub fn from_expr(entries: &[RestrictExpr], use_flags: &UseFlagSet) -> Self {
+        RestrictExpr::evaluate(entries, use_flags)
+            .into_iter()
+            .collect()
+    }
+}
+
+impl BitIndex for RestrictKind {
+    const KNOWN: &'static [Self] = &KNOWN;
+
+    fn bit_index(&self) -> Option<u8> {
+        Some(match self {
+            RestrictKind::Fetch => 0,
+            RestrictKind::Mirror => 1,
+            RestrictKind::Test => 2,
+            RestrictKind::Strip => 3,
+            RestrictKind::Binchecks => 4,
+            RestrictKind::Userpriv => 5,
+            RestrictKind::Splitdebug => 6,
+            RestrictKind::Other(_) => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_operations() {
+        let a: RestrictSet = [RestrictKind::Fetch, RestrictKind::Mirror].iter().collect();
+        let b: RestrictSet = [RestrictKind::Mirror, RestrictKind::Test].iter().collect();
+        assert_eq!((a | b).len(), 3);
+        assert_eq!((a & b).to_string(), "mirror");
+        assert_eq!((a - b).to_string(), "fetch");
+        assert_eq!(RestrictSet::all().len(), 7);
+        assert!(RestrictSet::new().is_empty());
+    }
+
+    #[test]
+    fn other_tokens_ignored() {
+        let mut set = RestrictSet::new();
+        let other = RestrictKind::Other("bindist".to_string());
+        assert!(!set.insert(&other));
+        assert!(!set.contains(&other));
+        assert!(set.insert(&RestrictKind::Strip));
+        assert!(!set.insert(&RestrictKind::Strip));
+        assert!(set.remove(&RestrictKind::Strip));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn bit_indices_follow_known() {
+        for (i, kind) in RestrictKind::KNOWN.iter().enumerate() {
+            assert_eq!(kind.bit_index(), Some(i as u8));
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use portage_atom::Cpv;
+
+use crate::interner::Interner;
+use crate::metadata::EbuildMetadata;
+use crate::spdx::SpdxMap;
+use crate::src_uri::SrcUriEntry;
+use crate::src_url::SrcUrl;
+
+/// An SPDX 2.3 software bill of materials for a set of package versions.
+///
+/// [`Display`](fmt::Display) renders the document in SPDX tag-value
+/// format. Each package gets its `LICENSE` as the declared license,
+/// converted with an [`SpdxMap`], its first `HOMEPAGE`, its first
+/// `scheme://` `SRC_URI` outside USE conditionals as the download
+/// location, and its `DESCRIPTION` as the summary. Fields without a
+/// usable value are `NOASSERTION`. Licenses without an SPDX identifier
+/// become `LicenseRef-` references, each declared once at the end of the
+/// document with the text given to [`Sbom::add_license_text`], or a
+/// pointer to the repository's `licenses/` directory.
+///
+/// SPDX documents are not specified by PMS.
+///
+/// # Examples
+///
+/// ```
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, Sbom};
+///
+/// let entry = CacheEntry::parse(
+///     "DESCRIPTION=Foo\nSLOT=0\nLICENSE=GPL-2+\nSRC_URI=https://e.org/foo-1.0.tar.gz\n",
+/// )
+/// .unwrap();
+/// let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+///
+/// let mut sbom = Sbom::new("my-system", "https://example.org/sbom/1", "2024-01-01T00:00:00Z");
+/// sbom.add(&cpv, &entry.metadata);
+/// let document = sbom.to_string();
+/// assert!(document.contains("PackageName: app-misc/foo\n"));
+/// assert!(document.contains("PackageLicenseDeclared: GPL-2.0-or-later\n"));
+/// assert!(document.contains("PackageDownloadLocation: https://e.org/foo-1.0.tar.gz\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sbom {
+    name: String,
+    namespace: String,
+    created: String,
+    licenses: SpdxMap,
+    packages: Vec<SbomPackage>,
+    /// Gentoo license names by the `LicenseRef-` ids they became.
+    license_refs: BTreeMap<String, String>,
+    /// License texts by Gentoo license name.
+    license_texts: BTreeMap<String, String>,
+}
+
+/// One package of an [`Sbom`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SbomPackage {
+    cpv: Cpv,
+    summary: String,
+    homepage: Option<String>,
+    download: Option<String>,
+    license: Option<String>,
+}
+
+impl Sbom {
+    /// Create an empty document.
+    ///
+    /// `namespace` is the unique document URI and `created` the ISO 8601
+    /// UTC creation time, e.g. `2024-01-01T00:00:00Z`. Licenses are
+    /// converted with [`SpdxMap::default`].
+    pub fn new(
+        name: impl Into<String>,
+        namespace: impl Into<String>,
+        created: impl Into<String>,
+    ) -> Self {
+        Sbom {
+            name: name.into(),
+            namespace: namespace.into(),
+            created: created.into(),
+            licenses: SpdxMap::default(),
+            packages: Vec::new(),
+            license_refs: BTreeMap::new(),
+            license_texts: BTreeMap::new(),
+        }
+    }
+
+    /// Convert licenses with `licenses` instead of the built-in table.
+    pub fn with_licenses(mut self, licenses: SpdxMap) -> Self {
+        self.licenses = licenses;
+        self
+    }
+
+    /// Use `text` as the extracted text of `license`, a Gentoo license
+    /// name, should it become a `LicenseRef-`.
+    pub fn add_license_text(&mut self, license: impl Into<String>, text: impl Into<String>) {
+        self.license_texts.insert(license.into(), text.into());
+    }
+
+    /// Add a package version.
+    pub fn add<I: Interner>(&mut self, cpv: &Cpv, metadata: &EbuildMetadata<I>) {
+        for name in metadata
+            .license
+            .iter()
+            .flat_map(|license| license.licenses())
+        {
+            let id = self.licenses.identifier(name);
+            if id.starts_with("LicenseRef-") {
+                self.license_refs
+                    .entry(id)
+                    .or_insert_with(|| name.to_string());
+            }
+        }
+        self.packages.push(SbomPackage {
+            cpv: cpv.clone(),
+            summary: metadata.description.clone(),
+            homepage: metadata
+                .homepage
+                .iter()
+                .find_map(|homepage| homepage.url())
+                .map(ToString::to_string),
+            download: download_url(&metadata.src_uri),
+            license: metadata
+                .license
+                .as_ref()
+                .map(|license| license.to_spdx(&self.licenses)),
+        });
+    }
+
+    /// The number of packages.
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    /// Whether no package was added.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+}
+
+impl fmt::Display for Sbom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "SPDXVersion: SPDX-2.3")?;
+        writeln!(f, "DataLicense: CC0-1.0")?;
+        writeln!(f, "SPDXID: SPDXRef-DOCUMENT")?;
+        writeln!(f, "DocumentName: {}", self.name)?;
+        writeln!(f, "DocumentNamespace: {}", self.namespace)?;
+        writeln!(
+            f,
+            "Creator: Tool: portage-metadata-{}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(f, "Created: {}", self.created)?;
+        for package in &self.packages {
+            let id = spdx_id(&package.cpv);
+            let or_none =
+                |value: &Option<String>| value.clone().unwrap_or_else(|| "NOASSERTION".to_string());
+            writeln!(f)?;
+            writeln!(f, "PackageName: {}", package.cpv.cpn)?;
+            writeln!(f, "SPDXID: {id}")?;
+            writeln!(f, "PackageVersion: {}", package.cpv.version)?;
+            writeln!(f, "PackageDownloadLocation: {}", or_none(&package.download))?;
+            writeln!(f, "FilesAnalyzed: false")?;
+            if let Some(homepage) = &package.homepage {
+                writeln!(f, "PackageHomePage: {homepage}")?;
+            }
+            writeln!(f, "PackageLicenseConcluded: NOASSERTION")?;
+            writeln!(f, "PackageLicenseDeclared: {}", or_none(&package.license))?;
+            writeln!(f, "PackageCopyrightText: NOASSERTION")?;
+            writeln!(f, "PackageSummary: {}", Text(&package.summary))?;
+            writeln!(f, "Relationship: SPDXRef-DOCUMENT DESCRIBES {id}")?;
+        }
+        for (id, name) in &self.license_refs {
+            let text = match self.license_texts.get(name) {
+                Some(text) => text.clone(),
+                None => format!("See licenses/{name} in the ebuild repository."),
+            };
+            writeln!(f)?;
+            writeln!(f, "LicenseID: {id}")?;
+            writeln!(f, "ExtractedText: {}", Text(&text))?;
+            writeln!(f, "LicenseName: {name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The first `scheme://` URL of `SRC_URI` outside USE conditionals.
+fn download_url(entries: &[SrcUriEntry]) -> Option<String> {
+    entries.iter().find_map(|entry| match entry {
+        SrcUriEntry::Uri { url, .. } | SrcUriEntry::Renamed { url, .. } => {
+            matches!(url, SrcUrl::Url { .. }).then(|| url.to_string())
+        }
+        SrcUriEntry::Group(entries) => download_url(entries),
+        SrcUriEntry::UseConditional { .. } => None,
+    })
+}
+
+/// A `<text>` value, with any `</text>` inside it escaped.
+struct Text<'a>(&'a str);
+
+impl fmt::Display for Text<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "<text>{}</text>",
+            self.0.replace("</text>", "&lt;/text&gt;")
+        )
+    }
+}
+
+// SPDX element ids allow only letters, digits, `.` and `-`.
+fn spdx_id(cpv: &Cpv) -> String {
+    let id: String = cpv
+        .to_string()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("SPDXRef-{id}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheEntry;
+
+    #[test]
+    fn document() {
+        let entry = CacheEntry::parse(
+            "DESCRIPTION=Foo </text> tool\nSLOT=0\nLICENSE=|| ( MIT custom ) doc? ( other )\n\
+             HOMEPAGE=foo https://foo.org/\n\
+             SRC_URI=ssl? ( https://e.org/ssl_1.patch ) mirror://gnu/foo.tar.gz \
+             ( https://e.org/foo.tar.gz )\n",
+        )
+        .unwrap();
+        let bare = CacheEntry::parse(
+            "DESCRIPTION=Bar\nSLOT=0\nLICENSE=custom\nSRC_URI=mirror://gnu/bar.tar.gz\n",
+        )
+        .unwrap();
+        let mut sbom = Sbom::new("test", "https://example.org/sbom", "2024-01-01T00:00:00Z");
+        sbom.add(&Cpv::parse("app-misc/foo-1.0_p1").unwrap(), &entry.metadata);
+        sbom.add(&Cpv::parse("dev-libs/bar_baz-2").unwrap(), &bare.metadata);
+        sbom.add_license_text("other", "Other license");
+        assert_eq!(sbom.len(), 2);
+        let document = sbom.to_string();
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            document,
+            format!(
+                "SPDXVersion: SPDX-2.3\n\
+                 DataLicense: CC0-1.0\n\
+                 SPDXID: SPDXRef-DOCUMENT\n\
+                 DocumentName: test\n\
+                 DocumentNamespace: https://example.org/sbom\n\
+                 Creator: Tool: portage-metadata-{version}\n\
+                 Created: 2024-01-01T00:00:00Z\n\
+                 \n\
+                 PackageName: app-misc/foo\n\
+                 SPDXID: SPDXRef-app-misc-foo-1.0-p1\n\
+                 PackageVersion: 1.0_p1\n\
+                 PackageDownloadLocation: https://e.org/foo.tar.gz\n\
+                 FilesAnalyzed: false\n\
+                 PackageHomePage: https://foo.org/\n\
+                 PackageLicenseConcluded: NOASSERTION\n\
+                 PackageLicenseDeclared: (MIT OR LicenseRef-custom) AND LicenseRef-other\n\
+                 PackageCopyrightText: NOASSERTION\n\
+                 PackageSummary: <text>Foo &lt;/text&gt; tool</text>\n\
+                 Relationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-app-misc-foo-1.0-p1\n\
+                 \n\
+                 PackageName: dev-libs/bar_baz\n\
+                 SPDXID: SPDXRef-dev-libs-bar-baz-2\n\
+                 PackageVersion: 2\n\
+                 PackageDownloadLocation: NOASSERTION\n\
+                 FilesAnalyzed: false\n\
+                 PackageLicenseConcluded: NOASSERTION\n\
+                 PackageLicenseDeclared: LicenseRef-custom\n\
+                 PackageCopyrightText: NOASSERTION\n\
+                 PackageSummary: <text>Bar</text>\n\
+                 Relationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-dev-libs-bar-baz-2\n\
+                 \n\
+                 LicenseID: LicenseRef-custom\n\
+                 ExtractedText: <text>See licenses/custom in the ebuild repository.</text>\n\
+                 LicenseName: custom\n\
+                 \n\
+                 LicenseID: LicenseRef-other\n\
+                 ExtractedText: <text>Other license</text>\n\
+                 LicenseName: other\n"
+            )
+        );
+    }
+}
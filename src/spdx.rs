@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+/// Built-in Gentoo → SPDX license identifier table.
+///
+/// Only licenses whose Gentoo name has an exact SPDX counterpart are
+/// listed; Gentoo names that are already valid SPDX identifiers are
+/// included so they are not turned into `LicenseRef-` references.
+const DEFAULT_TABLE: &[(&str, &str)] = &[
+    ("AGPL-3", "AGPL-3.0-only"),
+    ("AGPL-3+", "AGPL-3.0-or-later"),
+    ("Apache-1.1", "Apache-1.1"),
+    ("Apache-2.0", "Apache-2.0"),
+    ("Artistic", "Artistic-1.0-Perl"),
+    ("Artistic-2", "Artistic-2.0"),
+    ("BSD", "BSD-3-Clause"),
+    ("BSD-2", "BSD-2-Clause"),
+    ("BSD-4", "BSD-4-Clause"),
+    ("Boost-1.0", "BSL-1.0"),
+    ("CC-BY-3.0", "CC-BY-3.0"),
+    ("CC-BY-4.0", "CC-BY-4.0"),
+    ("CC-BY-SA-3.0", "CC-BY-SA-3.0"),
+    ("CC-BY-SA-4.0", "CC-BY-SA-4.0"),
+    ("CC0-1.0", "CC0-1.0"),
+    ("CDDL", "CDDL-1.0"),
+    ("EPL-1.0", "EPL-1.0"),
+    ("EPL-2.0", "EPL-2.0"),
+    ("FDL-1.1", "GFDL-1.1-only"),
+    ("FDL-1.1+", "GFDL-1.1-or-later"),
+    ("FDL-1.2", "GFDL-1.2-only"),
+    ("FDL-1.2+", "GFDL-1.2-or-later"),
+    ("FDL-1.3", "GFDL-1.3-only"),
+    ("FDL-1.3+", "GFDL-1.3-or-later"),
+    ("GPL-1", "GPL-1.0-only"),
+    ("GPL-1+", "GPL-1.0-or-later"),
+    ("GPL-2", "GPL-2.0-only"),
+    ("GPL-2+", "GPL-2.0-or-later"),
+    ("GPL-3", "GPL-3.0-only"),
+    ("GPL-3+", "GPL-3.0-or-later"),
+    ("HPND", "HPND"),
+    ("ISC", "ISC"),
+    ("LGPL-2", "LGPL-2.0-only"),
+    ("LGPL-2+", "LGPL-2.0-or-later"),
+    ("LGPL-2.1", "LGPL-2.1-only"),
+    ("LGPL-2.1+", "LGPL-2.1-or-later"),
+    ("LGPL-3", "LGPL-3.0-only"),
+    ("LGPL-3+", "LGPL-3.0-or-later"),
+    ("LPPL-1.3c", "LPPL-1.3c"),
+    ("MIT", "MIT"),
+    ("MPL-1.1", "MPL-1.1"),
+    ("MPL-2.0", "MPL-2.0"),
+    ("OFL-1.1", "OFL-1.1"),
+    ("PSF-2", "PSF-2.0"),
+    ("Ruby", "Ruby"),
+    ("Sleepycat", "Sleepycat"),
+    ("UoI-NCSA", "NCSA"),
+    ("Unlicense", "Unlicense"),
+    ("Vim", "Vim"),
+    ("WTFPL-2", "WTFPL"),
+    ("ZLIB", "Zlib"),
+    ("openssl", "OpenSSL"),
+];
+
+/// Mapping from Gentoo license names to SPDX license identifiers.
+///
+/// [`SpdxMap::default`] provides a built-in table covering common
+/// licenses; entries can be added or overridden with
+/// [`SpdxMap::insert`]. Names without a mapping are emitted as
+/// `LicenseRef-<name>` user-defined references.
+///
+/// Gentoo license names are the file names under the repository's
+/// `licenses/` directory; see
+/// [PMS 3.1.7](https://projects.gentoo.org/pms/9/pms.html#license-names)
+/// and [PMS 4.6](https://projects.gentoo.org/pms/9/pms.html#the-licenses-directory).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpdxMap {
+    table: BTreeMap<String, String>,
+}
+
+impl SpdxMap {
+    /// Create an empty mapping (every license becomes a `LicenseRef-`).
+    pub fn new() -> Self {
+        Self {
+            table: BTreeMap::new(),
+        }
+    }
+
+    /// Map the Gentoo license `gentoo` to the SPDX identifier `spdx`,
+    /// replacing any previous mapping.
+    pub fn insert(&mut self, gentoo: impl Into<String>, spdx: impl Into<String>) {
+        self.table.insert(gentoo.into(), spdx.into());
+    }
+
+    /// The SPDX identifier explicitly mapped to `gentoo`, if any.
+    pub fn get(&self, gentoo: &str) -> Option<&str> {
+        self.table.get(gentoo).map(String::as_str)
+    }
+
+    /// The SPDX identifier for `gentoo`, falling back to a `LicenseRef-`.
+    ///
+    /// Characters not allowed in a `LicenseRef-` idstring are replaced
+    /// with `-`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::SpdxMap;
+    ///
+    /// let map = SpdxMap::default();
+    /// assert_eq!(map.identifier("GPL-2+"), "GPL-2.0-or-later");
+    /// assert_eq!(map.identifier("all-rights-reserved"), "LicenseRef-all-rights-reserved");
+    /// ```
+    pub fn identifier(&self, gentoo: &str) -> String {
+        match self.get(gentoo) {
+            Some(spdx) => spdx.to_string(),
+            None => {
+                let id: String = gentoo
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || matches!(c, '-' | '.') {
+                            c
+                        } else {
+                            '-'
+                        }
+                    })
+                    .collect();
+                format!("LicenseRef-{id}")
+            }
+        }
+    }
+}
+
+impl Default for SpdxMap {
+    fn default() -> Self {
+        DEFAULT_TABLE.iter().copied().collect()
+    }
+}
+
+impl<G: Into<String>, S: Into<String>> FromIterator<(G, S)> for SpdxMap {
+    fn from_iter<T: IntoIterator<Item = (G, S)>>(iter: T) -> Self {
+        Self {
+            table: iter
+                .into_iter()
+                .map(|(g, s)| (g.into(), s.into()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_table() {
+        let map = SpdxMap::default();
+        assert_eq!(map.get("GPL-2"), Some("GPL-2.0-only"));
+        assert_eq!(map.get("BSD"), Some("BSD-3-Clause"));
+        assert_eq!(map.get("unknown"), None);
+    }
+
+    #[test]
+    fn override_entry() {
+        let mut map = SpdxMap::default();
+        map.insert("BSD", "BSD-2-Clause");
+        map.insert("public-domain", "LicenseRef-PublicDomain");
+        assert_eq!(map.identifier("BSD"), "BSD-2-Clause");
+        assert_eq!(map.identifier("public-domain"), "LicenseRef-PublicDomain");
+    }
+
+    #[test]
+    fn license_ref_sanitized() {
+        let map = SpdxMap::new();
+        assert_eq!(map.identifier("GPL-2+"), "LicenseRef-GPL-2-");
+        assert_eq!(map.identifier("my_lic"), "LicenseRef-my-lic");
+    }
+}
@@ -0,0 +1,238 @@
+use std::io::{self, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::dep_spec::DepSpec;
+#[cfg(feature = "fs")]
+use crate::error::Result;
+use crate::interner::Interner;
+use crate::keyword::Stability;
+use crate::metadata::EbuildMetadata;
+#[cfg(feature = "fs")]
+use crate::metadata_repo::MetadataRepo;
+
+/// Normalized SQL export of repository metadata, for ad-hoc analysis.
+///
+/// The output is a plain SQL script that both SQLite and PostgreSQL
+/// accept. It creates four tables keyed by `cpv` and fills them inside a
+/// single transaction:
+///
+/// - `packages`: one row per package version, with its category, package
+///   name, version, EAPI, slot, sub-slot and description;
+/// - `keywords`: one row per keyword, with the arch and `stable`,
+///   `testing` or `disabled`;
+/// - `licenses`: one row per license name in `LICENSE`;
+/// - `deps`: one row per atom in the dependency classes, with the class
+///   (`DEPEND`, `RDEPEND`, ...), the depended-on `category/package` as
+///   `dependency` and the full atom. Atoms under USE-conditional and `||`
+///   groups are included.
+///
+/// The schema is not specified by PMS.
+///
+/// # Examples
+///
+/// ```
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, SqlExport};
+///
+/// let entry = CacheEntry::parse("DESCRIPTION=Foo's tool\nSLOT=0\nKEYWORDS=~amd64\n").unwrap();
+/// let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+///
+/// let inserts = SqlExport::inserts(&cpv, &entry.metadata);
+/// assert_eq!(
+///     inserts[0],
+///     "INSERT INTO packages VALUES ('app-misc/foo-1.0', 'app-misc', 'foo', '1.0', '0', '0', NULL, 'Foo''s tool');"
+/// );
+/// assert_eq!(
+///     inserts[1],
+///     "INSERT INTO keywords VALUES ('app-misc/foo-1.0', 'amd64', 'testing');"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SqlExport;
+
+impl SqlExport {
+    /// The `CREATE TABLE` statements.
+    pub const SCHEMA: &'static str = "\
+CREATE TABLE packages (
+    cpv TEXT PRIMARY KEY,
+    category TEXT NOT NULL,
+    package TEXT NOT NULL,
+    version TEXT NOT NULL,
+    eapi TEXT NOT NULL,
+    slot TEXT NOT NULL,
+    subslot TEXT,
+    description TEXT NOT NULL
+);
+CREATE TABLE keywords (
+    cpv TEXT NOT NULL REFERENCES packages (cpv),
+    arch TEXT NOT NULL,
+    stability TEXT NOT NULL
+);
+CREATE TABLE licenses (
+    cpv TEXT NOT NULL REFERENCES packages (cpv),
+    license TEXT NOT NULL
+);
+CREATE TABLE deps (
+    cpv TEXT NOT NULL REFERENCES packages (cpv),
+    class TEXT NOT NULL,
+    dependency TEXT NOT NULL,
+    atom TEXT NOT NULL
+);
+";
+
+    /// The `INSERT` statements for one package version, `packages` first.
+    pub fn inserts<I: Interner>(cpv: &Cpv, metadata: &EbuildMetadata<I>) -> Vec<String> {
+        let key = quote(&cpv.to_string());
+        let mut inserts = vec![format!(
+            "INSERT INTO packages VALUES ({key}, {}, {}, {}, {}, {}, {}, {});",
+            quote(cpv.cpn.category.as_str()),
+            quote(cpv.cpn.package.as_str()),
+            quote(&cpv.version.to_string()),
+            quote(&metadata.eapi.to_string()),
+            quote(metadata.slot.slot.as_str()),
+            metadata
+                .slot
+                .subslot
+                .as_ref()
+                .map_or_else(|| "NULL".to_string(), |subslot| quote(subslot.as_str())),
+            quote(&metadata.description),
+        )];
+        for keyword in &metadata.keywords {
+            let stability = match keyword.stability {
+                Stability::Stable => "stable",
+                Stability::Testing => "testing",
+                Stability::Disabled | Stability::DisabledAll => "disabled",
+            };
+            inserts.push(format!(
+                "INSERT INTO keywords VALUES ({key}, {}, '{stability}');",
+                quote(&keyword.arch.to_string())
+            ));
+        }
+        if let Some(license) = &metadata.license {
+            let mut names: Vec<_> = license.licenses().collect();
+            names.sort_unstable();
+            names.dedup();
+            for name in names {
+                inserts.push(format!(
+                    "INSERT INTO licenses VALUES ({key}, {});",
+                    quote(name)
+                ));
+            }
+        }
+        for (class, entries) in metadata.dependencies() {
+            for dep in DepSpec::from_deps(entries).leaves() {
+                inserts.push(format!(
+                    "INSERT INTO deps VALUES ({key}, '{class}', {}, {});",
+                    quote(&dep.cpn.to_string()),
+                    quote(&dep.to_string())
+                ));
+            }
+        }
+        inserts
+    }
+
+    /// Write the schema and the rows of every entry as one transaction.
+    pub fn write<'a, W, I>(
+        mut out: W,
+        entries: impl IntoIterator<Item = (&'a Cpv, &'a CacheEntry<I>)>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        I: Interner + 'a,
+    {
+        write!(out, "BEGIN;\n{}", Self::SCHEMA)?;
+        for (cpv, entry) in entries {
+            for insert in Self::inserts(cpv, &entry.metadata) {
+                writeln!(out, "{insert}")?;
+            }
+        }
+        writeln!(out, "COMMIT;")
+    }
+
+    /// Export every entry of `repo` to a new file at `path`, sorted by
+    /// package version, and return the number of package versions
+    /// written.
+    ///
+    /// Errors in an entry carry its package version as
+    /// [`Error::Package`](crate::Error::Package) context; file access
+    /// failures are reported as [`Error::Io`](crate::Error::Io).
+    #[cfg(feature = "fs")]
+    pub fn write_repo(repo: &MetadataRepo, path: impl AsRef<Path>) -> Result<usize> {
+        repo.export(path.as_ref(), |out, entries| Self::write(out, entries))
+    }
+}
+
+// SQL string literal: single quotes doubled.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows() {
+        let entry = CacheEntry::parse(
+            "DESCRIPTION=x\nSLOT=2/2.1\nKEYWORDS=amd64 -x86\nLICENSE=MIT || ( MIT BSD )\n\
+             RDEPEND=ssl? ( >=dev-libs/openssl-3:= ) !app-misc/old\n",
+        )
+        .unwrap();
+        let cpv = Cpv::parse("app-misc/foo-1.0-r1").unwrap();
+        assert_eq!(
+            SqlExport::inserts(&cpv, &entry.metadata),
+            [
+                "INSERT INTO packages VALUES ('app-misc/foo-1.0-r1', 'app-misc', 'foo', '1.0-r1', '0', '2', '2.1', 'x');",
+                "INSERT INTO keywords VALUES ('app-misc/foo-1.0-r1', 'amd64', 'stable');",
+                "INSERT INTO keywords VALUES ('app-misc/foo-1.0-r1', 'x86', 'disabled');",
+                "INSERT INTO licenses VALUES ('app-misc/foo-1.0-r1', 'BSD');",
+                "INSERT INTO licenses VALUES ('app-misc/foo-1.0-r1', 'MIT');",
+                "INSERT INTO deps VALUES ('app-misc/foo-1.0-r1', 'RDEPEND', 'dev-libs/openssl', '>=dev-libs/openssl-3:=');",
+                "INSERT INTO deps VALUES ('app-misc/foo-1.0-r1', 'RDEPEND', 'app-misc/old', '!app-misc/old');",
+            ]
+        );
+    }
+
+    #[test]
+    fn transaction() {
+        let entry = CacheEntry::parse("DESCRIPTION=x\nSLOT=0\n").unwrap();
+        let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+        let mut out = Vec::new();
+        SqlExport::write(&mut out, [(&cpv, &entry)]).unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.starts_with("BEGIN;\nCREATE TABLE packages ("));
+        assert!(script.ends_with("'x');\nCOMMIT;\n"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn repo_to_file() {
+        use crate::temp_dir::TempDir;
+        use std::fs;
+
+        let root = TempDir::new("sql-export");
+        let cache = root.join("metadata/md5-cache/app-misc");
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join("foo-1.0"), "DESCRIPTION=foo\nSLOT=0\n").unwrap();
+        fs::write(cache.join("foo-2.0"), "DESCRIPTION=foo\nSLOT=0\n").unwrap();
+
+        let repo = MetadataRepo::new(&root);
+        let out = root.join("metadata.sql");
+        assert_eq!(SqlExport::write_repo(&repo, &out).unwrap(), 2);
+        let script = fs::read_to_string(&out).unwrap();
+        assert_eq!(script.matches("INSERT INTO packages").count(), 2);
+
+        let err = SqlExport::write_repo(&repo, root.join("missing/metadata.sql")).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with(&root.join("missing").display().to_string()));
+
+        fs::write(cache.join("foo-2.0"), "DESCRIPTION=foo\n").unwrap();
+        let err = SqlExport::write_repo(&repo, &out).unwrap_err();
+        assert_eq!(err.cpv(), Some("app-misc/foo-2.0"));
+    }
+}
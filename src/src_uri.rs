@@ -1,12 +1,20 @@
+use std::collections::BTreeSet;
 use std::fmt;
 
-use winnow::ascii::multispace0;
-use winnow::combinator::{alt, cut_err, delimited, dispatch, opt, peek, preceded, repeat};
+use winnow::ascii::{multispace0, multispace1};
+use winnow::combinator::{alt, cut_err, eof, opt, peek, preceded, terminated};
 use winnow::error::StrContext;
 use winnow::prelude::*;
-use winnow::token::{any, take_while};
+use winnow::token::take_while;
 
+use crate::dep_spec::{self, fmt_spec, parse_spec, GroupKind, ParseSpec, Shape, SpecTree};
+use crate::eapi::Eapi;
+use crate::eapi_feature::EapiFeature;
+use crate::eapi_registry::EapiRules;
 use crate::error::{Error, Result};
+use crate::names::{check_tokens, Leaf};
+use crate::src_url::SrcUrl;
+use crate::use_flags::UseFlagSet;
 
 /// A single entry in a `SRC_URI` expression.
 ///
@@ -17,12 +25,12 @@ use crate::error::{Error, Result};
 ///
 /// See [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri)
 /// and [PMS 8.2](https://projects.gentoo.org/pms/9/pms.html#dependency-specification-format).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SrcUriEntry {
     /// A plain URI. The filename is derived from the last path component.
     Uri {
         /// The download URL.
-        url: String,
+        url: SrcUrl,
         /// The target filename (last path component of the URL).
         filename: String,
         /// URI restriction prefix (EAPI 8+): `None`, `Some("fetch")`, or `Some("mirror")`.
@@ -31,7 +39,7 @@ pub enum SrcUriEntry {
     /// A renamed URI (EAPI 2+): `url -> target`.
     Renamed {
         /// The download URL.
-        url: String,
+        url: SrcUrl,
         /// The local filename to save as.
         target: String,
         /// URI restriction prefix (EAPI 8+): `None`, `Some("fetch")`, or `Some("mirror")`.
@@ -51,6 +59,64 @@ pub enum SrcUriEntry {
 }
 
 impl SrcUriEntry {
+    /// A plain URI, saved under the last component of its path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::SrcUriEntry;
+    ///
+    /// let entries = vec![
+    ///     SrcUriEntry::uri("https://example.com/foo-1.tar.gz"),
+    ///     SrcUriEntry::conditional(
+    ///         "doc",
+    ///         false,
+    ///         [SrcUriEntry::renamed("https://example.com/doc.tgz", "foo-doc-1.tgz")],
+    ///     ),
+    /// ];
+    /// assert_eq!(
+    ///     entries,
+    ///     SrcUriEntry::parse(
+    ///         "https://example.com/foo-1.tar.gz doc? ( https://example.com/doc.tgz -> foo-doc-1.tgz )"
+    ///     )
+    ///     .unwrap()
+    /// );
+    /// ```
+    pub fn uri(url: &str) -> Self {
+        SrcUriEntry::Uri {
+            url: SrcUrl::parse(url),
+            filename: filename_from_url(url),
+            restriction: None,
+        }
+    }
+
+    /// `url -> target`.
+    pub fn renamed(url: &str, target: impl Into<String>) -> Self {
+        SrcUriEntry::Renamed {
+            url: SrcUrl::parse(url),
+            target: target.into(),
+            restriction: None,
+        }
+    }
+
+    /// `flag? ( entries... )`, or `!flag? ( entries... )` if `negated`.
+    pub fn conditional(
+        flag: impl Into<String>,
+        negated: bool,
+        entries: impl IntoIterator<Item = Self>,
+    ) -> Self {
+        SrcUriEntry::UseConditional {
+            flag: flag.into(),
+            negated,
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    /// `( entries... )`.
+    pub fn group(entries: impl IntoIterator<Item = Self>) -> Self {
+        SrcUriEntry::Group(entries.into_iter().collect())
+    }
+
     /// Parse a `SRC_URI` expression string into a list of entries.
     ///
     /// # Examples
@@ -64,10 +130,192 @@ impl SrcUriEntry {
     /// assert_eq!(entries.len(), 2);
     /// ```
     pub fn parse(input: &str) -> Result<Vec<SrcUriEntry>> {
-        parse_src_uri_string
+        parse_spec
             .parse(input)
             .map_err(|e| Error::InvalidSrcUri(format!("{e}")))
     }
+
+    /// The URI entries active under `use_flags`.
+    ///
+    /// USE-conditional groups are evaluated and bare groups flattened, so
+    /// the result contains only [`SrcUriEntry::Uri`] and
+    /// [`SrcUriEntry::Renamed`] entries, in source order.
+    pub fn evaluate<'a>(
+        entries: &'a [SrcUriEntry],
+        use_flags: &UseFlagSet,
+    ) -> Vec<&'a SrcUriEntry> {
+        dep_spec::active_leaves(entries, use_flags)
+    }
+
+    /// Parse a `SRC_URI` expression, applying the PMS token rules strictly.
+    ///
+    /// [`SrcUriEntry::parse`] accepts `flag?(` without whitespace and bare
+    /// filenames containing `?`. Per PMS, tokens are whitespace-separated
+    /// and a token ending in `?` is a USE conditional that must be followed
+    /// by a `(` token, so this mode rejects:
+    ///
+    /// - a conditional not separated from its `(` by whitespace;
+    /// - a `flag?` token not followed by `(`;
+    /// - a token without `://` containing `?` elsewhere, which could be
+    ///   either a filename with a query string or a mistyped conditional;
+    /// - a conditional flag violating
+    ///   [PMS 3.1.4](https://projects.gentoo.org/pms/9/pms.html#use-flag-names).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::SrcUriEntry;
+    ///
+    /// assert!(SrcUriEntry::parse_strict("ssl? ( https://e.com/a?x=1 )").is_ok());
+    /// assert!(SrcUriEntry::parse_strict("ssl?( https://e.com/a )").is_err());
+    /// assert!(SrcUriEntry::parse_strict("download?id=1").is_err());
+    /// ```
+    pub fn parse_strict(input: &str) -> Result<Vec<SrcUriEntry>> {
+        let mut tokens = input.split_whitespace().peekable();
+        while let Some(token) = tokens.next() {
+            if token.contains("://") {
+                continue;
+            }
+            let ambiguous = match token.find('?') {
+                None => false,
+                Some(i) if i + 1 == token.len() => tokens.peek() != Some(&"("),
+                Some(_) => true,
+            };
+            if ambiguous {
+                return Err(Error::InvalidSrcUri(format!(
+                    "ambiguous token '{token}': expected a URI or 'flag? ('"
+                )));
+            }
+        }
+        if let Some((pos, what)) = check_tokens(input, Leaf::Other) {
+            return Err(Error::InvalidSrcUri(format!("position {pos}: {what}")));
+        }
+        Self::parse(input)
+    }
+
+    /// Parse a `SRC_URI` expression, rejecting syntax not allowed by `eapi`.
+    ///
+    /// In addition to [`SrcUriEntry::parse`], this checks that `->` renames
+    /// are only used from EAPI 2, that `fetch+`/`mirror+` prefixes are only
+    /// used from EAPI 8, and that rename targets are non-empty and contain
+    /// no `/`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{Eapi, SrcUriEntry};
+    ///
+    /// let input = "https://example.com/v1.tar.gz -> foo-1.tar.gz";
+    /// assert!(SrcUriEntry::parse_with_eapi(input, &Eapi::Two).is_ok());
+    /// assert!(SrcUriEntry::parse_with_eapi(input, &Eapi::One).is_err());
+    /// ```
+    pub fn parse_with_eapi(input: &str, eapi: &Eapi) -> Result<Vec<SrcUriEntry>> {
+        Self::parse_in(input, &EapiRules::new(eapi, None))
+    }
+
+    /// [`SrcUriEntry::parse_with_eapi`] against the features of `rules`.
+    pub(crate) fn parse_in(input: &str, rules: &EapiRules<'_>) -> Result<Vec<SrcUriEntry>> {
+        let entries = Self::parse(input)?;
+        validate_eapi(&entries, rules)?;
+        Ok(entries)
+    }
+
+    /// Rewrite every URL in the tree in place.
+    ///
+    /// Conditional groups, restriction prefixes and target filenames are
+    /// preserved, so a plain URI keeps its original distfile name even if
+    /// the rewritten URL ends differently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{SrcUriEntry, SrcUrl};
+    ///
+    /// let mut entries = SrcUriEntry::parse("ssl? ( https://example.com/a.tar.gz )").unwrap();
+    /// SrcUriEntry::map_urls(&mut entries, |url| {
+    ///     if let SrcUrl::Url { host, .. } = url {
+    ///         *host = "proxy.internal".to_string();
+    ///     }
+    /// });
+    /// assert_eq!(entries[0].to_string(), "ssl? ( https://proxy.internal/a.tar.gz )");
+    /// ```
+    pub fn map_urls<F: FnMut(&mut SrcUrl)>(entries: &mut [SrcUriEntry], mut f: F) {
+        map_urls_with(entries, &mut f);
+    }
+
+    /// Every target filename in the tree, regardless of USE conditionals.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::SrcUriEntry;
+    ///
+    /// let entries = SrcUriEntry::parse(
+    ///     "https://e.com/a.tar.gz ssl? ( https://m.org/a.tar.gz https://e.com/x -> b.patch )",
+    /// )
+    /// .unwrap();
+    /// let files: Vec<&str> = SrcUriEntry::distfiles(&entries).into_iter().collect();
+    /// assert_eq!(files, vec!["a.tar.gz", "b.patch"]);
+    /// ```
+    pub fn distfiles(entries: &[SrcUriEntry]) -> BTreeSet<&str> {
+        dep_spec::leaves(entries)
+            .into_iter()
+            .filter_map(|entry| match entry {
+                SrcUriEntry::Uri { filename, .. } => Some(filename.as_str()),
+                SrcUriEntry::Renamed { target, .. } => Some(target.as_str()),
+                SrcUriEntry::UseConditional { .. } | SrcUriEntry::Group(_) => None,
+            })
+            .collect()
+    }
+
+    /// Add every USE flag referenced by a conditional group to `out`.
+    pub(crate) fn collect_flags<'a>(entries: &'a [SrcUriEntry], out: &mut BTreeSet<&'a str>) {
+        dep_spec::collect_flags(entries, out);
+    }
+}
+
+fn map_urls_with<F: FnMut(&mut SrcUrl)>(entries: &mut [SrcUriEntry], f: &mut F) {
+    for entry in entries {
+        match entry {
+            SrcUriEntry::Uri { url, .. } | SrcUriEntry::Renamed { url, .. } => f(url),
+            SrcUriEntry::UseConditional { entries, .. } | SrcUriEntry::Group(entries) => {
+                map_urls_with(entries, f)
+            }
+        }
+    }
+}
+
+fn validate_eapi(entries: &[SrcUriEntry], rules: &EapiRules<'_>) -> Result<()> {
+    for entry in entries {
+        let restriction = match entry {
+            SrcUriEntry::Uri { restriction, .. } => restriction,
+            SrcUriEntry::Renamed {
+                target,
+                restriction,
+                ..
+            } => {
+                if !rules.supports(EapiFeature::SrcUriArrows) {
+                    return Err(rules.unsupported(format!("SRC_URI rename '-> {target}'")));
+                }
+                if target.is_empty() || target.contains('/') {
+                    return Err(Error::InvalidSrcUri(format!(
+                        "invalid rename target '{target}'"
+                    )));
+                }
+                restriction
+            }
+            SrcUriEntry::UseConditional { entries, .. } | SrcUriEntry::Group(entries) => {
+                validate_eapi(entries, rules)?;
+                continue;
+            }
+        };
+        if let Some(prefix) = restriction {
+            if !rules.supports(EapiFeature::SelectiveUriRestrictions) {
+                return Err(rules.unsupported(format!("SRC_URI '{prefix}+' prefix")));
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Extract filename from a URL (last path component).
@@ -82,55 +330,91 @@ fn filename_from_url(url: &str) -> String {
 }
 
 impl fmt::Display for SrcUriEntry {
+    /// `{:#}` prints an indented multi-line tree.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_spec(f, self, false)
+    }
+}
+
+impl SpecTree for SrcUriEntry {
+    fn shape(&self) -> Shape<'_, Self> {
         match self {
+            SrcUriEntry::Uri { .. } | SrcUriEntry::Renamed { .. } => Shape::Leaf,
+            SrcUriEntry::UseConditional {
+                flag,
+                negated,
+                entries,
+            } => Shape::UseConditional {
+                flag,
+                negated: *negated,
+                entries,
+            },
+            SrcUriEntry::Group(entries) => Shape::Group(GroupKind::AllOf, entries),
+        }
+    }
+
+    fn fmt_leaf(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (url, target, restriction) = match self {
             SrcUriEntry::Uri {
                 url, restriction, ..
-            } => {
-                if let Some(prefix) = restriction {
-                    write!(f, "{prefix}+")?;
-                }
-                write!(f, "{url}")
-            }
+            } => (url, None, restriction),
             SrcUriEntry::Renamed {
                 url,
                 target,
                 restriction,
-            } => {
-                if let Some(prefix) = restriction {
-                    write!(f, "{prefix}+")?;
-                }
-                write!(f, "{url} -> {target}")
-            }
-            SrcUriEntry::UseConditional {
-                flag,
-                negated,
-                entries,
-            } => {
-                if *negated {
-                    write!(f, "!")?;
-                }
-                write!(f, "{flag}? ( ")?;
-                for (i, entry) in entries.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
+            } => (url, Some(target), restriction),
+            SrcUriEntry::UseConditional { .. } | SrcUriEntry::Group(_) => return Ok(()),
+        };
+        if let Some(prefix) = restriction {
+            write!(f, "{prefix}+")?;
+        }
+        write!(f, "{url}")?;
+        if let Some(target) = target {
+            write!(f, " -> {target}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ParseSpec for SrcUriEntry {
+    /// Parse a single URI, optionally followed by `-> filename`.
+    fn parse_leaf(input: &mut &str) -> ModalResult<Self> {
+        (
+            parse_restriction_prefix,
+            parse_uri,
+            opt(preceded((multispace0, "->", multispace0), parse_filename)),
+        )
+            .map(|(restriction, url, rename)| {
+                if let Some(target) = rename {
+                    SrcUriEntry::Renamed {
+                        url: SrcUrl::parse(&url),
+                        target,
+                        restriction,
                     }
-                    write!(f, "{entry}")?;
-                }
-                write!(f, " )")
-            }
-            SrcUriEntry::Group(entries) => {
-                write!(f, "( ")?;
-                for (i, entry) in entries.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, " ")?;
+                } else {
+                    let filename = filename_from_url(&url);
+                    SrcUriEntry::Uri {
+                        url: SrcUrl::parse(&url),
+                        filename,
+                        restriction,
                     }
-                    write!(f, "{entry}")?;
                 }
-                write!(f, " )")
-            }
+            })
+            .parse_next(input)
+    }
+
+    fn conditional(flag: String, negated: bool, entries: Vec<Self>) -> Self {
+        SrcUriEntry::UseConditional {
+            flag,
+            negated,
+            entries,
         }
     }
+
+    /// Bare groups are kept, so `Display` reproduces them.
+    fn all_of(entries: Vec<Self>) -> Vec<Self> {
+        vec![SrcUriEntry::Group(entries)]
+    }
 }
 
 // Winnow parsers
@@ -169,10 +453,6 @@ fn is_filename_char(c: char) -> bool {
     c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+' | '{' | '}' | '@')
 }
 
-fn is_flag_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+'
-}
-
 fn parse_uri(input: &mut &str) -> ModalResult<String> {
     take_while(1.., is_uri_char)
         .map(|s: &str| s.to_string())
@@ -188,90 +468,16 @@ fn parse_restriction_prefix(input: &mut &str) -> ModalResult<Option<String>> {
 }
 
 fn parse_filename(input: &mut &str) -> ModalResult<String> {
-    take_while(1.., is_filename_char)
-        .map(|s: &str| s.to_string())
-        .parse_next(input)
-}
-
-/// Parse a single URI, optionally followed by `-> filename`.
-fn parse_uri_entry(input: &mut &str) -> ModalResult<SrcUriEntry> {
-    (
-        parse_restriction_prefix,
-        parse_uri,
-        opt(preceded((multispace0, "->", multispace0), parse_filename)),
-    )
-        .map(|(restriction, url, rename)| {
-            if let Some(target) = rename {
-                SrcUriEntry::Renamed {
-                    url,
-                    target,
-                    restriction,
-                }
-            } else {
-                let filename = filename_from_url(&url);
-                SrcUriEntry::Uri {
-                    url,
-                    filename,
-                    restriction,
-                }
-            }
-        })
-        .parse_next(input)
-}
-
-/// Parse `[!]flag? ( entries... )`.
-fn parse_use_conditional(input: &mut &str) -> ModalResult<SrcUriEntry> {
-    let negated = opt('!').parse_next(input)?.is_some();
-    let flag: String = take_while(1.., is_flag_char)
-        .map(|s: &str| s.to_string())
-        .parse_next(input)?;
-    '?'.parse_next(input)?;
-    multispace0.parse_next(input)?;
-    let entries = cut_err(delimited('(', parse_src_uri_entries, (multispace0, ')')))
-        .context(StrContext::Label("USE conditional group"))
-        .parse_next(input)?;
-    Ok(SrcUriEntry::UseConditional {
-        flag,
-        negated,
-        entries,
-    })
-}
-
-/// Parse `( entries... )` — bare parenthesized group.
-fn parse_group(input: &mut &str) -> ModalResult<SrcUriEntry> {
-    delimited(
-        '(',
-        parse_src_uri_entries,
-        cut_err((multispace0, ')')).context(StrContext::Label("closing ')'")),
+    // The target must end at a separator; otherwise `-> a/b` would silently
+    // parse as target `a` followed by a URI `/b`.
+    terminated(
+        take_while(1.., is_filename_char),
+        cut_err(peek(alt((eof, multispace1, ")")))).context(StrContext::Label("rename target")),
     )
-    .map(SrcUriEntry::Group)
-    .parse_next(input)
-}
-
-/// Parse a single SRC_URI entry.
-fn parse_src_uri_entry(input: &mut &str) -> ModalResult<SrcUriEntry> {
-    dispatch! {peek(any);
-        '(' => parse_group,
-        _ => alt((
-            parse_use_conditional,
-            parse_uri_entry,
-        )),
-    }
+    .map(|s: &str| s.to_string())
     .parse_next(input)
 }
 
-/// Parse zero or more SRC_URI entries separated by whitespace.
-fn parse_src_uri_entries(input: &mut &str) -> ModalResult<Vec<SrcUriEntry>> {
-    repeat(0.., preceded(multispace0, parse_src_uri_entry)).parse_next(input)
-}
-
-/// Parse a complete SRC_URI string.
-pub(crate) fn parse_src_uri_string(input: &mut &str) -> ModalResult<Vec<SrcUriEntry>> {
-    let entries = parse_src_uri_entries(input)?;
-    multispace0.parse_next(input)?;
-    Ok(entries)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,7 +628,7 @@ mod tests {
     #[test]
     fn display_uri() {
         let entry = SrcUriEntry::Uri {
-            url: "https://example.com/foo.tar.gz".to_string(),
+            url: SrcUrl::parse("https://example.com/foo.tar.gz"),
             filename: "foo.tar.gz".to_string(),
             restriction: None,
         };
@@ -432,7 +638,7 @@ mod tests {
     #[test]
     fn display_renamed() {
         let entry = SrcUriEntry::Renamed {
-            url: "https://example.com/v1.tar.gz".to_string(),
+            url: SrcUrl::parse("https://example.com/v1.tar.gz"),
             target: "foo-1.tar.gz".to_string(),
             restriction: None,
         };
@@ -737,4 +943,133 @@ mod tests {
             _ => unreachable!("expected Renamed"),
         }
     }
+
+    #[test]
+    fn evaluate_conditionals() {
+        let entries = SrcUriEntry::parse(
+            "https://e.com/a.tar.gz ssl? ( https://e.com/ssl.patch ) !doc? ( ( https://e.com/b.tar.gz ) )",
+        )
+        .unwrap();
+        let urls = |flags: &str| -> Vec<String> {
+            SrcUriEntry::evaluate(&entries, &UseFlagSet::parse(flags))
+                .iter()
+                .map(|e| e.to_string())
+                .collect()
+        };
+        assert_eq!(
+            urls("ssl"),
+            vec![
+                "https://e.com/a.tar.gz",
+                "https://e.com/ssl.patch",
+                "https://e.com/b.tar.gz"
+            ]
+        );
+        assert_eq!(urls("doc"), vec!["https://e.com/a.tar.gz"]);
+    }
+
+    #[test]
+    fn distfiles_ignores_conditionals() {
+        let entries = SrcUriEntry::parse(
+            "mirror://gnu/foo-1.tar.gz !doc? ( https://e.com/foo-1.tar.gz ) doc? ( ( https://e.com/d.zip -> foo-doc.zip ) )",
+        )
+        .unwrap();
+        assert_eq!(
+            SrcUriEntry::distfiles(&entries)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["foo-1.tar.gz", "foo-doc.zip"]
+        );
+    }
+
+    #[test]
+    fn parse_with_eapi_gating() {
+        let prefixed = "ssl? ( fetch+https://e.com/a.tar.gz )";
+        assert!(SrcUriEntry::parse_with_eapi(prefixed, &Eapi::Eight).is_ok());
+        assert!(matches!(
+            SrcUriEntry::parse_with_eapi(prefixed, &Eapi::Seven),
+            Err(Error::UnsupportedInEapi { .. })
+        ));
+        let renamed = "( https://e.com/v1 -> v1.tar.gz )";
+        assert!(SrcUriEntry::parse_with_eapi(renamed, &Eapi::Zero).is_err());
+        assert!(SrcUriEntry::parse_with_eapi("https://e.com/a", &Eapi::Zero).is_ok());
+    }
+
+    #[test]
+    fn invalid_rename_target() {
+        assert!(SrcUriEntry::parse_with_eapi("https://e.com/v1 -> a/b", &Eapi::Eight).is_err());
+        let entry = SrcUriEntry::Renamed {
+            url: SrcUrl::parse("https://e.com/v1"),
+            target: String::new(),
+            restriction: None,
+        };
+        assert!(validate_eapi(&[entry], &EapiRules::new(&Eapi::Eight, None)).is_err());
+    }
+
+    #[test]
+    fn map_urls_preserves_structure() {
+        let mut entries = SrcUriEntry::parse(
+            "mirror+https://e.com/v1 -> foo-1.tgz !doc? ( mirror://gnu/foo/foo-1.tar.gz )",
+        )
+        .unwrap();
+        SrcUriEntry::map_urls(&mut entries, |url| {
+            if let SrcUrl::Mirror { path, .. } = url {
+                *url = SrcUrl::parse(&format!("https://mirror.local/gnu/{path}"));
+            }
+        });
+        let out: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            out.join(" "),
+            "mirror+https://e.com/v1 -> foo-1.tgz !doc? ( https://mirror.local/gnu/foo/foo-1.tar.gz )"
+        );
+        assert_eq!(
+            SrcUriEntry::distfiles(&entries)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["foo-1.tar.gz", "foo-1.tgz"]
+        );
+    }
+
+    #[test]
+    fn query_string_not_conditional() {
+        let entries = SrcUriEntry::parse("download?id=1 -> foo.tgz").unwrap();
+        assert!(matches!(&entries[0], SrcUriEntry::Renamed { target, .. } if target == "foo.tgz"));
+        let entries = SrcUriEntry::parse("ssl?( https://e.com/a )").unwrap();
+        assert!(matches!(&entries[0], SrcUriEntry::UseConditional { .. }));
+    }
+
+    #[test]
+    fn parse_strict_token_rules() {
+        assert!(SrcUriEntry::parse_strict(
+            "https://e.com/get?f=a.tgz -> a.tgz !doc? ( mirror://gnu/b.tgz )"
+        )
+        .is_ok());
+        assert!(SrcUriEntry::parse_strict("ssl? https://e.com/a").is_err());
+        assert!(SrcUriEntry::parse_strict("ssl?").is_err());
+        assert!(SrcUriEntry::parse_strict("a.tgz?").is_err());
+        assert!(SrcUriEntry::parse_strict("+ssl? ( https://e.com/a )").is_err());
+    }
+
+    #[test]
+    fn display_alternate() {
+        let entries =
+            SrcUriEntry::parse("ssl? ( https://e.com/a -> a.tgz ( mirror://gnu/b.tgz ) )").unwrap();
+        assert_eq!(
+            format!("{:#}", entries[0]),
+            "ssl? (\n  https://e.com/a -> a.tgz\n  (\n    mirror://gnu/b.tgz\n  )\n)"
+        );
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(
+            vec![SrcUriEntry::group([
+                SrcUriEntry::uri("mirror://gnu/foo/foo-1.tar.xz?x=1"),
+                SrcUriEntry::conditional("x", true, [SrcUriEntry::renamed("https://e.com/a", "b")]),
+            ])],
+            SrcUriEntry::parse(
+                "( mirror://gnu/foo/foo-1.tar.xz?x=1 !x? ( https://e.com/a -> b ) )"
+            )
+            .unwrap()
+        );
+    }
 }
@@ -0,0 +1,175 @@
+use std::fmt;
+
+/// A structured `SRC_URI` URL.
+///
+/// URLs are split once at parse time so callers can filter by scheme,
+/// gather host statistics or expand `mirror://` URLs without re-parsing.
+/// [`Display`](fmt::Display) reproduces the original text.
+///
+/// See [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SrcUrl {
+    /// A `scheme://host/path?query` URL.
+    Url {
+        /// Scheme, e.g. `https` (without `://`).
+        scheme: String,
+        /// Authority part (host, and port or userinfo if present).
+        host: String,
+        /// Path, including its leading `/`; may be empty.
+        path: String,
+        /// Query string without the leading `?`.
+        query: Option<String>,
+    },
+    /// A `mirror://group/path` URL resolved through `thirdpartymirrors`.
+    Mirror {
+        /// Mirror group name, e.g. `gnu`.
+        group: String,
+        /// Path relative to the mirror root, without a leading `/`.
+        path: String,
+    },
+    /// A bare filename without a scheme, fetched from mirrors only.
+    Plain(String),
+}
+
+impl SrcUrl {
+    /// Split a URL into its components.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::SrcUrl;
+    ///
+    /// let url = SrcUrl::parse("https://example.com/foo-1.0.tar.gz?dl=1");
+    /// assert_eq!(url.scheme(), Some("https"));
+    /// assert_eq!(url.host(), Some("example.com"));
+    ///
+    /// let url = SrcUrl::parse("mirror://gnu/glibc/glibc-2.38.tar.xz");
+    /// assert!(matches!(url, SrcUrl::Mirror { ref group, .. } if group == "gnu"));
+    /// ```
+    pub fn parse(input: &str) -> Self {
+        if let Some(rest) = input.strip_prefix("mirror://") {
+            let (group, path) = rest.split_once('/').unwrap_or((rest, ""));
+            return SrcUrl::Mirror {
+                group: group.to_string(),
+                path: path.to_string(),
+            };
+        }
+        let Some((scheme, rest)) = input.split_once("://") else {
+            return SrcUrl::Plain(input.to_string());
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query.to_string())),
+            None => (rest, None),
+        };
+        let (host, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        SrcUrl::Url {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+            path: path.to_string(),
+            query,
+        }
+    }
+
+    /// The URL scheme; `mirror` for mirror URLs and `None` for bare filenames.
+    pub fn scheme(&self) -> Option<&str> {
+        match self {
+            SrcUrl::Url { scheme, .. } => Some(scheme),
+            SrcUrl::Mirror { .. } => Some("mirror"),
+            SrcUrl::Plain(_) => None,
+        }
+    }
+
+    /// The host of a `scheme://` URL.
+    pub fn host(&self) -> Option<&str> {
+        match self {
+            SrcUrl::Url { host, .. } => Some(host),
+            SrcUrl::Mirror { .. } | SrcUrl::Plain(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for SrcUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SrcUrl::Url {
+                scheme,
+                host,
+                path,
+                query,
+            } => {
+                write!(f, "{scheme}://{host}{path}")?;
+                if let Some(query) = query {
+                    write!(f, "?{query}")?;
+                }
+                Ok(())
+            }
+            SrcUrl::Mirror { group, path } => {
+                write!(f, "mirror://{group}")?;
+                if !path.is_empty() {
+                    write!(f, "/{path}")?;
+                }
+                Ok(())
+            }
+            SrcUrl::Plain(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl PartialEq<str> for SrcUrl {
+    fn eq(&self, other: &str) -> bool {
+        *self == SrcUrl::parse(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_url_components() {
+        let url = SrcUrl::parse("https://user@host:8080/a/b.tgz?x=1&y=/z");
+        assert_eq!(
+            url,
+            SrcUrl::Url {
+                scheme: "https".to_string(),
+                host: "user@host:8080".to_string(),
+                path: "/a/b.tgz".to_string(),
+                query: Some("x=1&y=/z".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_mirror_and_plain() {
+        assert_eq!(
+            SrcUrl::parse("mirror://debian/pool/f.deb"),
+            SrcUrl::Mirror {
+                group: "debian".to_string(),
+                path: "pool/f.deb".to_string(),
+            }
+        );
+        assert_eq!(
+            SrcUrl::parse("foo.tar.gz"),
+            SrcUrl::Plain("foo.tar.gz".to_string())
+        );
+        assert_eq!(SrcUrl::parse("foo.tar.gz").scheme(), None);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for input in [
+            "https://example.com/foo.tar.gz",
+            "https://example.com",
+            "https://example.com?q",
+            "ftp://ftp.example.org/pub/x.tgz",
+            "mirror://gnu/glibc/glibc-2.38.tar.xz",
+            "mirror://gnu",
+            "bare-1.0.tar.gz",
+        ] {
+            assert_eq!(SrcUrl::parse(input).to_string(), input);
+        }
+    }
+}
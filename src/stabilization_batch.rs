@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+#[cfg(feature = "fs")]
+use crate::error::Error;
+use crate::error::Result;
+use crate::interner::Interner;
+use crate::keyword::Stability;
+use crate::metadata::EbuildMetadata;
+
+/// A set of keyword requests applied across a repository's cache entries.
+///
+/// Each request names a package version and the arches to mark stable, or
+/// to keyword for testing with [`StabilizationBatch::keywording`]. The edits
+/// follow `ekeyword` semantics (see [`EbuildMetadata::stabilize`]); the
+/// returned [`StabilizationReport`] lists what changed.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, StabilizationBatch};
+///
+/// let cpv = Cpv::parse("app-misc/foo-1.0").unwrap();
+/// let mut entries = HashMap::new();
+/// entries.insert(
+///     cpv.clone(),
+///     CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64 ~x86\n").unwrap(),
+/// );
+///
+/// let mut batch = StabilizationBatch::new();
+/// batch.add(cpv.clone(), ["amd64"]);
+/// let report = batch.apply(&mut entries).unwrap();
+/// assert_eq!(report.changes.len(), 1);
+/// assert!(entries[&cpv].serialize().contains("KEYWORDS=amd64 ~x86\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StabilizationBatch {
+    target: Stability,
+    requests: Vec<(Cpv, Vec<String>)>,
+}
+
+/// One keyword edit made by a [`StabilizationBatch`].
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordChange {
+    /// The package version edited.
+    pub cpv: Cpv,
+    /// The arch edited.
+    pub arch: String,
+    /// The arch's previous stability, `None` if it was not listed.
+    pub old: Option<Stability>,
+    /// The arch's new stability.
+    pub new: Stability,
+}
+
+impl fmt::Display for KeywordChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prefix = |stability| match stability {
+            Stability::Testing => "~",
+            Stability::Disabled => "-",
+            _ => "",
+        };
+        write!(f, "{}: ", self.cpv)?;
+        match self.old {
+            Some(old) => write!(f, "{}{} -> ", prefix(old), self.arch)?,
+            None => write!(f, "(none) -> ")?,
+        }
+        write!(f, "{}{}", prefix(self.new), self.arch)
+    }
+}
+
+/// The outcome of applying a [`StabilizationBatch`].
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StabilizationReport {
+    /// Every keyword that changed, in request order.
+    pub changes: Vec<KeywordChange>,
+    /// Requested package versions with no cache entry.
+    pub missing: Vec<Cpv>,
+}
+
+impl StabilizationBatch {
+    /// Create an empty batch that marks arches stable.
+    pub fn new() -> Self {
+        StabilizationBatch {
+            target: Stability::Stable,
+            requests: Vec::new(),
+        }
+    }
+
+    /// Create an empty batch that keywords arches for testing (`~arch`).
+    pub fn keywording() -> Self {
+        StabilizationBatch {
+            target: Stability::Testing,
+            requests: Vec::new(),
+        }
+    }
+
+    /// Request `arches` for `cpv`.
+    pub fn add<S: Into<String>>(&mut self, cpv: Cpv, arches: impl IntoIterator<Item = S>) {
+        self.requests
+            .push((cpv, arches.into_iter().map(Into::into).collect()));
+    }
+
+    /// Whether the batch has no requests.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Apply the batch to in-memory cache entries.
+    ///
+    /// Fails on an invalid arch name, leaving earlier edits in place.
+    pub fn apply<I: Interner>(
+        &self,
+        entries: &mut HashMap<Cpv, CacheEntry<I>>,
+    ) -> Result<StabilizationReport> {
+        let mut report = StabilizationReport::default();
+        for (cpv, arches) in &self.requests {
+            match entries.get_mut(cpv) {
+                Some(entry) => self.apply_one(cpv, arches, &mut entry.metadata, &mut report)?,
+                None => report.missing.push(cpv.clone()),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Apply the batch to an md5-cache directory such as
+    /// `metadata/md5-cache`, reading `<category>/<package>-<version>` for
+    /// each request.
+    ///
+    /// Changed entries are written back only if `write` is set, so a dry
+    /// run reports the same changes. Errors in an entry carry its package
+    /// version as [`Error::Package`] context; file access failures are
+    /// reported as [`Error::Io`].
+    #[cfg(feature = "fs")]
+    pub fn apply_to_dir(
+        &self,
+        cache_dir: impl AsRef<Path>,
+        write: bool,
+    ) -> Result<StabilizationReport> {
+        let mut report = StabilizationReport::default();
+        for (cpv, arches) in &self.requests {
+            let path = cache_dir.as_ref().join(cpv.to_string());
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    report.missing.push(cpv.clone());
+                    continue;
+                }
+                Err(e) => return Err(Error::io(path, e)),
+            };
+            let in_package = |e: Error| e.in_package(cpv);
+            let mut entry: CacheEntry = CacheEntry::parse(&contents).map_err(in_package)?;
+            let before = report.changes.len();
+            self.apply_one(cpv, arches, &mut entry.metadata, &mut report)
+                .map_err(in_package)?;
+            if write && report.changes.len() > before {
+                fs::write(&path, entry.serialize()).map_err(|e| Error::io(&path, e))?;
+            }
+        }
+        Ok(report)
+    }
+
+    fn apply_one<I: Interner>(
+        &self,
+        cpv: &Cpv,
+        arches: &[String],
+        metadata: &mut EbuildMetadata<I>,
+        report: &mut StabilizationReport,
+    ) -> Result<()> {
+        for arch in arches {
+            let old = metadata
+                .keywords
+                .iter()
+                .find(|k| k.arch.as_str() == arch.as_str())
+                .map(|k| k.stability);
+            let changed = match self.target {
+                Stability::Testing => metadata.add_testing_keyword(arch)?,
+                _ => metadata.stabilize(arch)?,
+            };
+            if changed {
+                report.changes.push(KeywordChange {
+                    cpv: cpv.clone(),
+                    arch: arch.clone(),
+                    old,
+                    new: self.target,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for StabilizationBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpv(s: &str) -> Cpv {
+        Cpv::parse(s).unwrap()
+    }
+
+    #[test]
+    fn apply_in_memory() {
+        let mut entries: HashMap<Cpv, CacheEntry> = HashMap::new();
+        entries.insert(
+            cpv("app-misc/foo-1.0"),
+            CacheEntry::parse("DESCRIPTION=x\nSLOT=0\nKEYWORDS=~amd64 x86\n").unwrap(),
+        );
+        let mut batch = StabilizationBatch::new();
+        batch.add(cpv("app-misc/foo-1.0"), ["amd64", "x86", "arm64"]);
+        batch.add(cpv("app-misc/bar-2"), ["amd64"]);
+        let report = batch.apply(&mut entries).unwrap();
+        assert_eq!(report.missing, vec![cpv("app-misc/bar-2")]);
+        let changes: Vec<String> = report.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "app-misc/foo-1.0: ~amd64 -> amd64",
+                "app-misc/foo-1.0: (none) -> arm64",
+            ]
+        );
+
+        let mut bad = StabilizationBatch::keywording();
+        bad.add(cpv("app-misc/foo-1.0"), ["~amd64"]);
+        assert!(bad.apply(&mut entries).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn apply_to_cache_dir() {
+        use crate::temp_dir::TempDir;
+        let root = TempDir::new("stabilization");
+        fs::create_dir_all(root.join("app-misc")).unwrap();
+        let path = root.join("app-misc/foo-1.0");
+        fs::write(&path, "DESCRIPTION=x\nKEYWORDS=amd64\nSLOT=0\n").unwrap();
+
+        let mut batch = StabilizationBatch::keywording();
+        batch.add(cpv("app-misc/foo-1.0"), ["x86"]);
+        let dry = batch.apply_to_dir(&root, false).unwrap();
+        assert_eq!(dry.changes.len(), 1);
+        assert!(!fs::read_to_string(&path).unwrap().contains("~x86"));
+
+        let report = batch.apply_to_dir(&root, true).unwrap();
+        assert_eq!(report, dry);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("KEYWORDS=amd64 ~x86\n"));
+        assert!(batch.apply_to_dir(&root, true).unwrap().changes.is_empty());
+
+        fs::write(&path, "DESCRIPTION=x\nSLOT=0\nKEYWORDS=~~x86\n").unwrap();
+        let err = batch.apply_to_dir(&root, false).unwrap_err();
+        assert_eq!(err.cpv(), Some("app-misc/foo-1.0"));
+        assert!(err
+            .to_string()
+            .starts_with("app-misc/foo-1.0: KEYWORDS at line 3, column 10: "));
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::keyword_set::KeywordSet;
+
+/// A version arch testers could mark stable on an arch: testing there,
+/// already stable elsewhere, and newer than the arch's current stable
+/// version of the same slot.
+///
+/// Versions without a stable predecessor on the arch are not reported:
+/// stabilizing a package on a new arch is a keywording decision rather
+/// than routine arch-team work.
+///
+/// See [PMS 7.3.3](https://projects.gentoo.org/pms/9/pms.html#keywords).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, StableCandidate};
+///
+/// let entry = |keywords| {
+///     CacheEntry::parse(&format!("DESCRIPTION=x\nSLOT=0\nKEYWORDS={keywords}\n")).unwrap()
+/// };
+/// let repo = BTreeMap::from([
+///     (Cpv::parse("app-misc/foo-1").unwrap(), entry("amd64 arm64")),
+///     (Cpv::parse("app-misc/foo-2").unwrap(), entry("~amd64 arm64")),
+///     (Cpv::parse("app-misc/foo-3").unwrap(), entry("~amd64 ~arm64")),
+/// ]);
+///
+/// let candidates = StableCandidate::scan(&repo, "amd64");
+/// assert_eq!(
+///     candidates[0].to_string(),
+///     "app-misc/foo-2: ~amd64 (stable on arm64; amd64 has app-misc/foo-1)"
+/// );
+/// assert_eq!(candidates.len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StableCandidate {
+    /// The version to stabilize.
+    pub cpv: Cpv,
+    /// The target arch.
+    pub arch: String,
+    /// Other arches the version is stable on, sorted.
+    pub stable_on: Vec<String>,
+    /// The highest version of the slot currently stable on the arch.
+    pub predecessor: Cpv,
+}
+
+impl StableCandidate {
+    /// Every stabilization candidate for `arch` in `repo`, sorted by
+    /// package version.
+    ///
+    /// Versions are compared within a package and slot; a version older
+    /// than the highest stable one on `arch` is never a candidate.
+    pub fn scan(repo: &BTreeMap<Cpv, CacheEntry>, arch: &str) -> Vec<Self> {
+        let mut out = Vec::new();
+        for (cpv, entry) in repo {
+            let keywords = KeywordSet::from(entry.metadata.keywords.as_slice());
+            if !keywords.is_testing_on(arch) {
+                continue;
+            }
+            let stable_on: Vec<String> = keywords
+                .stable_arches()
+                .filter(|&other| other != arch)
+                .map(str::to_string)
+                .collect();
+            if stable_on.is_empty() {
+                continue;
+            }
+            let slot = &entry.metadata.slot.slot;
+            let newest_stable = repo
+                .iter()
+                .filter(|(other, other_entry)| {
+                    other.cpn == cpv.cpn
+                        && other_entry.metadata.slot.slot == *slot
+                        && KeywordSet::from(other_entry.metadata.keywords.as_slice())
+                            .is_stable_on(arch)
+                })
+                .map(|(other, _)| other)
+                .max();
+            if let Some(predecessor) = newest_stable.filter(|&stable| stable < cpv) {
+                out.push(StableCandidate {
+                    cpv: cpv.clone(),
+                    arch: arch.to_string(),
+                    stable_on,
+                    predecessor: predecessor.clone(),
+                });
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for StableCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: ~{} (stable on {}; {} has {})",
+            self.cpv,
+            self.arch,
+            self.stable_on.join(" "),
+            self.arch,
+            self.predecessor
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(entries: &[(&str, &str)]) -> BTreeMap<Cpv, CacheEntry> {
+        entries
+            .iter()
+            .map(|(cpv, text)| {
+                (
+                    Cpv::parse(cpv).unwrap(),
+                    CacheEntry::parse(&format!("EAPI=8\nDESCRIPTION=x\n{text}\n")).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn slots_and_newer_stable() {
+        let repo = repo(&[
+            ("dev-libs/foo-1", "SLOT=1\nKEYWORDS=amd64 x86"),
+            ("dev-libs/foo-1.1", "SLOT=1\nKEYWORDS=~amd64 x86 arm64"),
+            ("dev-libs/foo-2", "SLOT=2\nKEYWORDS=~amd64 x86"),
+            ("dev-libs/bar-1", "SLOT=0\nKEYWORDS=~amd64 x86"),
+            ("dev-libs/bar-2", "SLOT=0\nKEYWORDS=amd64 x86"),
+            ("dev-libs/baz-1", "SLOT=0\nKEYWORDS=amd64"),
+            ("dev-libs/baz-2", "SLOT=0\nKEYWORDS=~amd64 ~x86"),
+        ]);
+        let candidates: Vec<String> = StableCandidate::scan(&repo, "amd64")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            candidates,
+            ["dev-libs/foo-1.1: ~amd64 (stable on arm64 x86; amd64 has dev-libs/foo-1)"]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use portage_atom::{Cpv, Dep, SlotDep, SlotOperator};
+
+use crate::cache::CacheEntry;
+use crate::dep_spec::DepSpec;
+
+/// An installed package bound by a `:=` slot operator to a sub-slot its
+/// dependency no longer provides, and so in need of a rebuild.
+///
+/// Package managers record the slot and sub-slot a `:=` dependency
+/// resolved to at build time, e.g. `dev-libs/openssl:0/3=`. When the
+/// provider of that slot now has a different sub-slot, the dependent must
+/// be rebuilt; this is the core of `@preserved-rebuild` and
+/// `--changed-slot`.
+///
+/// See [PMS 8.3.3](https://projects.gentoo.org/pms/9/pms.html#slot-dependencies).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, SubslotRebuild};
+///
+/// let entry = |text: &str| CacheEntry::parse(text).unwrap();
+/// let installed = BTreeMap::from([(
+///     Cpv::parse("net-misc/curl-8.0").unwrap(),
+///     entry("DESCRIPTION=x\nSLOT=0\nRDEPEND=dev-libs/openssl:0/1.1=\n"),
+/// )]);
+/// let repo = BTreeMap::from([
+///     (Cpv::parse("dev-libs/openssl-1.1.1").unwrap(), entry("DESCRIPTION=x\nSLOT=0/1.1\n")),
+///     (Cpv::parse("dev-libs/openssl-3.0.0").unwrap(), entry("DESCRIPTION=x\nSLOT=0/3\n")),
+/// ]);
+///
+/// let rebuilds = SubslotRebuild::scan(&installed, &repo);
+/// assert_eq!(
+///     rebuilds[0].to_string(),
+///     "net-misc/curl-8.0: dev-libs/openssl:0/1.1= now provided by dev-libs/openssl-3.0.0 (0/3)"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubslotRebuild {
+    /// The package needing a rebuild.
+    pub cpv: Cpv,
+    /// The recorded `:=` dependency.
+    pub dep: Dep,
+    /// The highest version now providing the bound slot.
+    pub provider: Cpv,
+    /// The provider's sub-slot.
+    pub subslot: String,
+}
+
+impl SubslotRebuild {
+    /// Every package in `installed` with a `:=` binding whose slot is now
+    /// provided by a version of `providers` with another sub-slot,
+    /// sorted by package version.
+    ///
+    /// `installed` holds the metadata recorded at build time, with slot
+    /// operators resolved to `slot/subslot=`; bindings without a recorded
+    /// sub-slot are skipped. `providers` is where the current sub-slots
+    /// come from: the installed packages for `@preserved-rebuild`, or the
+    /// visible repository entries to plan ahead. Within a slot the highest
+    /// version is taken as the provider, regardless of the dependency's
+    /// version constraints.
+    pub fn scan(
+        installed: &BTreeMap<Cpv, CacheEntry>,
+        providers: &BTreeMap<Cpv, CacheEntry>,
+    ) -> Vec<Self> {
+        let mut out = Vec::new();
+        for (cpv, entry) in installed {
+            let mut bindings = Vec::new();
+            for (_, entries) in entry.metadata.dependencies() {
+                let spec = DepSpec::from_deps(entries);
+                bindings.extend(spec.leaves().copied().filter(|dep| is_binding(dep)));
+            }
+            for dep in bindings {
+                if let Some(rebuild) = Self::check(cpv, dep, providers) {
+                    if !out.contains(&rebuild) {
+                        out.push(rebuild);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    fn check(cpv: &Cpv, dep: &Dep, providers: &BTreeMap<Cpv, CacheEntry>) -> Option<Self> {
+        let Some(SlotDep::Slot {
+            slot: Some(bound), ..
+        }) = &dep.slot_dep
+        else {
+            return None;
+        };
+        let bound_subslot = bound.subslot.as_ref()?;
+        let (provider, entry) = providers.iter().rev().find(|(provider, entry)| {
+            provider.cpn == dep.cpn && entry.metadata.slot.slot == bound.slot
+        })?;
+        let subslot = entry
+            .metadata
+            .slot
+            .subslot
+            .as_ref()
+            .unwrap_or(&entry.metadata.slot.slot);
+        (subslot != bound_subslot).then(|| SubslotRebuild {
+            cpv: cpv.clone(),
+            dep: dep.clone(),
+            provider: provider.clone(),
+            subslot: subslot.as_str().to_string(),
+        })
+    }
+}
+
+/// Whether `dep` is a non-blocker `:=` atom.
+fn is_binding(dep: &Dep) -> bool {
+    let bound = matches!(
+        dep.slot_dep,
+        Some(SlotDep::Slot {
+            op: Some(SlotOperator::Equal),
+            ..
+        })
+    );
+    bound && dep.blocker.is_none()
+}
+
+impl fmt::Display for SubslotRebuild {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let slot = match &self.dep.slot_dep {
+            Some(SlotDep::Slot {
+                slot: Some(slot), ..
+            }) => slot.slot.as_str(),
+            _ => "",
+        };
+        write!(
+            f,
+            "{}: {} now provided by {} ({slot}/{})",
+            self.cpv, self.dep, self.provider, self.subslot
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(entries: &[(&str, &str)]) -> BTreeMap<Cpv, CacheEntry> {
+        entries
+            .iter()
+            .map(|(cpv, text)| {
+                (
+                    Cpv::parse(cpv).unwrap(),
+                    CacheEntry::parse(&format!("EAPI=8\nDESCRIPTION=x\n{text}\n")).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bindings() {
+        let installed = repo(&[
+            (
+                "app-misc/a-1",
+                "SLOT=0\nDEPEND=dev-libs/foo:1/1=\nRDEPEND=ssl? ( dev-libs/foo:1/1= ) \
+                 dev-libs/foo:2/2= dev-libs/foo:= dev-libs/bar:0/0= !dev-libs/foo:1/0=",
+            ),
+            ("app-misc/b-1", "SLOT=0\nRDEPEND=dev-libs/foo:2/2.1="),
+        ]);
+        let providers = repo(&[
+            ("dev-libs/foo-1.5", "SLOT=1/1.5"),
+            ("dev-libs/foo-1.9", "SLOT=1/1.9"),
+            ("dev-libs/foo-2.1", "SLOT=2/2.1"),
+            ("dev-libs/bar-1", "SLOT=0"),
+        ]);
+        let rebuilds: Vec<String> = SubslotRebuild::scan(&installed, &providers)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rebuilds,
+            [
+                "app-misc/a-1: dev-libs/foo:1/1= now provided by dev-libs/foo-1.9 (1/1.9)",
+                "app-misc/a-1: dev-libs/foo:2/2= now provided by dev-libs/foo-2.1 (2/2.1)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::{Duration, SystemTime};
+
+use crate::error::{Error, Result};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Parse a repository timestamp as found in `metadata/timestamp.chk`.
+///
+/// Accepts the RFC 2822 form written by the Gentoo rsync mirrors
+/// (`Wed, 15 Oct 2025 00:45:01 +0000`), the `date(1)` default form
+/// (`Wed Oct 15 00:45:01 UTC 2025`), and a leading count of seconds
+/// since the epoch as in `metadata/timestamp.x`. The weekday is optional
+/// and not checked.
+pub(crate) fn parse_timestamp(input: &str) -> Result<SystemTime> {
+    let invalid = || Error::InvalidTimestamp(input.trim().to_string());
+    let mut tokens: Vec<&str> = input
+        .split_whitespace()
+        .map(|token| token.trim_end_matches(','))
+        .collect();
+    // A day of the month is followed by the month; seconds are not.
+    if let Some(seconds) = tokens.first().and_then(|t| t.parse::<u64>().ok()) {
+        if tokens.get(1).is_none_or(|t| month(t).is_none()) {
+            return Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+        }
+    }
+    if tokens
+        .first()
+        .is_some_and(|t| month(t).is_none() && t.parse::<u32>().is_err())
+    {
+        tokens.remove(0);
+    }
+    let (day, month, year, time, zone) = match tokens[..] {
+        [day, month, year, time, zone] if day.parse::<u32>().is_ok() => {
+            (day, month, year, time, zone)
+        }
+        [month, day, time, zone, year] => (day, month, year, time, zone),
+        [month, day, time, year] => (day, month, year, time, "UTC"),
+        _ => return Err(invalid()),
+    };
+    let day: u32 = day.parse().map_err(|_| invalid())?;
+    let month = self::month(month).ok_or_else(invalid)?;
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let mut clock = time.split(':').map(|n| n.parse::<i64>().ok());
+    let (Some(Some(hour)), Some(Some(minute)), second, None) =
+        (clock.next(), clock.next(), clock.next(), clock.next())
+    else {
+        return Err(invalid());
+    };
+    let second = second.unwrap_or(Some(0)).ok_or_else(invalid)?;
+    if !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+    let offset = utc_offset(zone).ok_or_else(invalid)?;
+    let seconds =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    let seconds = u64::try_from(seconds).map_err(|_| invalid())?;
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// The month number (1-12) of an English month name or abbreviation.
+fn month(name: &str) -> Option<u32> {
+    let prefix = name.get(..3)?.to_ascii_lowercase();
+    MONTHS
+        .iter()
+        .position(|m| *m == prefix)
+        .map(|i| i as u32 + 1)
+}
+
+/// The offset from UTC in seconds of `+hhmm`, `-hhmm`, `UTC`, `GMT` or `Z`.
+fn utc_offset(zone: &str) -> Option<i64> {
+    if ["UTC", "GMT", "Z"].contains(&zone) {
+        return Some(0);
+    }
+    let (sign, digits) = match zone.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(input: &str) -> u64 {
+        parse_timestamp(input)
+            .unwrap()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(seconds("Wed, 15 Oct 2025 00:45:01 +0000\n"), 1760489101);
+        assert_eq!(seconds("15 Oct 2025 02:45:01 +0200"), 1760489101);
+        assert_eq!(seconds("Wed Oct 15 00:45:01 UTC 2025"), 1760489101);
+        assert_eq!(seconds("Thu Feb  29 12:00 2024"), 1709208000);
+        assert_eq!(
+            seconds("1760489101 Wed Oct 15 00:45:01 UTC 2025"),
+            1760489101
+        );
+        assert_eq!(seconds("Thu, 01 Jan 1970 00:00:00 GMT"), 0);
+    }
+
+    #[test]
+    fn invalid() {
+        for input in [
+            "",
+            "yesterday",
+            "Wed, 15 Foo 2025 00:45:01 +0000",
+            "Wed, 15 Oct 2025 24:00:00 +0000",
+            "Wed, 15 Oct 2025 00:45:01 CEST",
+            "Wed, 15 Oct 2025 00:45:01:02 +0000",
+        ] {
+            assert_eq!(
+                parse_timestamp(input),
+                Err(Error::InvalidTimestamp(input.to_string())),
+                "{input:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Bulk tokenization of whitespace-separated cache fields.
+
+use smallvec::SmallVec;
+
+/// The whitespace-separated tokens of a line-based field such as
+/// `KEYWORDS`, `IUSE` or `DEFINED_PHASES`, indexed in one pass.
+///
+/// Token bodies are skipped eight bytes at a time, and the token count is
+/// known before any is parsed, so callers can size their collection once.
+/// Only ASCII whitespace, including the vertical tab that
+/// `split_whitespace` also splits on, separates tokens; md5-cache values
+/// never contain other whitespace.
+pub(crate) struct Tokens<'a> {
+    input: &'a str,
+    bounds: SmallVec<[(u32, u32); 16]>,
+}
+
+impl<'a> Tokens<'a> {
+    /// Index the tokens of `input`.
+    pub(crate) fn new(input: &'a str) -> Self {
+        let bytes = input.as_bytes();
+        let mut bounds = SmallVec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            while i < bytes.len() && is_space(bytes[i]) {
+                i += 1;
+            }
+            if i == bytes.len() {
+                break;
+            }
+            let start = i;
+            i = token_end(bytes, i);
+            bounds.push((start as u32, i as u32));
+        }
+        Tokens { input, bounds }
+    }
+
+    /// The number of tokens.
+    pub(crate) fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    /// The tokens, in order.
+    pub(crate) fn iter(&self) -> impl ExactSizeIterator<Item = &'a str> + '_ {
+        let input = self.input;
+        self.bounds
+            .iter()
+            .map(move |&(start, end)| &input[start as usize..end as usize])
+    }
+}
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+/// The index of the first whitespace byte at or after `i`, or the length.
+fn token_end(bytes: &[u8], mut i: usize) -> usize {
+    const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+    const HIGH: u64 = u64::from_ne_bytes([0x80; 8]);
+    // Whitespace bytes are all below 0x21: skip words with no such byte.
+    while let Some(word) = bytes.get(i..i + 8) {
+        let word = u64::from_ne_bytes(word.try_into().unwrap());
+        if word.wrapping_sub(ONES * 0x21) & !word & HIGH != 0 {
+            break;
+        }
+        i += 8;
+    }
+    while i < bytes.len() && !is_space(bytes[i]) {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(input: &str) -> Vec<&str> {
+        Tokens::new(input).iter().collect()
+    }
+
+    #[test]
+    fn matches_split_whitespace() {
+        for input in [
+            "",
+            "   ",
+            "amd64",
+            "amd64 ~arm64 -x86",
+            "  leading and trailing  ",
+            "tabs\tand\nnewlines\r\nmixed",
+            "vertical\x0btab and\x0cform feed",
+            "averyveryverylongtokenthatspansseveralwords x",
+            "abcdefg\u{1}h control",
+            "ünïcödé tokens",
+        ] {
+            assert_eq!(
+                split(input),
+                input.split_whitespace().collect::<Vec<_>>(),
+                "{input:?}"
+            );
+        }
+        assert_eq!(Tokens::new("a b c").len(), 3);
+    }
+}
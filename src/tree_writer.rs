@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::SystemTime;
+
+use portage_atom::Cpv;
+
+use crate::cache::CacheEntry;
+use crate::error::{Error, Result};
+use crate::metadata_repo::{read_dir, scan_cache_dir, write_atomic, MetadataRepo};
+
+/// Writes a complete md5-cache tree that depends only on the entries.
+///
+/// After [`TreeWriter::write`], the cache holds exactly the given entries:
+/// files for other package versions are removed, along with categories
+/// left empty. Each file is the entry's [`CacheEntry::serialize`] output,
+/// written in package version order through a hidden temporary file and a
+/// rename, so readers never see a partial entry; temporary files left by
+/// an interrupted run are removed. Files whose content is
+/// already right are not rewritten, which keeps their modification time
+/// and lets `rsync` skip them; with [`with_mtime`](TreeWriter::with_mtime)
+/// every file gets the same fixed time instead, making the tree
+/// byte-for-byte reproducible.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::{Duration, SystemTime};
+/// use portage_metadata::{MetadataRepo, TreeWriter};
+///
+/// let source = MetadataRepo::new("/var/db/repos/gentoo");
+/// let entries = source.load().unwrap();
+///
+/// let epoch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+/// let report = TreeWriter::new()
+///     .with_mtime(epoch)
+///     .write(&MetadataRepo::new("/srv/mirror/gentoo"), &entries)
+///     .unwrap();
+/// println!("{} written, {} removed", report.written.len(), report.removed.len());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeWriter {
+    mtime: Option<SystemTime>,
+}
+
+/// What a [`TreeWriter`] changed.
+///
+/// See [PMS 14.3](https://projects.gentoo.org/pms/9/pms.html#mddict-cache-file-format).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeWriteReport {
+    /// Entries created or rewritten, sorted.
+    pub written: Vec<Cpv>,
+    /// Number of entries that already had the right content.
+    pub unchanged: usize,
+    /// Entries removed from the cache, sorted.
+    pub removed: Vec<Cpv>,
+}
+
+impl TreeWriter {
+    /// A writer leaving modification times to the filesystem.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the modification time of every cache file to `mtime`, e.g.
+    /// the time of the commit the cache was generated from.
+    pub fn with_mtime(mut self, mtime: SystemTime) -> Self {
+        self.mtime = Some(mtime);
+        self
+    }
+
+    /// Make the cache of `repo` hold exactly `entries`.
+    ///
+    /// A missing cache directory is created. File access failures are
+    /// reported as [`Error::Io`].
+    pub fn write(
+        &self,
+        repo: &MetadataRepo,
+        entries: &BTreeMap<Cpv, CacheEntry>,
+    ) -> Result<TreeWriteReport> {
+        let cache_dir = repo.cache_dir();
+        fs::create_dir_all(&cache_dir).map_err(|e| Error::io(&cache_dir, e))?;
+        let mut report = TreeWriteReport::default();
+
+        let mut hidden = Vec::new();
+        let mut existing = scan_cache_dir(&cache_dir, &mut hidden)?;
+        existing.sort();
+        // Temporary files left behind by an interrupted write.
+        for path in hidden.iter().filter(|path| is_stale_temp(path)) {
+            fs::remove_file(path).map_err(|e| Error::io(path, e))?;
+        }
+        let mut categories = BTreeSet::new();
+        for (cpv, path) in existing {
+            if !entries.contains_key(&cpv) {
+                fs::remove_file(&path).map_err(|e| Error::io(&path, e))?;
+                categories.extend(path.parent().map(Path::to_path_buf));
+                report.removed.push(cpv);
+            }
+        }
+        for dir in categories {
+            if read_dir(&dir)?.is_empty() {
+                fs::remove_dir(&dir).map_err(|e| Error::io(&dir, e))?;
+            }
+        }
+
+        for (cpv, entry) in entries {
+            let path = repo.entry_path(cpv);
+            let text = entry.serialize();
+            if fs::read(&path).is_ok_and(|old| old == text.as_bytes()) {
+                if let Some(mtime) = self.mtime {
+                    File::options()
+                        .append(true)
+                        .open(&path)
+                        .and_then(|file| file.set_modified(mtime))
+                        .map_err(|e| Error::io(&path, e))?;
+                }
+                report.unchanged += 1;
+                continue;
+            }
+            write_atomic(&path, &text, self.mtime)?;
+            report.written.push(cpv.clone());
+        }
+        Ok(report)
+    }
+}
+
+fn is_stale_temp(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "tmp")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+    use std::time::Duration;
+
+    fn cpv(s: &str) -> Cpv {
+        Cpv::parse(s).unwrap()
+    }
+
+    #[test]
+    fn rewrite_and_remove() {
+        let root = TempDir::new("tree-writer");
+        let repo = MetadataRepo::new(&root);
+        let entry = |description: &str| {
+            CacheEntry::parse(&format!("SLOT=0\nDESCRIPTION={description}\n")).unwrap()
+        };
+        let mut entries = BTreeMap::from([
+            (cpv("app-misc/foo-1"), entry("foo")),
+            (cpv("dev-libs/bar-1"), entry("bar")),
+        ]);
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let writer = TreeWriter::new().with_mtime(mtime);
+
+        let report = writer.write(&repo, &entries).unwrap();
+        assert_eq!(report.written.len(), 2);
+        assert_eq!(repo.load().unwrap(), entries);
+
+        // An interrupted run leaves a temporary file that readers skip.
+        let stale = repo.cache_dir().join("app-misc/.foo-1.4242.tmp");
+        fs::write(&stale, "SLOT=0\n").unwrap();
+        assert_eq!(repo.load().unwrap(), entries);
+
+        entries.remove(&cpv("dev-libs/bar-1"));
+        entries.insert(cpv("app-misc/foo-2"), entry("foo 2"));
+        let report = writer.write(&repo, &entries).unwrap();
+        assert_eq!(
+            report,
+            TreeWriteReport {
+                written: vec![cpv("app-misc/foo-2")],
+                unchanged: 1,
+                removed: vec![cpv("dev-libs/bar-1")],
+            }
+        );
+        assert!(!repo.cache_dir().join("dev-libs").exists());
+        assert!(!stale.exists());
+        for cpv in entries.keys() {
+            let modified = fs::metadata(repo.entry_path(cpv))
+                .unwrap()
+                .modified()
+                .unwrap();
+            assert_eq!(modified, mtime);
+        }
+        assert_eq!(
+            fs::read_to_string(repo.entry_path(&cpv("app-misc/foo-2"))).unwrap(),
+            entries[&cpv("app-misc/foo-2")].serialize()
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::interner::Interner;
+use crate::metadata::EbuildMetadata;
+use crate::src_uri::SrcUriEntry;
+use crate::src_url::SrcUrl;
+
+/// The problem found with a URL by [`UriLint`].
+///
+/// See [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UriIssue {
+    /// Plain `http://` instead of `https://`.
+    InsecureHttp,
+    /// `ftp://`, which is unencrypted and widely unsupported.
+    Ftp,
+    /// The host is on the deny-list; holds the matching deny-list entry.
+    DeniedHost(String),
+    /// The `mirror://` group is on the deny-list; holds the group name.
+    DeniedMirror(String),
+}
+
+impl fmt::Display for UriIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UriIssue::InsecureHttp => write!(f, "insecure http:// URL"),
+            UriIssue::Ftp => write!(f, "ftp:// URL"),
+            UriIssue::DeniedHost(host) => write!(f, "denied host {host}"),
+            UriIssue::DeniedMirror(group) => write!(f, "deprecated mirror://{group}"),
+        }
+    }
+}
+
+/// A single lint finding.
+///
+/// See [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UriFinding {
+    /// The metadata variable the URL comes from (`SRC_URI` or `HOMEPAGE`).
+    pub variable: &'static str,
+    /// The offending URL.
+    pub url: String,
+    /// What is wrong with it.
+    pub issue: UriIssue,
+}
+
+impl fmt::Display for UriFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.variable, self.url, self.issue)
+    }
+}
+
+/// Detects insecure and deprecated URLs in `SRC_URI` and `HOMEPAGE`.
+///
+/// Flags `http://` and `ftp://` URLs, and URLs whose host is on a
+/// user-supplied deny-list of dead or untrusted hosts. A deny-list entry
+/// also matches its subdomains. `mirror://` URLs are flagged when their
+/// group is on a separate deny-list of deprecated mirrors.
+///
+/// As a [`MetadataCheck`] named `uri`, it reports denied hosts and mirrors
+/// as errors, since fetching from them fails, and insecure schemes as
+/// warnings.
+///
+/// See [PMS 7.2](https://projects.gentoo.org/pms/9/pms.html#mandatory-ebuilddefined-variables)
+/// and [PMS 7.3.2](https://projects.gentoo.org/pms/9/pms.html#srcuri).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UriLint {
+    denied_hosts: BTreeSet<String>,
+    denied_mirrors: BTreeSet<String>,
+}
+
+impl UriLint {
+    /// Create a lint with an empty deny-list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a host to the deny-list (case-insensitive).
+    pub fn deny_host(&mut self, host: &str) {
+        self.denied_hosts.insert(host.to_ascii_lowercase());
+    }
+
+    /// Add a `mirror://` group to the deny-list, e.g. `berlios`.
+    pub fn deny_mirror(&mut self, group: &str) {
+        self.denied_mirrors.insert(group.to_string());
+    }
+
+    /// Check a single URL.
+    ///
+    /// A denied host takes precedence over the scheme checks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{SrcUrl, UriIssue, UriLint};
+    ///
+    /// let mut lint = UriLint::new();
+    /// lint.deny_host("code.google.com");
+    /// assert_eq!(
+    ///     lint.check_url(&SrcUrl::parse("http://example.com/a.tgz")),
+    ///     Some(UriIssue::InsecureHttp),
+    /// );
+    /// assert_eq!(
+    ///     lint.check_url(&SrcUrl::parse("https://code.google.com/p/x")),
+    ///     Some(UriIssue::DeniedHost("code.google.com".to_string())),
+    /// );
+    /// assert_eq!(lint.check_url(&SrcUrl::parse("mirror://gnu/a.tgz")), None);
+    /// ```
+    pub fn check_url(&self, url: &SrcUrl) -> Option<UriIssue> {
+        let (scheme, host) = match url {
+            SrcUrl::Url { scheme, host, .. } => (scheme, host),
+            SrcUrl::Mirror { group, .. } => {
+                return self
+                    .denied_mirrors
+                    .contains(group)
+                    .then(|| UriIssue::DeniedMirror(group.clone()));
+            }
+            SrcUrl::Plain(_) => return None,
+        };
+        if let Some(denied) = self.denied_entry(host) {
+            return Some(UriIssue::DeniedHost(denied.to_string()));
+        }
+        if scheme.eq_ignore_ascii_case("http") {
+            Some(UriIssue::InsecureHttp)
+        } else if scheme.eq_ignore_ascii_case("ftp") {
+            Some(UriIssue::Ftp)
+        } else {
+            None
+        }
+    }
+
+    /// Check every URL in a `SRC_URI` tree, regardless of USE conditionals.
+    pub fn check_src_uri(&self, entries: &[SrcUriEntry]) -> Vec<UriFinding> {
+        let mut out = Vec::new();
+        self.collect_src_uri(entries, &mut out);
+        out
+    }
+
+    /// Check the URLs of a `HOMEPAGE` value.
+    pub fn check_homepage<S: AsRef<str>>(&self, homepage: &[S]) -> Vec<UriFinding> {
+        homepage
+            .iter()
+            .filter_map(|url| {
+                let url = url.as_ref();
+                self.check_url(&SrcUrl::parse(url)).map(|issue| UriFinding {
+                    variable: "HOMEPAGE",
+                    url: url.to_string(),
+                    issue,
+                })
+            })
+            .collect()
+    }
+
+    /// Check both `HOMEPAGE` and `SRC_URI` of an ebuild.
+    pub fn check_metadata<I: Interner>(&self, metadata: &EbuildMetadata<I>) -> Vec<UriFinding> {
+        let homepage: Vec<String> = metadata.homepage.iter().map(ToString::to_string).collect();
+        let mut out = self.check_homepage(&homepage);
+        out.extend(self.check_src_uri(&metadata.src_uri));
+        out
+    }
+
+    fn collect_src_uri(&self, entries: &[SrcUriEntry], out: &mut Vec<UriFinding>) {
+        for entry in entries {
+            match entry {
+                SrcUriEntry::Uri { url, .. } | SrcUriEntry::Renamed { url, .. } => {
+                    if let Some(issue) = self.check_url(url) {
+                        out.push(UriFinding {
+                            variable: "SRC_URI",
+                            url: url.to_string(),
+                            issue,
+                        });
+                    }
+                }
+                SrcUriEntry::UseConditional { entries, .. } | SrcUriEntry::Group(entries) => {
+                    self.collect_src_uri(entries, out)
+                }
+            }
+        }
+    }
+
+    /// The deny-list entry matching `authority`, ignoring userinfo and port.
+    fn denied_entry(&self, authority: &str) -> Option<&str> {
+        let host = authority.rsplit('@').next().unwrap_or(authority);
+        let host = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+        self.denied_hosts
+            .iter()
+            .find(|denied| {
+                host == **denied
+                    || host
+                        .strip_suffix(denied.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            })
+            .map(String::as_str)
+    }
+}
+
+impl MetadataCheck for UriLint {
+    fn name(&self) -> &'static str {
+        "uri"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        for finding in self.check_metadata(&cx.entry.metadata) {
+            let severity = match finding.issue {
+                UriIssue::DeniedHost(_) | UriIssue::DeniedMirror(_) => Severity::Error,
+                UriIssue::InsecureHttp | UriIssue::Ftp => Severity::Warning,
+            };
+            findings.push(cx.finding(self.name(), severity, finding.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn schemes() {
+        let lint = UriLint::new();
+        assert_eq!(
+            lint.check_url(&SrcUrl::parse("ftp://ftp.gnu.org/a.tgz")),
+            Some(UriIssue::Ftp)
+        );
+        assert_eq!(
+            lint.check_url(&SrcUrl::parse("HTTP://example.com/")),
+            Some(UriIssue::InsecureHttp)
+        );
+        assert_eq!(lint.check_url(&SrcUrl::parse("https://e.com/a")), None);
+        assert_eq!(lint.check_url(&SrcUrl::parse("a.tgz")), None);
+    }
+
+    #[test]
+    fn denied_subdomain_and_port() {
+        let mut lint = UriLint::new();
+        lint.deny_host("Berlios.de");
+        let denied = Some(UriIssue::DeniedHost("berlios.de".to_string()));
+        assert_eq!(
+            lint.check_url(&SrcUrl::parse("https://download.berlios.de/x.tgz")),
+            denied
+        );
+        assert_eq!(
+            lint.check_url(&SrcUrl::parse("https://u@berlios.de:443/x.tgz")),
+            denied
+        );
+        assert_eq!(
+            lint.check_url(&SrcUrl::parse("https://notberlios.de/x.tgz")),
+            None
+        );
+    }
+
+    #[test]
+    fn check_src_uri_tree() {
+        let entries = SrcUriEntry::parse(
+            "https://e.com/a doc? ( http://e.com/doc.tgz ( ftp://e.com/b -> c.tgz ) )",
+        )
+        .unwrap();
+        let findings = UriLint::new().check_src_uri(&entries);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            findings[0].to_string(),
+            "SRC_URI: http://e.com/doc.tgz: insecure http:// URL"
+        );
+        assert_eq!(findings[1].issue, UriIssue::Ftp);
+    }
+
+    #[test]
+    fn check_homepage_values() {
+        let findings = UriLint::new().check_homepage(&["https://a.org", "http://b.org"]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].variable, "HOMEPAGE");
+        assert_eq!(findings[0].url, "http://b.org");
+    }
+
+    #[test]
+    fn metadata_check() {
+        let mut lint = UriLint::new();
+        lint.deny_host("berlios.de");
+        lint.deny_mirror("berlios");
+        let text = "DESCRIPTION=x\nSLOT=0\nHOMEPAGE=http://foo.berlios.de/\n\
+                    SRC_URI=mirror://berlios/foo/foo-1.tgz mirror://gnu/foo-1.sig ftp://e.com/a\n";
+        let findings: Vec<_> = run_check(lint, &[("app-misc/foo-1", text)])
+            .into_iter()
+            .map(|f| (f.severity, f.message))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    Severity::Error,
+                    "HOMEPAGE: http://foo.berlios.de/: denied host berlios.de".to_string()
+                ),
+                (
+                    Severity::Error,
+                    "SRC_URI: mirror://berlios/foo/foo-1.tgz: deprecated mirror://berlios"
+                        .to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "SRC_URI: ftp://e.com/a: ftp:// URL".to_string()
+                ),
+            ]
+        );
+    }
+}
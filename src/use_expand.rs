@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::use_flags::UseFlagSet;
+
+/// The set of `USE_EXPAND` variables defined by a profile.
+///
+/// A `USE_EXPAND` variable such as `PYTHON_TARGETS="python3_11 python3_12"`
+/// is a user-facing shorthand for the USE flags `python_targets_python3_11`
+/// and `python_targets_python3_12`: the lowercased variable name followed by
+/// an underscore is prepended to every value. This type translates in both
+/// directions so evaluation can start from the user-facing variables.
+///
+/// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UseExpandConfig {
+    vars: BTreeSet<String>,
+}
+
+impl UseExpandConfig {
+    /// Create an empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the value of the profile's `USE_EXPAND` variable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::UseExpandConfig;
+    ///
+    /// let config = UseExpandConfig::parse("PYTHON_TARGETS CPU_FLAGS_X86");
+    /// assert!(config.contains("CPU_FLAGS_X86"));
+    /// ```
+    pub fn parse(input: &str) -> Self {
+        input.split_whitespace().collect()
+    }
+
+    /// Add a `USE_EXPAND` variable.
+    pub fn add(&mut self, var: impl Into<String>) {
+        self.vars.insert(var.into());
+    }
+
+    /// Whether `var` is a known `USE_EXPAND` variable.
+    pub fn contains(&self, var: &str) -> bool {
+        self.vars.contains(var)
+    }
+
+    /// Iterate over the known variables in sorted order.
+    pub fn vars(&self) -> impl Iterator<Item = &str> {
+        self.vars.iter().map(String::as_str)
+    }
+
+    /// The USE flag for `value` of the variable `var`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::UseExpandConfig;
+    ///
+    /// assert_eq!(
+    ///     UseExpandConfig::flag("PYTHON_TARGETS", "python3_11"),
+    ///     "python_targets_python3_11",
+    /// );
+    /// ```
+    pub fn flag(var: &str, value: &str) -> String {
+        format!("{}_{value}", var.to_ascii_lowercase())
+    }
+
+    /// Translate a variable's space-separated value into USE flags.
+    ///
+    /// Negated values (`-value`) are kept negated: `-sse2` in
+    /// `CPU_FLAGS_X86` becomes `-cpu_flags_x86_sse2`.
+    pub fn to_flags(var: &str, values: &str) -> Vec<String> {
+        values
+            .split_whitespace()
+            .map(|value| match value.strip_prefix('-') {
+                Some(value) => format!("-{}", Self::flag(var, value)),
+                None => Self::flag(var, value),
+            })
+            .collect()
+    }
+
+    /// Split a USE flag into its `USE_EXPAND` variable and value.
+    ///
+    /// Returns `None` for flags that do not belong to a known variable.
+    /// When several variables match (e.g. `VIDEO` and `VIDEO_CARDS`), the
+    /// longest one wins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::UseExpandConfig;
+    ///
+    /// let config = UseExpandConfig::parse("PYTHON_TARGETS");
+    /// assert_eq!(
+    ///     config.split_flag("python_targets_python3_11"),
+    ///     Some(("PYTHON_TARGETS", "python3_11")),
+    /// );
+    /// assert_eq!(config.split_flag("ssl"), None);
+    /// ```
+    pub fn split_flag<'a>(&'a self, flag: &'a str) -> Option<(&'a str, &'a str)> {
+        self.vars
+            .iter()
+            .filter_map(|var| {
+                let rest = strip_prefix_ignore_case(flag, var)?;
+                let value = rest.strip_prefix('_')?;
+                (!value.is_empty()).then_some((var.as_str(), value))
+            })
+            .max_by_key(|(var, _)| var.len())
+    }
+
+    /// Apply a variable's value to `use_flags`.
+    ///
+    /// Values are processed in order; `-value` disables the flag, so later
+    /// tokens override earlier ones.
+    pub fn expand_into(&self, use_flags: &mut UseFlagSet, var: &str, values: &str) {
+        for flag in Self::to_flags(var, values) {
+            match flag.strip_prefix('-') {
+                Some(flag) => {
+                    use_flags.disable(flag);
+                }
+                None => {
+                    use_flags.enable(flag);
+                }
+            }
+        }
+    }
+
+    /// Collapse enabled `USE_EXPAND` flags back into variable values.
+    ///
+    /// Flags not belonging to any known variable are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::{UseExpandConfig, UseFlagSet};
+    ///
+    /// let config = UseExpandConfig::parse("PYTHON_TARGETS");
+    /// let flags = UseFlagSet::parse("ssl python_targets_python3_12 python_targets_python3_11");
+    /// let vars = config.collapse(&flags);
+    /// assert_eq!(vars["PYTHON_TARGETS"], vec!["python3_11", "python3_12"]);
+    /// ```
+    pub fn collapse<'a>(&'a self, use_flags: &'a UseFlagSet) -> BTreeMap<&'a str, Vec<&'a str>> {
+        let mut out: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for flag in use_flags.iter() {
+            if let Some((var, value)) = self.split_flag(flag) {
+                out.entry(var).or_default().push(value);
+            }
+        }
+        out
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for UseExpandConfig {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        Self {
+            vars: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_flags_with_negation() {
+        assert_eq!(
+            UseExpandConfig::to_flags("CPU_FLAGS_X86", "sse2 -avx"),
+            vec!["cpu_flags_x86_sse2", "-cpu_flags_x86_avx"]
+        );
+    }
+
+    #[test]
+    fn split_prefers_longest_var() {
+        let config = UseExpandConfig::parse("VIDEO VIDEO_CARDS");
+        assert_eq!(
+            config.split_flag("video_cards_amdgpu"),
+            Some(("VIDEO_CARDS", "amdgpu"))
+        );
+        assert_eq!(config.split_flag("video_foo"), Some(("VIDEO", "foo")));
+    }
+
+    #[test]
+    fn split_requires_value() {
+        let config = UseExpandConfig::parse("L10N");
+        assert_eq!(config.split_flag("l10n_"), None);
+        assert_eq!(config.split_flag("l10n"), None);
+        assert_eq!(config.split_flag("l10n_de"), Some(("L10N", "de")));
+    }
+
+    #[test]
+    fn expand_into_overrides() {
+        let config = UseExpandConfig::parse("PYTHON_TARGETS");
+        let mut flags = UseFlagSet::parse("ssl python_targets_python3_10");
+        config.expand_into(&mut flags, "PYTHON_TARGETS", "python3_12 -python3_10");
+        assert_eq!(flags.to_string(), "python_targets_python3_12 ssl");
+    }
+
+    #[test]
+    fn collapse_round_trip() {
+        let config = UseExpandConfig::parse("PYTHON_TARGETS L10N");
+        let mut flags = UseFlagSet::new();
+        config.expand_into(&mut flags, "L10N", "de fr");
+        config.expand_into(&mut flags, "PYTHON_TARGETS", "python3_12");
+        let vars = config.collapse(&flags);
+        assert_eq!(vars.len(), 2);
+        assert_eq!(vars["L10N"], vec!["de", "fr"]);
+        assert_eq!(vars["PYTHON_TARGETS"], vec!["python3_12"]);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+
+use crate::check::{CheckContext, Finding, MetadataCheck, Severity};
+use crate::profile::Profile;
+
+/// Reports USE flags referenced but not declared in `IUSE`, and `IUSE`
+/// flags never referenced by the metadata.
+///
+/// Undeclared flags are errors unless the profile injects them through
+/// `IUSE_IMPLICIT` or `USE_EXPAND_IMPLICIT`. Unused flags are only
+/// informational, since the ebuild's phase functions may test them.
+///
+/// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use portage_atom::Cpv;
+/// use portage_metadata::{CacheEntry, CheckRunner, Profile, UseFlagCheck};
+///
+/// let repo = BTreeMap::from([(
+///     Cpv::parse("app-misc/foo-1").unwrap(),
+///     CacheEntry::parse(
+///         "DESCRIPTION=x\nSLOT=0\nIUSE=ssl\nRDEPEND=prefix? ( x/y ) doc? ( x/z )\n",
+///     )
+///     .unwrap(),
+/// )]);
+/// let profile = Profile::from_vars([("IUSE_IMPLICIT", "prefix")]);
+///
+/// let mut runner = CheckRunner::new();
+/// runner.add(UseFlagCheck::new(&profile));
+/// let findings: Vec<_> = runner.run(&repo).iter().map(|f| f.to_string()).collect();
+/// assert_eq!(
+///     findings,
+///     [
+///         "app-misc/foo-1: error [use-flags]: USE flag doc is not in IUSE",
+///         "app-misc/foo-1: info [use-flags]: IUSE flag ssl is never referenced",
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UseFlagCheck {
+    implicit: BTreeSet<String>,
+}
+
+impl UseFlagCheck {
+    /// Accept the implicit flags of `profile` as declared.
+    pub fn new(profile: &Profile) -> Self {
+        UseFlagCheck {
+            implicit: profile.implicit_flags(),
+        }
+    }
+}
+
+impl MetadataCheck for UseFlagCheck {
+    fn name(&self) -> &'static str {
+        "use-flags"
+    }
+
+    fn check(&self, cx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let metadata = &cx.entry.metadata;
+        let implicit: Vec<&str> = self.implicit.iter().map(String::as_str).collect();
+        for flag in metadata.undeclared_flags(&implicit) {
+            let message = format!("USE flag {flag} is not in IUSE");
+            findings.push(cx.finding(self.name(), Severity::Error, message));
+        }
+        for flag in metadata.unused_flags() {
+            let message = format!("IUSE flag {flag} is never referenced");
+            findings.push(cx.finding(self.name(), Severity::Info, message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::run_check;
+
+    #[test]
+    fn references_across_fields() {
+        let text = "EAPI=8\nDESCRIPTION=x\nSLOT=0\nIUSE=a b c d elibc_musl\n\
+                    REQUIRED_USE=a\nRESTRICT=!b? ( test )\nRDEPEND=x/y[c?]\n\
+                    LICENSE=d? ( MIT ) e? ( GPL-2 )\n";
+        let messages: Vec<_> = run_check(UseFlagCheck::default(), &[("app-misc/foo-1", text)])
+            .into_iter()
+            .map(|f| f.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "USE flag e is not in IUSE",
+                "IUSE flag elibc_musl is never referenced"
+            ]
+        );
+    }
+}
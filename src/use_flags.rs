@@ -0,0 +1,154 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A set of enabled USE flags.
+///
+/// Used to evaluate USE-conditional groups (`flag? ( ... )`) and
+/// `REQUIRED_USE` constraints for a concrete configuration. Flags not in
+/// the set are considered disabled.
+///
+/// See [PMS 11.1.1](https://projects.gentoo.org/pms/9/pms.html#use-and-iuse-handling).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UseFlagSet {
+    flags: BTreeSet<String>,
+}
+
+impl UseFlagSet {
+    /// Create an empty set (all flags disabled).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a space-separated `USE`-style string.
+    ///
+    /// Tokens prefixed with `-` disable a previously enabled flag, so
+    /// later tokens override earlier ones as in `make.conf`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use portage_metadata::UseFlagSet;
+    ///
+    /// let flags = UseFlagSet::parse("ssl gtk -gtk qt5");
+    /// assert!(flags.is_enabled("ssl"));
+    /// assert!(!flags.is_enabled("gtk"));
+    /// assert!(flags.is_enabled("qt5"));
+    /// ```
+    pub fn parse(input: &str) -> Self {
+        let mut set = Self::new();
+        for token in input.split_whitespace() {
+            match token.strip_prefix('-') {
+                Some(flag) => {
+                    set.disable(flag);
+                }
+                None => {
+                    set.enable(token);
+                }
+            }
+        }
+        set
+    }
+
+    /// Whether `flag` is enabled.
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    /// Enable `flag`. Returns `true` if it was previously disabled.
+    pub fn enable(&mut self, flag: impl Into<String>) -> bool {
+        self.flags.insert(flag.into())
+    }
+
+    /// Disable `flag`. Returns `true` if it was previously enabled.
+    pub fn disable(&mut self, flag: &str) -> bool {
+        self.flags.remove(flag)
+    }
+
+    /// Enable or disable `flag`. Returns `true` if its state changed.
+    pub fn set(&mut self, flag: &str, enabled: bool) -> bool {
+        if enabled {
+            self.enable(flag)
+        } else {
+            self.disable(flag)
+        }
+    }
+
+    /// Iterate over the enabled flags in sorted order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.flags.iter().map(String::as_str)
+    }
+
+    /// Number of enabled flags.
+    pub fn len(&self) -> usize {
+        self.flags.len()
+    }
+
+    /// Whether no flags are enabled.
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for UseFlagSet {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        Self {
+            flags: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<S: Into<String>> Extend<S> for UseFlagSet {
+    fn extend<T: IntoIterator<Item = S>>(&mut self, iter: T) {
+        self.flags.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+impl fmt::Display for UseFlagSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, flag) in self.flags.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{flag}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_with_negation() {
+        let flags = UseFlagSet::parse("a b -a c");
+        assert!(!flags.is_enabled("a"));
+        assert!(flags.is_enabled("b"));
+        assert!(flags.is_enabled("c"));
+        assert_eq!(flags.len(), 2);
+    }
+
+    #[test]
+    fn enable_disable() {
+        let mut flags = UseFlagSet::new();
+        assert!(flags.enable("ssl"));
+        assert!(!flags.enable("ssl"));
+        assert!(flags.disable("ssl"));
+        assert!(!flags.disable("ssl"));
+        assert!(flags.is_empty());
+    }
+
+    #[test]
+    fn set_reports_change() {
+        let mut flags = UseFlagSet::new();
+        assert!(flags.set("x", true));
+        assert!(!flags.set("x", true));
+        assert!(flags.set("x", false));
+    }
+
+    #[test]
+    fn display_sorted() {
+        let flags: UseFlagSet = ["gtk", "alsa", "ssl"].into_iter().collect();
+        assert_eq!(flags.to_string(), "alsa gtk ssl");
+    }
+}
@@ -0,0 +1,280 @@
+use crate::auto_enforce::UseFlagState;
+use crate::required_use::RequiredUseExpr;
+use crate::use_flags::UseFlagSet;
+
+/// Rounds over the whole constraint before giving up: fixing one part
+/// may break another that was already satisfied.
+const MAX_PASSES: usize = 8;
+
+/// What a [`UseChoice`] asks the chooser to decide.
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChoiceKind {
+    /// None of the options of a `||` or `^^` group holds: pick the one to
+    /// satisfy.
+    Enable,
+    /// Several options of a `^^` or `??` group hold: pick the one to keep.
+    /// The others must be plain flags, which are then flipped.
+    Keep,
+}
+
+/// A decision requested by [`UseResolution::resolve`].
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UseChoice<'a> {
+    /// What is being decided.
+    pub kind: ChoiceKind,
+    /// The unsatisfied `||`, `^^` or `??` group.
+    pub group: &'a RequiredUseExpr,
+    /// The alternatives to choose from, in group order.
+    pub options: Vec<&'a RequiredUseExpr>,
+}
+
+/// Picks among the alternatives of a [`UseChoice`], e.g. by prompting
+/// the user.
+///
+/// Returns the index of the chosen option, or `None` to stop resolving.
+/// Closures `FnMut(&UseChoice) -> Option<usize>` implement this trait.
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+pub trait UseChooser {
+    /// The index into `choice.options` to apply.
+    fn choose(&mut self, choice: &UseChoice<'_>) -> Option<usize>;
+}
+
+impl<F: FnMut(&UseChoice<'_>) -> Option<usize>> UseChooser for F {
+    fn choose(&mut self, choice: &UseChoice<'_>) -> Option<usize> {
+        self(choice)
+    }
+}
+
+/// The flag changes that make a `REQUIRED_USE` constraint hold.
+///
+/// Unlike [`AutoEnforce`](crate::AutoEnforce), which only handles
+/// constraints with a single deterministic solution, resolution walks any
+/// unsatisfied constraint and leaves every real decision — which member of
+/// `|| ( gtk qt5 )` to enable, which of several enabled `^^` members to
+/// keep — to a [`UseChooser`]. Plain flags are set as required, and
+/// USE-conditional groups are only entered when their condition holds.
+///
+/// See [PMS 7.3.4](https://projects.gentoo.org/pms/9/pms.html#use-state-constraints).
+///
+/// # Examples
+///
+/// ```
+/// use portage_metadata::{RequiredUseExpr, UseChoice, UseFlagSet, UseResolution};
+///
+/// let expr = RequiredUseExpr::parse("X? ( || ( gtk qt5 ) ) ?? ( gtk wayland )").unwrap();
+/// let mut flags = UseFlagSet::parse("X wayland");
+///
+/// // A TUI would prompt here; pick qt5.
+/// let mut chooser = |choice: &UseChoice<'_>| {
+///     choice.options.iter().position(|o| o.to_string() == "qt5")
+/// };
+/// let resolution = UseResolution::resolve(&expr, &mut flags, &mut chooser);
+/// assert!(resolution.satisfied);
+/// assert_eq!(resolution.changes[0].to_string(), "qt5");
+/// assert_eq!(flags.to_string(), "X qt5 wayland");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UseResolution {
+    /// Flags whose state changed, in the order they were first touched.
+    pub changes: Vec<UseFlagState>,
+    /// Whether the constraint holds afterwards. `false` if the chooser
+    /// stopped, a group could not be narrowed down, or resolution did not
+    /// settle.
+    pub satisfied: bool,
+}
+
+impl UseResolution {
+    /// Resolve `expr` against `use_flags`, applying the changes in place.
+    ///
+    /// The chooser may be asked again about the same group when a later
+    /// change breaks an earlier decision. When resolution fails,
+    /// `use_flags` keeps the changes made so far.
+    pub fn resolve(
+        expr: &RequiredUseExpr,
+        use_flags: &mut UseFlagSet,
+        chooser: &mut impl UseChooser,
+    ) -> Self {
+        let mut resolver = Resolver {
+            use_flags,
+            chooser,
+            original: Vec::new(),
+        };
+        for _ in 0..MAX_PASSES {
+            if expr.is_satisfied(resolver.use_flags) || !resolver.satisfy(expr) {
+                break;
+            }
+        }
+        let changes = resolver
+            .original
+            .iter()
+            .filter(|state| !state.holds(resolver.use_flags))
+            .map(|state| UseFlagState::new(&state.name, !state.enabled))
+            .collect();
+        UseResolution {
+            changes,
+            satisfied: expr.is_satisfied(resolver.use_flags),
+        }
+    }
+}
+
+struct Resolver<'f, 'c, C> {
+    use_flags: &'f mut UseFlagSet,
+    chooser: &'c mut C,
+    /// The initial state of every flag touched so far.
+    original: Vec<UseFlagState>,
+}
+
+impl<C: UseChooser> Resolver<'_, '_, C> {
+    fn set(&mut self, name: &str, enabled: bool) {
+        if !self.original.iter().any(|state| state.name == name) {
+            let state = UseFlagState::new(name, self.use_flags.is_enabled(name));
+            self.original.push(state);
+        }
+        self.use_flags.set(name, enabled);
+    }
+
+    /// Make `expr` hold; `false` if that needs a decision nobody made.
+    fn satisfy(&mut self, expr: &RequiredUseExpr) -> bool {
+        if expr.is_satisfied(self.use_flags) {
+            return true;
+        }
+        match expr {
+            RequiredUseExpr::Flag { name, negated } => {
+                self.set(name, !negated);
+                true
+            }
+            // An unsatisfied conditional group has its condition enabled.
+            RequiredUseExpr::All(entries) | RequiredUseExpr::UseConditional { entries, .. } => {
+                entries.iter().all(|entry| self.satisfy(entry))
+            }
+            RequiredUseExpr::AnyOf(entries) => self.enable_one(expr, entries),
+            RequiredUseExpr::ExactlyOne(entries) => {
+                let none = !entries.iter().any(|e| e.is_satisfied(self.use_flags));
+                (!none || self.enable_one(expr, entries)) && self.keep_one(expr, entries)
+            }
+            RequiredUseExpr::AtMostOne(entries) => self.keep_one(expr, entries),
+        }
+    }
+
+    fn enable_one(&mut self, group: &RequiredUseExpr, entries: &[RequiredUseExpr]) -> bool {
+        let choice = UseChoice {
+            kind: ChoiceKind::Enable,
+            group,
+            options: entries.iter().collect(),
+        };
+        match self.chooser.choose(&choice) {
+            Some(i) if i < entries.len() => self.satisfy(&entries[i]),
+            _ => false,
+        }
+    }
+
+    fn keep_one(&mut self, group: &RequiredUseExpr, entries: &[RequiredUseExpr]) -> bool {
+        let options: Vec<&RequiredUseExpr> = entries
+            .iter()
+            .filter(|e| e.is_satisfied(self.use_flags))
+            .collect();
+        if options.len() <= 1 {
+            return true;
+        }
+        let choice = UseChoice {
+            kind: ChoiceKind::Keep,
+            group,
+            options,
+        };
+        let Some(keep) = self
+            .chooser
+            .choose(&choice)
+            .filter(|&i| i < choice.options.len())
+        else {
+            return false;
+        };
+        for (i, option) in choice.options.iter().enumerate() {
+            match option {
+                _ if i == keep => {}
+                RequiredUseExpr::Flag { name, negated } => self.set(name, *negated),
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(expr: &RequiredUseExpr, flags: &str, pick: &str) -> (UseResolution, String) {
+        let mut flags = UseFlagSet::parse(flags);
+        let mut chooser =
+            |choice: &UseChoice<'_>| choice.options.iter().position(|o| o.to_string() == pick);
+        let resolution = UseResolution::resolve(expr, &mut flags, &mut chooser);
+        (resolution, flags.to_string())
+    }
+
+    fn parse(input: &str) -> RequiredUseExpr {
+        RequiredUseExpr::parse(input).unwrap()
+    }
+
+    #[test]
+    fn keep_and_enable() {
+        let (resolution, flags) = resolve(&parse("^^ ( a b c )"), "a b c", "b");
+        assert!(resolution.satisfied);
+        assert_eq!(
+            resolution.changes,
+            [UseFlagState::new("a", false), UseFlagState::new("c", false)]
+        );
+        assert_eq!(flags, "b");
+
+        let tls = RequiredUseExpr::conditional(
+            "ssl",
+            false,
+            [RequiredUseExpr::exactly_one([
+                RequiredUseExpr::flag("gnutls"),
+                RequiredUseExpr::all([
+                    RequiredUseExpr::flag("openssl"),
+                    RequiredUseExpr::flag("tls"),
+                ]),
+            ])],
+        );
+        let (resolution, flags) = resolve(&tls, "ssl", "openssl tls");
+        assert!(resolution.satisfied);
+        assert_eq!(flags, "openssl ssl tls");
+
+        let (resolution, flags) = resolve(&parse("!test? ( !doc ) || ( a b )"), "doc", "b");
+        assert!(resolution.satisfied);
+        assert_eq!(
+            resolution.changes,
+            [
+                UseFlagState::new("doc", false),
+                UseFlagState::new("b", true)
+            ]
+        );
+        assert_eq!(flags, "b");
+    }
+
+    #[test]
+    fn unresolved() {
+        // The chooser declines.
+        let (resolution, flags) = resolve(&parse("doc? ( || ( a b ) )"), "doc", "c");
+        assert!(!resolution.satisfied);
+        assert!(resolution.changes.is_empty());
+        assert_eq!(flags, "doc");
+
+        // Only flags can be turned off to keep one alternative.
+        let nested = RequiredUseExpr::at_most_one([
+            RequiredUseExpr::all([RequiredUseExpr::flag("b"), RequiredUseExpr::flag("c")]),
+            RequiredUseExpr::flag("a"),
+        ]);
+        let (resolution, _) = resolve(&nested, "a b c", "a");
+        assert!(!resolution.satisfied);
+
+        // Contradictions never settle.
+        let (resolution, _) = resolve(&parse("a !a"), "", "a");
+        assert!(!resolution.satisfied);
+    }
+}